@@ -29,6 +29,8 @@ impl Default for TransportMulticastConf {
         Self {
             join_interval: Some(2500),
             max_sessions: Some(1000),
+            listen: vec![],
+            connect: vec![],
         }
     }
 }
@@ -92,3 +94,12 @@ impl Default for SharedMemoryConf {
         Self { enabled: true }
     }
 }
+
+impl Default for AdminSpaceConf {
+    fn default() -> Self {
+        Self {
+            write: true,
+            writers: AdminWritersConf::default(),
+        }
+    }
+}
@@ -171,6 +171,11 @@ validated_struct::validator! {
                 join_interval: Option<ZInt>,
                 /// Maximum number of multicast sessions (default: 1000)
                 max_sessions: Option<usize>,
+                /// Multicast endpoints (e.g. `udp/224.0.0.225:7448`) on which peers open a multicast transport at startup.
+                /// Startup fails if one of them can't be opened.
+                listen: Vec<EndPoint>,
+                /// Multicast endpoints on which peers open a multicast transport, retrying until they can be opened.
+                connect: Vec<EndPoint>,
             },
             pub qos: QoSConf {
                 /// Whether QoS is enabled or not.
@@ -223,6 +228,12 @@ validated_struct::validator! {
                     /// Fragmented messages that are larger than the configured size will be dropped.
                     max_message_size: Option<usize>,
                 },
+                pub compression: #[derive(Default)]
+                CompressionConf {
+                    /// Whether batches should be compressed with LZ4 on unicast links.
+                    /// Compression is used on a link only if both ends enable it. (default `false`).
+                    enabled: bool,
+                },
                 pub tls: #[derive(Default)]
                 TLSConf {
                     root_ca_certificate: Option<String>,
@@ -248,6 +259,11 @@ validated_struct::validator! {
                     password: Option<String>,
                     /// The path to a file containing the user password dictionary, a file containing "<user>:<password>"
                     dictionary_file: Option<String>,
+                    #[validated(recursive_accessors)]
+                    /// The users accepted in addition to the ones of the dictionary file, as "<user>": "<password>".
+                    ///
+                    /// Please refer to [`UsrPwdUsers`]'s documentation for further details.
+                    users: UsrPwdUsers,
                 } where (user_conf_validator),
                 pub pubkey: #[derive(Default)]
                 PubKeyConf {
@@ -260,6 +276,32 @@ validated_struct::validator! {
                 },
             },
         },
+        /// Access control applied by the routing layer to the declarations and messages received from remote peers.
+        pub access_control: #[derive(Default)]
+        AccessControlConf {
+            /// Whether access control is enforced or not (default `false`).
+            enabled: bool,
+            /// The permission applied when no rule matches a message ("allow" or "deny", default "allow").
+            default_permission: Option<Permission>,
+            /// The access control rules. Rules denying an action take precedence over rules allowing it.
+            rules: Vec<AclRule>,
+        },
+        /// Downsampling applied by the routing layer to the publications routed to remote peers.
+        pub downsampling: #[derive(Default)]
+        DownsamplingConf {
+            /// The downsampling rules. For each face, the first rule applying to it and matching a key expression is enforced on it.
+            rules: Vec<DownsamplingRule>,
+        },
+        /// Configuration of the adminspace of the router (`/@/router/<pid>/**`).
+        pub adminspace: AdminSpaceConf {
+            /// Whether the configuration of the router may be changed through the adminspace, i.e. by putting or deleting
+            /// values under `/@/router/<pid>/config/**` (default `true`).
+            write: bool,
+            /// If any identity is listed, configuration changes are only accepted from the remote peers authenticated with one of them.
+            /// Changes issued by the local sessions (e.g. plugins) are always accepted.
+            #[validated(recursive_accessors)]
+            pub writers: AdminWritersConf,
+        },
         /// A list of directories where plugins may be searched for if no `__path__` was specified for them.
         /// The executable's current directory will be added to the search paths.
         plugins_search_dirs: Vec<String>, // TODO (low-prio): Switch this String to a PathBuf? (applies to other paths in the config as well)
@@ -271,6 +313,264 @@ validated_struct::validator! {
     }
 }
 
+/// The permission granted by an [`AclRule`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Allow,
+    Deny,
+}
+
+/// The actions [`AclRule`]s apply to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AclAction {
+    Put,
+    Delete,
+    Subscribe,
+    Query,
+    Reply,
+    Token,
+}
+
+/// The identities of the remote peers an [`AclRule`], a [`DownsamplingRule`] or the
+/// [`AdminWritersConf`] apply to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct SubjectsConf {
+    /// The user names authenticated through `transport/auth/usrpwd`.
+    #[serde(default)]
+    pub users: Vec<String>,
+    /// The PEM encoded public keys authenticated through `transport/auth/pubkey` (PKCS#1 for RSA keys, SubjectPublicKeyInfo otherwise).
+    #[serde(default)]
+    pub public_keys: Vec<String>,
+    /// The zenoh ids of the remote peers.
+    ///
+    /// Unlike the users and the public keys, the zenoh ids are not authenticated: any remote
+    /// peer may claim any of them. They are thus only safe to deny actions.
+    #[serde(default)]
+    pub peer_ids: Vec<String>,
+}
+
+/// The remote peers allowed to change the configuration through the adminspace.
+///
+/// Since the `peer_ids` are not authenticated (see [`SubjectsConf::peer_ids`]), a warning is
+/// logged when some are listed.
+///
+/// Each list of identities may be read or replaced through `adminspace/writers/<list>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdminWritersConf {
+    #[serde(flatten)]
+    pub subjects: SubjectsConf,
+}
+
+impl AdminWritersConf {
+    fn identities(&self, key: &str) -> Option<&Vec<String>> {
+        match key {
+            "users" => Some(&self.subjects.users),
+            "public_keys" => Some(&self.subjects.public_keys),
+            "peer_ids" => Some(&self.subjects.peer_ids),
+            _ => None,
+        }
+    }
+
+    fn identities_mut(&mut self, key: &str) -> Option<&mut Vec<String>> {
+        match key {
+            "users" => Some(&mut self.subjects.users),
+            "public_keys" => Some(&mut self.subjects.public_keys),
+            "peer_ids" => Some(&mut self.subjects.peer_ids),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> validated_struct::ValidatedMapAssociatedTypes<'a> for AdminWritersConf {
+    type Accessor = &'a dyn Any;
+}
+impl validated_struct::ValidatedMap for AdminWritersConf {
+    fn insert<'d, D: serde::Deserializer<'d>>(
+        &mut self,
+        key: &str,
+        deserializer: D,
+    ) -> Result<(), validated_struct::InsertionError>
+    where
+        validated_struct::InsertionError: From<D::Error>,
+    {
+        match validated_struct::split_once(key, '/') {
+            ("", "") => *self = serde::Deserialize::deserialize(deserializer)?,
+            ("", key) => return self.insert(key, deserializer),
+            (key, "") => match self.identities_mut(key) {
+                Some(identities) => *identities = serde::Deserialize::deserialize(deserializer)?,
+                None => return Err("unknown key".into()),
+            },
+            _ => return Err("unknown key".into()),
+        }
+        Ok(())
+    }
+    fn get<'a>(&'a self, key: &str) -> Result<&'a dyn Any, GetError> {
+        match validated_struct::split_once(key, '/') {
+            ("", "") => Ok(self),
+            ("", key) => self.get(key),
+            (key, "") => match self.identities(key) {
+                Some(identities) => Ok(identities),
+                None => Err(GetError::NoMatchingKey),
+            },
+            _ => Err(GetError::NoMatchingKey),
+        }
+    }
+
+    type Keys = Vec<String>;
+    fn keys(&self) -> Self::Keys {
+        vec!["users".into(), "public_keys".into(), "peer_ids".into()]
+    }
+
+    fn get_json(&self, key: &str) -> Result<String, GetError> {
+        match validated_struct::split_once(key, '/') {
+            ("", "") => Ok(serde_json::to_string(self).unwrap()),
+            ("", key) => self.get_json(key),
+            (key, "") => match self.identities(key) {
+                Some(identities) => Ok(serde_json::to_string(identities).unwrap()),
+                None => Err(GetError::NoMatchingKey),
+            },
+            _ => Err(GetError::NoMatchingKey),
+        }
+    }
+}
+
+/// An access control rule.
+///
+/// A rule applies to a remote peer if it was authenticated with one of the listed `users` or
+/// `public_keys`, or if its zenoh id is one of the listed `peer_ids`. A rule with no subject
+/// applies to every remote peer.
+///
+/// Since the `peer_ids` are not authenticated (see [`SubjectsConf::peer_ids`]), a warning is
+/// logged for the rules allowing actions to some of them.
+///
+/// A rule denying an action applies to the key expressions intersecting one of its `key_exprs`,
+/// while a rule allowing an action only applies to the key expressions included in one of them.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AclRule {
+    #[serde(flatten)]
+    pub subjects: SubjectsConf,
+    /// The key expressions the rule applies to.
+    pub key_exprs: Vec<String>,
+    /// The actions the rule applies to.
+    pub actions: Vec<AclAction>,
+    pub permission: Permission,
+}
+
+/// What is done with the publications exceeding the rate of a [`DownsamplingRule`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownsamplingStrategy {
+    /// The publications in excess are dropped.
+    #[default]
+    Drop,
+    /// The publications in excess are dropped except the latest one, which is sent once the rate allows it.
+    Latest,
+}
+
+/// A downsampling rule, limiting the rate of the publications routed to some remote peers.
+///
+/// A rule applies to a remote peer if it was authenticated with one of the listed `users` or
+/// `public_keys`, if its zenoh id is one of the listed `peer_ids`, or if it is reached through
+/// one of the listed network `interfaces` (names or IP addresses). A rule with no subject and
+/// no interface applies to every remote peer.
+///
+/// The rate is enforced independently on each key expression included in one of its `key_exprs`.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownsamplingRule {
+    #[serde(flatten)]
+    pub subjects: SubjectsConf,
+    /// The network interfaces, by name or IP address, through which the remote peers are reached.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// The key expressions the rule applies to.
+    pub key_exprs: Vec<String>,
+    /// The maximum number of publications per second routed on each key expression.
+    pub max_rate: f64,
+    #[serde(default)]
+    pub strategy: DownsamplingStrategy,
+}
+
+/// The users accepted by the user-password authenticator, as "<user>": "<password>".
+///
+/// Just like in the dictionary file, a password may be given as a salted hash in the PHC string
+/// format, e.g. `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<key>` or `$pbkdf2-sha256$i=600000$<salt>$<key>`,
+/// as derived by `UserPasswordAuthenticator::stored_password`.
+///
+/// Users may be added or removed at runtime by putting or deleting `transport/auth/usrpwd/users/<user>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct UsrPwdUsers(HashMap<String, String>);
+
+impl Deref for UsrPwdUsers {
+    type Target = HashMap<String, String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl UsrPwdUsers {
+    pub fn remove(&mut self, user: &str) -> ZResult<()> {
+        match self.0.remove(user) {
+            Some(_) => Ok(()),
+            None => bail!("No user {} to remove", user),
+        }
+    }
+}
+
+impl<'a> validated_struct::ValidatedMapAssociatedTypes<'a> for UsrPwdUsers {
+    type Accessor = &'a dyn Any;
+}
+impl validated_struct::ValidatedMap for UsrPwdUsers {
+    fn insert<'d, D: serde::Deserializer<'d>>(
+        &mut self,
+        key: &str,
+        deserializer: D,
+    ) -> Result<(), validated_struct::InsertionError>
+    where
+        validated_struct::InsertionError: From<D::Error>,
+    {
+        if key.is_empty() {
+            self.0 = serde::Deserialize::deserialize(deserializer)?;
+        } else if key.contains('/') {
+            return Err(format!("Invalid user name: {}", key).into());
+        } else {
+            let password: String = serde::Deserialize::deserialize(deserializer)?;
+            self.0.insert(key.to_string(), password);
+        }
+        Ok(())
+    }
+    fn get<'a>(&'a self, key: &str) -> Result<&'a dyn Any, GetError> {
+        if key.is_empty() {
+            return Ok(&self.0);
+        }
+        match self.0.get(key) {
+            Some(password) => Ok(password),
+            None => Err(GetError::NoMatchingKey),
+        }
+    }
+
+    type Keys = Vec<String>;
+    fn keys(&self) -> Self::Keys {
+        self.0.keys().cloned().collect()
+    }
+
+    fn get_json(&self, key: &str) -> Result<String, GetError> {
+        if key.is_empty() {
+            return Ok(serde_json::to_string(&self.0).unwrap());
+        }
+        match self.0.get(key) {
+            Some(password) => Ok(serde_json::to_string(password).unwrap()),
+            None => Err(GetError::NoMatchingKey),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PluginSearchDirs(Vec<String>);
 impl Default for PluginSearchDirs {
@@ -325,6 +625,69 @@ fn config_deser() {
         .unwrap(),
     )
     .unwrap_err());
+    let config = Config::from_deserializer(
+        &mut json5::Deserializer::from_str(
+            r#"{access_control: { enabled: true, rules: [
+              { users: ["alice"], key_exprs: ["/demo/**"], actions: ["put", "subscribe"], permission: "allow" }
+            ]}}"#,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(*config.access_control().enabled());
+    assert_eq!(
+        config.access_control().rules()[0].subjects.users,
+        vec!["alice"]
+    );
+    assert_eq!(
+        config.access_control().rules()[0].actions,
+        vec![AclAction::Put, AclAction::Subscribe]
+    );
+    let config = Config::from_deserializer(
+        &mut json5::Deserializer::from_str(
+            r#"{adminspace: { write: true, writers: { users: ["admin"] }}}"#,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(*config.adminspace().write());
+    assert_eq!(config.adminspace().writers().subjects.users, vec!["admin"]);
+    std::mem::drop(
+        Config::from_deserializer(
+            &mut json5::Deserializer::from_str(r#"{adminspace: { writers: { user: ["admin"] }}}"#)
+                .unwrap(),
+        )
+        .unwrap_err(),
+    );
+    std::mem::drop(Config::from_deserializer(
+        &mut json5::Deserializer::from_str(
+            r#"{access_control: { rules: [{ user: ["alice"], key_exprs: ["/demo/**"], actions: ["put"], permission: "allow" }]}}"#,
+        )
+        .unwrap(),
+    )
+    .unwrap_err());
+    std::mem::drop(Config::from_deserializer(
+        &mut json5::Deserializer::from_str(
+            r#"{access_control: { rules: [{ key_exprs: ["/demo/**"], actions: ["write"], permission: "allow" }]}}"#,
+        )
+        .unwrap(),
+    )
+    .unwrap_err());
+    let config = Config::from_deserializer(
+        &mut json5::Deserializer::from_str(
+            r#"{downsampling: { rules: [
+              { interfaces: ["eth0"], key_exprs: ["/demo/**"], max_rate: 0.5, strategy: "latest" }
+            ]}}"#,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(config.downsampling().rules()[0].interfaces, vec!["eth0"]);
+    assert_eq!(config.downsampling().rules()[0].max_rate, 0.5);
+    assert_eq!(
+        config.downsampling().rules()[0].strategy,
+        DownsamplingStrategy::Latest
+    );
     dbg!(Config::from_file("../../EXAMPLE_CONFIG.json5").unwrap());
 }
 
@@ -344,11 +707,17 @@ impl Config {
     }
 
     pub fn remove<K: AsRef<str>>(&mut self, key: K) -> ZResult<()> {
+        const USERS: &str = "transport/auth/usrpwd/users/";
+
         let key = key.as_ref();
         let key = key.strip_prefix('/').unwrap_or(key);
+        if let Some(user) = key.strip_prefix(USERS) {
+            return self.transport.auth.usrpwd.users.remove(user);
+        }
         if !key.starts_with("plugins/") {
             bail!(
-                "Removal of values from Config is only supported for keys starting with `plugins/`"
+                "Removal of values from Config is only supported for keys starting with `plugins/` or `{}`",
+                USERS
             )
         }
         self.plugins.remove(&key["plugins/".len()..])
@@ -431,6 +800,35 @@ fn config_from_json() {
         .unwrap();
     dbg!(std::mem::size_of_val(&config));
     println!("{}", serde_json::to_string_pretty(&config).unwrap());
+    config
+        .insert(
+            "transport/auth/usrpwd/users/alice",
+            &mut from_str(r#""pwd""#),
+        )
+        .unwrap();
+    assert_eq!(
+        config
+            .get_json("transport/auth/usrpwd/users/alice")
+            .unwrap(),
+        r#""pwd""#
+    );
+    assert!(config
+        .insert("transport/auth/usrpwd/users/a/b", &mut from_str(r#""pwd""#))
+        .is_err());
+    config.remove("transport/auth/usrpwd/users/alice").unwrap();
+    assert!(config.transport().auth().usrpwd().users().is_empty());
+    assert!(config.remove("transport/auth/usrpwd/users/alice").is_err());
+    config
+        .insert("adminspace/writers/users", &mut from_str(r#"["admin"]"#))
+        .unwrap();
+    assert_eq!(config.adminspace().writers().subjects.users, vec!["admin"]);
+    assert_eq!(
+        config.get_json("adminspace/writers/users").unwrap(),
+        r#"["admin"]"#
+    );
+    assert!(config
+        .insert("adminspace/writers/user", &mut from_str(r#"["admin"]"#))
+        .is_err());
 }
 
 pub type Notification = Arc<str>;
@@ -13,6 +13,7 @@
 //
 use hmac::{Hmac, Mac, NewMac};
 use sha3::{Digest, Sha3_256};
+use subtle::ConstantTimeEq;
 use zenoh_core::Result as ZResult;
 
 pub fn sign(key: &[u8], data: &[u8]) -> ZResult<Vec<u8>> {
@@ -24,3 +25,8 @@ pub fn sign(key: &[u8], data: &[u8]) -> ZResult<Vec<u8>> {
 pub fn digest(data: &[u8]) -> Vec<u8> {
     Sha3_256::digest(data).as_slice().to_vec()
 }
+
+/// Compare two HMACs, or two digests, in a time independent of their content.
+pub fn eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
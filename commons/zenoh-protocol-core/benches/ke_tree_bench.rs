@@ -0,0 +1,98 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use itertools::iproduct;
+use rand::{Rng, SeedableRng};
+use zenoh_protocol_core::key_expr::intersect;
+use zenoh_protocol_core::KeTree;
+
+fn mk_route([tld, site, room, robot, sensor]: [&str; 5]) -> String {
+    format!("/{}/{}/{}/{}/{}", tld, site, room, robot, sensor)
+}
+
+// Returns the existing resource keys and `n` subscriptions on them.
+fn mk_routes(n: usize) -> (Vec<String>, Vec<String>) {
+    let tlds = ["com", "org", "fr"];
+    let sites = (1..10).map(|n| format!("site_{}", n)).collect::<Vec<_>>();
+    let rooms = (1..10).map(|n| format!("room_{}", n)).collect::<Vec<_>>();
+    let robots = (1..10).map(|n| format!("robot_{}", n)).collect::<Vec<_>>();
+    let sensors = [
+        "temperature",
+        "positition_X",
+        "positition_Y",
+        "position_Z",
+        "battery",
+    ];
+    let all_existing = iproduct!(tlds, &sites, &rooms, &robots, sensors)
+        .map(|(tld, site, room, robot, sensor)| [tld, site, room, robot, sensor])
+        .collect::<Vec<_>>();
+    let mut rng = rand::rngs::StdRng::from_seed([32; 32]);
+    let mut routes = vec!["/**".to_owned(), "/*/**".to_owned()];
+    routes.push("/**/site_0/**".to_owned());
+    routes.push("/**/site_1/**".to_owned());
+    routes.push("/**/site_5/**".to_owned());
+    routes.push("/**/site_9/**".to_owned());
+    for _ in 0..(n / 2) {
+        let selected_route_id: usize = rng.gen_range(0..all_existing.len());
+        let mut selected_route_components = all_existing[selected_route_id];
+        routes.push(mk_route(selected_route_components));
+        selected_route_components[4] = "*";
+        routes.push(mk_route(selected_route_components));
+    }
+    let all_existing = all_existing.into_iter().map(mk_route).collect::<Vec<_>>();
+    (all_existing, routes)
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    for n in [100, 1000, 10000] {
+        let (all_existing, routes) = mk_routes(n);
+        let publications = all_existing.iter().step_by(97).collect::<Vec<_>>();
+
+        c.bench_function(&format!("bench_ke_tree_linear_matching_{}", n), |b| {
+            b.iter(|| {
+                for publication in &publications {
+                    black_box(routes.iter().filter(|r| intersect(r, publication)).count());
+                }
+            })
+        });
+
+        let mut tree = KeTree::new();
+        for (i, route) in routes.iter().enumerate() {
+            tree.insert(route, i);
+        }
+        c.bench_function(&format!("bench_ke_tree_matching_{}", n), |b| {
+            b.iter(|| {
+                for publication in &publications {
+                    black_box(tree.intersecting(publication).len());
+                }
+            })
+        });
+
+        c.bench_function(&format!("bench_ke_tree_insert_remove_{}", n), |b| {
+            b.iter(|| {
+                let mut tree = KeTree::new();
+                for (i, route) in routes.iter().enumerate() {
+                    tree.insert(route, i);
+                }
+                for (i, route) in routes.iter().enumerate() {
+                    tree.remove(route, |v| *v == i);
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
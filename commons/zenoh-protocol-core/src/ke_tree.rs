@@ -0,0 +1,202 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::key_expr::chunk_intersect;
+use std::collections::{HashMap, HashSet};
+
+// Splits a key expression in its `/` separated chunks, ignoring a trailing `/`
+// the same way [`intersect`](crate::key_expr::intersect) does.
+fn chunks(key_expr: &str) -> Vec<&str> {
+    let key_expr = match key_expr.strip_suffix('/') {
+        Some(stripped) if !stripped.is_empty() => stripped,
+        _ => key_expr,
+    };
+    key_expr.split('/').collect()
+}
+
+#[inline(always)]
+fn is_wild(chunk: &str) -> bool {
+    chunk.contains('*')
+}
+
+struct Node<V> {
+    values: Vec<V>,
+    // Children indexed by a chunk without any wildcard.
+    children: HashMap<String, Node<V>>,
+    // Children indexed by a chunk containing `*` or equal to `**`.
+    wild_children: HashMap<String, Node<V>>,
+}
+
+impl<V> Node<V> {
+    fn new() -> Self {
+        Node {
+            values: vec![],
+            children: HashMap::new(),
+            wild_children: HashMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.children.is_empty() && self.wild_children.is_empty()
+    }
+
+    fn children_for_mut(&mut self, chunk: &str) -> &mut HashMap<String, Node<V>> {
+        if is_wild(chunk) {
+            &mut self.wild_children
+        } else {
+            &mut self.children
+        }
+    }
+
+    fn all_children(&self) -> impl Iterator<Item = &Node<V>> {
+        self.children.values().chain(self.wild_children.values())
+    }
+
+    fn remove<F: FnMut(&V) -> bool>(&mut self, chunks: &[&str], predicate: &mut F) -> Vec<V> {
+        match chunks.split_first() {
+            None => {
+                let (removed, kept) = std::mem::take(&mut self.values)
+                    .into_iter()
+                    .partition(|v| predicate(v));
+                self.values = kept;
+                removed
+            }
+            Some((chunk, rest)) => {
+                let children = self.children_for_mut(chunk);
+                match children.get_mut(*chunk) {
+                    Some(child) => {
+                        let removed = child.remove(rest, predicate);
+                        if child.is_empty() {
+                            children.remove(*chunk);
+                        }
+                        removed
+                    }
+                    None => vec![],
+                }
+            }
+        }
+    }
+}
+
+/// An index of values by key expression.
+///
+/// Each value is stored under the key expression it was inserted with, and
+/// [`intersecting`](KeTree::intersecting) returns all the values whose key expression
+/// intersects a given one. Key expressions are stored chunk by chunk, so that only the
+/// branches that may match (i.e. the ones with an equal chunk or a wildcard chunk) are
+/// explored, instead of testing every stored key expression.
+///
+/// The `*`, `**` and in-chunk wildcards (e.g. `/a*b`) are supported on both sides,
+/// with the same semantic as [`intersect`](crate::key_expr::intersect).
+pub struct KeTree<V> {
+    root: Node<V>,
+    len: usize,
+}
+
+impl<V> KeTree<V> {
+    pub fn new() -> Self {
+        KeTree {
+            root: Node::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of values in the tree.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts `value` under `key_expr`.
+    ///
+    /// Several values may be inserted under the same key expression.
+    pub fn insert(&mut self, key_expr: &str, value: V) {
+        let mut node = &mut self.root;
+        for chunk in chunks(key_expr) {
+            node = node
+                .children_for_mut(chunk)
+                .entry(chunk.to_string())
+                .or_insert_with(Node::new);
+        }
+        node.values.push(value);
+        self.len += 1;
+    }
+
+    /// Removes and returns the values inserted under `key_expr` for which `predicate` returns `true`.
+    pub fn remove<F: FnMut(&V) -> bool>(&mut self, key_expr: &str, mut predicate: F) -> Vec<V> {
+        let removed = self.root.remove(&chunks(key_expr), &mut predicate);
+        self.len -= removed.len();
+        removed
+    }
+
+    /// Returns the values whose key expression intersects `key_expr`.
+    ///
+    /// Each value is returned at most once, in no particular order.
+    pub fn intersecting<'a>(&'a self, key_expr: &str) -> Vec<&'a V> {
+        let chunks = chunks(key_expr);
+        let mut result = vec![];
+        // A state is a node (i.e. the chunks consumed in the tree) and the number of
+        // chunks consumed in `key_expr`. Each state is explored only once, which keeps
+        // `**` from exploring the same branches over and over.
+        let mut visited: HashSet<(*const Node<V>, usize)> = HashSet::new();
+        let mut stack = vec![(&self.root, 0)];
+        while let Some((node, i)) = stack.pop() {
+            if !visited.insert((node as *const Node<V>, i)) {
+                continue;
+            }
+            match chunks.get(i) {
+                None => {
+                    result.extend(node.values.iter());
+                    // A trailing `**` in the tree also matches an empty suffix.
+                    if let Some(child) = node.wild_children.get("**") {
+                        stack.push((child, i));
+                    }
+                }
+                Some(&"**") => {
+                    stack.push((node, i + 1));
+                    stack.extend(node.all_children().map(|child| (child, i)));
+                }
+                Some(chunk) => {
+                    if is_wild(chunk) {
+                        for (c, child) in &node.children {
+                            if chunk_intersect(c.as_bytes(), chunk.as_bytes()) {
+                                stack.push((child, i + 1));
+                            }
+                        }
+                    } else if let Some(child) = node.children.get(*chunk) {
+                        stack.push((child, i + 1));
+                    }
+                    for (c, child) in &node.wild_children {
+                        if c == "**" {
+                            stack.extend((i..=chunks.len()).map(|j| (child, j)));
+                        } else if chunk_intersect(c.as_bytes(), chunk.as_bytes()) {
+                            stack.push((child, i + 1));
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<V> Default for KeTree<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -118,7 +118,7 @@ fn chunk_it_intersect(mut it1: &[u8], mut it2: &[u8]) -> bool {
     it1.is_empty() && it2.is_empty() || it1 == b"*" || it2 == b"*"
 }
 #[inline(always)]
-fn chunk_intersect(c1: &[u8], c2: &[u8]) -> bool {
+pub(crate) fn chunk_intersect(c1: &[u8], c2: &[u8]) -> bool {
     if c1 == c2 {
         return true;
     }
@@ -214,7 +214,8 @@ DEFINE_INCLUDE!(res_include, end, wild, advance, chunk_include);
 /// Retruns `true` if the first key expression (`this`) includes the second key expression (`sub`).
 ///
 /// I.e. if there exists no resource key (with no wildcards) that matches
-/// `sub` but does not match `this`.
+/// `sub` but does not match `this`. The key expressions are expected in
+/// canonical form (see [`canonize`]).
 #[inline(always)]
 pub fn include(this: &str, sub: &str) -> bool {
     res_include(this.as_bytes(), sub.as_bytes())
@@ -231,6 +232,79 @@ pub fn matches(s1: &str, s2: &str) -> bool {
     }
 }
 
+/// The characters that have a special meaning in zenoh and can't appear in a key expression.
+pub const RESERVED_CHARS: [char; 4] = ['?', '[', ']', '#'];
+
+/// Returns the canonical form of the key expression `expr`, or an error if it is invalid.
+///
+/// A valid key expression contains none of the [`RESERVED_CHARS`] and no empty chunk
+/// (a leading `/` is allowed). Its canonical form has no trailing `/`, and has any sequence
+/// of `**` chunks collapsed into a single one (e.g. `/a/**/**/b/` becomes `/a/**/b`).
+pub fn canonize(expr: &str) -> ZResult<Cow<'_, str>> {
+    if let Some(c) = expr.chars().find(|c| RESERVED_CHARS.contains(c)) {
+        bail!(
+            "Invalid key expression '{}': '{}' is a reserved character",
+            expr,
+            c
+        )
+    }
+    let trimmed = expr.strip_suffix('/').unwrap_or(expr);
+    let chunks = trimmed.strip_prefix('/').unwrap_or(trimmed);
+    if chunks.is_empty() {
+        bail!("Invalid key expression '{}': empty key expression", expr)
+    }
+    if chunks.split('/').any(str::is_empty) {
+        bail!("Invalid key expression '{}': empty chunk", expr)
+    }
+    let mut canonical = String::with_capacity(trimmed.len());
+    let mut previous = None;
+    for (i, chunk) in trimmed.split('/').enumerate() {
+        if chunk == "**" && previous == Some("**") {
+            continue;
+        }
+        if i > 0 {
+            canonical.push('/');
+        }
+        canonical.push_str(chunk);
+        previous = Some(chunk);
+    }
+    if canonical.len() == trimmed.len() {
+        Ok(Cow::Borrowed(trimmed))
+    } else {
+        Ok(Cow::Owned(canonical))
+    }
+}
+
+/// The relation between the sets of keys matched by two key expressions.
+///
+/// The levels are ordered, each one implying the previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyExprRelation {
+    /// No key matches both key expressions.
+    Disjoint,
+    /// Some keys match both key expressions.
+    Intersects,
+    /// All the keys matching the second key expression match the first one.
+    Includes,
+    /// Both key expressions match the same keys.
+    Equals,
+}
+
+/// Returns the relation of the key expression `this` to the key expression `other`.
+pub fn relation(this: &str, other: &str) -> KeyExprRelation {
+    if include(this, other) {
+        if include(other, this) {
+            KeyExprRelation::Equals
+        } else {
+            KeyExprRelation::Includes
+        }
+    } else if intersect(this, other) {
+        KeyExprRelation::Intersects
+    } else {
+        KeyExprRelation::Disjoint
+    }
+}
+
 /// A zenoh **resource** is represented by a pair composed by a **key** and a
 /// **value**, such as, ```(/car/telemetry/speed, 320)```.  A **resource key**
 /// is an arbitrary array of characters, with the exclusion of the symbols
@@ -244,6 +318,9 @@ pub fn matches(s1: &str, s2: &str) -> bool {
 /// character, while the ```**``` expands to  strings that may also include the
 /// ```/``` character.  
 ///
+/// Key expressions built with [`KeyExpr::new_checked`] are validated and
+/// canonicalized (see [`canonize`]), while the `From` conversions accept any string.
+///
 /// Finally, it is worth mentioning that for time and space efficiency matters,
 /// zenoh will automatically map key expressions to small integers. The mapping is automatic,
 /// but it can be triggered excplicily by with `zenoh::Session::declare_expr()`.
@@ -263,6 +340,29 @@ pub struct KeyExpr<'a> {
 }
 
 impl<'a> KeyExpr<'a> {
+    /// Builds a global key expression from `expr`, after validating and canonicalizing it
+    /// (see [`canonize`]).
+    ///
+    /// This is not a `TryFrom` implementation: the `From<&str>` and `From<String>` conversions
+    /// already provide an infallible `TryFrom` through the blanket implementation of the
+    /// standard library, and an inherent `try_from` method would shadow it.
+    ///
+    /// # Examples
+    /// ```
+    /// use zenoh_protocol_core::KeyExpr;
+    ///
+    /// assert_eq!(KeyExpr::new_checked("/a/**/**/b/").unwrap().as_str(), "/a/**/b");
+    /// assert!(KeyExpr::new_checked("/a//b").is_err());
+    /// assert!(KeyExpr::new_checked("/a/b?x").is_err());
+    /// ```
+    pub fn new_checked<S: Into<Cow<'a, str>>>(expr: S) -> ZResult<KeyExpr<'a>> {
+        let suffix = match expr.into() {
+            Cow::Borrowed(expr) => canonize(expr)?,
+            Cow::Owned(expr) => canonize(&expr)?.into_owned().into(),
+        };
+        Ok(KeyExpr { scope: 0, suffix })
+    }
+
     pub fn as_str(&'a self) -> &'a str {
         if self.scope == 0 {
             self.suffix.as_ref()
@@ -314,6 +414,37 @@ impl<'a> KeyExpr<'a> {
         }
         self
     }
+
+    /// Returns the relation of this key expression to `other`.
+    ///
+    /// Scoped key expressions can't be compared without resolving their scope:
+    /// `None` is returned unless they are identical.
+    pub fn relation_to(&self, other: &KeyExpr) -> Option<KeyExprRelation> {
+        match (self.try_as_str(), other.try_as_str()) {
+            (Ok(this), Ok(other)) => Some(relation(this, other)),
+            _ if self == other => Some(KeyExprRelation::Equals),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if all the keys matching `other` match this key expression.
+    ///
+    /// See [`relation_to`](KeyExpr::relation_to) for scoped key expressions.
+    pub fn includes(&self, other: &KeyExpr) -> Option<bool> {
+        self.relation_to(other)
+            .map(|relation| relation >= KeyExprRelation::Includes)
+    }
+
+    /// Returns `true` if some keys match both this key expression and `other`.
+    ///
+    /// See [`relation_to`](KeyExpr::relation_to) for scoped key expressions.
+    pub fn intersects(&self, other: &KeyExpr) -> Option<bool> {
+        match (self.try_as_str(), other.try_as_str()) {
+            (Ok(this), Ok(other)) => Some(intersect(this, other)),
+            _ if self == other => Some(true),
+            _ => None,
+        }
+    }
 }
 
 impl TryInto<String> for KeyExpr<'_> {
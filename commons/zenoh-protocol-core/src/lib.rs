@@ -44,7 +44,9 @@ pub type ExprId = ZInt;
 pub const EMPTY_EXPR_ID: ExprId = 0;
 
 pub mod key_expr;
-pub use crate::key_expr::KeyExpr;
+pub use crate::key_expr::{KeyExpr, KeyExprRelation};
+pub mod ke_tree;
+pub use crate::ke_tree::KeTree;
 
 mod encoding;
 pub use encoding::{Encoding, KnownEncoding};
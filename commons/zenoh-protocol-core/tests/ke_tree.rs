@@ -0,0 +1,90 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use rand::{Rng, SeedableRng};
+use zenoh_protocol_core::key_expr::intersect;
+use zenoh_protocol_core::KeTree;
+
+fn random_key_expr(rng: &mut impl Rng) -> String {
+    const CHUNKS: [&str; 9] = ["a", "b", "ab", "*", "**", "a*", "*b", "a*b", "@"];
+    loop {
+        let mut key_expr = String::new();
+        for _ in 0..rng.gen_range(1..6) {
+            key_expr.push('/');
+            key_expr.push_str(CHUNKS[rng.gen_range(0..CHUNKS.len())]);
+        }
+        if rng.gen_bool(0.1) {
+            key_expr.push('/');
+        }
+        // `intersect` does not consider `**/**` as matching an empty suffix
+        if !key_expr.contains("**/**") {
+            return key_expr;
+        }
+    }
+}
+
+fn intersecting(tree: &KeTree<usize>, key_expr: &str) -> Vec<usize> {
+    let mut values = tree
+        .intersecting(key_expr)
+        .into_iter()
+        .copied()
+        .collect::<Vec<_>>();
+    values.sort_unstable();
+    values
+}
+
+#[test]
+fn ke_tree_intersecting_test() {
+    let mut rng = rand::rngs::StdRng::from_seed([42; 32]);
+    for _ in 0..100 {
+        let key_exprs = (0..50)
+            .map(|_| random_key_expr(&mut rng))
+            .collect::<Vec<_>>();
+        let mut tree = KeTree::new();
+        for (i, key_expr) in key_exprs.iter().enumerate() {
+            tree.insert(key_expr, i);
+        }
+        assert_eq!(tree.len(), key_exprs.len());
+        for _ in 0..50 {
+            let query = random_key_expr(&mut rng);
+            let expected = (0..key_exprs.len())
+                .filter(|i| intersect(&key_exprs[*i], &query))
+                .collect::<Vec<_>>();
+            let found = intersecting(&tree, &query);
+            assert_eq!(found, expected, "intersecting({})", query);
+        }
+    }
+}
+
+#[test]
+fn ke_tree_remove_test() {
+    let mut tree = KeTree::new();
+    tree.insert("/a/b", 0);
+    tree.insert("/a/b", 1);
+    tree.insert("/a/*", 2);
+    tree.insert("/**", 3);
+    assert_eq!(intersecting(&tree, "/a/b"), [0, 1, 2, 3]);
+
+    assert_eq!(tree.remove("/a/b", |v| *v == 1), [1]);
+    assert_eq!(tree.remove("/a/c", |_| true), Vec::<usize>::new());
+    assert_eq!(tree.remove("/a/*", |v| *v == 3), Vec::<usize>::new());
+    assert_eq!(tree.len(), 3);
+    assert_eq!(intersecting(&tree, "/a/b"), [0, 2, 3]);
+
+    assert_eq!(tree.remove("/**", |_| true), [3]);
+    assert_eq!(intersecting(&tree, "/a/c"), [2]);
+    assert_eq!(tree.remove("/a/b", |_| true), [0]);
+    assert_eq!(tree.remove("/a/*", |_| true), [2]);
+    assert!(tree.is_empty());
+    assert!(tree.intersecting("/**").is_empty());
+}
@@ -11,7 +11,9 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
-use zenoh_protocol_core::key_expr::intersect;
+use rand::{Rng, SeedableRng};
+use zenoh_protocol_core::key_expr::{include, intersect};
+use zenoh_protocol_core::{ExprId, KeyExpr, KeyExprRelation};
 
 #[test]
 fn key_expr_test() {
@@ -64,3 +66,109 @@ fn key_expr_test() {
     assert!(!intersect("/x/c*", "/x/abc*"));
     assert!(!intersect("/x/*d", "/x/*e"));
 }
+
+#[test]
+fn key_expr_canonize_test() {
+    fn canon(expr: &str) -> String {
+        KeyExpr::new_checked(expr).unwrap().as_str().to_string()
+    }
+    assert_eq!(canon("/a/b"), "/a/b");
+    assert_eq!(canon("/a/b/"), "/a/b");
+    assert_eq!(canon("a/b"), "a/b");
+    assert_eq!(canon("/**/**"), "/**");
+    assert_eq!(canon("/a/**/**/**/b/**/**/"), "/a/**/b/**");
+    assert_eq!(canon("/a/**/*/**"), "/a/**/*/**");
+    assert_eq!(canon("/a**/**/**b"), "/a**/**/**b");
+    assert_eq!(canon(&String::from("/x/**/**")), "/x/**");
+    assert!(KeyExpr::new_checked("").is_err());
+    assert!(KeyExpr::new_checked("/").is_err());
+    assert!(KeyExpr::new_checked("//").is_err());
+    assert!(KeyExpr::new_checked("/a//b").is_err());
+    assert!(KeyExpr::new_checked("/a/b//").is_err());
+    assert!(KeyExpr::new_checked("/a?b").is_err());
+    assert!(KeyExpr::new_checked("/a/[b]").is_err());
+    assert!(KeyExpr::new_checked("/a#b".to_string()).is_err());
+}
+
+#[test]
+fn key_expr_relation_test() {
+    fn relation(l: &str, r: &str) -> KeyExprRelation {
+        KeyExpr::new_checked(l)
+            .unwrap()
+            .relation_to(&KeyExpr::new_checked(r).unwrap())
+            .unwrap()
+    }
+    assert_eq!(relation("/a/b", "/a/b/"), KeyExprRelation::Equals);
+    assert_eq!(relation("/a/**/**", "/a/**"), KeyExprRelation::Equals);
+    assert_eq!(relation("/a/*", "/a/b"), KeyExprRelation::Includes);
+    assert_eq!(relation("/a/b", "/a/*"), KeyExprRelation::Intersects);
+    assert_eq!(relation("/a/*b", "/a/b*"), KeyExprRelation::Intersects);
+    assert_eq!(relation("/a/*", "/b/*"), KeyExprRelation::Disjoint);
+
+    let scope: ExprId = 1;
+    let scoped = KeyExpr::from(scope).with_suffix("/b");
+    assert_eq!(scoped.relation_to(&scoped), Some(KeyExprRelation::Equals));
+    assert_eq!(scoped.intersects(&scoped), Some(true));
+    // The relations to a scoped key expression are unknown until its scope is resolved
+    let any = KeyExpr::from("/**");
+    assert_eq!(scoped.relation_to(&any), None);
+    assert_eq!(any.relation_to(&scoped), None);
+    assert_eq!(any.includes(&scoped), None);
+    assert_eq!(scoped.intersects(&any), None);
+}
+
+fn random_key_expr(rng: &mut impl Rng, chunks: &[&str]) -> String {
+    let mut key_expr = String::new();
+    for _ in 0..rng.gen_range(1..6) {
+        key_expr.push('/');
+        key_expr.push_str(chunks[rng.gen_range(0..chunks.len())]);
+    }
+    if rng.gen_bool(0.2) {
+        key_expr.push('/');
+    }
+    key_expr
+}
+
+#[test]
+fn key_expr_properties_test() {
+    const WILD_CHUNKS: [&str; 8] = ["a", "b", "ab", "*", "**", "a*", "*b", "a*b"];
+    const KEY_CHUNKS: [&str; 3] = ["a", "b", "ab"];
+    let mut rng = rand::rngs::StdRng::from_seed([7; 32]);
+    for _ in 0..5000 {
+        let raw_l = random_key_expr(&mut rng, &WILD_CHUNKS);
+        let raw_r = random_key_expr(&mut rng, &WILD_CHUNKS);
+        let l = KeyExpr::new_checked(raw_l.as_str()).unwrap();
+        let r = KeyExpr::new_checked(raw_r).unwrap();
+
+        // canonicalization is idempotent and preserves the set of matched keys
+        assert_eq!(
+            KeyExpr::new_checked(l.as_str()).unwrap().as_str(),
+            l.as_str()
+        );
+        assert!(include(&raw_l, l.as_str()) && include(l.as_str(), &raw_l));
+
+        // the methods agree with the current matcher
+        let (intersects, includes) = (l.intersects(&r).unwrap(), l.includes(&r).unwrap());
+        assert_eq!(intersects, intersect(l.as_str(), r.as_str()));
+        assert_eq!(includes, include(l.as_str(), r.as_str()));
+        let relation = l.relation_to(&r).unwrap();
+        assert_eq!(
+            relation == KeyExprRelation::Equals,
+            includes && r.includes(&l).unwrap()
+        );
+        assert_eq!(relation >= KeyExprRelation::Includes, includes);
+        assert_eq!(relation >= KeyExprRelation::Intersects, intersects);
+
+        // and with the keys they match
+        for _ in 0..10 {
+            let key = KeyExpr::new_checked(random_key_expr(&mut rng, &KEY_CHUNKS)).unwrap();
+            let (l_key, r_key) = (l.intersects(&key).unwrap(), r.intersects(&key).unwrap());
+            if includes && r_key {
+                assert!(l_key, "{} includes {} but not {}", l, r, key);
+            }
+            if l_key && r_key {
+                assert!(intersects, "{} and {} both match {}", l, r, key);
+            }
+        }
+    }
+}
@@ -127,6 +127,7 @@ pub mod tmsg {
         use super::ZInt;
 
         pub const QOS: ZInt = 1 << 0; // 0x01 QoS       if PRIORITY==1 then the transport supports QoS
+        pub const COMPRESSION: ZInt = 1 << 1; // 0x02 Compression if COMPRESSION==1 then the link supports batch compression
     }
 
     pub mod join_options {
@@ -183,7 +184,9 @@ pub mod zmsg {
 
     // Zenoh message flags
     pub mod flag {
+        pub const B: u8 = 1 << 6; // 0x40 QueryBody     if B==1 then the query body is present
         pub const D: u8 = 1 << 5; // 0x20 Drop          if D==1 then the message can be dropped
+        pub const E: u8 = 1 << 6; // 0x40 Error         if E==1 then the reply is an error
         pub const F: u8 = 1 << 5; // 0x20 Final         if F==1 then this is the final message (e.g., ReplyContext, Pull)
         pub const I: u8 = 1 << 6; // 0x40 DataInfo      if I==1 then DataInfo is present
         pub const K: u8 = 1 << 7; // 0x80 KeySuffix     if K==1 then key_expr has suffix
@@ -226,11 +229,13 @@ pub mod zmsg {
             pub const PUBLISHER: u8 = 0x02;
             pub const SUBSCRIBER: u8 = 0x03;
             pub const QUERYABLE: u8 = 0x04;
+            pub const TOKEN: u8 = 0x05;
 
             pub const FORGET_RESOURCE: u8 = 0x11;
             pub const FORGET_PUBLISHER: u8 = 0x12;
             pub const FORGET_SUBSCRIBER: u8 = 0x13;
             pub const FORGET_QUERYABLE: u8 = 0x14;
+            pub const FORGET_TOKEN: u8 = 0x15;
 
             // SubModes
             pub const MODE_PUSH: u8 = 0x00;
@@ -385,7 +390,7 @@ impl Attachment {
 ///
 ///  7 6 5 4 3 2 1 0
 /// +-+-+-+-+-+-+-+-+
-/// |X|X|F|  R_CTX  |
+/// |X|E|F|  R_CTX  |
 /// +-+-+-+---------+
 /// ~      qid      ~
 /// +---------------+
@@ -395,6 +400,7 @@ impl Attachment {
 /// +---------------+
 ///
 /// - if F==1 then the message is a REPLY_FINAL
+/// - if E==1 then the payload of the decorated **Data** is an error raised by the replier
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReplierInfo {
@@ -405,6 +411,7 @@ pub struct ReplierInfo {
 pub struct ReplyContext {
     pub qid: ZInt,
     pub replier: Option<ReplierInfo>,
+    pub is_error: bool,
 }
 
 impl Header for ReplyContext {
@@ -414,6 +421,9 @@ impl Header for ReplyContext {
         if self.is_final() {
             header |= zmsg::flag::F;
         }
+        if self.is_error {
+            header |= zmsg::flag::E;
+        }
         header
     }
 }
@@ -422,7 +432,20 @@ impl ReplyContext {
     // Note: id replier_id=None flag F is set, meaning it's a REPLY_FINAL
     #[inline(always)]
     pub fn new(qid: ZInt, replier: Option<ReplierInfo>) -> ReplyContext {
-        ReplyContext { qid, replier }
+        ReplyContext {
+            qid,
+            replier,
+            is_error: false,
+        }
+    }
+
+    #[inline(always)]
+    pub fn new_error(qid: ZInt, replier: ReplierInfo) -> ReplyContext {
+        ReplyContext {
+            qid,
+            replier: Some(replier),
+            is_error: true,
+        }
     }
 
     #[inline(always)]
@@ -684,6 +707,8 @@ pub enum Declaration {
     ForgetSubscriber(ForgetSubscriber),
     Queryable(Queryable),
     ForgetQueryable(ForgetQueryable),
+    Token(Token),
+    ForgetToken(ForgetToken),
 }
 
 /// ```text
@@ -903,6 +928,54 @@ impl Header for ForgetQueryable {
     }
 }
 
+/// ```text
+///  7 6 5 4 3 2 1 0
+/// +-+-+-+-+-+-+-+-+
+/// |K|X|X|  TOKEN  |
+/// +---------------+
+/// ~    KeyExpr     ~ if K==1 then key_expr has suffix
+/// +---------------+
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub key: KeyExpr<'static>,
+}
+
+impl Header for Token {
+    #[inline(always)]
+    fn header(&self) -> u8 {
+        let mut header = zmsg::declaration::id::TOKEN;
+        if self.key.has_suffix() {
+            header |= zmsg::flag::K;
+        }
+        header
+    }
+}
+
+/// ```text
+///  7 6 5 4 3 2 1 0
+/// +-+-+-+-+-+-+-+-+
+/// |K|X|X| F_TOKEN |
+/// +---------------+
+/// ~    KeyExpr     ~ if K==1 then key_expr has suffix
+/// +---------------+
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForgetToken {
+    pub key: KeyExpr<'static>,
+}
+
+impl Header for ForgetToken {
+    #[inline(always)]
+    fn header(&self) -> u8 {
+        let mut header = zmsg::declaration::id::FORGET_TOKEN;
+        if self.key.has_suffix() {
+            header |= zmsg::flag::K;
+        }
+        header
+    }
+}
+
 /// ```text
 ///  7 6 5 4 3 2 1 0
 /// +-+-+-+-+-+-+-+-+
@@ -969,7 +1042,7 @@ impl Header for Pull {
 /// ```text
 ///  7 6 5 4 3 2 1 0
 /// +-+-+-+-+-+-+-+-+
-/// |K|X|T|  QUERY  |
+/// |K|B|T|  QUERY  |
 /// +-+-+-+---------+
 /// ~    KeyExpr     ~ if K==1 then key_expr has suffix
 /// +---------------+
@@ -981,6 +1054,8 @@ impl Header for Pull {
 /// +---------------+
 /// ~ consolidation ~
 /// +---------------+
+/// ~   QueryBody   ~ if B==1
+/// +---------------+
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct Query {
@@ -989,6 +1064,23 @@ pub struct Query {
     pub qid: ZInt,
     pub target: Option<QueryTarget>,
     pub consolidation: ConsolidationStrategy,
+    pub body: Option<QueryBody>,
+}
+
+/// # QueryBody
+///
+/// ```text
+///  7 6 5 4 3 2 1 0
+/// +-+-+-+-+-+-+-+-+
+/// ~   DataInfo    ~
+/// +---------------+
+/// ~    Payload    ~
+/// +---------------+
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryBody {
+    pub data_info: DataInfo,
+    pub payload: ZBuf,
 }
 
 impl Header for Query {
@@ -998,6 +1090,9 @@ impl Header for Query {
         if self.target.is_some() {
             header |= zmsg::flag::T;
         }
+        if self.body.is_some() {
+            header |= zmsg::flag::B;
+        }
         if self.key.has_suffix() {
             header |= zmsg::flag::K;
         }
@@ -1202,6 +1297,7 @@ impl ZenohMessage {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[inline(always)]
     pub fn make_query(
         key: KeyExpr<'static>,
@@ -1209,6 +1305,7 @@ impl ZenohMessage {
         qid: ZInt,
         target: Option<QueryTarget>,
         consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
         routing_context: Option<RoutingContext>,
         attachment: Option<Attachment>,
     ) -> ZenohMessage {
@@ -1219,6 +1316,7 @@ impl ZenohMessage {
                 qid,
                 target,
                 consolidation,
+                body,
             }),
             channel: zmsg::default_channel::QUERY,
             routing_context,
@@ -1412,7 +1510,7 @@ impl fmt::Display for Hello {
 /// +-+-+-+-+-+-+-+-+
 /// |O|S|A|   INIT  |
 /// +-+-+-+-+-------+
-/// ~             |Q~ if O==1
+/// ~           |C|Q~ if O==1
 /// +---------------+
 /// | v_maj | v_min | if A==0 -- Protocol Version VMaj.VMin
 /// +-------+-------+
@@ -1429,6 +1527,7 @@ impl fmt::Display for Hello {
 ///     if A==1 and S==0 then the agreed resolution is the one communicated by the initiator.
 ///
 /// - if Q==1 then the initiator/responder support QoS.
+/// - if C==1 then the initiator/responder support batch compression on the link.
 /// ```
 #[derive(Debug, Clone, PartialEq)]
 pub struct InitSyn {
@@ -1437,6 +1536,7 @@ pub struct InitSyn {
     pub pid: PeerId,
     pub sn_resolution: ZInt,
     pub is_qos: bool,
+    pub is_compression: bool,
 }
 
 impl Header for InitSyn {
@@ -1459,11 +1559,14 @@ impl Options for InitSyn {
         if self.is_qos {
             options |= tmsg::init_options::QOS;
         }
+        if self.is_compression {
+            options |= tmsg::init_options::COMPRESSION;
+        }
         options
     }
 
     fn has_options(&self) -> bool {
-        self.is_qos
+        self.is_qos || self.is_compression
     }
 }
 
@@ -1473,6 +1576,7 @@ pub struct InitAck {
     pub pid: PeerId,
     pub sn_resolution: Option<ZInt>,
     pub is_qos: bool,
+    pub is_compression: bool,
     pub cookie: ZSlice,
 }
 
@@ -1497,11 +1601,14 @@ impl Options for InitAck {
         if self.is_qos {
             options |= tmsg::init_options::QOS;
         }
+        if self.is_compression {
+            options |= tmsg::init_options::COMPRESSION;
+        }
         options
     }
 
     fn has_options(&self) -> bool {
-        self.is_qos
+        self.is_qos || self.is_compression
     }
 }
 
@@ -2033,6 +2140,7 @@ impl TransportMessage {
         pid: PeerId,
         sn_resolution: ZInt,
         is_qos: bool,
+        is_compression: bool,
         attachment: Option<Attachment>,
     ) -> TransportMessage {
         TransportMessage {
@@ -2042,6 +2150,7 @@ impl TransportMessage {
                 pid,
                 sn_resolution,
                 is_qos,
+                is_compression,
             }),
             attachment,
             #[cfg(feature = "stats")]
@@ -2054,6 +2163,7 @@ impl TransportMessage {
         pid: PeerId,
         sn_resolution: Option<ZInt>,
         is_qos: bool,
+        is_compression: bool,
         cookie: ZSlice,
         attachment: Option<Attachment>,
     ) -> TransportMessage {
@@ -2063,6 +2173,7 @@ impl TransportMessage {
                 pid,
                 sn_resolution,
                 is_qos,
+                is_compression,
                 cookie,
             }),
             attachment,
@@ -51,6 +51,7 @@ pub trait MessageReader {
     fn read_declarations(&mut self) -> Option<Vec<Declaration>>;
     fn read_declaration(&mut self) -> Option<Declaration>;
     fn read_query(&mut self, header: u8) -> Option<ZenohBody>;
+    fn read_query_body(&mut self) -> Option<QueryBody>;
     fn read_link_state_list(&mut self, _header: u8) -> Option<ZenohBody>;
     fn read_link_state(&mut self) -> Option<LinkState>;
     fn read_submode(&mut self) -> Option<(SubMode, Option<Period>)>;
@@ -246,6 +247,7 @@ impl MessageReader for ZBufReader<'_> {
             SEQ_NUM_RES
         };
         let is_qos = imsg::has_option(options, tmsg::init_options::QOS);
+        let is_compression = imsg::has_option(options, tmsg::init_options::COMPRESSION);
 
         Some(TransportBody::InitSyn(InitSyn {
             version,
@@ -253,6 +255,7 @@ impl MessageReader for ZBufReader<'_> {
             pid,
             sn_resolution,
             is_qos,
+            is_compression,
         }))
     }
 
@@ -270,6 +273,7 @@ impl MessageReader for ZBufReader<'_> {
             None
         };
         let is_qos = imsg::has_option(options, tmsg::init_options::QOS);
+        let is_compression = imsg::has_option(options, tmsg::init_options::COMPRESSION);
         let cookie = self.read_zslice_array()?;
 
         Some(TransportBody::InitAck(InitAck {
@@ -277,6 +281,7 @@ impl MessageReader for ZBufReader<'_> {
             pid,
             sn_resolution,
             is_qos,
+            is_compression,
             cookie,
         }))
     }
@@ -443,7 +448,13 @@ impl MessageReader for ZBufReader<'_> {
             })
         };
 
-        Some(ReplyContext { qid, replier })
+        let is_error = imsg::has_flag(header, zmsg::flag::E);
+
+        Some(ReplyContext {
+            qid,
+            replier,
+            is_error,
+        })
     }
 
     fn read_zenoh_message(&mut self, reliability: Reliability) -> Option<ZenohMessage> {
@@ -715,6 +726,14 @@ impl MessageReader for ZBufReader<'_> {
                 let kind = self.read_zint()?;
                 Some(Declaration::ForgetQueryable(ForgetQueryable { key, kind }))
             }
+            TOKEN => {
+                let key = self.read_key_expr(imsg::has_flag(header, zmsg::flag::K))?;
+                Some(Declaration::Token(Token { key }))
+            }
+            FORGET_TOKEN => {
+                let key = self.read_key_expr(imsg::has_flag(header, zmsg::flag::K))?;
+                Some(Declaration::ForgetToken(ForgetToken { key }))
+            }
             unknown => {
                 log::trace!("Invalid ID for Declaration: {}", unknown);
                 None
@@ -732,6 +751,11 @@ impl MessageReader for ZBufReader<'_> {
             None
         };
         let consolidation = self.read_consolidation()?;
+        let body = if imsg::has_flag(header, zmsg::flag::B) {
+            Some(self.read_query_body()?)
+        } else {
+            None
+        };
 
         Some(ZenohBody::Query(Query {
             key,
@@ -739,9 +763,20 @@ impl MessageReader for ZBufReader<'_> {
             qid,
             target,
             consolidation,
+            body,
         }))
     }
 
+    fn read_query_body(&mut self) -> Option<QueryBody> {
+        let data_info = self.read_data_info()?;
+        #[cfg(feature = "shared-memory")]
+        let payload = self.read_zbuf(data_info.sliced)?;
+        #[cfg(not(feature = "shared-memory"))]
+        let payload = self.read_zbuf()?;
+
+        Some(QueryBody { data_info, payload })
+    }
+
     fn read_link_state_list(&mut self, _header: u8) -> Option<ZenohBody> {
         let len = self.read_zint()?;
         let mut link_states: Vec<LinkState> = Vec::with_capacity(len as usize);
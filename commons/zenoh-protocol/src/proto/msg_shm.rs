@@ -62,14 +62,20 @@ impl ZenohMessage {
             res = attachment.buffer.map_to_shmbuf(shmr.clone())?;
         }
 
-        if let ZenohBody::Data(Data {
-            payload, data_info, ..
-        }) = &mut self.body
-        {
-            if payload.has_shminfo() {
+        match &mut self.body {
+            ZenohBody::Data(Data {
+                payload, data_info, ..
+            }) if payload.has_shminfo() => {
                 res = res || payload.map_to_shmbuf(shmr)?;
                 unset_sliced!(self, data_info);
             }
+            ZenohBody::Query(Query {
+                body: Some(body), ..
+            }) if body.payload.has_shminfo() => {
+                res = res || body.payload.map_to_shmbuf(shmr)?;
+                body.data_info.sliced = false;
+            }
+            _ => {}
         }
 
         Ok(res)
@@ -82,14 +88,20 @@ impl ZenohMessage {
             res = attachment.buffer.map_to_shminfo()?;
         }
 
-        if let ZenohBody::Data(Data {
-            payload, data_info, ..
-        }) = &mut self.body
-        {
-            if payload.has_shmbuf() {
+        match &mut self.body {
+            ZenohBody::Data(Data {
+                payload, data_info, ..
+            }) if payload.has_shmbuf() => {
                 res = res || payload.map_to_shminfo()?;
                 set_sliced!(self, data_info);
             }
+            ZenohBody::Query(Query {
+                body: Some(body), ..
+            }) if body.payload.has_shmbuf() => {
+                res = res || body.payload.map_to_shminfo()?;
+                body.data_info.sliced = true;
+            }
+            _ => {}
         }
 
         Ok(res)
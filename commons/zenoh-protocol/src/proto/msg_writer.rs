@@ -57,6 +57,7 @@ pub trait MessageWriter {
     fn write_unit(&mut self, unit: &Unit) -> bool;
     fn write_pull(&mut self, pull: &Pull) -> bool;
     fn write_query(&mut self, query: &Query) -> bool;
+    fn write_query_body(&mut self, body: &QueryBody) -> bool;
     fn write_link_state_list(&mut self, link_state_list: &LinkStateList) -> bool;
     fn write_link_state(&mut self, link_state: &LinkState) -> bool;
     fn write_query_target(&mut self, target: &QueryTarget) -> bool;
@@ -493,6 +494,12 @@ impl MessageWriter for WBuf {
                     && self.write_key_expr(&fq.key)
                     && self.write_zint(fq.kind)
             }
+            Declaration::Token(t) => {
+                self.write_byte(t.header()).is_some() && self.write_key_expr(&t.key)
+            }
+            Declaration::ForgetToken(ft) => {
+                self.write_byte(ft.header()).is_some() && self.write_key_expr(&ft.key)
+            }
         }
     }
 
@@ -543,7 +550,23 @@ impl MessageWriter for WBuf {
         if let Some(t) = query.target.as_ref() {
             zcheck!(self.write_query_target(t));
         }
-        self.write_consolidation(&query.consolidation)
+        zcheck!(self.write_consolidation(&query.consolidation));
+        if let Some(body) = query.body.as_ref() {
+            zcheck!(self.write_query_body(body));
+        }
+        true
+    }
+
+    fn write_query_body(&mut self, body: &QueryBody) -> bool {
+        zcheck!(self.write_data_info(&body.data_info));
+        #[cfg(feature = "shared-memory")]
+        {
+            self.write_zbuf(&body.payload, body.data_info.sliced)
+        }
+        #[cfg(not(feature = "shared-memory"))]
+        {
+            self.write_zbuf(&body.payload)
+        }
     }
 
     fn write_link_state_list(&mut self, link_state_list: &LinkStateList) -> bool {
@@ -22,9 +22,9 @@ use zenoh_protocol::io::{WBufCodec, ZBufCodec};
 use zenoh_protocol::proto::defaults::SEQ_NUM_RES;
 use zenoh_protocol::proto::{
     Attachment, DataInfo, Declaration, ForgetPublisher, ForgetQueryable, ForgetResource,
-    ForgetSubscriber, FramePayload, MessageReader, MessageWriter, Publisher, Queryable,
-    ReplierInfo, ReplyContext, Resource, RoutingContext, Subscriber, TransportMessage,
-    ZenohMessage,
+    ForgetSubscriber, ForgetToken, FramePayload, MessageReader, MessageWriter, Publisher,
+    QueryBody, Queryable, ReplierInfo, ReplyContext, Resource, RoutingContext, Subscriber, Token,
+    TransportMessage, ZenohMessage,
 };
 use zenoh_protocol_core::{whatami::WhatAmIMatcher, *};
 
@@ -82,15 +82,18 @@ fn gen_routing_context() -> RoutingContext {
 
 fn gen_reply_context(is_final: bool) -> ReplyContext {
     let qid = gen!(ZInt);
-    let replier = if !is_final {
-        Some(ReplierInfo {
-            kind: thread_rng().gen_range(0..4),
-            id: gen_pid(),
-        })
-    } else {
-        None
+    if is_final {
+        return ReplyContext::new(qid, None);
+    }
+    let replier = ReplierInfo {
+        kind: thread_rng().gen_range(0..4),
+        id: gen_pid(),
     };
-    ReplyContext::new(qid, replier)
+    if thread_rng().gen_bool(0.5) {
+        ReplyContext::new_error(qid, replier)
+    } else {
+        ReplyContext::new(qid, Some(replier))
+    }
 }
 
 fn gen_attachment() -> Attachment {
@@ -190,6 +193,8 @@ fn gen_declarations() -> Vec<Declaration> {
             key: gen_key(),
             kind: queryable::EVAL,
         }),
+        Declaration::Token(Token { key: gen_key() }),
+        Declaration::ForgetToken(ForgetToken { key: gen_key() }),
     ]
 }
 
@@ -357,23 +362,27 @@ fn codec_hello() {
 fn codec_init() {
     for _ in 0..NUM_ITER {
         let is_qos = [true, false];
+        let is_compression = [true, false];
         let wami = [WhatAmI::Router, WhatAmI::Client];
         let sn_resolution = [SEQ_NUM_RES, gen!(ZInt)];
         let attachment = [None, Some(gen_attachment())];
 
         for q in is_qos.iter() {
-            for w in wami.iter() {
-                for s in sn_resolution.iter() {
-                    for a in attachment.iter() {
-                        let msg = TransportMessage::make_init_syn(
-                            gen!(u8),
-                            *w,
-                            gen_pid(),
-                            *s,
-                            *q,
-                            a.clone(),
-                        );
-                        test_write_read_transport_message(msg);
+            for c in is_compression.iter() {
+                for w in wami.iter() {
+                    for s in sn_resolution.iter() {
+                        for a in attachment.iter() {
+                            let msg = TransportMessage::make_init_syn(
+                                gen!(u8),
+                                *w,
+                                gen_pid(),
+                                *s,
+                                *q,
+                                *c,
+                                a.clone(),
+                            );
+                            test_write_read_transport_message(msg);
+                        }
                     }
                 }
             }
@@ -381,18 +390,21 @@ fn codec_init() {
 
         let sn_resolution = [None, Some(gen!(ZInt))];
         for q in is_qos.iter() {
-            for w in wami.iter() {
-                for s in sn_resolution.iter() {
-                    for a in attachment.iter() {
-                        let msg = TransportMessage::make_init_ack(
-                            *w,
-                            gen_pid(),
-                            *s,
-                            *q,
-                            gen_buffer(64).into(),
-                            a.clone(),
-                        );
-                        test_write_read_transport_message(msg);
+            for c in is_compression.iter() {
+                for w in wami.iter() {
+                    for s in sn_resolution.iter() {
+                        for a in attachment.iter() {
+                            let msg = TransportMessage::make_init_ack(
+                                *w,
+                                gen_pid(),
+                                *s,
+                                *q,
+                                *c,
+                                gen_buffer(64).into(),
+                                a.clone(),
+                            );
+                            test_write_read_transport_message(msg);
+                        }
                     }
                 }
             }
@@ -863,23 +875,33 @@ fn codec_query() {
     for _ in 0..NUM_ITER {
         let value_selector = [String::default(), "my_value_selector".to_string()];
         let target = [None, Some(gen_query_target())];
+        let body = [
+            None,
+            Some(QueryBody {
+                data_info: gen_data_info(),
+                payload: ZBuf::from(gen_buffer(MAX_PAYLOAD_SIZE)),
+            }),
+        ];
         let routing_context = [None, Some(gen_routing_context())];
         let attachment = [None, Some(gen_attachment())];
 
         for p in value_selector.iter() {
             for t in target.iter() {
-                for roc in routing_context.iter() {
-                    for a in attachment.iter() {
-                        let msg = ZenohMessage::make_query(
-                            gen_key(),
-                            p.clone(),
-                            gen!(ZInt),
-                            t.clone(),
-                            gen_consolidation(),
-                            *roc,
-                            a.clone(),
-                        );
-                        test_write_read_zenoh_message(msg);
+                for b in body.iter() {
+                    for roc in routing_context.iter() {
+                        for a in attachment.iter() {
+                            let msg = ZenohMessage::make_query(
+                                gen_key(),
+                                p.clone(),
+                                gen!(ZInt),
+                                t.clone(),
+                                gen_consolidation(),
+                                b.clone(),
+                                *roc,
+                                a.clone(),
+                            );
+                            test_write_read_zenoh_message(msg);
+                        }
                     }
                 }
             }
@@ -106,6 +106,11 @@ pub trait LinkManagerUnicastTrait: Send + Sync {
     async fn new_link(&self, endpoint: EndPoint) -> ZResult<LinkUnicast>;
     async fn new_listener(&self, endpoint: EndPoint) -> ZResult<Locator>;
     async fn del_listener(&self, endpoint: &EndPoint) -> ZResult<()>;
+    /// Reload the listeners with their endpoint configuration extended with `config`, e.g. to
+    /// present a rotated certificate. Only the links accepted from now on are affected.
+    async fn reload_listeners(&self, _config: &Properties) -> ZResult<()> {
+        Ok(())
+    }
     fn get_listeners(&self) -> Vec<EndPoint>;
     fn get_locators(&self) -> Vec<Locator>;
 }
@@ -130,6 +135,11 @@ pub trait LinkUnicastTrait: Send + Sync {
     fn get_dst(&self) -> &Locator;
     fn is_reliable(&self) -> bool;
     fn is_streamed(&self) -> bool;
+    /// The DER encoded certificate chain the remote end has been authenticated with, starting
+    /// with its own certificate. It is empty if the link does not authenticate the remote end.
+    fn get_peer_certificates(&self) -> &[Vec<u8>] {
+        &[]
+    }
     async fn write(&self, buffer: &[u8]) -> ZResult<usize>;
     async fn write_all(&self, buffer: &[u8]) -> ZResult<()>;
     async fn read(&self, buffer: &mut [u8]) -> ZResult<usize>;
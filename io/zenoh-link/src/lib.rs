@@ -25,6 +25,10 @@ pub use zenoh_link_quic as quic;
 use zenoh_link_quic::{
     LinkManagerUnicastQuic, QuicConfigurator, QuicLocatorInspector, QUIC_LOCATOR_PREFIX,
 };
+#[cfg(all(feature = "transport_serial", target_family = "unix"))]
+pub use zenoh_link_serial as serial;
+#[cfg(all(feature = "transport_serial", target_family = "unix"))]
+use zenoh_link_serial::{LinkManagerUnicastSerial, SerialLocatorInspector, SERIAL_LOCATOR_PREFIX};
 #[cfg(feature = "transport_tcp")]
 pub use zenoh_link_tcp as tcp;
 #[cfg(feature = "transport_tcp")]
@@ -47,6 +51,10 @@ pub use zenoh_link_unixsock_stream as unixsock_stream;
 use zenoh_link_unixsock_stream::{
     LinkManagerUnicastUnixSocketStream, UNIXSOCKSTREAM_LOCATOR_PREFIX,
 };
+#[cfg(feature = "transport_ws")]
+pub use zenoh_link_ws as ws;
+#[cfg(feature = "transport_ws")]
+use zenoh_link_ws::{LinkManagerUnicastWs, WsLocatorInspector, WS_LOCATOR_PREFIX};
 
 pub use zenoh_link_commons::*;
 pub use zenoh_protocol_core::{EndPoint, Locator};
@@ -55,12 +63,16 @@ pub use zenoh_protocol_core::{EndPoint, Locator};
 pub struct LocatorInspector {
     #[cfg(feature = "transport_quic")]
     quic_inspector: QuicLocatorInspector,
+    #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+    serial_inspector: SerialLocatorInspector,
     #[cfg(feature = "transport_tcp")]
     tcp_inspector: TcpLocatorInspector,
     #[cfg(feature = "transport_tls")]
     tls_inspector: TlsLocatorInspector,
     #[cfg(feature = "transport_udp")]
     udp_inspector: UdpLocatorInspector,
+    #[cfg(feature = "transport_ws")]
+    ws_inspector: WsLocatorInspector,
 }
 impl LocatorInspector {
     pub async fn is_multicast(&self, locator: &Locator) -> ZResult<bool> {
@@ -78,6 +90,10 @@ impl LocatorInspector {
             QUIC_LOCATOR_PREFIX => self.quic_inspector.is_multicast(locator).await,
             #[cfg(all(feature = "transport_unixsock-stream", target_family = "unix"))]
             UNIXSOCKSTREAM_LOCATOR_PREFIX => Ok(false),
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            SERIAL_LOCATOR_PREFIX => self.serial_inspector.is_multicast(locator).await,
+            #[cfg(feature = "transport_ws")]
+            WS_LOCATOR_PREFIX => self.ws_inspector.is_multicast(locator).await,
             _ => bail!("Unsupported protocol: {}.", protocol),
         }
     }
@@ -147,6 +163,10 @@ impl LinkManagerBuilderUnicast {
             UNIXSOCKSTREAM_LOCATOR_PREFIX => {
                 Ok(Arc::new(LinkManagerUnicastUnixSocketStream::new(_manager)))
             }
+            #[cfg(feature = "transport_ws")]
+            WS_LOCATOR_PREFIX => Ok(Arc::new(LinkManagerUnicastWs::new(_manager))),
+            #[cfg(all(feature = "transport_serial", target_family = "unix"))]
+            SERIAL_LOCATOR_PREFIX => Ok(Arc::new(LinkManagerUnicastSerial::new(_manager))),
             _ => bail!("Unicast not supported for {} protocol", protocol),
         }
     }
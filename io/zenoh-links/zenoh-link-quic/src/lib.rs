@@ -14,12 +14,13 @@
 use async_std::net::ToSocketAddrs;
 use async_trait::async_trait;
 use config::{
+    TLS_CLIENT_AUTH, TLS_CLIENT_CERTIFICATE_FILE, TLS_CLIENT_PRIVATE_KEY_FILE,
     TLS_ROOT_CA_CERTIFICATE_FILE, TLS_SERVER_CERTIFICATE_FILE, TLS_SERVER_PRIVATE_KEY_FILE,
 };
 use std::net::SocketAddr;
 use webpki::{DnsName, DnsNameRef};
 use zenoh_cfg_properties::Properties;
-use zenoh_config::{Config, Locator};
+use zenoh_config::{Config, Locator, ZN_FALSE, ZN_TRUE};
 use zenoh_core::{bail, zconfigurable, zerror, Result as ZResult};
 use zenoh_link_commons::{ConfigurationInspector, LocatorInspector};
 
@@ -75,6 +76,24 @@ impl ConfigurationInspector<Config> for QuicConfigurator {
                 tls_server_certificate.into(),
             );
         }
+        if let Some(tls_client_auth) = c.client_auth() {
+            match tls_client_auth {
+                true => properties.insert(TLS_CLIENT_AUTH.into(), ZN_TRUE.into()),
+                false => properties.insert(TLS_CLIENT_AUTH.into(), ZN_FALSE.into()),
+            };
+        }
+        if let Some(tls_client_private_key) = c.client_private_key() {
+            properties.insert(
+                TLS_CLIENT_PRIVATE_KEY_FILE.into(),
+                tls_client_private_key.into(),
+            );
+        }
+        if let Some(tls_client_certificate) = c.client_certificate() {
+            properties.insert(
+                TLS_CLIENT_CERTIFICATE_FILE.into(),
+                tls_client_certificate.into(),
+            );
+        }
 
         Ok(properties)
     }
@@ -91,6 +110,9 @@ zconfigurable! {
     // Amount of time in microseconds to throttle the accept loop upon an error.
     // Default set to 100 ms.
     static ref QUIC_ACCEPT_THROTTLE_TIME: u64 = 100_000;
+    // Amount of time in milliseconds between two checks of the listeners certificate files.
+    // A certificate is reloaded for the new handshakes as soon as one of its files changes.
+    static ref QUIC_CERTIFICATES_WATCH_INTERVAL: u64 = 1_000;
 }
 
 pub mod config {
@@ -104,6 +126,15 @@ pub mod config {
 
     pub const TLS_SERVER_CERTIFICATE_FILE: &str = ZN_TLS_SERVER_CERTIFICATE_STR;
     pub const TLS_SERVER_CERTIFICATE_RAW: &str = "tls_server_certificate_raw";
+
+    pub const TLS_CLIENT_PRIVATE_KEY_FILE: &str = ZN_TLS_CLIENT_PRIVATE_KEY_STR;
+    pub const TLS_CLIENT_PRIVATE_KEY_RAW: &str = "tls_client_private_key_raw";
+
+    pub const TLS_CLIENT_CERTIFICATE_FILE: &str = ZN_TLS_CLIENT_CERTIFICATE_STR;
+    pub const TLS_CLIENT_CERTIFICATE_RAW: &str = "tls_client_certificate_raw";
+
+    pub const TLS_CLIENT_AUTH: &str = ZN_TLS_CLIENT_AUTH_STR;
+    pub const TLS_CLIENT_AUTH_DEFAULT: &str = ZN_TLS_CLIENT_AUTH_DEFAULT;
 }
 
 async fn get_quic_addr(address: &Locator) -> ZResult<SocketAddr> {
@@ -14,7 +14,7 @@
 
 use crate::{
     config::*, get_quic_addr, get_quic_dns, ALPN_QUIC_HTTP, QUIC_ACCEPT_THROTTLE_TIME,
-    QUIC_DEFAULT_MTU, QUIC_LOCATOR_PREFIX,
+    QUIC_CERTIFICATES_WATCH_INTERVAL, QUIC_DEFAULT_MTU, QUIC_LOCATOR_PREFIX,
 };
 use async_std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 use async_std::prelude::*;
@@ -22,13 +22,16 @@ use async_std::sync::Mutex as AsyncMutex;
 use async_std::task;
 use async_std::task::JoinHandle;
 use async_trait::async_trait;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign;
 use std::collections::HashMap;
 use std::fmt;
 use std::io::BufReader;
 use std::net::IpAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+use zenoh_cfg_properties::Properties;
 use zenoh_core::{bail, Result as ZResult};
 use zenoh_core::{zasynclock, zerror, zread, zwrite};
 use zenoh_link_commons::{
@@ -42,6 +45,8 @@ pub struct LinkUnicastQuic {
     src_addr: SocketAddr,
     src_locator: Locator,
     dst_locator: Locator,
+    // The DER encoded certificates presented by the remote end during the handshake
+    peer_certificates: Vec<Vec<u8>>,
     send: AsyncMutex<quinn::SendStream>,
     recv: AsyncMutex<quinn::RecvStream>,
 }
@@ -54,12 +59,19 @@ impl LinkUnicastQuic {
         send: quinn::SendStream,
         recv: quinn::RecvStream,
     ) -> LinkUnicastQuic {
+        let peer_certificates = connection
+            .connection
+            .peer_identity()
+            .and_then(|id| id.downcast::<Vec<rustls::Certificate>>().ok())
+            .map_or_else(Vec::new, |certs| certs.into_iter().map(|c| c.0).collect());
+
         // Build the Quic object
         LinkUnicastQuic {
             connection,
             src_addr,
             src_locator: Locator::new(QUIC_LOCATOR_PREFIX, &src_addr),
             dst_locator,
+            peer_certificates,
             send: AsyncMutex::new(send),
             recv: AsyncMutex::new(recv),
         }
@@ -151,6 +163,11 @@ impl LinkUnicastTrait for LinkUnicastQuic {
     fn is_streamed(&self) -> bool {
         true
     }
+
+    #[inline(always)]
+    fn get_peer_certificates(&self) -> &[Vec<u8>] {
+        &self.peer_certificates
+    }
 }
 
 impl Drop for LinkUnicastQuic {
@@ -187,6 +204,7 @@ impl fmt::Debug for LinkUnicastQuic {
 /*************************************/
 struct ListenerUnicastQuic {
     endpoint: EndPoint,
+    certificate: Arc<ServerCertificate>,
     active: Arc<AtomicBool>,
     signal: Signal,
     handle: JoinHandle<ZResult<()>>,
@@ -195,12 +213,14 @@ struct ListenerUnicastQuic {
 impl ListenerUnicastQuic {
     fn new(
         endpoint: EndPoint,
+        certificate: Arc<ServerCertificate>,
         active: Arc<AtomicBool>,
         signal: Signal,
         handle: JoinHandle<ZResult<()>>,
     ) -> ListenerUnicastQuic {
         ListenerUnicastQuic {
             endpoint,
+            certificate,
             active,
             signal,
             handle,
@@ -208,6 +228,143 @@ impl ListenerUnicastQuic {
     }
 }
 
+// The certificate presented by a listener. It is resolved on every handshake so that it can be
+// replaced when rotated, without disturbing the links that have already been established.
+struct ServerCertificate(RwLock<Arc<sign::CertifiedKey>>);
+
+impl ResolvesServerCert for ServerCertificate {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<sign::CertifiedKey>> {
+        Some(zread!(self.0).clone())
+    }
+}
+
+async fn load_server_certificate(endpoint: &EndPoint) -> ZResult<Arc<sign::CertifiedKey>> {
+    let config = match endpoint.config.as_ref() {
+        Some(c) => c,
+        None => bail!("No QUIC configuration provided"),
+    };
+
+    let f = if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_RAW) {
+        value.as_bytes().to_vec()
+    } else if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_FILE) {
+        async_std::fs::read(value)
+            .await
+            .map_err(|e| zerror!("Invalid QUIC CA certificate file: {}", e))?
+    } else {
+        bail!("No QUIC CA certificate has been provided.");
+    };
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(f.as_slice()))
+        .map_err(|e| zerror!("Invalid QUIC CA certificate file: {}", e))?
+        .drain(..)
+        .map(rustls::Certificate)
+        .collect();
+
+    // Private keys
+    let f = if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_RAW) {
+        value.as_bytes().to_vec()
+    } else if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_FILE) {
+        async_std::fs::read(value)
+            .await
+            .map_err(|e| zerror!("Invalid QUIC CA certificate file: {}", e))?
+    } else {
+        bail!("No QUIC CA private key has been provided.");
+    };
+    let private_key = rustls::PrivateKey(
+        rustls_pemfile::read_all(&mut BufReader::new(f.as_slice()))
+            .map_err(|e| zerror!("Invalid QUIC CA private key file: {}", e))?
+            .iter()
+            .filter_map(|x| match x {
+                rustls_pemfile::Item::RSAKey(k)
+                | rustls_pemfile::Item::PKCS8Key(k)
+                | rustls_pemfile::Item::ECKey(k) => Some(k.to_vec()),
+                _ => None,
+            })
+            .take(1)
+            .next()
+            .ok_or_else(|| zerror!("No QUIC CA private key has been provided."))?,
+    );
+    let private_key = sign::any_supported_type(&private_key)
+        .map_err(|e| zerror!("Invalid QUIC CA private key file: {}", e))?;
+
+    Ok(Arc::new(sign::CertifiedKey::new(certificates, private_key)))
+}
+
+// Read the PEM configured either inline with the `raw` key or as a file with the `file` key
+async fn read_pem(endpoint: &EndPoint, raw: &str, file: &str) -> ZResult<Option<Vec<u8>>> {
+    let config = match &endpoint.config {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+    if let Some(value) = config.get(raw) {
+        Ok(Some(value.as_bytes().to_vec()))
+    } else if let Some(value) = config.get(file) {
+        let bytes = async_std::fs::read(value)
+            .await
+            .map_err(|e| zerror!("Invalid QUIC file {}: {}", value, e))?;
+        Ok(Some(bytes))
+    } else {
+        Ok(None)
+    }
+}
+
+// Load the certificate and key the client authenticates with, if any is configured
+async fn load_client_certificate(
+    endpoint: &EndPoint,
+) -> ZResult<Option<(Vec<rustls::Certificate>, rustls::PrivateKey)>> {
+    let certificate = read_pem(
+        endpoint,
+        TLS_CLIENT_CERTIFICATE_RAW,
+        TLS_CLIENT_CERTIFICATE_FILE,
+    )
+    .await?;
+    let key = read_pem(
+        endpoint,
+        TLS_CLIENT_PRIVATE_KEY_RAW,
+        TLS_CLIENT_PRIVATE_KEY_FILE,
+    )
+    .await?;
+    match (certificate, key) {
+        (Some(certificate), Some(key)) => {
+            let certificates = rustls_pemfile::certs(&mut BufReader::new(certificate.as_slice()))
+                .map_err(|e| zerror!("Invalid QUIC client certificate: {}", e))?
+                .drain(..)
+                .map(rustls::Certificate)
+                .collect();
+            let private_key = rustls_pemfile::read_all(&mut BufReader::new(key.as_slice()))
+                .map_err(|e| zerror!("Invalid QUIC client private key: {}", e))?
+                .drain(..)
+                .find_map(|x| match x {
+                    rustls_pemfile::Item::RSAKey(k)
+                    | rustls_pemfile::Item::PKCS8Key(k)
+                    | rustls_pemfile::Item::ECKey(k) => Some(rustls::PrivateKey(k)),
+                    _ => None,
+                })
+                .ok_or_else(|| zerror!("Invalid QUIC client private key."))?;
+            Ok(Some((certificates, private_key)))
+        }
+        (None, None) => Ok(None),
+        (Some(_), None) => bail!("Missing QUIC client private key."),
+        (None, Some(_)) => bail!("Missing QUIC client certificate."),
+    }
+}
+
+// The last modification times of the certificate files of a listener, used to detect rotations
+async fn server_certificate_modified(endpoint: &EndPoint) -> Vec<Option<SystemTime>> {
+    let mut modified = vec![];
+    if let Some(config) = &endpoint.config {
+        for (raw, file) in [
+            (TLS_SERVER_PRIVATE_KEY_RAW, TLS_SERVER_PRIVATE_KEY_FILE),
+            (TLS_SERVER_CERTIFICATE_RAW, TLS_SERVER_CERTIFICATE_FILE),
+        ] {
+            if let (None, Some(path)) = (config.get(raw), config.get(file)) {
+                let metadata = async_std::fs::metadata(path).await;
+                modified.push(metadata.and_then(|m| m.modified()).ok());
+            }
+        }
+    }
+    modified
+}
+
 pub struct LinkManagerUnicastQuic {
     manager: NewLinkChannelSender,
     listeners: Arc<RwLock<HashMap<SocketAddr, ListenerUnicastQuic>>>,
@@ -266,10 +423,19 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastQuic {
             root_cert_store.add(c).map_err(|e| zerror!("{}", e))?;
         }
 
-        let mut client_crypto = rustls::ClientConfig::builder()
+        let client_crypto = rustls::ClientConfig::builder()
             .with_safe_defaults()
-            .with_root_certificates(root_cert_store)
-            .with_no_client_auth();
+            .with_root_certificates(root_cert_store);
+        // Present a certificate to the listeners authenticating their clients
+        let client_certificate = load_client_certificate(&endpoint)
+            .await
+            .map_err(|e| zerror!("Can not create a new QUIC link bound to {}: {}", host, e))?;
+        let mut client_crypto = match client_certificate {
+            Some((certificates, private_key)) => client_crypto
+                .with_single_cert(certificates, private_key)
+                .map_err(|e| zerror!("Can not create a new QUIC link bound to {}: {}", host, e))?,
+            None => client_crypto.with_no_client_auth(),
+        };
         client_crypto.alpn_protocols = ALPN_QUIC_HTTP.iter().map(|&x| x.into()).collect();
 
         let ip_addr: IpAddr = if addr.is_ipv4() {
@@ -309,59 +475,54 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastQuic {
     }
 
     async fn new_listener(&self, mut endpoint: EndPoint) -> ZResult<Locator> {
-        let config = match endpoint.config.as_ref() {
-            Some(c) => c,
-            None => bail!("No QUIC configuration provided"),
-        };
-
         let locator = &endpoint.locator;
         let addr = get_quic_addr(locator).await?;
 
-        let f = if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_RAW) {
-            value.as_bytes().to_vec()
-        } else if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_FILE) {
-            async_std::fs::read(value)
-                .await
-                .map_err(|e| zerror!("Invalid QUIC CA certificate file: {}", e))?
-        } else {
-            bail!("No QUIC CA certificate has been provided.");
-        };
-        let certificates = rustls_pemfile::certs(&mut BufReader::new(f.as_slice()))
-            .map_err(|e| zerror!("Invalid QUIC CA certificate file: {}", e))?
-            .drain(..)
-            .map(rustls::Certificate)
-            .collect();
+        let certificate = Arc::new(ServerCertificate(RwLock::new(
+            load_server_certificate(&endpoint).await?,
+        )));
+        let modified = server_certificate_modified(&endpoint).await;
 
-        // Private keys
-        let f = if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_RAW) {
-            value.as_bytes().to_vec()
-        } else if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_FILE) {
-            async_std::fs::read(value)
-                .await
-                .map_err(|e| zerror!("Invalid QUIC CA certificate file: {}", e))?
-        } else {
-            bail!("No QUIC CA private key has been provided.");
-        };
-        let private_key = rustls::PrivateKey(
-            rustls_pemfile::read_all(&mut BufReader::new(f.as_slice()))
-                .map_err(|e| zerror!("Invalid QUIC CA private key file: {}", e))?
-                .iter()
-                .filter_map(|x| match x {
-                    rustls_pemfile::Item::RSAKey(k)
-                    | rustls_pemfile::Item::PKCS8Key(k)
-                    | rustls_pemfile::Item::ECKey(k) => Some(k.to_vec()),
-                    _ => None,
-                })
-                .take(1)
-                .next()
-                .ok_or_else(|| zerror!("No QUIC CA private key has been provided."))?,
-        );
+        let mut client_auth: bool = TLS_CLIENT_AUTH_DEFAULT.parse().unwrap();
+        if let Some(config) = &endpoint.config {
+            if let Some(value) = config.get(TLS_CLIENT_AUTH) {
+                client_auth = value.parse()?
+            }
+        }
 
         // Server config
-        let mut server_crypto = rustls::ServerConfig::builder()
-            .with_safe_defaults()
-            .with_no_client_auth()
-            .with_single_cert(certificates, private_key)?;
+        let server_crypto = rustls::ServerConfig::builder().with_safe_defaults();
+        let server_crypto = if client_auth {
+            // Only accept the clients presenting a certificate issued by the root CA
+            let ca = read_pem(
+                &endpoint,
+                TLS_ROOT_CA_CERTIFICATE_RAW,
+                TLS_ROOT_CA_CERTIFICATE_FILE,
+            )
+            .await
+            .map_err(|e| zerror!("Can not create a new QUIC listener on {}. {}", addr, e))?
+            .ok_or_else(|| {
+                zerror!(
+                    "Can not create a new QUIC listener on {}. Missing root CA certificate to authenticate the clients.",
+                    addr
+                )
+            })?;
+            let mut roots = rustls::RootCertStore::empty();
+            let certificates = rustls_pemfile::certs(&mut BufReader::new(ca.as_slice()))
+                .map_err(|e| zerror!("Invalid QUIC CA certificate file: {}", e))?;
+            let (valid, _) = roots.add_parsable_certificates(&certificates);
+            if valid == 0 {
+                bail!(
+                    "Can not create a new QUIC listener on {}. Invalid root CA certificate.",
+                    addr
+                );
+            }
+            server_crypto
+                .with_client_cert_verifier(rustls::server::AllowAnyAuthenticatedClient::new(roots))
+        } else {
+            server_crypto.with_no_client_auth()
+        };
+        let mut server_crypto = server_crypto.with_cert_resolver(certificate.clone());
         server_crypto.alpn_protocols = ALPN_QUIC_HTTP.iter().map(|&x| x.into()).collect();
         let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(server_crypto));
 
@@ -403,10 +564,15 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastQuic {
 
         // Initialize the QuicAcceptor
         let locator = endpoint.locator.clone();
-        let listener = ListenerUnicastQuic::new(endpoint, active, signal, handle);
+        let listener =
+            ListenerUnicastQuic::new(endpoint, certificate, active, signal.clone(), handle);
         // Update the list of active listeners on the manager
         zwrite!(self.listeners).insert(local_addr, listener);
 
+        // Spawn the task reloading the certificate when its files change
+        let c_listeners = self.listeners.clone();
+        task::spawn(watch_task(local_addr, c_listeners, modified, signal));
+
         Ok(locator)
     }
 
@@ -429,6 +595,37 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastQuic {
         listener.handle.await
     }
 
+    async fn reload_listeners(&self, config: &Properties) -> ZResult<()> {
+        let listeners: Vec<(SocketAddr, EndPoint, Arc<ServerCertificate>)> = zread!(self.listeners)
+            .iter()
+            .map(|(addr, l)| (*addr, l.endpoint.clone(), l.certificate.clone()))
+            .collect();
+
+        let mut res = Ok(());
+        for (addr, mut endpoint, certificate) in listeners {
+            endpoint.extend_configuration(config.iter().map(|(k, v)| (k.clone(), v.clone())));
+            match load_server_certificate(&endpoint).await {
+                Ok(key) => {
+                    *zwrite!(certificate.0) = key;
+                    if let Some(listener) = zwrite!(self.listeners).get_mut(&addr) {
+                        listener.endpoint = endpoint;
+                    }
+                    log::info!("Reloaded the QUIC certificate of listener {}", addr);
+                }
+                Err(e) => {
+                    let e = zerror!(
+                        "Can not reload the QUIC certificate of listener {}. {}",
+                        addr,
+                        e
+                    );
+                    log::warn!("{}", e);
+                    res = Err(e.into());
+                }
+            }
+        }
+        res
+    }
+
     fn get_listeners(&self) -> Vec<EndPoint> {
         zread!(self.listeners)
             .values()
@@ -578,3 +775,38 @@ async fn accept_task(
 
     Ok(())
 }
+
+async fn watch_task(
+    addr: SocketAddr,
+    listeners: Arc<RwLock<HashMap<SocketAddr, ListenerUnicastQuic>>>,
+    mut modified: Vec<Option<SystemTime>>,
+    signal: Signal,
+) {
+    let interval = Duration::from_millis(*QUIC_CERTIFICATES_WATCH_INTERVAL);
+    // Check the certificate files until the listener is stopped
+    while signal.wait().timeout(interval).await.is_err() {
+        let (endpoint, certificate) = match zread!(listeners).get(&addr) {
+            Some(l) => (l.endpoint.clone(), l.certificate.clone()),
+            None => break,
+        };
+
+        let current = server_certificate_modified(&endpoint).await;
+        if current == modified {
+            continue;
+        }
+        modified = current;
+
+        // Keep the current certificate if the new one can not be loaded, e.g. while being written
+        match load_server_certificate(&endpoint).await {
+            Ok(key) => {
+                *zwrite!(certificate.0) = key;
+                log::info!("Reloaded the QUIC certificate of listener {}", addr);
+            }
+            Err(e) => log::warn!(
+                "Can not reload the QUIC certificate of listener {}. {}",
+                addr,
+                e
+            ),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! The framing of the batches sent over a serial line.
+//!
+//! Each batch is followed by the CRC-32 (LE) of the batch, the result is COBS-encoded
+//! (Consistent Overhead Byte Stuffing) so that it does not contain any zero byte, and
+//! the frame is terminated with a zero byte:
+//! ```text
+//! +-----------------------------+------+
+//! | COBS(batch | crc: u32 (LE)) | 0x00 |
+//! +-----------------------------+------+
+//! ```
+//! A receiver joining in the middle of a frame, or a frame corrupted on the line,
+//! only costs the frames up to the next zero byte.
+
+const DELIMITER: u8 = 0;
+const CRC_LEN: usize = 4;
+// The maximum number of bytes covered by a COBS code byte
+const COBS_MAX_BLOCK: usize = 254;
+
+/// The maximum length of the frame of a `len` bytes batch, delimiter included.
+pub(crate) fn max_frame_len(len: usize) -> usize {
+    let len = len + CRC_LEN;
+    len + len / COBS_MAX_BLOCK + 2
+}
+
+/// Append the frame of `batch` to `buf`.
+pub(crate) fn encode(buf: &mut Vec<u8>, batch: &[u8]) {
+    let crc = crc32fast::hash(batch).to_le_bytes();
+    // the position of the pending code byte, and the length of its block
+    let mut code = buf.len();
+    let mut block = 0;
+    buf.push(0);
+    for &byte in batch.iter().chain(crc.iter()) {
+        if byte != DELIMITER {
+            buf.push(byte);
+            block += 1;
+        }
+        if byte == DELIMITER || block == COBS_MAX_BLOCK {
+            buf[code] = block as u8 + 1;
+            code = buf.len();
+            block = 0;
+            buf.push(0);
+        }
+    }
+    buf[code] = block as u8 + 1;
+    buf.push(DELIMITER);
+}
+
+// Decode a COBS-encoded frame (without its delimiter), returning None if malformed.
+fn cobs_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(frame.len());
+    let mut i = 0;
+    while i < frame.len() {
+        let code = frame[i] as usize;
+        let block = frame.get(i + 1..i + code)?;
+        decoded.extend_from_slice(block);
+        i += code;
+        // a block shorter than the maximum stands for a zero byte, except at the end
+        if code <= COBS_MAX_BLOCK && i < frame.len() {
+            decoded.push(DELIMITER);
+        }
+    }
+    Some(decoded)
+}
+
+/// Extracts the batches from the bytes received on a serial line.
+pub(crate) struct Decoder {
+    frame: Vec<u8>,
+    max_frame_len: usize,
+    // set when the current frame exceeded the maximum length, until its delimiter
+    overflow: bool,
+}
+
+impl Decoder {
+    /// Creates a decoder for batches of at most `mtu` bytes.
+    pub(crate) fn new(mtu: usize) -> Decoder {
+        let max_frame_len = max_frame_len(mtu);
+        Decoder {
+            frame: Vec::with_capacity(max_frame_len),
+            max_frame_len,
+            overflow: false,
+        }
+    }
+
+    /// Feeds the received `bytes` to the decoder, calling `on_batch` with each complete
+    /// and valid batch. Returns the number of malformed or corrupted frames dropped.
+    pub(crate) fn decode<F: FnMut(Vec<u8>)>(&mut self, bytes: &[u8], mut on_batch: F) -> usize {
+        let mut dropped = 0;
+        for &byte in bytes {
+            if byte != DELIMITER {
+                if self.frame.len() < self.max_frame_len {
+                    self.frame.push(byte);
+                } else {
+                    self.overflow = true;
+                }
+                continue;
+            }
+            if self.overflow {
+                dropped += 1;
+            } else if !self.frame.is_empty() {
+                match cobs_decode(&self.frame) {
+                    Some(mut batch) if batch.len() >= CRC_LEN => {
+                        let crc = batch.split_off(batch.len() - CRC_LEN);
+                        if crc32fast::hash(&batch).to_le_bytes() == crc[..] {
+                            on_batch(batch);
+                        } else {
+                            dropped += 1;
+                        }
+                    }
+                    _ => dropped += 1,
+                }
+            }
+            self.frame.clear();
+            self.overflow = false;
+        }
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(batches: &[Vec<u8>]) -> (Vec<Vec<u8>>, usize) {
+        let mut buf = vec![];
+        for batch in batches {
+            encode(&mut buf, batch);
+        }
+        // the only zero bytes are the frame delimiters
+        let delimiters = buf.iter().filter(|&&b| b == DELIMITER).count();
+        assert_eq!(delimiters, batches.len());
+        decode_all(&buf)
+    }
+
+    fn decode_all(bytes: &[u8]) -> (Vec<Vec<u8>>, usize) {
+        let mut decoder = Decoder::new(1_024);
+        let mut batches = vec![];
+        // feed the bytes in small chunks, as read from the line
+        let dropped = bytes
+            .chunks(7)
+            .map(|chunk| decoder.decode(chunk, |b| batches.push(b)))
+            .sum();
+        (batches, dropped)
+    }
+
+    #[test]
+    fn framing_roundtrip() {
+        let batches = vec![
+            vec![],
+            vec![0],
+            vec![0, 0, 1, 0],
+            (1..=255).collect(),
+            (0..1_024).map(|i| (i % 256) as u8).collect(),
+            vec![42; 1_024],
+        ];
+        for batch in batches.iter() {
+            let mut buf = vec![];
+            encode(&mut buf, batch);
+            assert!(buf.len() <= max_frame_len(batch.len()));
+        }
+        assert_eq!(roundtrip(&batches), (batches, 0));
+    }
+
+    #[test]
+    fn framing_errors() {
+        let mut buf = vec![];
+        encode(&mut buf, b"first");
+        let first = buf.len();
+        encode(&mut buf, b"second");
+        encode(&mut buf, b"third");
+
+        // a corrupted frame is dropped, without affecting the next ones
+        let mut corrupted = buf.clone();
+        corrupted[first + 2] ^= 0x10;
+        let (batches, dropped) = decode_all(&corrupted);
+        assert_eq!(batches, vec![b"first".to_vec(), b"third".to_vec()]);
+        assert_eq!(dropped, 1);
+
+        // a receiver starting in the middle of a frame resynchronizes on the next one
+        let (batches, dropped) = decode_all(&buf[first + 3..]);
+        assert_eq!(batches, vec![b"third".to_vec()]);
+        assert_eq!(dropped, 1);
+
+        // a frame exceeding the MTU is dropped
+        let mut buf = vec![];
+        encode(&mut buf, &[1; 2_048]);
+        encode(&mut buf, b"last");
+        let (batches, dropped) = decode_all(&buf);
+        assert_eq!(batches, vec![b"last".to_vec()]);
+        assert_eq!(dropped, 1);
+    }
+}
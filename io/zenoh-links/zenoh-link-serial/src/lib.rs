@@ -0,0 +1,81 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_trait::async_trait;
+use std::path::Path;
+use zenoh_link_commons::LocatorInspector;
+
+use zenoh_core::{zconfigurable, zerror, Result as ZResult};
+use zenoh_protocol_core::Locator;
+
+#[cfg(target_family = "unix")]
+mod framing;
+#[cfg(target_family = "unix")]
+mod unicast;
+#[cfg(target_family = "unix")]
+pub use unicast::*;
+
+// Maximum MTU (Serial PDU) in bytes.
+// NOTE: A serial line is a byte stream without any error detection. Each batch is sent
+//       as a frame delimited with COBS and protected by a CRC, which is dropped when
+//       corrupted. The frame size is kept small since a corrupted byte costs the whole
+//       frame, and since the microcontrollers on the other side have little memory.
+const SERIAL_MAX_MTU: u16 = 1_500;
+
+// The baud rate used when not specified in the locator metadata.
+const SERIAL_DEFAULT_BAUDRATE: u32 = 115_200;
+
+pub const SERIAL_LOCATOR_PREFIX: &str = "serial";
+
+// The locator metadata key giving the baud rate of the serial line.
+pub const SERIAL_BAUDRATE: &str = "baudrate";
+
+#[derive(Default, Clone, Copy)]
+pub struct SerialLocatorInspector;
+#[async_trait]
+impl LocatorInspector for SerialLocatorInspector {
+    fn protocol(&self) -> &str {
+        SERIAL_LOCATOR_PREFIX
+    }
+    async fn is_multicast(&self, _locator: &Locator) -> ZResult<bool> {
+        Ok(false)
+    }
+}
+
+zconfigurable! {
+    // Default MTU (Serial PDU) in bytes.
+    static ref SERIAL_DEFAULT_MTU: u16 = SERIAL_MAX_MTU;
+    // Amount of time in microseconds to throttle the listener before reopening
+    // the device, once the previous link on it has been closed or upon an error.
+    // Default set to 100 ms.
+    static ref SERIAL_ACCEPT_THROTTLE_TIME: u64 = 100_000;
+}
+
+pub fn get_serial_path(locator: &Locator) -> &Path {
+    locator.address().as_ref()
+}
+
+pub fn get_serial_baudrate(locator: &Locator) -> ZResult<u32> {
+    match locator.metadata().and_then(|m| m.get(SERIAL_BAUDRATE)) {
+        Some(baudrate) => baudrate.parse().map_err(|_| {
+            zerror!(
+                "Invalid {} in serial locator {}: {}",
+                SERIAL_BAUDRATE,
+                locator,
+                baudrate
+            )
+            .into()
+        }),
+        None => Ok(SERIAL_DEFAULT_BAUDRATE),
+    }
+}
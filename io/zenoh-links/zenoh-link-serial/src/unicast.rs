@@ -0,0 +1,468 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::prelude::*;
+use async_std::sync::Mutex as AsyncMutex;
+use async_std::task;
+use async_std::task::JoinHandle;
+use async_trait::async_trait;
+use nix::fcntl::{flock, FlockArg, OFlag};
+use nix::sys::termios::{self, ControlFlags, SetArg, SpecialCharacterIndices};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use zenoh_core::{bail, zerror, zread, zwrite};
+use zenoh_core::{Error as ZError, Result as ZResult};
+use zenoh_link_commons::{
+    LinkManagerUnicastTrait, LinkUnicast, LinkUnicastTrait, NewLinkChannelSender,
+};
+use zenoh_protocol_core::{EndPoint, Locator};
+use zenoh_sync::Signal;
+
+use super::framing::{self, Decoder};
+use super::{
+    get_serial_baudrate, get_serial_path, SERIAL_ACCEPT_THROTTLE_TIME, SERIAL_DEFAULT_MTU,
+    SERIAL_LOCATOR_PREFIX,
+};
+
+// The size of the buffer used by the reader thread to read from the device.
+const SERIAL_READ_BUFFER_SIZE: usize = 4_096;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn to_baud_rate(baudrate: u32) -> Option<termios::BaudRate> {
+    use termios::BaudRate::*;
+    let baud_rate = match baudrate {
+        1_200 => B1200,
+        2_400 => B2400,
+        4_800 => B4800,
+        9_600 => B9600,
+        19_200 => B19200,
+        38_400 => B38400,
+        57_600 => B57600,
+        115_200 => B115200,
+        230_400 => B230400,
+        460_800 => B460800,
+        500_000 => B500000,
+        576_000 => B576000,
+        921_600 => B921600,
+        1_000_000 => B1000000,
+        1_152_000 => B1152000,
+        1_500_000 => B1500000,
+        2_000_000 => B2000000,
+        2_500_000 => B2500000,
+        3_000_000 => B3000000,
+        3_500_000 => B3500000,
+        4_000_000 => B4000000,
+        _ => return None,
+    };
+    Some(baud_rate)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn to_baud_rate(baudrate: u32) -> Option<u32> {
+    Some(baudrate)
+}
+
+// Open the serial device in raw mode, and lock it for exclusive use.
+fn open_device(path: &Path, baudrate: u32) -> ZResult<File> {
+    let e = |e: &dyn fmt::Display| -> ZError {
+        zerror!("Can not open serial device {}: {}", path.display(), e).into()
+    };
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(OFlag::O_NOCTTY.bits())
+        .open(path)
+        .map_err(|err| e(&err))?;
+    let fd = file.as_raw_fd();
+    flock(fd, FlockArg::LockExclusiveNonblock).map_err(|err| e(&err))?;
+
+    let mut tio = termios::tcgetattr(fd).map_err(|err| e(&err))?;
+    termios::cfmakeraw(&mut tio);
+    tio.control_flags
+        .insert(ControlFlags::CLOCAL | ControlFlags::CREAD);
+    // Reads return after 100 ms without any byte received, for the
+    // reader thread to notice that the link has been closed
+    tio.control_chars[SpecialCharacterIndices::VMIN as usize] = 0;
+    tio.control_chars[SpecialCharacterIndices::VTIME as usize] = 1;
+    let baud_rate =
+        to_baud_rate(baudrate).ok_or_else(|| e(&format!("unsupported baud rate {}", baudrate)))?;
+    termios::cfsetspeed(&mut tio, baud_rate).map_err(|err| e(&err))?;
+    termios::tcsetattr(fd, SetArg::TCSANOW, &tio).map_err(|err| e(&err))?;
+
+    Ok(file)
+}
+
+// Read the bytes from the device and decode the received batches, until the link is closed.
+fn read_task(file: &File, path: &str, closed: &AtomicBool, batches: flume::Sender<Vec<u8>>) {
+    let mut decoder = Decoder::new(*SERIAL_DEFAULT_MTU as usize);
+    let mut buffer = vec![0_u8; SERIAL_READ_BUFFER_SIZE];
+    while !closed.load(Ordering::Acquire) && !batches.is_disconnected() {
+        match (&*file).read(&mut buffer) {
+            // No byte received before the read timeout
+            Ok(0) => continue,
+            Ok(n) => {
+                let dropped = decoder.decode(&buffer[..n], |batch| {
+                    let _ = batches.send(batch);
+                });
+                if dropped > 0 {
+                    log::debug!(
+                        "Dropped {} corrupted frames on serial link {}",
+                        dropped,
+                        path
+                    );
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                log::debug!("Read error on serial link {}: {}", path, e);
+                break;
+            }
+        }
+    }
+    // Release the device for the next link on it
+    let _ = flock(file.as_raw_fd(), FlockArg::UnlockNonblock);
+}
+
+pub struct LinkUnicastSerial {
+    // The serial device, shared with the reader thread
+    file: Arc<File>,
+    // The lock serializing the writes of the frames on the device
+    write_lock: AsyncMutex<()>,
+    // The batches received by the reader thread
+    batches: flume::Receiver<Vec<u8>>,
+    // Set once the link is closed, to stop the reader thread
+    closed: Arc<AtomicBool>,
+    // Triggered once the reader thread has stopped reading the device
+    stopped: Signal,
+    // The path of the serial device
+    path: String,
+    locator: Locator,
+}
+
+impl LinkUnicastSerial {
+    fn new(file: File, path: &Path) -> ZResult<LinkUnicastSerial> {
+        let file = Arc::new(file);
+        let path = path.display().to_string();
+        let closed = Arc::new(AtomicBool::new(false));
+        let stopped = Signal::new();
+        let (sender, batches) = flume::unbounded();
+
+        // Spawn the reader thread: the device is read with blocking calls
+        let c_file = file.clone();
+        let c_path = path.clone();
+        let c_closed = closed.clone();
+        let c_stopped = stopped.clone();
+        std::thread::Builder::new()
+            .name(format!("zenoh-serial-{}", path))
+            .spawn(move || {
+                read_task(&c_file, &c_path, &c_closed, sender);
+                c_stopped.trigger();
+            })
+            .map_err(|e| zerror!("Can not create a new serial link on {}: {}", path, e))?;
+
+        // Build the Serial object
+        Ok(LinkUnicastSerial {
+            file,
+            write_lock: AsyncMutex::new(()),
+            batches,
+            closed,
+            stopped,
+            locator: Locator::new(SERIAL_LOCATOR_PREFIX, &path),
+            path,
+        })
+    }
+}
+
+#[async_trait]
+impl LinkUnicastTrait for LinkUnicastSerial {
+    async fn close(&self) -> ZResult<()> {
+        log::trace!("Closing serial link: {}", self);
+        // Stop the reader thread, the device is closed once the link is dropped
+        self.closed.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    async fn write(&self, buffer: &[u8]) -> ZResult<usize> {
+        if self.closed.load(Ordering::Acquire) {
+            bail!("Write error on serial link {}: link closed", self);
+        }
+        let mut frame = Vec::with_capacity(framing::max_frame_len(buffer.len()));
+        framing::encode(&mut frame, buffer);
+
+        // Whole frames are written by a blocking task, even if this future is dropped
+        let _guard = self.write_lock.lock().await;
+        let file = self.file.clone();
+        task::spawn_blocking(move || (&*file).write_all(&frame))
+            .await
+            .map_err(|e| {
+                let e = zerror!("Write error on serial link {}: {}", self, e);
+                log::trace!("{}", e);
+                e
+            })?;
+        Ok(buffer.len())
+    }
+
+    async fn write_all(&self, buffer: &[u8]) -> ZResult<()> {
+        let _ = self.write(buffer).await?;
+        Ok(())
+    }
+
+    async fn read(&self, buffer: &mut [u8]) -> ZResult<usize> {
+        let batch = self.batches.recv_async().await.map_err(|_| {
+            let e = zerror!("Read error on serial link {}: link closed", self);
+            log::trace!("{}", e);
+            e
+        })?;
+        if batch.len() > buffer.len() {
+            log::trace!(
+                "Serial link {}: truncated batch of {} bytes to {} bytes",
+                self,
+                batch.len(),
+                buffer.len()
+            );
+        }
+        let len = batch.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&batch[..len]);
+        Ok(len)
+    }
+
+    async fn read_exact(&self, buffer: &mut [u8]) -> ZResult<()> {
+        let mut read: usize = 0;
+        while read < buffer.len() {
+            let n = self.read(&mut buffer[read..]).await?;
+            read += n;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn get_src(&self) -> &Locator {
+        &self.locator
+    }
+
+    #[inline(always)]
+    fn get_dst(&self) -> &Locator {
+        &self.locator
+    }
+
+    #[inline(always)]
+    fn get_mtu(&self) -> u16 {
+        *SERIAL_DEFAULT_MTU
+    }
+
+    #[inline(always)]
+    fn is_reliable(&self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn is_streamed(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for LinkUnicastSerial {
+    fn drop(&mut self) {
+        // Stop the reader thread
+        self.closed.store(true, Ordering::Release);
+    }
+}
+
+impl fmt::Display for LinkUnicastSerial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LinkUnicastSerial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Serial").field("path", &self.path).finish()
+    }
+}
+
+/*************************************/
+/*          LISTENER                 */
+/*************************************/
+struct ListenerUnicastSerial {
+    endpoint: EndPoint,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    handle: JoinHandle<ZResult<()>>,
+}
+
+impl ListenerUnicastSerial {
+    fn new(
+        endpoint: EndPoint,
+        active: Arc<AtomicBool>,
+        signal: Signal,
+        handle: JoinHandle<ZResult<()>>,
+    ) -> ListenerUnicastSerial {
+        ListenerUnicastSerial {
+            endpoint,
+            active,
+            signal,
+            handle,
+        }
+    }
+}
+
+pub struct LinkManagerUnicastSerial {
+    manager: NewLinkChannelSender,
+    listeners: Arc<RwLock<HashMap<String, ListenerUnicastSerial>>>,
+}
+
+impl LinkManagerUnicastSerial {
+    pub fn new(manager: NewLinkChannelSender) -> Self {
+        Self {
+            manager,
+            listeners: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkManagerUnicastTrait for LinkManagerUnicastSerial {
+    async fn new_link(&self, endpoint: EndPoint) -> ZResult<LinkUnicast> {
+        let path = get_serial_path(&endpoint.locator);
+        let baudrate = get_serial_baudrate(&endpoint.locator)?;
+
+        let file = open_device(path, baudrate)?;
+        let link = Arc::new(LinkUnicastSerial::new(file, path)?);
+
+        Ok(LinkUnicast(link))
+    }
+
+    async fn new_listener(&self, endpoint: EndPoint) -> ZResult<Locator> {
+        let path = get_serial_path(&endpoint.locator).to_path_buf();
+        let baudrate = get_serial_baudrate(&endpoint.locator)?;
+
+        // Open the device right away to report any error
+        let file = open_device(&path, baudrate).map_err(|e| {
+            zerror!(
+                "Can not create a new serial listener on {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+
+        // Spawn the accept loop for the listener
+        let active = Arc::new(AtomicBool::new(true));
+        let signal = Signal::new();
+
+        let c_active = active.clone();
+        let c_signal = signal.clone();
+        let c_manager = self.manager.clone();
+        let c_listeners = self.listeners.clone();
+        let c_path = path.clone();
+        let handle = task::spawn(async move {
+            // Wait for the accept loop to terminate
+            let res = accept_task(file, &c_path, baudrate, c_active, c_signal, c_manager).await;
+            zwrite!(c_listeners).remove(&c_path.display().to_string());
+            res
+        });
+
+        let locator = endpoint.locator.clone();
+        let listener = ListenerUnicastSerial::new(endpoint, active, signal, handle);
+        // Update the list of active listeners on the manager
+        zwrite!(self.listeners).insert(path.display().to_string(), listener);
+
+        Ok(locator)
+    }
+
+    async fn del_listener(&self, endpoint: &EndPoint) -> ZResult<()> {
+        let path = get_serial_path(&endpoint.locator).display().to_string();
+
+        // Stop the listener
+        let listener = zwrite!(self.listeners).remove(&path).ok_or_else(|| {
+            let e = zerror!(
+                "Can not delete the serial listener because it has not been found: {}",
+                path
+            );
+            log::trace!("{}", e);
+            e
+        })?;
+
+        // Send the stop signal
+        listener.active.store(false, Ordering::Release);
+        listener.signal.trigger();
+        listener.handle.await
+    }
+
+    fn get_listeners(&self) -> Vec<EndPoint> {
+        zread!(self.listeners)
+            .values()
+            .map(|l| l.endpoint.clone())
+            .collect()
+    }
+
+    fn get_locators(&self) -> Vec<Locator> {
+        zread!(self.listeners)
+            .values()
+            .map(|l| l.endpoint.locator.clone())
+            .collect()
+    }
+}
+
+// A serial line is point-to-point: the listener hands a link on the device to the
+// transport manager, and reopens the device for a new link once that one is closed.
+async fn accept_task(
+    file: File,
+    path: &Path,
+    baudrate: u32,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    manager: NewLinkChannelSender,
+) -> ZResult<()> {
+    let throttle = Duration::from_micros(*SERIAL_ACCEPT_THROTTLE_TIME);
+    let mut file = Some(file);
+
+    log::trace!("Ready to accept serial links on: {}", path.display());
+    while active.load(Ordering::Acquire) {
+        let link = match file
+            .take()
+            .map_or_else(|| open_device(path, baudrate), Ok)
+            .and_then(|file| LinkUnicastSerial::new(file, path))
+        {
+            Ok(link) => Arc::new(link),
+            Err(e) => {
+                // The previous link on the device might not be dropped yet
+                log::debug!("{}", e);
+                task::sleep(throttle).race(signal.wait()).await;
+                continue;
+            }
+        };
+
+        log::debug!("Accepted serial link on {}", path.display());
+        let stopped = link.stopped.clone();
+        // Communicate the new link to the initial transport manager
+        if let Err(e) = manager.send_async(LinkUnicast(link)).await {
+            log::error!("{}-{}: {}", file!(), line!(), e)
+        }
+
+        // Wait for the device to be released by the link before reopening it
+        stopped.wait().race(signal.wait()).await;
+        task::sleep(throttle).race(signal.wait()).await;
+    }
+
+    Ok(())
+}
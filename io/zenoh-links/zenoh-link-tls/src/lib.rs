@@ -108,6 +108,9 @@ zconfigurable! {
     // Amount of time in microseconds to throttle the accept loop upon an error.
     // Default set to 100 ms.
     static ref TLS_ACCEPT_THROTTLE_TIME: u64 = 100_000;
+    // Amount of time in milliseconds between two checks of the listeners certificate files.
+    // A certificate is reloaded for the new handshakes as soon as one of its files changes.
+    static ref TLS_CERTIFICATES_WATCH_INTERVAL: u64 = 1_000;
 }
 
 pub mod config {
@@ -13,8 +13,8 @@ use crate::TLS_LOCATOR_PREFIX;
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
 use crate::{
-    config::*, get_tls_addr, get_tls_dns, get_tls_host, TLS_ACCEPT_THROTTLE_TIME, TLS_DEFAULT_MTU,
-    TLS_LINGER_TIMEOUT,
+    config::*, get_tls_addr, get_tls_dns, get_tls_host, TLS_ACCEPT_THROTTLE_TIME,
+    TLS_CERTIFICATES_WATCH_INTERVAL, TLS_DEFAULT_MTU, TLS_LINGER_TIMEOUT,
 };
 use async_rustls::rustls::internal::pemfile;
 pub use async_rustls::rustls::*;
@@ -27,6 +27,7 @@ use async_std::sync::Mutex as AsyncMutex;
 use async_std::task;
 use async_std::task::JoinHandle;
 use async_trait::async_trait;
+use rustls_pemfile::Item;
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::convert::TryInto;
@@ -35,7 +36,8 @@ use std::io::Cursor;
 use std::net::{Ipv4Addr, Ipv6Addr, Shutdown};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+use zenoh_cfg_properties::Properties;
 use zenoh_core::bail;
 use zenoh_core::Result as ZResult;
 use zenoh_core::{zasynclock, zerror, zread, zwrite};
@@ -61,6 +63,8 @@ pub struct LinkUnicastTls {
     // The destination socket address of this link (address used on the local host)
     dst_addr: SocketAddr,
     dst_locator: Locator,
+    // The DER encoded certificates presented by the remote end during the handshake
+    peer_certificates: Vec<Vec<u8>>,
     // Make sure there are no concurrent read or writes
     write_mtx: AsyncMutex<()>,
     read_mtx: AsyncMutex<()>,
@@ -75,7 +79,14 @@ impl LinkUnicastTls {
         src_addr: SocketAddr,
         dst_addr: SocketAddr,
     ) -> LinkUnicastTls {
-        let (tcp_stream, _) = socket.get_ref();
+        let (tcp_stream, session) = socket.get_ref();
+        let peer_certificates = session
+            .get_peer_certificates()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| c.0)
+            .collect();
+
         // Set the TLS nodelay option
         if let Err(err) = tcp_stream.set_nodelay(true) {
             log::warn!(
@@ -108,6 +119,7 @@ impl LinkUnicastTls {
             src_locator: Locator::new(TLS_LOCATOR_PREFIX, &src_addr),
             dst_addr,
             dst_locator: Locator::new(TLS_LOCATOR_PREFIX, &dst_addr),
+            peer_certificates,
             write_mtx: AsyncMutex::new(()),
             read_mtx: AsyncMutex::new(()),
         }
@@ -194,6 +206,11 @@ impl LinkUnicastTrait for LinkUnicastTls {
     fn is_streamed(&self) -> bool {
         true
     }
+
+    #[inline(always)]
+    fn get_peer_certificates(&self) -> &[Vec<u8>] {
+        &self.peer_certificates
+    }
 }
 
 impl Drop for LinkUnicastTls {
@@ -225,6 +242,7 @@ impl fmt::Debug for LinkUnicastTls {
 /*************************************/
 struct ListenerUnicastTls {
     endpoint: EndPoint,
+    certificate: Arc<ServerCertificate>,
     active: Arc<AtomicBool>,
     signal: Signal,
     handle: JoinHandle<ZResult<()>>,
@@ -233,12 +251,14 @@ struct ListenerUnicastTls {
 impl ListenerUnicastTls {
     fn new(
         endpoint: EndPoint,
+        certificate: Arc<ServerCertificate>,
         active: Arc<AtomicBool>,
         signal: Signal,
         handle: JoinHandle<ZResult<()>>,
     ) -> ListenerUnicastTls {
         ListenerUnicastTls {
             endpoint,
+            certificate,
             active,
             signal,
             handle,
@@ -246,6 +266,170 @@ impl ListenerUnicastTls {
     }
 }
 
+// The certificate presented by a listener. It is resolved on every handshake so that it can be
+// replaced when rotated, without disturbing the links that have already been established.
+struct ServerCertificate(RwLock<sign::CertifiedKey>);
+
+impl ResolvesServerCert for ServerCertificate {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<sign::CertifiedKey> {
+        Some(zread!(self.0).clone())
+    }
+}
+
+async fn load_server_certificate(endpoint: &EndPoint) -> ZResult<sign::CertifiedKey> {
+    let mut tls_server_private_key = Vec::new();
+    let mut tls_server_certificate = Vec::new();
+
+    if let Some(config) = &endpoint.config {
+        let config = &***config;
+        if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_RAW) {
+            tls_server_private_key = value.as_bytes().to_vec()
+        } else if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_FILE) {
+            tls_server_private_key = fs::read(value)
+                .await
+                .map_err(|e| zerror!("Invalid TLS private key file: {}", e))?
+        }
+        if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_RAW) {
+            tls_server_certificate = value.as_bytes().to_vec()
+        } else if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_FILE) {
+            tls_server_certificate = fs::read(value)
+                .await
+                .map_err(|e| zerror!("Invalid TLS serer certificate file: {}", e))?
+        }
+    }
+
+    // Configure the server private key
+    if tls_server_private_key.is_empty() {
+        bail!("Missing server private key.");
+    }
+    let key = load_private_key(&tls_server_private_key)?;
+    let key = sign::any_supported_type(&key).map_err(|_| zerror!("Invalid TLS private key."))?;
+
+    // Configure the server certificate
+    if tls_server_certificate.is_empty() {
+        bail!("Missing server certificate.");
+    }
+    let certs = pemfile::certs(&mut Cursor::new(&tls_server_certificate))
+        .map_err(|_| zerror!("Invalid TLS server certificate."))?;
+
+    Ok(sign::CertifiedKey::new(certs, Arc::new(key)))
+}
+
+// Read the PEM configured either inline with the `raw` key or as a file with the `file` key
+async fn read_pem(endpoint: &EndPoint, raw: &str, file: &str) -> ZResult<Option<Vec<u8>>> {
+    let config = match &endpoint.config {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+    if let Some(value) = config.get(raw) {
+        Ok(Some(value.as_bytes().to_vec()))
+    } else if let Some(value) = config.get(file) {
+        let bytes = fs::read(value)
+            .await
+            .map_err(|e| zerror!("Invalid TLS file {}: {}", value, e))?;
+        Ok(Some(bytes))
+    } else {
+        Ok(None)
+    }
+}
+
+// Load the first RSA (PKCS#1), PKCS#8 or EC (SEC1) private key found in a PEM
+fn load_private_key(pem: &[u8]) -> ZResult<PrivateKey> {
+    let item = rustls_pemfile::read_all(&mut Cursor::new(pem))
+        .map_err(|e| zerror!("Invalid TLS private key: {}", e))?
+        .into_iter()
+        .find(|x| matches!(x, Item::RSAKey(_) | Item::PKCS8Key(_) | Item::ECKey(_)));
+    match item {
+        Some(Item::RSAKey(key)) | Some(Item::PKCS8Key(key)) => Ok(PrivateKey(key)),
+        Some(Item::ECKey(key)) => sec1_to_pkcs8(&key),
+        _ => bail!("Invalid TLS private key."),
+    }
+}
+
+// Only the PKCS#8 encoding of the EC keys is supported by rustls: wrap the SEC1 encoded key in a
+// PKCS#8 structure, trying each supported curve.
+fn sec1_to_pkcs8(sec1: &[u8]) -> ZResult<PrivateKey> {
+    // The PKCS#8 version followed by the id-ecPublicKey algorithm identifier of each curve
+    const PREFIXES: [&[u8]; 2] = [
+        // prime256v1
+        b"\x02\x01\x00\x30\x13\x06\x07\x2a\x86\x48\xce\x3d\x02\x01\x06\x08\x2a\x86\x48\xce\x3d\x03\x01\x07",
+        // secp384r1
+        b"\x02\x01\x00\x30\x10\x06\x07\x2a\x86\x48\xce\x3d\x02\x01\x06\x05\x2b\x81\x04\x00\x22",
+    ];
+
+    fn push_der(buffer: &mut Vec<u8>, tag: u8, value: &[u8]) {
+        buffer.push(tag);
+        if value.len() < 0x80 {
+            buffer.push(value.len() as u8);
+        } else {
+            let len = value.len().to_be_bytes();
+            let len = &len[len.iter().take_while(|b| **b == 0).count()..];
+            buffer.push(0x80 | len.len() as u8);
+            buffer.extend_from_slice(len);
+        }
+        buffer.extend_from_slice(value);
+    }
+
+    for prefix in PREFIXES {
+        let mut info = prefix.to_vec();
+        // OCTET STRING
+        push_der(&mut info, 0x04, sec1);
+        let mut pkcs8 = vec![];
+        // SEQUENCE
+        push_der(&mut pkcs8, 0x30, &info);
+        let key = PrivateKey(pkcs8);
+        if sign::any_ecdsa_type(&key).is_ok() {
+            return Ok(key);
+        }
+    }
+    bail!("Unsupported TLS EC private key.")
+}
+
+// Load the certificate and key the client authenticates with, if any is configured
+async fn load_client_certificate(
+    endpoint: &EndPoint,
+) -> ZResult<Option<(Vec<Certificate>, PrivateKey)>> {
+    let certificate = read_pem(
+        endpoint,
+        TLS_CLIENT_CERTIFICATE_RAW,
+        TLS_CLIENT_CERTIFICATE_FILE,
+    )
+    .await?;
+    let key = read_pem(
+        endpoint,
+        TLS_CLIENT_PRIVATE_KEY_RAW,
+        TLS_CLIENT_PRIVATE_KEY_FILE,
+    )
+    .await?;
+    match (certificate, key) {
+        (Some(certificate), Some(key)) => {
+            let certs = pemfile::certs(&mut Cursor::new(&certificate))
+                .map_err(|_| zerror!("Invalid TLS client certificate."))?;
+            Ok(Some((certs, load_private_key(&key)?)))
+        }
+        (None, None) => Ok(None),
+        (Some(_), None) => bail!("Missing client private key."),
+        (None, Some(_)) => bail!("Missing client certificate."),
+    }
+}
+
+// The last modification times of the certificate files of a listener, used to detect rotations
+async fn server_certificate_modified(endpoint: &EndPoint) -> Vec<Option<SystemTime>> {
+    let mut modified = vec![];
+    if let Some(config) = &endpoint.config {
+        for (raw, file) in [
+            (TLS_SERVER_PRIVATE_KEY_RAW, TLS_SERVER_PRIVATE_KEY_FILE),
+            (TLS_SERVER_CERTIFICATE_RAW, TLS_SERVER_CERTIFICATE_FILE),
+        ] {
+            if let (None, Some(path)) = (config.get(raw), config.get(file)) {
+                let metadata = fs::metadata(path).await;
+                modified.push(metadata.and_then(|m| m.modified()).ok());
+            }
+        }
+    }
+    modified
+}
+
 pub struct LinkManagerUnicastTls {
     manager: NewLinkChannelSender,
     listeners: Arc<RwLock<HashMap<SocketAddr, ListenerUnicastTls>>>,
@@ -283,7 +467,7 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastTls {
 
         // Initialize the TLS stream
         let mut bytes = Vec::new();
-        if let Some(config) = endpoint.config {
+        if let Some(config) = &endpoint.config {
             if let Some(value) = config.get(TLS_ROOT_CA_CERTIFICATE_RAW) {
                 bytes = value.as_bytes().to_vec()
             } else if let Some(value) = config.get(TLS_ROOT_CA_CERTIFICATE_FILE) {
@@ -293,18 +477,23 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastTls {
             }
         }
 
-        let config = if bytes.is_empty() {
-            Arc::new(ClientConfig::new())
-        } else {
-            let mut cc = ClientConfig::new();
+        let mut cc = ClientConfig::new();
+        if !bytes.is_empty() {
             let _ = cc
                 .root_store
                 .add_pem_file(&mut Cursor::new(&bytes))
                 .map_err(|_| zerror!("Invalid TLS CA certificate file"))?;
-            Arc::new(cc)
-        };
+        }
+        // Present a certificate to the listeners authenticating their clients
+        if let Some((certs, key)) = load_client_certificate(&endpoint)
+            .await
+            .map_err(|e| zerror!("Can not create a new TLS link bound to {}: {}", host, e))?
+        {
+            cc.set_single_client_cert(certs, key)
+                .map_err(|e| zerror!("Can not create a new TLS link bound to {}: {}", host, e))?;
+        }
 
-        let connector = TlsConnector::from(config);
+        let connector = TlsConnector::from(Arc::new(cc));
         let tls_stream = connector
             .connect(domain.as_ref(), tcp_stream)
             .await
@@ -322,60 +511,47 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastTls {
         let host = get_tls_host(locator)?;
 
         let mut client_auth: bool = TLS_CLIENT_AUTH_DEFAULT.parse().unwrap();
-        let mut tls_server_private_key = Vec::new();
-        let mut tls_server_certificate = Vec::new();
-
         if let Some(config) = &endpoint.config {
-            let config = &***config;
-            if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_RAW) {
-                tls_server_private_key = value.as_bytes().to_vec()
-            } else if let Some(value) = config.get(TLS_SERVER_PRIVATE_KEY_FILE) {
-                tls_server_private_key = fs::read(value)
-                    .await
-                    .map_err(|e| zerror!("Invalid TLS private key file: {}", e))?
-            }
-            if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_RAW) {
-                tls_server_certificate = value.as_bytes().to_vec()
-            } else if let Some(value) = config.get(TLS_SERVER_CERTIFICATE_FILE) {
-                tls_server_certificate = fs::read(value)
-                    .await
-                    .map_err(|e| zerror!("Invalid TLS serer certificate file: {}", e))?
-            }
             if let Some(value) = config.get(TLS_CLIENT_AUTH) {
                 client_auth = value.parse()?
             }
         }
 
-        // Configure the server private key
-        if tls_server_private_key.is_empty() {
-            bail!(
-                "Can not create a new TLS listener on {}. Missing server private key.",
-                addr,
-            );
-        }
-        let mut keys =
-            pemfile::rsa_private_keys(&mut Cursor::new(&tls_server_private_key)).unwrap();
-
-        // Configure the server certificate
-        if tls_server_certificate.is_empty() {
-            bail!(
-                "Can not create a new TLS listener on {}. Missing server certificate.",
-                addr,
-            );
-        }
-        let certs = pemfile::certs(&mut Cursor::new(&tls_server_certificate)).unwrap();
+        let certificate = load_server_certificate(&endpoint)
+            .await
+            .map_err(|e| zerror!("Can not create a new TLS listener on {}. {}", addr, e))?;
+        let certificate = Arc::new(ServerCertificate(RwLock::new(certificate)));
+        let modified = server_certificate_modified(&endpoint).await;
 
         let mut sc = if client_auth {
-            // @TODO: implement Client authentication
-            bail!(
-                "Can not create a new TLS listener on {}. ClientAuth not supported.",
-                addr
-            );
+            // Only accept the clients presenting a certificate issued by the root CA
+            let ca = read_pem(
+                &endpoint,
+                TLS_ROOT_CA_CERTIFICATE_RAW,
+                TLS_ROOT_CA_CERTIFICATE_FILE,
+            )
+            .await
+            .map_err(|e| zerror!("Can not create a new TLS listener on {}. {}", addr, e))?
+            .ok_or_else(|| {
+                zerror!(
+                    "Can not create a new TLS listener on {}. Missing root CA certificate to authenticate the clients.",
+                    addr
+                )
+            })?;
+            let mut roots = RootCertStore::empty();
+            match roots.add_pem_file(&mut Cursor::new(&ca)) {
+                Ok((valid, _)) if valid > 0 => {}
+                _ => bail!(
+                    "Can not create a new TLS listener on {}. Invalid root CA certificate.",
+                    addr
+                ),
+            }
+            ServerConfig::new(AllowAnyAuthenticatedClient::new(roots))
         } else {
             ServerConfig::new(NoClientAuth::new())
         };
 
-        sc.set_single_cert(certs, keys.remove(0)).unwrap();
+        sc.cert_resolver = certificate.clone();
 
         // Initialize the TcpListener
         let socket = TcpListener::bind(addr)
@@ -409,10 +585,15 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastTls {
         let mut locator: Locator = locator.to_owned();
         assert!(locator.set_addr(&format!("{}:{}", host, local_port)));
 
-        let listener = ListenerUnicastTls::new(endpoint, active, signal, handle);
+        let listener =
+            ListenerUnicastTls::new(endpoint, certificate, active, signal.clone(), handle);
         // Update the list of active listeners on the manager
         zwrite!(self.listeners).insert(local_addr, listener);
 
+        // Spawn the task reloading the certificate when its files change
+        let c_listeners = self.listeners.clone();
+        task::spawn(watch_task(local_addr, c_listeners, modified, signal));
+
         Ok(locator)
     }
 
@@ -435,6 +616,37 @@ impl LinkManagerUnicastTrait for LinkManagerUnicastTls {
         listener.handle.await
     }
 
+    async fn reload_listeners(&self, config: &Properties) -> ZResult<()> {
+        let listeners: Vec<(SocketAddr, EndPoint, Arc<ServerCertificate>)> = zread!(self.listeners)
+            .iter()
+            .map(|(addr, l)| (*addr, l.endpoint.clone(), l.certificate.clone()))
+            .collect();
+
+        let mut res = Ok(());
+        for (addr, mut endpoint, certificate) in listeners {
+            endpoint.extend_configuration(config.iter().map(|(k, v)| (k.clone(), v.clone())));
+            match load_server_certificate(&endpoint).await {
+                Ok(key) => {
+                    *zwrite!(certificate.0) = key;
+                    if let Some(listener) = zwrite!(self.listeners).get_mut(&addr) {
+                        listener.endpoint = endpoint;
+                    }
+                    log::info!("Reloaded the TLS certificate of listener {}", addr);
+                }
+                Err(e) => {
+                    let e = zerror!(
+                        "Can not reload the TLS certificate of listener {}. {}",
+                        addr,
+                        e
+                    );
+                    log::warn!("{}", e);
+                    res = Err(e.into());
+                }
+            }
+        }
+        res
+    }
+
     fn get_listeners(&self) -> Vec<EndPoint> {
         zread!(self.listeners)
             .values()
@@ -562,3 +774,38 @@ async fn accept_task(
 
     Ok(())
 }
+
+async fn watch_task(
+    addr: SocketAddr,
+    listeners: Arc<RwLock<HashMap<SocketAddr, ListenerUnicastTls>>>,
+    mut modified: Vec<Option<SystemTime>>,
+    signal: Signal,
+) {
+    let interval = Duration::from_millis(*TLS_CERTIFICATES_WATCH_INTERVAL);
+    // Check the certificate files until the listener is stopped
+    while signal.wait().timeout(interval).await.is_err() {
+        let (endpoint, certificate) = match zread!(listeners).get(&addr) {
+            Some(l) => (l.endpoint.clone(), l.certificate.clone()),
+            None => break,
+        };
+
+        let current = server_certificate_modified(&endpoint).await;
+        if current == modified {
+            continue;
+        }
+        modified = current;
+
+        // Keep the current certificate if the new one can not be loaded, e.g. while being written
+        match load_server_certificate(&endpoint).await {
+            Ok(key) => {
+                *zwrite!(certificate.0) = key;
+                log::info!("Reloaded the TLS certificate of listener {}", addr);
+            }
+            Err(e) => log::warn!(
+                "Can not reload the TLS certificate of listener {}. {}",
+                addr,
+                e
+            ),
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::sync::Mutex as AsyncMutex;
+use async_std::task;
+use async_std::task::JoinHandle;
+use async_trait::async_trait;
+use async_tungstenite::tungstenite::Message;
+use async_tungstenite::WebSocketStream;
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::Shutdown;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use zenoh_core::Result as ZResult;
+use zenoh_core::{bail, zerror, zread, zwrite};
+use zenoh_link_commons::{
+    LinkManagerUnicastTrait, LinkUnicast, LinkUnicastTrait, NewLinkChannelSender,
+};
+use zenoh_protocol_core::{EndPoint, Locator};
+use zenoh_sync::Signal;
+
+use super::{
+    get_ws_addr, get_ws_url, WS_ACCEPT_THROTTLE_TIME, WS_DEFAULT_MTU, WS_HANDSHAKE_TIMEOUT,
+    WS_LOCATOR_PREFIX,
+};
+
+// The read half of a WebSocket link, with the part of the last received message
+// not yet consumed by the reads on the link.
+struct WsReader {
+    stream: SplitStream<WebSocketStream<TcpStream>>,
+    buffer: Vec<u8>,
+    offset: usize,
+}
+
+pub struct LinkUnicastWs {
+    // The underlying socket as returned from the async-std library, used to shut it down
+    socket: TcpStream,
+    // The write half of the WebSocket
+    sink: AsyncMutex<SplitSink<WebSocketStream<TcpStream>, Message>>,
+    // The read half of the WebSocket
+    reader: AsyncMutex<WsReader>,
+    // The source socket address of this link (address used on the local host)
+    src_addr: SocketAddr,
+    src_locator: Locator,
+    // The destination socket address of this link (address used on the remote host)
+    dst_addr: SocketAddr,
+    dst_locator: Locator,
+}
+
+impl LinkUnicastWs {
+    fn new(
+        socket: TcpStream,
+        websocket: WebSocketStream<TcpStream>,
+        src_addr: SocketAddr,
+        dst_addr: SocketAddr,
+    ) -> LinkUnicastWs {
+        // Set the TCP nodelay option
+        if let Err(err) = socket.set_nodelay(true) {
+            log::warn!(
+                "Unable to set NODEALY option on WebSocket link {} => {}: {}",
+                src_addr,
+                dst_addr,
+                err
+            );
+        }
+
+        let (sink, stream) = websocket.split();
+        // Build the WebSocket object
+        LinkUnicastWs {
+            socket,
+            sink: AsyncMutex::new(sink),
+            reader: AsyncMutex::new(WsReader {
+                stream,
+                buffer: vec![],
+                offset: 0,
+            }),
+            src_addr,
+            src_locator: Locator::new(WS_LOCATOR_PREFIX, &src_addr),
+            dst_addr,
+            dst_locator: Locator::new(WS_LOCATOR_PREFIX, &dst_addr),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkUnicastTrait for LinkUnicastWs {
+    async fn close(&self) -> ZResult<()> {
+        log::trace!("Closing WebSocket link: {}", self);
+        // Send the close frame, unless a write is pending on the link
+        if let Some(mut sink) = self.sink.try_lock() {
+            let _ = sink.send(Message::Close(None)).await;
+        }
+        // Close the underlying TCP socket
+        self.socket.shutdown(Shutdown::Both).map_err(|e| {
+            let e = zerror!("WebSocket link shutdown {}: {:?}", self, e);
+            log::trace!("{}", e);
+            e.into()
+        })
+    }
+
+    async fn write(&self, buffer: &[u8]) -> ZResult<usize> {
+        self.write_all(buffer).await?;
+        Ok(buffer.len())
+    }
+
+    async fn write_all(&self, buffer: &[u8]) -> ZResult<()> {
+        // Each write is sent as a binary message
+        let mut sink = self.sink.lock().await;
+        sink.send(Message::Binary(buffer.to_vec()))
+            .await
+            .map_err(|e| {
+                let e = zerror!("Write error on WebSocket link {}: {}", self, e);
+                log::trace!("{}", e);
+                e.into()
+            })
+    }
+
+    async fn read(&self, buffer: &mut [u8]) -> ZResult<usize> {
+        let mut guard = self.reader.lock().await;
+        let reader = &mut *guard;
+        // Wait for a new binary message once the previous one has been consumed
+        while reader.offset == reader.buffer.len() {
+            match reader.stream.next().await {
+                Some(Ok(Message::Binary(data))) => {
+                    reader.buffer = data;
+                    reader.offset = 0;
+                }
+                // Control frames are answered by the WebSocket library itself
+                Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => {}
+                Some(Ok(Message::Close(_))) | None => return Ok(0),
+                Some(Ok(message)) => {
+                    let e = zerror!(
+                        "Read error on WebSocket link {}: unexpected message {:?}",
+                        self,
+                        message
+                    );
+                    log::trace!("{}", e);
+                    return Err(e.into());
+                }
+                Some(Err(e)) => {
+                    let e = zerror!("Read error on WebSocket link {}: {}", self, e);
+                    log::trace!("{}", e);
+                    return Err(e.into());
+                }
+            }
+        }
+        let data = &reader.buffer[reader.offset..];
+        let len = data.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&data[..len]);
+        reader.offset += len;
+        Ok(len)
+    }
+
+    async fn read_exact(&self, buffer: &mut [u8]) -> ZResult<()> {
+        let mut read = 0;
+        while read < buffer.len() {
+            match self.read(&mut buffer[read..]).await? {
+                0 => bail!("Read error on WebSocket link {}: connection closed", self),
+                n => read += n,
+            }
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn get_src(&self) -> &Locator {
+        &self.src_locator
+    }
+
+    #[inline(always)]
+    fn get_dst(&self) -> &Locator {
+        &self.dst_locator
+    }
+
+    #[inline(always)]
+    fn get_mtu(&self) -> u16 {
+        *WS_DEFAULT_MTU
+    }
+
+    #[inline(always)]
+    fn is_reliable(&self) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn is_streamed(&self) -> bool {
+        true
+    }
+}
+
+impl Drop for LinkUnicastWs {
+    fn drop(&mut self) {
+        // Close the underlying TCP socket
+        let _ = self.socket.shutdown(Shutdown::Both);
+    }
+}
+
+impl fmt::Display for LinkUnicastWs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} => {}", self.src_addr, self.dst_addr)?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LinkUnicastWs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ws")
+            .field("src", &self.src_addr)
+            .field("dst", &self.dst_addr)
+            .finish()
+    }
+}
+
+/*************************************/
+/*          LISTENER                 */
+/*************************************/
+struct ListenerUnicastWs {
+    endpoint: EndPoint,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    handle: JoinHandle<ZResult<()>>,
+}
+
+impl ListenerUnicastWs {
+    fn new(
+        endpoint: EndPoint,
+        active: Arc<AtomicBool>,
+        signal: Signal,
+        handle: JoinHandle<ZResult<()>>,
+    ) -> ListenerUnicastWs {
+        ListenerUnicastWs {
+            endpoint,
+            active,
+            signal,
+            handle,
+        }
+    }
+}
+
+pub struct LinkManagerUnicastWs {
+    manager: NewLinkChannelSender,
+    listeners: Arc<RwLock<HashMap<SocketAddr, ListenerUnicastWs>>>,
+}
+
+impl LinkManagerUnicastWs {
+    pub fn new(manager: NewLinkChannelSender) -> Self {
+        Self {
+            manager,
+            listeners: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkManagerUnicastTrait for LinkManagerUnicastWs {
+    async fn new_link(&self, endpoint: EndPoint) -> ZResult<LinkUnicast> {
+        let dst_addr = get_ws_addr(&endpoint.locator).await?;
+
+        let stream = TcpStream::connect(dst_addr).await.map_err(|e| {
+            zerror!(
+                "Can not create a new WebSocket link bound to {}: {}",
+                dst_addr,
+                e
+            )
+        })?;
+
+        let src_addr = stream.local_addr().map_err(|e| {
+            zerror!(
+                "Can not create a new WebSocket link bound to {}: {}",
+                dst_addr,
+                e
+            )
+        })?;
+
+        let dst_addr = stream.peer_addr().map_err(|e| {
+            zerror!(
+                "Can not create a new WebSocket link bound to {}: {}",
+                dst_addr,
+                e
+            )
+        })?;
+
+        // Perform the WebSocket opening handshake
+        let (websocket, _) =
+            async_tungstenite::client_async(get_ws_url(&endpoint.locator), stream.clone())
+                .await
+                .map_err(|e| {
+                    zerror!(
+                        "Can not create a new WebSocket link bound to {}: {}",
+                        dst_addr,
+                        e
+                    )
+                })?;
+
+        let link = Arc::new(LinkUnicastWs::new(stream, websocket, src_addr, dst_addr));
+
+        Ok(LinkUnicast(link))
+    }
+
+    async fn new_listener(&self, mut endpoint: EndPoint) -> ZResult<Locator> {
+        let addr = get_ws_addr(&endpoint.locator).await?;
+
+        // Bind the TCP socket
+        let socket = TcpListener::bind(addr)
+            .await
+            .map_err(|e| zerror!("Can not create a new WebSocket listener on {}: {}", addr, e))?;
+
+        let local_addr = socket
+            .local_addr()
+            .map_err(|e| zerror!("Can not create a new WebSocket listener on {}: {}", addr, e))?;
+
+        // Update the endpoint locator address
+        assert!(endpoint.set_addr(&format!("{}", local_addr)));
+
+        // Spawn the accept loop for the listener
+        let active = Arc::new(AtomicBool::new(true));
+        let signal = Signal::new();
+
+        let c_active = active.clone();
+        let c_signal = signal.clone();
+        let c_manager = self.manager.clone();
+        let c_listeners = self.listeners.clone();
+        let c_addr = local_addr;
+        let handle = task::spawn(async move {
+            // Wait for the accept loop to terminate
+            let res = accept_task(socket, c_active, c_signal, c_manager).await;
+            zwrite!(c_listeners).remove(&c_addr);
+            res
+        });
+
+        let locator = endpoint.locator.clone();
+        let listener = ListenerUnicastWs::new(endpoint, active, signal, handle);
+        // Update the list of active listeners on the manager
+        zwrite!(self.listeners).insert(local_addr, listener);
+
+        Ok(locator)
+    }
+
+    async fn del_listener(&self, endpoint: &EndPoint) -> ZResult<()> {
+        let addr = get_ws_addr(&endpoint.locator).await?;
+
+        // Stop the listener
+        let listener = zwrite!(self.listeners).remove(&addr).ok_or_else(|| {
+            let e = zerror!(
+                "Can not delete the WebSocket listener because it has not been found: {}",
+                addr
+            );
+            log::trace!("{}", e);
+            e
+        })?;
+
+        // Send the stop signal
+        listener.active.store(false, Ordering::Release);
+        listener.signal.trigger();
+        listener.handle.await
+    }
+
+    fn get_listeners(&self) -> Vec<EndPoint> {
+        zread!(self.listeners)
+            .values()
+            .map(|l| l.endpoint.clone())
+            .collect()
+    }
+
+    fn get_locators(&self) -> Vec<Locator> {
+        let mut locators = Vec::new();
+        let default_ipv4 = Ipv4Addr::new(0, 0, 0, 0);
+        let default_ipv6 = Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0);
+
+        let guard = zread!(self.listeners);
+        for (key, value) in guard.iter() {
+            let listener_locator = &value.endpoint.locator;
+            if key.ip() == default_ipv4 || key.ip() == default_ipv6 {
+                match zenoh_util::net::get_local_addresses() {
+                    Ok(ipaddrs) => {
+                        for ipaddr in ipaddrs {
+                            if !ipaddr.is_loopback()
+                                && !ipaddr.is_multicast()
+                                && ipaddr.is_ipv4() == key.is_ipv4()
+                            {
+                                let mut l = Locator::new(
+                                    WS_LOCATOR_PREFIX,
+                                    &SocketAddr::new(ipaddr, key.port()),
+                                );
+                                l.metadata = value.endpoint.locator.metadata.clone();
+                                locators.push(l);
+                            }
+                        }
+                    }
+                    Err(err) => log::error!("Unable to get local addresses : {}", err),
+                }
+            } else {
+                locators.push(listener_locator.clone());
+            }
+        }
+        std::mem::drop(guard);
+
+        locators
+    }
+}
+
+async fn accept_task(
+    socket: TcpListener,
+    active: Arc<AtomicBool>,
+    signal: Signal,
+    manager: NewLinkChannelSender,
+) -> ZResult<()> {
+    enum Action {
+        Accept((TcpStream, SocketAddr)),
+        Stop,
+    }
+
+    async fn accept(socket: &TcpListener) -> ZResult<Action> {
+        let res = socket.accept().await.map_err(|e| zerror!(e))?;
+        Ok(Action::Accept(res))
+    }
+
+    async fn stop(signal: Signal) -> ZResult<Action> {
+        signal.wait().await;
+        Ok(Action::Stop)
+    }
+
+    let src_addr = socket.local_addr().map_err(|e| {
+        let e = zerror!("Can not accept WebSocket connections: {}", e);
+        log::warn!("{}", e);
+        e
+    })?;
+
+    log::trace!("Ready to accept WebSocket connections on: {:?}", src_addr);
+    while active.load(Ordering::Acquire) {
+        // Wait for incoming connections
+        let (stream, dst_addr) = match accept(&socket).race(stop(signal.clone())).await {
+            Ok(action) => match action {
+                Action::Accept((stream, addr)) => (stream, addr),
+                Action::Stop => break,
+            },
+            Err(e) => {
+                log::warn!("{}. Hint: increase the system open file limit.", e);
+                // Throttle the accept loop upon an error
+                // NOTE: This might be due to various factors. However, the most common case is that
+                //       the process has reached the maximum number of open files in the system. On
+                //       Linux systems this limit can be changed by using the "ulimit" command line
+                //       tool. In case of systemd-based systems, this can be changed by using the
+                //       "sysctl" command line tool.
+                task::sleep(Duration::from_micros(*WS_ACCEPT_THROTTLE_TIME)).await;
+                continue;
+            }
+        };
+
+        log::debug!(
+            "Accepted WebSocket connection on {:?}: {:?}",
+            src_addr,
+            dst_addr
+        );
+        // Perform the WebSocket opening handshake in a separate task, not to block
+        // the accept loop on slow or misbehaving clients
+        let c_manager = manager.clone();
+        task::spawn(async move {
+            let handshake = async_tungstenite::accept_async(stream.clone())
+                .timeout(Duration::from_millis(*WS_HANDSHAKE_TIMEOUT));
+            let websocket = match handshake.await {
+                Ok(Ok(websocket)) => websocket,
+                Ok(Err(e)) => {
+                    log::debug!("WebSocket handshake with {} failed: {}", dst_addr, e);
+                    return;
+                }
+                Err(_) => {
+                    log::debug!("WebSocket handshake with {} timed out", dst_addr);
+                    return;
+                }
+            };
+            // Create the new link object
+            let link = Arc::new(LinkUnicastWs::new(stream, websocket, src_addr, dst_addr));
+
+            // Communicate the new link to the initial transport manager
+            if let Err(e) = c_manager.send_async(LinkUnicast(link)).await {
+                log::error!("{}-{}: {}", file!(), line!(), e)
+            }
+        });
+    }
+
+    Ok(())
+}
@@ -20,10 +20,17 @@ use super::protocol::core::{Priority, Reliability, ZInt};
 use super::protocol::io::WBuf;
 use super::protocol::proto::{TransportMessage, ZenohMessage};
 use super::seq_num::SeqNumGenerator;
+use zenoh_core::{bail, zerror, Result as ZResult};
 
 type LengthType = u16;
 const LENGTH_BYTES: [u8; 2] = [0_u8, 0_u8];
 
+// On links where compression has been negotiated, the serialized messages of each batch
+// are preceded by one byte indicating whether they have been compressed or not.
+pub(crate) const COMPRESSION_BYTES: usize = 1;
+const COMPRESSION_NONE: u8 = 0x00;
+const COMPRESSION_LZ4: u8 = 0x01;
+
 #[derive(Clone, Copy, Debug)]
 enum CurrentFrame {
     Reliable,
@@ -378,7 +385,6 @@ impl SerializationBatch {
         }
     }
 
-    #[cfg(test)]
     pub(crate) fn get_serialized_messages(&self) -> &[u8] {
         if self.is_streamed() {
             self.buffer.as_ref().get_first_slice(LENGTH_BYTES.len()..)
@@ -386,6 +392,62 @@ impl SerializationBatch {
             self.buffer.as_ref().get_first_slice(..)
         }
     }
+
+    /// Compress the serialized messages with LZ4 and get a `&[u8]` to transmit them on a link
+    /// where compression has been negotiated. The messages are sent as they are when compressing
+    /// them does not reduce their size, hence the transmitted bytes never exceed `len() + COMPRESSION_BYTES`.
+    ///
+    /// # Arguments
+    /// * `buffer` - The buffer to compress the messages onto, it is resized as needed.
+    ///
+    pub(crate) fn compress<'a>(&self, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        let messages = self.get_serialized_messages();
+        let offset = if self.is_streamed() {
+            LENGTH_BYTES.len()
+        } else {
+            0
+        };
+        let start = offset + COMPRESSION_BYTES;
+        buffer.resize(
+            start + lz4_flex::block::get_maximum_output_size(messages.len()),
+            0,
+        );
+
+        let len = match lz4_flex::block::compress_into(messages, &mut buffer[start..]) {
+            Ok(len) if len < messages.len() => {
+                buffer[offset] = COMPRESSION_LZ4;
+                len
+            }
+            _ => {
+                buffer[offset] = COMPRESSION_NONE;
+                buffer[start..start + messages.len()].copy_from_slice(messages);
+                messages.len()
+            }
+        };
+
+        let len = COMPRESSION_BYTES + len;
+        if self.is_streamed() {
+            buffer[..offset].copy_from_slice(&(len as LengthType).to_le_bytes());
+        }
+        &buffer[..offset + len]
+    }
+}
+
+/// Decompress a batch received on a link where compression has been negotiated.
+///
+/// Returns `None` if the messages have not been compressed, in which case they can be read from
+/// `batch[COMPRESSION_BYTES..]`, or the number of bytes decompressed onto `buffer` otherwise.
+pub(crate) fn decompress(batch: &[u8], buffer: &mut [u8]) -> ZResult<Option<usize>> {
+    match batch.first() {
+        Some(&COMPRESSION_NONE) => Ok(None),
+        Some(&COMPRESSION_LZ4) => {
+            let len = lz4_flex::block::decompress_into(&batch[COMPRESSION_BYTES..], buffer)
+                .map_err(|e| zerror!("Invalid LZ4 compressed batch: {}", e))?;
+            Ok(Some(len))
+        }
+        Some(c) => bail!("Unknown batch compression: {}", c),
+        None => bail!("Missing batch compression header"),
+    }
 }
 
 #[cfg(test)]
@@ -631,4 +693,64 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn serialization_batch_compression() {
+        let batch_size: u16 = 8_192;
+        // A compressible payload and a payload too small to be compressed
+        for (payload, compressed) in [(vec![0_u8; 4_096], true), (vec![1_u8, 2, 3], false)] {
+            for is_streamed in [false, true] {
+                let mut sn_gen = SeqNumGenerator::make(0, SEQ_NUM_RES).unwrap();
+                let mut batch = SerializationBatch::new(batch_size, is_streamed);
+
+                let channel = Channel::default();
+                let mut msg = ZenohMessage::make_data(
+                    "test".into(),
+                    ZBuf::from(payload.clone()),
+                    channel,
+                    CongestionControl::default(),
+                    None,
+                    None,
+                    None,
+                    None,
+                );
+                assert!(batch.serialize_zenoh_message(&mut msg, channel.priority, &mut sn_gen));
+                batch.write_len();
+
+                let mut buffer = vec![];
+                let bytes = batch.compress(&mut buffer);
+                let bytes = if is_streamed {
+                    let len = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+                    assert_eq!(len, bytes.len() - LENGTH_BYTES.len());
+                    &bytes[LENGTH_BYTES.len()..]
+                } else {
+                    bytes
+                };
+                assert!(bytes.len() <= batch.len() + COMPRESSION_BYTES);
+                assert_eq!(compressed, bytes.len() < batch.len());
+
+                let mut output = vec![0_u8; batch_size as usize];
+                let messages = match decompress(bytes, &mut output).unwrap() {
+                    Some(len) => &output[..len],
+                    None => &bytes[COMPRESSION_BYTES..],
+                };
+                assert_eq!(messages, batch.get_serialized_messages());
+
+                let zbuf: ZBuf = messages.to_vec().into();
+                let mut zbuf = zbuf.reader();
+                match zbuf.read_transport_message().unwrap().body {
+                    TransportBody::Frame(Frame {
+                        payload: FramePayload::Messages { mut messages },
+                        ..
+                    }) => assert_eq!(messages.pop().unwrap().body, msg.body),
+                    _ => panic!("Expected a Frame"),
+                }
+            }
+        }
+
+        let mut output = vec![0_u8; batch_size as usize];
+        assert!(decompress(&[], &mut output).is_err());
+        assert!(decompress(&[0xff, 0, 1], &mut output).is_err());
+        assert!(decompress(&[COMPRESSION_LZ4, 0xff, 0xff], &mut output).is_err());
+    }
 }
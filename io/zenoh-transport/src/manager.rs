@@ -430,6 +430,25 @@ impl TransportManager {
         }
     }
 
+    /// Reload the listeners with the link configuration of `config`, e.g. to present the
+    /// TLS certificates that have been rotated. The links already established are not affected.
+    pub async fn reload_listeners(&self, config: &Config) -> ZResult<()> {
+        let (c, errors) = zenoh_link::LinkConfigurator::default()
+            .configurations(config)
+            .await;
+        if let Some((proto, err)) = errors.into_iter().next() {
+            bail!("Invalid {} configuration: {}", proto, err);
+        }
+        self.reload_listeners_unicast(&c).await
+        // @TODO: multicast
+    }
+
+    /// Reload the authenticators with the authentication configuration of `config`, e.g. to
+    /// add or remove users. The transports already established are not affected.
+    pub async fn reload_authenticators(&self, config: &Config) -> ZResult<()> {
+        self.reload_authenticators_unicast(config).await
+    }
+
     pub fn get_listeners(&self) -> Vec<EndPoint> {
         self.get_listeners_unicast()
         // @TODO: multicast
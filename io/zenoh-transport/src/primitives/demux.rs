@@ -69,6 +69,12 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
                             self.primitives
                                 .forget_queryable(&q.key, q.kind, msg.routing_context);
                         }
+                        Declaration::Token(t) => {
+                            self.primitives.decl_token(&t.key, msg.routing_context);
+                        }
+                        Declaration::ForgetToken(ft) => {
+                            self.primitives.forget_token(&ft.key, msg.routing_context);
+                        }
                     }
                 }
             }
@@ -88,6 +94,7 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
                         congestion_control,
                         data_info,
                         msg.routing_context,
+                        msg.attachment,
                     );
                 }
                 Some(rep) => match rep.replier {
@@ -96,9 +103,11 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
                             rep.qid,
                             replier.kind,
                             replier.id,
+                            rep.is_error,
                             key,
                             data_info,
                             payload,
+                            msg.attachment,
                         );
                     }
                     None => {
@@ -121,7 +130,7 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
                 qid,
                 target,
                 consolidation,
-                ..
+                body,
             }) => {
                 self.primitives.send_query(
                     &key,
@@ -129,7 +138,9 @@ impl<P: 'static + Primitives> TransportPeerEventHandler for DeMux<P> {
                     qid,
                     target.unwrap_or_default(),
                     consolidation,
+                    body,
                     msg.routing_context,
+                    msg.attachment,
                 );
             }
 
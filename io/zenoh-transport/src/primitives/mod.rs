@@ -20,7 +20,7 @@ use super::protocol::core::{
     SubInfo, ZInt,
 };
 use super::protocol::io::ZBuf;
-use super::protocol::proto::{DataInfo, RoutingContext};
+use super::protocol::proto::{Attachment, DataInfo, QueryBody, RoutingContext};
 pub use demux::*;
 pub use mux::*;
 
@@ -53,6 +53,10 @@ pub trait Primitives: Send + Sync {
         routing_context: Option<RoutingContext>,
     );
 
+    fn decl_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>);
+    fn forget_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>);
+
+    #[allow(clippy::too_many_arguments)]
     fn send_data(
         &self,
         key_expr: &KeyExpr,
@@ -61,8 +65,10 @@ pub trait Primitives: Send + Sync {
         cogestion_control: CongestionControl,
         data_info: Option<DataInfo>,
         routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
     );
 
+    #[allow(clippy::too_many_arguments)]
     fn send_query(
         &self,
         key_expr: &KeyExpr,
@@ -70,17 +76,22 @@ pub trait Primitives: Send + Sync {
         qid: ZInt,
         target: QueryTarget,
         consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
         routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
     );
 
+    #[allow(clippy::too_many_arguments)]
     fn send_reply_data(
         &self,
         qid: ZInt,
         replier_kind: ZInt,
         replier_id: PeerId,
+        is_error: bool,
         key_expr: KeyExpr,
         info: Option<DataInfo>,
         payload: ZBuf,
+        attachment: Option<Attachment>,
     );
 
     fn send_reply_final(&self, qid: ZInt);
@@ -137,6 +148,9 @@ impl Primitives for DummyPrimitives {
     ) {
     }
 
+    fn decl_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+    fn forget_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+
     fn send_data(
         &self,
         _key_expr: &KeyExpr,
@@ -145,6 +159,7 @@ impl Primitives for DummyPrimitives {
         _cogestion_control: CongestionControl,
         _info: Option<DataInfo>,
         _routing_context: Option<RoutingContext>,
+        _attachment: Option<Attachment>,
     ) {
     }
     fn send_query(
@@ -154,7 +169,9 @@ impl Primitives for DummyPrimitives {
         _qid: ZInt,
         _target: QueryTarget,
         _consolidation: ConsolidationStrategy,
+        _body: Option<QueryBody>,
         _routing_context: Option<RoutingContext>,
+        _attachment: Option<Attachment>,
     ) {
     }
     fn send_reply_data(
@@ -162,9 +179,11 @@ impl Primitives for DummyPrimitives {
         _qid: ZInt,
         _replier_kind: ZInt,
         _replier_id: PeerId,
+        _is_error: bool,
         _key_expr: KeyExpr,
         _info: Option<DataInfo>,
         _payload: ZBuf,
+        _attachment: Option<Attachment>,
     ) {
     }
     fn send_reply_final(&self, _qid: ZInt) {}
@@ -11,30 +11,50 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
-use super::super::TransportUnicast;
+use super::super::{TransportMulticast, TransportUnicast};
 use super::protocol::core::{
     Channel, CongestionControl, ConsolidationStrategy, KeyExpr, PeerId, QueryTarget, QueryableInfo,
     SubInfo, ZInt,
 };
 use super::protocol::io::ZBuf;
 use super::protocol::proto::{
-    zmsg, DataInfo, Declaration, ForgetPublisher, ForgetQueryable, ForgetResource,
-    ForgetSubscriber, Publisher, Queryable, ReplierInfo, ReplyContext, Resource, RoutingContext,
-    Subscriber, ZenohMessage,
+    zmsg, Attachment, DataInfo, Declaration, ForgetPublisher, ForgetQueryable, ForgetResource,
+    ForgetSubscriber, ForgetToken, Publisher, QueryBody, Queryable, ReplierInfo, ReplyContext,
+    Resource, RoutingContext, Subscriber, Token, ZenohMessage,
 };
 use super::Primitives;
+use zenoh_core::Result as ZResult;
 
-pub struct Mux {
-    handler: TransportUnicast,
+/// A transport on which the [`Mux`] sends the messages of the primitives.
+pub trait MuxTransport: Send + Sync {
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()>;
 }
 
-impl Mux {
-    pub fn new(handler: TransportUnicast) -> Mux {
+impl MuxTransport for TransportUnicast {
+    #[inline(always)]
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
+        TransportUnicast::handle_message(self, message)
+    }
+}
+
+impl MuxTransport for TransportMulticast {
+    #[inline(always)]
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
+        TransportMulticast::handle_message(self, message)
+    }
+}
+
+pub struct Mux<T: MuxTransport = TransportUnicast> {
+    handler: T,
+}
+
+impl<T: MuxTransport> Mux<T> {
+    pub fn new(handler: T) -> Mux<T> {
         Mux { handler }
     }
 }
 
-impl Primitives for Mux {
+impl<T: MuxTransport> Primitives for Mux<T> {
     fn decl_resource(&self, expr_id: ZInt, key_expr: &KeyExpr) {
         let d = Declaration::Resource(Resource {
             expr_id,
@@ -134,6 +154,26 @@ impl Primitives for Mux {
                 .handle_message(ZenohMessage::make_declare(decls, routing_context, None));
     }
 
+    fn decl_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        let d = Declaration::Token(Token {
+            key: key_expr.to_owned(),
+        });
+        let decls = vec![d];
+        let _ =
+            self.handler
+                .handle_message(ZenohMessage::make_declare(decls, routing_context, None));
+    }
+
+    fn forget_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        let d = Declaration::ForgetToken(ForgetToken {
+            key: key_expr.to_owned(),
+        });
+        let decls = vec![d];
+        let _ =
+            self.handler
+                .handle_message(ZenohMessage::make_declare(decls, routing_context, None));
+    }
+
     fn send_data(
         &self,
         key_expr: &KeyExpr,
@@ -142,6 +182,7 @@ impl Primitives for Mux {
         cogestion_control: CongestionControl,
         data_info: Option<DataInfo>,
         routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
     ) {
         let _ = self.handler.handle_message(ZenohMessage::make_data(
             key_expr.to_owned(),
@@ -151,7 +192,7 @@ impl Primitives for Mux {
             data_info,
             routing_context,
             None,
-            None,
+            attachment,
         ));
     }
 
@@ -162,7 +203,9 @@ impl Primitives for Mux {
         qid: ZInt,
         target: QueryTarget,
         consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
         routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
     ) {
         let target_opt = if target == QueryTarget::default() {
             None
@@ -175,8 +218,9 @@ impl Primitives for Mux {
             qid,
             target_opt,
             consolidation,
+            body,
             routing_context,
-            None,
+            attachment,
         ));
     }
 
@@ -185,10 +229,21 @@ impl Primitives for Mux {
         qid: ZInt,
         replier_kind: ZInt,
         replier_id: PeerId,
+        is_error: bool,
         key_expr: KeyExpr,
         data_info: Option<DataInfo>,
         payload: ZBuf,
+        attachment: Option<Attachment>,
     ) {
+        let replier = ReplierInfo {
+            kind: replier_kind,
+            id: replier_id,
+        };
+        let reply_context = if is_error {
+            ReplyContext::new_error(qid, replier)
+        } else {
+            ReplyContext::new(qid, Some(replier))
+        };
         let _ = self.handler.handle_message(ZenohMessage::make_data(
             key_expr.to_owned(),
             payload,
@@ -196,14 +251,8 @@ impl Primitives for Mux {
             zmsg::default_congestion_control::REPLY,
             data_info,
             None,
-            Some(ReplyContext::new(
-                qid,
-                Some(ReplierInfo {
-                    kind: replier_kind,
-                    id: replier_id,
-                }),
-            )),
-            None,
+            Some(reply_context),
+            attachment,
         ));
     }
 
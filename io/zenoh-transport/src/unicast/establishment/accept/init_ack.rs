@@ -47,12 +47,16 @@ pub(super) async fn send(
         Some(agreed_sn_resolution)
     };
 
+    // Use compression on the link only if both ends have it enabled
+    let is_compression = manager.config.unicast.is_compression && input.is_compression;
+
     // Create the cookie
     let cookie = Cookie {
         whatami: input.whatami,
         pid: input.pid,
         sn_resolution: agreed_sn_resolution,
         is_qos: input.is_qos,
+        is_compression,
         nonce: zasynclock!(manager.prng).gen_range(0..agreed_sn_resolution),
     };
 
@@ -106,6 +110,7 @@ pub(super) async fn send(
         apid,
         sn_resolution,
         input.is_qos,
+        is_compression,
         cookie,
         attachment,
     );
@@ -29,6 +29,7 @@ pub(super) struct Output {
     pub(super) pid: PeerId,
     pub(super) sn_resolution: ZInt,
     pub(super) is_qos: bool,
+    pub(super) is_compression: bool,
     pub(super) init_syn_properties: EstablishmentProperties,
 }
 pub(super) async fn recv(
@@ -99,6 +100,7 @@ pub(super) async fn recv(
         pid: init_syn.pid,
         sn_resolution: init_syn.sn_resolution,
         is_qos: init_syn.is_qos,
+        is_compression: init_syn.is_compression,
         init_syn_properties,
     };
     Ok(output)
@@ -70,6 +70,7 @@ pub(crate) async fn accept_link(
         sn_resolution: output.cookie.sn_resolution,
         is_shm: output.is_shm,
         is_qos: output.cookie.is_qos,
+        auth_ids: auth_link.auth_ids.clone(),
     };
     let transport = step!(transport_init(manager, input).await);
 
@@ -95,7 +96,11 @@ pub(crate) async fn accept_link(
     let _ = step!(step!(transport
         .get_inner()
         .map_err(|e| (e, Some(tmsg::close_reason::INVALID))))
-    .add_link(link.clone(), LinkUnicastDirection::Inbound)
+    .add_link(
+        link.clone(),
+        LinkUnicastDirection::Inbound,
+        output.cookie.is_compression,
+    )
     .map_err(|e| (e, Some(tmsg::close_reason::MAX_LINKS))));
 
     // Sync the RX sequence number
@@ -0,0 +1,136 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use super::{AuthId, LinkAuthenticator, LinkAuthenticatorId, LinkUnicastAuthenticatorTrait};
+use super::{Link, LinkUnicast, PeerId};
+use async_std::sync::Arc;
+use async_trait::async_trait;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+use zenoh_config::Config;
+use zenoh_core::{zerror, Result as ZResult};
+
+/// Extract the identity of the certificate a remote end has been authenticated with.
+pub fn certificate_auth_id(der: &[u8]) -> ZResult<AuthId> {
+    let (_, certificate) =
+        X509Certificate::from_der(der).map_err(|e| zerror!("Invalid peer certificate: {}", e))?;
+
+    let subject = certificate.subject().to_string();
+    let alt_names = match certificate
+        .subject_alternative_name()
+        .map_err(|e| zerror!("Invalid peer certificate: {}", e))?
+    {
+        Some(ext) => ext
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(s) | GeneralName::RFC822Name(s) | GeneralName::URI(s) => {
+                    Some(s.to_string())
+                }
+                GeneralName::IPAddress(ip) => match ip.len() {
+                    4 => Some(IpAddr::from(<[u8; 4]>::try_from(*ip).ok()?).to_string()),
+                    16 => Some(IpAddr::from(<[u8; 16]>::try_from(*ip).ok()?).to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    Ok(AuthId::Certificate { subject, alt_names })
+}
+
+/// A link authenticator binding the certificate presented by the remote end of a TLS or QUIC
+/// link to the transport established over it. The certificate itself is validated by the link.
+pub struct CertificateAuthenticator;
+
+impl CertificateAuthenticator {
+    pub fn make() -> LinkAuthenticator {
+        LinkAuthenticator(Arc::new(CertificateAuthenticator))
+    }
+
+    pub async fn from_config(config: &Config) -> ZResult<Option<LinkAuthenticator>> {
+        // Only the listeners authenticating their clients get a certificate from the remote end
+        match config.transport().link().tls().client_auth() {
+            Some(true) => Ok(Some(Self::make())),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl LinkUnicastAuthenticatorTrait for CertificateAuthenticator {
+    fn id(&self) -> LinkAuthenticatorId {
+        LinkAuthenticatorId::Certificate
+    }
+
+    async fn close(&self) {}
+
+    async fn handle_new_link(&self, _link: &LinkUnicast) -> ZResult<Option<PeerId>> {
+        Ok(None)
+    }
+
+    async fn get_auth_id(&self, link: &LinkUnicast) -> Option<AuthId> {
+        let certificate = link.get_peer_certificates().first()?;
+        match certificate_auth_id(certificate) {
+            Ok(auth_id) => Some(auth_id),
+            Err(e) => {
+                log::warn!("Can not identify the remote end of link {}: {}", link, e);
+                None
+            }
+        }
+    }
+
+    async fn handle_link_err(&self, _link: &Link) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NOTE: an auto-generated certificate for the client "alice", with alternative names.
+    const CERT: &str = "-----BEGIN CERTIFICATE-----
+MIICqDCCAZCgAwIBAgIULpsbysxsWGbp6GDkPxu6gUM60ucwDQYJKoZIhvcNAQEL
+BQAwGzEZMBcGA1UEAwwQcmVsb2FkIHRlc3QgQ0EgYTAgFw0yNjEwMTcwMjMyMzVa
+GA8yMTI2MDkyMzAyMzIzNVowIDEOMAwGA1UECgwFemVub2gxDjAMBgNVBAMMBWFs
+aWNlMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAENUThiUhrNUs1EO9QdHVkHKC7
+GaqnfxDwxuKGZwudV+4JM4f0KE7pu+kV+APsc5Wx21VJMCPleXtGNP9tFjQEg6OB
+pzCBpDAvBgNVHREEKDAmgg5hbGljZS56ZW5vaC5pb4EOYWxpY2VAemVub2guaW+H
+BH8AAAEwDAYDVR0TAQH/BAIwADAOBgNVHQ8BAf8EBAMCB4AwEwYDVR0lBAwwCgYI
+KwYBBQUHAwIwHQYDVR0OBBYEFJNyh2o/H8EgiXuxoZRX3brKFTjgMB8GA1UdIwQY
+MBaAFNyI+VoxfrytL7mtk9FxuQMekkA7MA0GCSqGSIb3DQEBCwUAA4IBAQAiEwh3
+1qp6+JsLi3jPthsF6VtaNggYCOHq8dPYj7RBeXgJxOmdejKIuyE0a/t04bZlcCnI
+CXbaCOdDH/BNyYbwn923hFQqBuWhRbGflUfU0BwTdAiBflrz3MECDU79CV6NX3lH
+LWUOy2hKJJhlEn/IplN4Q68uGp5jDGlm8oQVVCYqqpanljCEkRdjSn/srNcz/0z6
+xlD33N0FT9dpSxBELz5NmLt/AvKZBZjgrm1sSDhtJZL5eqs3K2VwShbPzzh6qBjo
+8n03ExqxlaOPTtG2DFlSgtpWpUe7J6EqhaItEbHN0wg44Ay7ys0NpAW8DuDUvWAD
+dg3FabGW8vT08qKs
+-----END CERTIFICATE-----";
+
+    #[test]
+    fn certificate_identity() {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(CERT.as_bytes()).unwrap();
+        match certificate_auth_id(&pem.contents).unwrap() {
+            AuthId::Certificate { subject, alt_names } => {
+                assert_eq!(subject, "O=zenoh, CN=alice");
+                assert_eq!(alt_names, ["alice.zenoh.io", "alice@zenoh.io", "127.0.0.1"]);
+            }
+            id => panic!("Unexpected identity: {:?}", id),
+        }
+        assert!(certificate_auth_id(b"not a certificate").is_err());
+    }
+}
@@ -11,6 +11,8 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+#[cfg(feature = "auth_certificate")]
+mod certificate;
 #[cfg(feature = "auth_pubkey")]
 mod pubkey;
 #[cfg(feature = "shared-memory")]
@@ -21,6 +23,8 @@ mod userpassword;
 use crate::unicast::establishment::Cookie;
 use async_std::sync::Arc;
 use async_trait::async_trait;
+#[cfg(feature = "auth_certificate")]
+pub use certificate::*;
 #[cfg(feature = "auth_pubkey")]
 pub use pubkey::*;
 #[cfg(feature = "shared-memory")]
@@ -33,7 +37,7 @@ use std::ops::Deref;
 pub use userpassword::*;
 use zenoh_config::Config;
 use zenoh_core::Result as ZResult;
-use zenoh_link::{Link, Locator};
+use zenoh_link::{Link, LinkUnicast, Locator};
 use zenoh_protocol::core::{PeerId, ZInt};
 #[cfg(feature = "auth_usrpwd")]
 use zenoh_protocol::io::{WBuf, ZBuf};
@@ -45,13 +49,24 @@ use zenoh_protocol::io::{WBuf, ZBuf};
 #[repr(u8)]
 pub enum LinkAuthenticatorId {
     Reserved = 0,
+    Certificate = 1,
 }
 
 pub struct LinkAuthenticator(Arc<dyn LinkUnicastAuthenticatorTrait + Send + Sync>);
 
 impl LinkAuthenticator {
     pub(crate) async fn from_config(_config: &Config) -> ZResult<HashSet<LinkAuthenticator>> {
-        Ok(HashSet::new())
+        let mut las = HashSet::new();
+
+        #[cfg(feature = "auth_certificate")]
+        {
+            let mut res = CertificateAuthenticator::from_config(_config).await?;
+            if let Some(la) = res.take() {
+                las.insert(la);
+            }
+        }
+
+        Ok(las)
     }
 }
 
@@ -89,7 +104,10 @@ pub trait LinkUnicastAuthenticatorTrait {
     async fn close(&self);
 
     /// Handle new links
-    async fn handle_new_link(&self, link: &Link) -> ZResult<Option<PeerId>>;
+    async fn handle_new_link(&self, link: &LinkUnicast) -> ZResult<Option<PeerId>>;
+
+    /// Return the identity the remote end of a new link has been authenticated with, if any.
+    async fn get_auth_id(&self, link: &LinkUnicast) -> Option<AuthId>;
 
     /// Handle any error on a link. This callback is mainly used to clean-up any internal state
     /// of the authenticator in such a way no unnecessary data is left around.
@@ -112,10 +130,14 @@ impl LinkUnicastAuthenticatorTrait for DummyLinkUnicastAuthenticator {
 
     async fn close(&self) {}
 
-    async fn handle_new_link(&self, _link: &Link) -> ZResult<Option<PeerId>> {
+    async fn handle_new_link(&self, _link: &LinkUnicast) -> ZResult<Option<PeerId>> {
         Ok(None)
     }
 
+    async fn get_auth_id(&self, _link: &LinkUnicast) -> Option<AuthId> {
+        None
+    }
+
     async fn handle_link_err(&self, _link: &Link) {}
 }
 
@@ -194,12 +216,40 @@ impl Hash for PeerAuthenticator {
     }
 }
 
+/// The identity a remote peer has been authenticated with by a [`PeerAuthenticator`] or a
+/// [`LinkAuthenticator`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AuthId {
+    /// The user name validated by the user-password authenticator.
+    Username(String),
+    /// The PEM encoding of the public key validated by the public-key authenticator:
+    /// PKCS#1 for RSA keys, SubjectPublicKeyInfo for Ed25519 and X25519 keys.
+    PublicKey(String),
+    /// The subject and subject alternative names of the certificate validated by a TLS or QUIC link.
+    Certificate {
+        subject: String,
+        alt_names: Vec<String>,
+    },
+}
+
+impl fmt::Display for AuthId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthId::Username(user) => write!(f, "user:{}", user),
+            AuthId::PublicKey(_) => write!(f, "pubkey"),
+            AuthId::Certificate { subject, .. } => write!(f, "cert:{}", subject),
+        }
+    }
+}
+
 // Authenticated peer link
 #[derive(Debug)]
 pub struct AuthenticatedPeerLink {
     pub src: Locator,
     pub dst: Locator,
     pub peer_id: Option<PeerId>,
+    /// The identities the link authenticators validated for the remote end of the link.
+    pub auth_ids: Vec<AuthId>,
 }
 
 impl fmt::Display for AuthenticatedPeerLink {
@@ -312,6 +362,24 @@ pub trait PeerAuthenticatorTrait: Send + Sync {
     /// * `peerd_id` - The [`PeerId`][PeerId] of the transport being closed.
     ///
     async fn handle_close(&self, peer_id: &PeerId);
+
+    /// Return the identity the given peer has been authenticated with, if any.
+    ///
+    /// # Arguments
+    /// * `peerd_id` - The [`PeerId`][PeerId] of the authenticated peer.
+    ///
+    async fn get_auth_id(&self, _peer_id: &PeerId) -> Option<AuthId> {
+        None
+    }
+
+    /// Apply a configuration change at runtime. The transports already established are not affected.
+    ///
+    /// # Arguments
+    /// * `config` - The updated [`Config`][Config].
+    ///
+    async fn reload(&self, _config: &Config) -> ZResult<()> {
+        Ok(())
+    }
 }
 
 /*************************************/
@@ -12,15 +12,22 @@
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
 use super::{
-    AuthenticatedPeerLink, PeerAuthenticator, PeerAuthenticatorId, PeerAuthenticatorTrait,
+    AuthId, AuthenticatedPeerLink, PeerAuthenticator, PeerAuthenticatorId, PeerAuthenticatorTrait,
 };
 use super::{Locator, PeerId, WBuf, ZBuf, ZInt};
 use crate::unicast::establishment::Cookie;
+use argon2::password_hash::{Output, PasswordHash, PasswordHasher, SaltString};
+use argon2::Argon2;
 use async_std::fs;
+use async_std::prelude::*;
 use async_std::sync::{Arc, Mutex, RwLock};
+use async_std::task;
 use async_trait::async_trait;
+use pbkdf2::Pbkdf2;
 use std::collections::{HashMap, HashSet};
-use zenoh_buffers::reader::HasReader;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime};
+use zenoh_buffers::reader::{HasReader, Reader};
 use zenoh_buffers::{SplitBuffer, ZBufReader};
 use zenoh_cfg_properties::Properties;
 use zenoh_config::Config;
@@ -28,9 +35,26 @@ use zenoh_core::{bail, zasynclock, zasyncread, zasyncwrite, zerror};
 use zenoh_core::{zcheck, Result as ZResult};
 use zenoh_crypto::hmac;
 use zenoh_protocol::io::{WBufCodec, ZBufCodec};
+use zenoh_sync::Signal;
 
 const WBUF_SIZE: usize = 64;
-const USRPWD_VERSION: ZInt = 1;
+// The HMAC of the plain text password keyed by the nonce
+const USRPWD_VERSION_HMAC: ZInt = 1;
+// The SCRAM-like proof of the password, possibly hashed (see `client_proof`)
+const USRPWD_VERSION_SCRAM: ZInt = 2;
+// Amount of time between two checks of the dictionary file.
+// The dictionary is reloaded for the new handshakes as soon as the file changes.
+const DICTIONARY_WATCH_INTERVAL: Duration = Duration::from_millis(1_000);
+// The maximum costs of the password hashes. The hash of the password of this peer is derived
+// with the algorithm and parameters sent by the remote peer: they are bounded so that the
+// remote peer can not exhaust the memory or the CPU of this one.
+const ARGON2_MAX_M_COST: u32 = 256 * 1024; // KiB
+const ARGON2_MAX_T_COST: u32 = 16;
+const ARGON2_MAX_P_COST: u32 = 16;
+const PBKDF2_MAX_ROUNDS: u32 = 2_000_000;
+// The algorithm and parameters of the templates of the plain text passwords and of the unknown
+// users, when no password of the lookup is hashed.
+const DEFAULT_TEMPLATE: &str = "$pbkdf2-sha256$i=10000,l=32";
 
 /// # Attachment decorator
 ///
@@ -58,10 +82,16 @@ const USRPWD_VERSION: ZInt = 1;
 /// +-+-+-+-+-+-+-+-+
 /// |0 0 0|  ATTCH  |
 /// +-+-+-+---------+
-/// ~    version    ~
+/// ~    version    ~ -- Always 1, the peers only supporting version 1 ignore what follows
+/// +---------------+
+/// ~  max version  ~ -- Optional, the highest version supported
+/// +---------------+
+/// ~     user      ~ -- Optional, present with the max version
 /// +---------------+
 struct InitSynProperty {
     version: ZInt,
+    max_version: ZInt,
+    user: Option<Vec<u8>>,
 }
 
 /*************************************/
@@ -73,8 +103,11 @@ struct InitSynProperty {
 /// +-+-+-+---------+
 /// ~     nonce     ~
 /// +---------------+
+/// ~   template    ~ -- Present if version == 2, the password hash to derive (see `Password`)
+/// +---------------+
 struct InitAckProperty {
     nonce: ZInt,
+    template: Option<String>,
 }
 
 /*************************************/
@@ -86,28 +119,54 @@ struct InitAckProperty {
 /// +-+-+-+---------+
 /// ~     user      ~
 /// +---------------+
-/// ~     hash      ~
-/// +---------------+
+/// ~     proof     ~ -- if version == 1: the HMAC of the password keyed by the nonce
+/// +---------------+ if version == 2: the proof of the knowledge of the password, see `client_proof`
 struct OpenSynProperty {
     user: Vec<u8>,
-    hmac: Vec<u8>,
+    proof: Vec<u8>,
+}
+
+/*************************************/
+/*              Cookie               */
+/*************************************/
+struct CookieProperty {
+    version: ZInt,
+    template: Option<String>,
 }
 
 trait WUsrPw {
     fn write_init_syn_property_usrpwd(&mut self, init_syn_property: &InitSynProperty) -> bool;
     fn write_init_ack_property_usrpwd(&mut self, init_ack_property: &InitAckProperty) -> bool;
     fn write_open_syn_property_usrpwd(&mut self, open_syn_property: &OpenSynProperty) -> bool;
+    fn write_cookie_property_usrpwd(&mut self, cookie_property: &CookieProperty) -> bool;
 }
 impl WUsrPw for WBuf {
     fn write_init_syn_property_usrpwd(&mut self, init_syn_property: &InitSynProperty) -> bool {
-        self.write_zint(init_syn_property.version)
+        zcheck!(self.write_zint(init_syn_property.version));
+        match init_syn_property.user.as_ref() {
+            Some(user) => {
+                self.write_zint(init_syn_property.max_version) && self.write_bytes_array(user)
+            }
+            None => true,
+        }
     }
     fn write_init_ack_property_usrpwd(&mut self, init_ack_property: &InitAckProperty) -> bool {
-        self.write_zint(init_ack_property.nonce)
+        zcheck!(self.write_zint(init_ack_property.nonce));
+        match init_ack_property.template.as_ref() {
+            Some(template) => self.write_bytes_array(template.as_bytes()),
+            None => true,
+        }
     }
     fn write_open_syn_property_usrpwd(&mut self, open_syn_property: &OpenSynProperty) -> bool {
         zcheck!(self.write_bytes_array(&open_syn_property.user));
-        self.write_bytes_array(&open_syn_property.hmac)
+        self.write_bytes_array(&open_syn_property.proof)
+    }
+    fn write_cookie_property_usrpwd(&mut self, cookie_property: &CookieProperty) -> bool {
+        zcheck!(self.write_zint(cookie_property.version));
+        match cookie_property.template.as_ref() {
+            Some(template) => self.write_bytes_array(template.as_bytes()),
+            None => true,
+        }
     }
 }
 
@@ -115,20 +174,286 @@ trait ZUsrPw {
     fn read_init_syn_property_usrpwd(&mut self) -> Option<InitSynProperty>;
     fn read_init_ack_property_usrpwd(&mut self) -> Option<InitAckProperty>;
     fn read_open_syn_property_usrpwd(&mut self) -> Option<OpenSynProperty>;
+    fn read_cookie_property_usrpwd(&mut self) -> Option<CookieProperty>;
 }
 impl ZUsrPw for ZBufReader<'_> {
     fn read_init_syn_property_usrpwd(&mut self) -> Option<InitSynProperty> {
         let version = self.read_zint()?;
-        Some(InitSynProperty { version })
+        let (max_version, user) = if self.can_read() {
+            (self.read_zint()?, Some(self.read_bytes_array()?))
+        } else {
+            (version, None)
+        };
+        Some(InitSynProperty {
+            version,
+            max_version,
+            user,
+        })
     }
     fn read_init_ack_property_usrpwd(&mut self) -> Option<InitAckProperty> {
         let nonce = self.read_zint()?;
-        Some(InitAckProperty { nonce })
+        let template = if self.can_read() {
+            Some(String::from_utf8(self.read_bytes_array()?).ok()?)
+        } else {
+            None
+        };
+        Some(InitAckProperty { nonce, template })
     }
     fn read_open_syn_property_usrpwd(&mut self) -> Option<OpenSynProperty> {
         let user = self.read_bytes_array()?;
-        let hmac = self.read_bytes_array()?;
-        Some(OpenSynProperty { user, hmac })
+        let proof = self.read_bytes_array()?;
+        Some(OpenSynProperty { user, proof })
+    }
+    fn read_cookie_property_usrpwd(&mut self) -> Option<CookieProperty> {
+        let version = self.read_zint()?;
+        let template = if self.can_read() {
+            Some(String::from_utf8(self.read_bytes_array()?).ok()?)
+        } else {
+            None
+        };
+        Some(CookieProperty { version, template })
+    }
+}
+
+/*************************************/
+/*            Passwords              */
+/*************************************/
+#[derive(Clone, PartialEq, Eq)]
+enum Password {
+    Plain(Vec<u8>),
+    // A salted hash in the PHC string format, e.g. `$argon2id$v=19$m=19456,t=2,p=1$<salt>$<key>`,
+    // whose output is the stored key of the password (see `stored_password`) rather than its hash.
+    // The template is the same PHC string without the output: it carries everything the remote
+    // peer needs to derive the same hash from its password.
+    Hashed {
+        template: String,
+        stored_key: Vec<u8>,
+    },
+}
+
+impl Password {
+    fn template(&self) -> Option<String> {
+        match self {
+            Password::Plain(_) => None,
+            Password::Hashed { template, .. } => Some(template.clone()),
+        }
+    }
+
+    // The key the proof of the remote peer is checked with, `template` being the one sent to it
+    fn stored_key(&self, template: &str) -> ZResult<Vec<u8>> {
+        match self {
+            Password::Plain(password) => Ok(stored_key(&client_key(&hash_password(
+                password, template,
+            )?)?)),
+            Password::Hashed { stored_key, .. } => Ok(stored_key.clone()),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Password {
+    fn from(password: Vec<u8>) -> Password {
+        let hashed = std::str::from_utf8(&password).ok().and_then(|s| {
+            let phc = PasswordHash::new(s).ok()?;
+            if !is_supported(&phc) {
+                return None;
+            }
+            let stored_key = phc.hash?.as_bytes().to_vec();
+            let template = PasswordHash { hash: None, ..phc };
+            if let Err(e) = hash_params(&template) {
+                log::warn!("Peers will refuse to derive the password hash {}: {}", s, e);
+            }
+            Some(Password::Hashed {
+                template: template.to_string(),
+                stored_key,
+            })
+        });
+        hashed.unwrap_or(Password::Plain(password))
+    }
+}
+
+// The proof of the knowledge of a password is built as in SCRAM (RFC 5802), the salted password
+// being either the plain text password or its hash. Only the stored key is needed to check a
+// proof, and it is not enough to build one: a leaked dictionary does not let anyone authenticate.
+fn client_key(salted_password: &[u8]) -> ZResult<Vec<u8>> {
+    hmac::sign(salted_password, b"Client Key")
+}
+
+fn stored_key(client_key: &[u8]) -> Vec<u8> {
+    hmac::digest(client_key)
+}
+
+fn auth_message(nonce: ZInt, user: &[u8]) -> Vec<u8> {
+    let mut message = nonce.to_le_bytes().to_vec();
+    message.extend_from_slice(user);
+    message
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect()
+}
+
+// ClientProof = ClientKey XOR HMAC(StoredKey, AuthMessage)
+fn client_proof(salted_password: &[u8], nonce: ZInt, user: &[u8]) -> ZResult<Vec<u8>> {
+    let client_key = client_key(salted_password)?;
+    let signature = hmac::sign(&stored_key(&client_key), &auth_message(nonce, user))?;
+    Ok(xor(&client_key, &signature))
+}
+
+// Recover the client key from the proof and check it against the stored key
+fn verify_proof(stored: &[u8], nonce: ZInt, user: &[u8], proof: &[u8]) -> ZResult<bool> {
+    let signature = hmac::sign(stored, &auth_message(nonce, user))?;
+    if proof.len() != signature.len() {
+        return Ok(false);
+    }
+    Ok(hmac::eq(&stored_key(&xor(proof, &signature)), stored))
+}
+
+// The proof of the version 1 of the handshake: the HMAC of the plain text password keyed by the nonce
+fn client_hmac(password: &[u8], nonce: ZInt) -> ZResult<Vec<u8>> {
+    hmac::sign(&nonce.to_le_bytes(), password)
+}
+
+fn verify_hmac(password: &[u8], nonce: ZInt, proof: &[u8]) -> ZResult<bool> {
+    Ok(hmac::eq(&client_hmac(password, nonce)?, proof))
+}
+
+fn is_supported(phc: &PasswordHash) -> bool {
+    phc.salt.is_some()
+        && matches!(
+            phc.algorithm.as_str(),
+            "argon2d" | "argon2i" | "argon2id" | "pbkdf2-sha256" | "pbkdf2-sha512"
+        )
+}
+
+enum HashParams {
+    Argon2(argon2::Params),
+    Pbkdf2(pbkdf2::Params),
+}
+
+// The parameters of a supported password hash, if they are within the maximum costs
+fn hash_params(phc: &PasswordHash) -> ZResult<HashParams> {
+    if !is_supported(phc) {
+        bail!("Unsupported password hash: {}", phc.algorithm);
+    }
+    if phc.algorithm.as_str().starts_with("argon2") {
+        let params =
+            argon2::Params::try_from(phc).map_err(|e| zerror!("Invalid password hash: {}", e))?;
+        if params.m_cost() > ARGON2_MAX_M_COST
+            || params.t_cost() > ARGON2_MAX_T_COST
+            || params.p_cost() > ARGON2_MAX_P_COST
+        {
+            bail!("Password hash parameters out of range: {}", phc.params);
+        }
+        Ok(HashParams::Argon2(params))
+    } else {
+        let params =
+            pbkdf2::Params::try_from(phc).map_err(|e| zerror!("Invalid password hash: {}", e))?;
+        if params.rounds > PBKDF2_MAX_ROUNDS {
+            bail!("Password hash parameters out of range: {}", phc.params);
+        }
+        Ok(HashParams::Pbkdf2(params))
+    }
+}
+
+// Check that the hash of a password can be derived with `template` within the maximum costs
+fn check_template(template: &str) -> ZResult<()> {
+    let phc = PasswordHash::new(template).map_err(|e| zerror!("Invalid password hash: {}", e))?;
+    hash_params(&PasswordHash { hash: None, ..phc }).map(|_| ())
+}
+
+// Derive the hash of `password` with the algorithm, parameters and salt of `template`
+fn hash_password(password: &[u8], template: &str) -> ZResult<Vec<u8>> {
+    let phc = PasswordHash::new(template).map_err(|e| zerror!("Invalid password hash: {}", e))?;
+    // The output of the template, if any, is not the one of the hash
+    let phc = PasswordHash { hash: None, ..phc };
+    let params = hash_params(&phc)?;
+    // The salt is always present for the supported hashes
+    let salt = phc.salt.unwrap();
+    let hashed = match params {
+        HashParams::Argon2(params) => Argon2::default().hash_password_customized(
+            password,
+            Some(phc.algorithm),
+            phc.version,
+            params,
+            salt,
+        ),
+        HashParams::Pbkdf2(params) => Pbkdf2.hash_password_customized(
+            password,
+            Some(phc.algorithm),
+            phc.version,
+            params,
+            salt,
+        ),
+    }
+    .map_err(|e| zerror!("Invalid password hash: {}", e))?;
+    match hashed.hash {
+        Some(hash) => Ok(hash.as_bytes().to_vec()),
+        None => bail!("Invalid password hash: missing output"),
+    }
+}
+
+/*************************************/
+/*            Dictionary             */
+/*************************************/
+async fn load_dictionary(path: &str) -> ZResult<HashMap<Vec<u8>, Password>> {
+    let content = fs::read_to_string(path)
+        .await
+        .map_err(|e| zerror!("Invalid user-password dictionary file: {}", e))?;
+    let mut ps = Properties::from(content);
+    Ok(ps
+        .drain()
+        .map(|(user, password)| (user.into(), Vec::from(password).into()))
+        .collect())
+}
+
+// The last modification time of the dictionary file, used to detect changes
+async fn dictionary_modified(path: Option<&str>) -> Option<SystemTime> {
+    let metadata = fs::metadata(path?).await;
+    metadata.and_then(|m| m.modified()).ok()
+}
+
+async fn watch_task(
+    lookup: Arc<RwLock<Lookup>>,
+    mut watched: (Option<String>, Option<SystemTime>),
+    signal: Signal,
+) {
+    // Check the dictionary file until the authenticator is closed
+    while signal
+        .wait()
+        .timeout(DICTIONARY_WATCH_INTERVAL)
+        .await
+        .is_err()
+    {
+        let file = zasyncread!(lookup).dictionary_file.clone();
+        let modified = dictionary_modified(file.as_deref()).await;
+        let current = (file, modified);
+        if current == watched {
+            continue;
+        }
+        watched = current;
+
+        let file = match watched.0.as_ref() {
+            Some(file) => file,
+            None => {
+                zasyncwrite!(lookup).dictionary.clear();
+                continue;
+            }
+        };
+        // Keep the current dictionary if the new one can not be loaded, e.g. while being written
+        match load_dictionary(file).await {
+            Ok(dictionary) => {
+                let mut guard = zasyncwrite!(lookup);
+                if guard.dictionary_file.as_ref() == Some(file) {
+                    guard.dictionary = dictionary;
+                    log::info!("Reloaded the user-password dictionary {}", file);
+                }
+            }
+            Err(e) => log::warn!(
+                "Can not reload the user-password dictionary {}. {}",
+                file,
+                e
+            ),
+        }
     }
 }
 
@@ -141,14 +466,78 @@ struct Credentials {
 }
 
 struct Authenticated {
-    credentials: Credentials,
+    user: Vec<u8>,
+    password: Password,
     links: HashSet<(Locator, Locator)>,
 }
 
+#[derive(Default)]
+struct Lookup {
+    // The users of the dictionary file, replaced whenever the file changes
+    dictionary_file: Option<String>,
+    dictionary: HashMap<Vec<u8>, Password>,
+    // The users of the configuration as last applied, and the ones added at runtime
+    configured: HashMap<String, String>,
+    users: HashMap<Vec<u8>, Password>,
+}
+
+impl Lookup {
+    fn get(&self, user: &[u8]) -> Option<&Password> {
+        self.users.get(user).or_else(|| self.dictionary.get(user))
+    }
+
+    // The template sent to the peer authenticating as `user`. The users with a plain text password
+    // and the unknown users get one as well: it has the algorithm and the parameters of a hashed
+    // password of the lookup, and a salt derived from the user name with `salt_key`, so that the
+    // InitAck does not reveal whether a user exists nor how its password is stored.
+    fn template(&self, user: &[u8], salt_key: &[u8]) -> ZResult<String> {
+        if let Some(template) = self.get(user).and_then(Password::template) {
+            return Ok(template);
+        }
+        let template = self
+            .users
+            .iter()
+            .chain(self.dictionary.iter())
+            .filter_map(|(u, p)| Some((u, p.template()?)))
+            .min_by(|a, b| a.0.cmp(b.0))
+            .map(|(_, template)| template)
+            .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+        let phc =
+            PasswordHash::new(&template).map_err(|e| zerror!("Invalid password hash: {}", e))?;
+        let salt = hmac::sign(salt_key, user)?;
+        let salt = SaltString::b64_encode(&salt[..16])
+            .map_err(|e| zerror!("Invalid password hash: {}", e))?;
+        Ok(PasswordHash {
+            salt: Some(salt.as_salt()),
+            hash: None,
+            ..phc
+        }
+        .to_string())
+    }
+
+    fn configure(&mut self, users: &HashMap<String, String>) {
+        for user in self.configured.keys() {
+            if !users.contains_key(user) {
+                self.users.remove(user.as_bytes());
+            }
+        }
+        for (user, password) in users.iter() {
+            if self.configured.get(user) != Some(password) {
+                let password = Vec::from(password.as_bytes()).into();
+                self.users.insert(user.as_bytes().to_vec(), password);
+            }
+        }
+        self.configured = users.clone();
+    }
+}
+
 pub struct UserPasswordAuthenticator {
-    lookup: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+    lookup: Arc<RwLock<Lookup>>,
     credentials: Option<Credentials>,
     authenticated: Mutex<HashMap<PeerId, Authenticated>>,
+    // The key the salts of the templates of the plain text passwords are derived with
+    salt_key: [u8; 32],
+    signal: Option<Signal>,
 }
 
 impl UserPasswordAuthenticator {
@@ -160,52 +549,93 @@ impl UserPasswordAuthenticator {
             user: cr.0,
             password: cr.1,
         });
+        let lookup = Lookup {
+            users: lookup.into_iter().map(|(u, p)| (u, p.into())).collect(),
+            ..Default::default()
+        };
         UserPasswordAuthenticator {
-            lookup: RwLock::new(lookup),
+            lookup: Arc::new(RwLock::new(lookup)),
             credentials,
             authenticated: Mutex::new(HashMap::new()),
+            salt_key: rand::random(),
+            signal: None,
         }
     }
 
+    /// Derive the salted hash of `password` to be stored in place of the password itself, in the
+    /// dictionary file or in the configured users. `template` is a PHC string giving the
+    /// algorithm (argon2 or PBKDF2), its parameters and the salt, e.g.
+    /// `$argon2id$v=19$m=19456,t=2,p=1$<salt>`.
+    ///
+    /// The output of the returned PHC string is not the hash of the password but a key derived
+    /// from it, which lets the authenticator check the password without being able to prove it.
+    pub fn stored_password(password: &[u8], template: &str) -> ZResult<String> {
+        let hash = hash_password(password, template)?;
+        let stored_key = stored_key(&client_key(&hash)?);
+        let phc =
+            PasswordHash::new(template).map_err(|e| zerror!("Invalid password hash: {}", e))?;
+        let output =
+            Output::new(&stored_key).map_err(|e| zerror!("Invalid password hash: {}", e))?;
+        Ok(PasswordHash {
+            hash: Some(output),
+            ..phc
+        }
+        .to_string())
+    }
+
+    /// Add a user, or replace its password. The password may be given as a salted hash, as
+    /// derived by [`UserPasswordAuthenticator::stored_password`].
     pub async fn add_user(&self, user: Vec<u8>, password: Vec<u8>) -> ZResult<()> {
         let mut guard = zasyncwrite!(self.lookup);
-        guard.insert(user, password);
+        guard.users.insert(user, password.into());
         Ok(())
     }
 
+    /// Remove a user. A user of the dictionary file is back as soon as the file changes.
     pub async fn del_user(&self, user: &[u8]) -> ZResult<()> {
         let mut guard = zasyncwrite!(self.lookup);
-        guard.remove(user);
+        guard.users.remove(user);
+        guard.dictionary.remove(user);
         Ok(())
     }
 
     pub async fn from_config(config: &Config) -> ZResult<Option<UserPasswordAuthenticator>> {
         let c = config.transport().auth().usrpwd();
 
-        let mut lookup: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let mut lookup = Lookup::default();
         if let Some(dict) = c.dictionary_file() {
-            let content = fs::read_to_string(dict)
-                .await
-                .map_err(|e| zerror!("Invalid user-password dictionary file: {}", e))?;
             // Populate the user-password dictionary
-            let mut ps = Properties::from(content);
-            for (user, password) in ps.drain() {
-                lookup.insert(user.into(), password.into());
-            }
+            lookup.dictionary = load_dictionary(dict).await?;
+            lookup.dictionary_file = Some(dict.clone());
             log::debug!("User-password dictionary has been configured");
         }
+        lookup.configure(c.users());
 
-        let mut credentials: Option<(Vec<u8>, Vec<u8>)> = None;
+        let mut credentials: Option<Credentials> = None;
         if let Some(user) = c.user() {
             if let Some(password) = c.password() {
                 log::debug!("User and password have been configured");
-                credentials = Some((user.to_string().into(), password.to_string().into()));
+                credentials = Some(Credentials {
+                    user: user.to_string().into(),
+                    password: password.to_string().into(),
+                });
             }
         }
 
-        if !lookup.is_empty() || credentials.is_some() {
+        if lookup.dictionary_file.is_some() || !lookup.users.is_empty() || credentials.is_some() {
             log::debug!("User-password authentication is enabled");
-            Ok(Some(UserPasswordAuthenticator::new(lookup, credentials)))
+            let file = lookup.dictionary_file.clone();
+            let modified = dictionary_modified(file.as_deref()).await;
+            let lookup = Arc::new(RwLock::new(lookup));
+            let signal = Signal::new();
+            task::spawn(watch_task(lookup.clone(), (file, modified), signal.clone()));
+            Ok(Some(UserPasswordAuthenticator {
+                lookup,
+                credentials,
+                authenticated: Mutex::new(HashMap::new()),
+                salt_key: rand::random(),
+                signal: Some(signal),
+            }))
         } else {
             Ok(None)
         }
@@ -219,7 +649,10 @@ impl PeerAuthenticatorTrait for UserPasswordAuthenticator {
     }
 
     async fn close(&self) {
-        // No cleanup needed
+        // Stop watching the dictionary file
+        if let Some(signal) = self.signal.as_ref() {
+            signal.trigger();
+        }
     }
 
     async fn get_init_syn_properties(
@@ -232,8 +665,11 @@ impl PeerAuthenticatorTrait for UserPasswordAuthenticator {
             return Ok(None);
         }
 
+        // Announce the version 1 so that the peers only supporting it accept the InitSyn
         let init_syn_property = InitSynProperty {
-            version: USRPWD_VERSION,
+            version: USRPWD_VERSION_HMAC,
+            max_version: USRPWD_VERSION_SCRAM,
+            user: self.credentials.as_ref().map(|cr| cr.user.clone()),
         };
         let mut wbuf = WBuf::new(WBUF_SIZE, false);
         wbuf.write_init_syn_property_usrpwd(&init_syn_property);
@@ -257,20 +693,43 @@ impl PeerAuthenticatorTrait for UserPasswordAuthenticator {
             None => bail!("Received InitSyn with invalid attachment on link: {}", link),
         };
 
-        if init_syn_property.version > USRPWD_VERSION {
+        if init_syn_property.version > USRPWD_VERSION_SCRAM {
             bail!("Rejected InitSyn with invalid attachment on link: {}", link)
         }
 
+        // Let the peer derive the hash of its password as the stored one. The peers only
+        // supporting the version 1 send the HMAC of their plain text password instead.
+        let (version, template) = if init_syn_property.max_version >= USRPWD_VERSION_SCRAM {
+            let user = match init_syn_property.user.as_ref() {
+                Some(user) => user,
+                None => bail!("Received InitSyn with invalid attachment on link: {}", link),
+            };
+            let template = zasyncread!(self.lookup).template(user, &self.salt_key)?;
+            (USRPWD_VERSION_SCRAM, Some(template))
+        } else {
+            (USRPWD_VERSION_HMAC, None)
+        };
+
         // Create the InitAck attachment
         let init_ack_property = InitAckProperty {
             nonce: cookie.nonce,
+            template: template.clone(),
         };
         // Encode the InitAck property
         let mut wbuf = WBuf::new(WBUF_SIZE, false);
         wbuf.write_init_ack_property_usrpwd(&init_ack_property);
         let attachment = wbuf;
 
-        Ok((Some(attachment.contiguous().into_owned()), None))
+        // Store the version and the template in the cookie
+        let cookie_property = CookieProperty { version, template };
+        let mut wbuf = WBuf::new(WBUF_SIZE, false);
+        wbuf.write_cookie_property_usrpwd(&cookie_property);
+        let cookie = wbuf;
+
+        Ok((
+            Some(attachment.contiguous().into_owned()),
+            Some(cookie.contiguous().into_owned()),
+        ))
     }
 
     async fn handle_init_ack(
@@ -295,13 +754,30 @@ impl PeerAuthenticatorTrait for UserPasswordAuthenticator {
             None => bail!("Received InitAck with invalid attachment on link: {}", link),
         };
 
-        // Create the HMAC of the password using the nonce received as a key (it's a challenge)
-        let key = init_ack_property.nonce.to_le_bytes();
-        let hmac = hmac::sign(&key, &credentials.password)?;
+        // Prove the knowledge of the password for the nonce received (it's a challenge)
+        let nonce = init_ack_property.nonce;
+        let proof = match init_ack_property.template {
+            // Version 2: derive the hash of the password as the one of the remote peer
+            Some(template) => {
+                if let Err(e) = check_template(&template) {
+                    bail!(
+                        "Received InitAck with invalid template on link: {}. {}",
+                        link,
+                        e
+                    )
+                }
+                let password = credentials.password.clone();
+                let salted_password =
+                    task::spawn_blocking(move || hash_password(&password, &template)).await?;
+                client_proof(&salted_password, nonce, &credentials.user)?
+            }
+            // Version 1: the remote peer only supports the HMAC of the plain text password
+            None => client_hmac(&credentials.password, nonce)?,
+        };
         // Create the OpenSyn attachment
         let open_syn_property = OpenSynProperty {
             user: credentials.user.clone(),
-            hmac,
+            proof,
         };
         // Encode the InitAck attachment
         let mut wbuf = WBuf::new(WBUF_SIZE, false);
@@ -317,24 +793,47 @@ impl PeerAuthenticatorTrait for UserPasswordAuthenticator {
         cookie: &Cookie,
         property: (Option<Vec<u8>>, Option<Vec<u8>>),
     ) -> ZResult<Option<Vec<u8>>> {
-        let (attachment, _cookie) = property;
+        let (attachment, cookie_property) = property;
         let zbuf: ZBuf = match attachment {
             Some(p) => p.into(),
             None => bail!("Received OpenSyn with no attachment on link: {}", link),
         };
         let open_syn_property = match zbuf.reader().read_open_syn_property_usrpwd() {
             Some(osp) => osp,
-            None => bail!("Received InitAck with invalid attachment on link: {}", link),
+            None => bail!("Received OpenSyn with invalid attachment on link: {}", link),
+        };
+        let zbuf: ZBuf = match cookie_property {
+            Some(p) => p.into(),
+            None => bail!("Received OpenSyn with no cookie on link: {}", link),
+        };
+        let cookie_property = match zbuf.reader().read_cookie_property_usrpwd() {
+            Some(cp) => cp,
+            None => bail!("Received OpenSyn with invalid cookie on link: {}", link),
         };
         let password = match zasyncread!(self.lookup).get(&open_syn_property.user) {
             Some(password) => password.clone(),
             None => bail!("Received OpenSyn with invalid user on link: {}", link),
         };
 
-        // Create the HMAC of the password using the nonce received as challenge
-        let key = cookie.nonce.to_le_bytes();
-        let hmac = hmac::sign(&key, &password)?;
-        if hmac != open_syn_property.hmac {
+        // Check the proof of the password for the nonce sent as challenge
+        let user = &open_syn_property.user;
+        let proof = &open_syn_property.proof;
+        let is_valid = match (cookie_property.version, cookie_property.template) {
+            (USRPWD_VERSION_SCRAM, Some(template)) => {
+                let stored = password.clone();
+                let stored = task::spawn_blocking(move || stored.stored_key(&template)).await?;
+                verify_proof(&stored, cookie.nonce, user, proof)?
+            }
+            (USRPWD_VERSION_HMAC, None) => match &password {
+                Password::Plain(plain) => verify_hmac(plain, cookie.nonce, proof)?,
+                Password::Hashed { .. } => bail!(
+                    "Received OpenSyn with version 1 for a hashed password on link: {}",
+                    link
+                ),
+            },
+            _ => bail!("Received OpenSyn with invalid cookie on link: {}", link),
+        };
+        if !is_valid {
             bail!("Received OpenSyn with invalid password on link: {}", link)
         }
 
@@ -342,21 +841,19 @@ impl PeerAuthenticatorTrait for UserPasswordAuthenticator {
         let mut guard = zasynclock!(self.authenticated);
         match guard.get_mut(&cookie.pid) {
             Some(auth) => {
-                if open_syn_property.user != auth.credentials.user
-                    || password != auth.credentials.password
-                {
+                if open_syn_property.user != auth.user || password != auth.password {
                     bail!("Received OpenSyn with invalid password on link: {}", link)
                 }
                 auth.links.insert((link.src.clone(), link.dst.clone()));
             }
             None => {
-                let credentials = Credentials {
+                let mut links = HashSet::new();
+                links.insert((link.src.clone(), link.dst.clone()));
+                let auth = Authenticated {
                     user: open_syn_property.user,
                     password,
+                    links,
                 };
-                let mut links = HashSet::new();
-                links.insert((link.src.clone(), link.dst.clone()));
-                let auth = Authenticated { credentials, links };
                 guard.insert(cookie.pid, auth);
             }
         }
@@ -391,6 +888,21 @@ impl PeerAuthenticatorTrait for UserPasswordAuthenticator {
     async fn handle_close(&self, peer_id: &PeerId) {
         zasynclock!(self.authenticated).remove(peer_id);
     }
+
+    async fn get_auth_id(&self, peer_id: &PeerId) -> Option<AuthId> {
+        zasynclock!(self.authenticated)
+            .get(peer_id)
+            .map(|auth| AuthId::Username(String::from_utf8_lossy(&auth.user).into()))
+    }
+
+    async fn reload(&self, config: &Config) -> ZResult<()> {
+        let c = config.transport().auth().usrpwd();
+        let mut guard = zasyncwrite!(self.lookup);
+        guard.configure(c.users());
+        // The dictionary file is (re)loaded by the watch task
+        guard.dictionary_file = c.dictionary_file().clone();
+        Ok(())
+    }
 }
 
 impl From<Arc<UserPasswordAuthenticator>> for PeerAuthenticator {
@@ -404,3 +916,125 @@ impl From<UserPasswordAuthenticator> for PeerAuthenticator {
         Self::from(Arc::new(v))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usrpwd_hash() {
+        for template in [
+            "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHRzYWx0",
+            "$pbkdf2-sha256$i=1000,l=32$c2FsdHNhbHRzYWx0",
+            "$pbkdf2-sha512$i=1000,l=64$c2FsdHNhbHRzYWx0",
+        ] {
+            let phc = UserPasswordAuthenticator::stored_password(b"password01", template).unwrap();
+            let password = Password::from(phc.as_bytes().to_vec());
+            // The template does not reveal the stored key
+            assert_eq!(password.template().unwrap(), template);
+            let stored_key = password.stored_key(template).unwrap();
+
+            let hash = hash_password(b"password01", template).unwrap();
+            let proof = client_proof(&hash, 42, b"user01").unwrap();
+            assert!(verify_proof(&stored_key, 42, b"user01", &proof).unwrap());
+            // The proof is bound to the nonce and to the user
+            assert!(!verify_proof(&stored_key, 43, b"user01", &proof).unwrap());
+            assert!(!verify_proof(&stored_key, 42, b"user02", &proof).unwrap());
+
+            let hash = hash_password(b"invalid", template).unwrap();
+            let proof = client_proof(&hash, 42, b"user01").unwrap();
+            assert!(!verify_proof(&stored_key, 42, b"user01", &proof).unwrap());
+            // The stored key is not enough to prove the password
+            let proof = client_proof(&stored_key, 42, b"user01").unwrap();
+            assert!(!verify_proof(&stored_key, 42, b"user01", &proof).unwrap());
+        }
+
+        // The costs chosen by the remote peer are bounded
+        for template in [
+            "$argon2id$v=19$m=4194304,t=1,p=1$c2FsdHNhbHRzYWx0",
+            "$argon2id$v=19$m=64,t=100000,p=1$c2FsdHNhbHRzYWx0",
+            "$pbkdf2-sha256$i=4294967295,l=32$c2FsdHNhbHRzYWx0",
+        ] {
+            assert!(check_template(template).is_err());
+            assert!(hash_password(b"password01", template).is_err());
+        }
+
+        // Anything else is a plain text password, hashed with the template sent to the peer
+        let template = "$pbkdf2-sha256$i=1000,l=32$c2FsdHNhbHRzYWx0";
+        for plain in [
+            "password01",
+            "$argon2id$v=19$m=64,t=1,p=1",
+            "$md5$c2FsdA$aGFzaA",
+        ] {
+            let password = Password::from(plain.as_bytes().to_vec());
+            assert!(password.template().is_none());
+            let hash = hash_password(plain.as_bytes(), template).unwrap();
+            let proof = client_proof(&hash, 42, b"user01").unwrap();
+            let stored_key = password.stored_key(template).unwrap();
+            assert!(verify_proof(&stored_key, 42, b"user01", &proof).unwrap());
+            // The version 1 proof is the HMAC of the plain text password
+            let proof = client_hmac(plain.as_bytes(), 42).unwrap();
+            assert!(verify_hmac(plain.as_bytes(), 42, &proof).unwrap());
+            assert!(!verify_hmac(plain.as_bytes(), 43, &proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn usrpwd_template() {
+        let salt_key = [7u8; 32];
+        let hashed = "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHRzYWx0";
+        let mut lookup = Lookup::default();
+        lookup
+            .users
+            .insert(b"plain".to_vec(), b"password01".to_vec().into());
+
+        // Without hashed passwords, the default algorithm is used for everyone
+        let plain = lookup.template(b"plain", &salt_key).unwrap();
+        let unknown = lookup.template(b"unknown", &salt_key).unwrap();
+        assert!(plain.starts_with(DEFAULT_TEMPLATE));
+        assert!(unknown.starts_with(DEFAULT_TEMPLATE));
+        assert!(check_template(&unknown).is_ok());
+        // The template of a user does not change, and differs from the one of another user
+        assert_eq!(unknown, lookup.template(b"unknown", &salt_key).unwrap());
+        assert_ne!(plain, unknown);
+
+        // With hashed passwords, the users without one get a template of the same kind
+        let phc = UserPasswordAuthenticator::stored_password(b"password02", hashed).unwrap();
+        lookup
+            .users
+            .insert(b"hashed".to_vec(), phc.into_bytes().into());
+        assert_eq!(lookup.template(b"hashed", &salt_key).unwrap(), hashed);
+        for user in [&b"plain"[..], b"unknown"] {
+            let template = lookup.template(user, &salt_key).unwrap();
+            assert!(template.starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+            assert_ne!(template, hashed);
+        }
+    }
+
+    #[test]
+    fn usrpwd_version() {
+        // The InitSyn is accepted by the peers only reading the version 1
+        let init_syn_property = InitSynProperty {
+            version: USRPWD_VERSION_HMAC,
+            max_version: USRPWD_VERSION_SCRAM,
+            user: Some(b"user01".to_vec()),
+        };
+        let mut wbuf = WBuf::new(WBUF_SIZE, false);
+        assert!(wbuf.write_init_syn_property_usrpwd(&init_syn_property));
+        let zbuf: ZBuf = wbuf.contiguous().into_owned().into();
+        let mut reader = zbuf.reader();
+        assert_eq!(reader.read_zint().unwrap(), USRPWD_VERSION_HMAC);
+        let mut reader = zbuf.reader();
+        let p = reader.read_init_syn_property_usrpwd().unwrap();
+        assert_eq!(p.max_version, USRPWD_VERSION_SCRAM);
+        assert_eq!(p.user.unwrap(), b"user01");
+
+        // The InitSyn of a peer only supporting the version 1
+        let mut wbuf = WBuf::new(WBUF_SIZE, false);
+        assert!(wbuf.write_zint(USRPWD_VERSION_HMAC));
+        let zbuf: ZBuf = wbuf.contiguous().into_owned().into();
+        let p = zbuf.reader().read_init_syn_property_usrpwd().unwrap();
+        assert_eq!(p.max_version, USRPWD_VERSION_HMAC);
+        assert!(p.user.is_none());
+    }
+}
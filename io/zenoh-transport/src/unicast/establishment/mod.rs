@@ -20,7 +20,7 @@ use super::protocol::core::{PeerId, Property, WhatAmI, ZInt};
 use super::protocol::io::{WBuf, ZBuf};
 use super::protocol::proto::{Attachment, TransportMessage};
 use super::{TransportConfigUnicast, TransportPeer, TransportUnicast};
-use authenticator::AuthenticatedPeerLink;
+use authenticator::{AuthId, AuthenticatedPeerLink};
 use rand::Rng;
 use std::ops::{Deref, DerefMut};
 use std::time::Duration;
@@ -104,6 +104,7 @@ pub struct Cookie {
     pid: PeerId,
     sn_resolution: ZInt,
     is_qos: bool,
+    is_compression: bool,
     nonce: ZInt,
 }
 pub type CookieHash = Vec<u8>;
@@ -129,6 +130,9 @@ impl Cookie {
         zwrite!(wbuf.write_peeexpr_id(&self.pid));
         zwrite!(wbuf.write_zint(self.sn_resolution));
         zwrite!(wbuf.write_byte(if self.is_qos { 1 } else { 0 }).is_some());
+        zwrite!(wbuf
+            .write_byte(if self.is_compression { 1 } else { 0 })
+            .is_some());
         zwrite!(wbuf.write_zint(self.nonce));
         zwrite!(wbuf.write_properties(properties.as_slice()));
 
@@ -157,6 +161,7 @@ impl Cookie {
         let pid = zread!(reader.read_peeexpr_id());
         let sn_resolution = zread!(reader.read_zint());
         let is_qos = zread!(reader.read_byte()) == 1;
+        let is_compression = zread!(reader.read_byte()) == 1;
         let nonce = zread!(reader.read_zint());
 
         let mut ps = zread!(reader.read_properties());
@@ -170,6 +175,7 @@ impl Cookie {
             pid,
             sn_resolution,
             is_qos,
+            is_compression,
             nonce,
         };
         Ok((cookie, properties))
@@ -209,6 +215,7 @@ pub(super) struct InputInit {
     pub(super) sn_resolution: ZInt,
     pub(super) is_shm: bool,
     pub(super) is_qos: bool,
+    pub(super) auth_ids: Vec<AuthId>,
 }
 async fn transport_init(
     manager: &TransportManager,
@@ -217,6 +224,14 @@ async fn transport_init(
     // Initialize the transport if it is new
     let initial_sn_tx = zasynclock!(manager.prng).gen_range(0..input.sn_resolution);
 
+    // Collect the identities the peer has been authenticated with
+    let mut auth_ids = input.auth_ids;
+    for pa in zasyncread!(manager.state.unicast.peer_authenticator).iter() {
+        if let Some(auth_id) = pa.get_auth_id(&input.pid).await {
+            auth_ids.push(auth_id);
+        }
+    }
+
     let config = TransportConfigUnicast {
         peer: input.pid,
         whatami: input.whatami,
@@ -224,6 +239,7 @@ async fn transport_init(
         is_shm: input.is_shm,
         is_qos: input.is_qos,
         initial_sn_tx,
+        auth_ids,
     };
 
     manager.init_transport_unicast(config)
@@ -32,6 +32,7 @@ pub(super) struct Output {
     pub(super) whatami: WhatAmI,
     pub(super) sn_resolution: ZInt,
     pub(super) is_qos: bool,
+    pub(super) is_compression: bool,
     pub(super) is_shm: bool,
     pub(super) cookie: ZSlice,
     pub(super) open_syn_attachment: Option<Attachment>,
@@ -158,6 +159,8 @@ pub(super) async fn recv(
         whatami: init_ack.whatami,
         sn_resolution,
         is_qos: init_ack.is_qos,
+        // Compression is enabled only if requested in the InitSyn and confirmed in the InitAck
+        is_compression: manager.config.unicast.is_compression && init_ack.is_compression,
         is_shm,
         cookie: init_ack.cookie,
         open_syn_attachment: attachment_from_properties(&ps_attachment).ok(),
@@ -53,6 +53,7 @@ pub(super) async fn send(
         manager.config.pid,
         manager.config.sn_resolution,
         manager.config.unicast.is_qos,
+        manager.config.unicast.is_compression,
         attachment_from_properties(&ps_attachment).ok(),
     );
     let _ = link
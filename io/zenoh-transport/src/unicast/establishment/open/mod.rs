@@ -61,12 +61,14 @@ pub(crate) async fn open_link(
     }
 
     let pid = output.pid;
+    let is_compression = output.is_compression;
     let input = InputInit {
         pid,
         whatami: output.whatami,
         sn_resolution: output.sn_resolution,
         is_shm: output.is_shm,
         is_qos: output.is_qos,
+        auth_ids: auth_link.auth_ids.clone(),
     };
     let transport = step!(super::transport_init(manager, input).await);
 
@@ -106,7 +108,7 @@ pub(crate) async fn open_link(
     let _ = step!(step!(transport
         .get_inner()
         .map_err(|e| (e, Some(tmsg::close_reason::INVALID))))
-    .add_link(link.clone(), LinkUnicastDirection::Outbound)
+    .add_link(link.clone(), LinkUnicastDirection::Outbound, is_compression)
     .map_err(|e| (e, Some(tmsg::close_reason::MAX_LINKS))));
 
     // Sync the RX sequence number
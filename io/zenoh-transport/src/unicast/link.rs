@@ -11,6 +11,7 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+use super::common::batch::{self, COMPRESSION_BYTES};
 use super::common::{conduit::TransportConduitTx, pipeline::TransmissionPipeline};
 use super::protocol::core::Priority;
 use super::protocol::io::{ZBuf, ZSlice};
@@ -26,7 +27,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use zenoh_buffers::buffer::InsertBuffer;
 use zenoh_buffers::reader::{HasReader, Reader};
-use zenoh_collections::RecyclingObjectPool;
+use zenoh_collections::{RecyclingObject, RecyclingObjectPool};
 use zenoh_core::Result as ZResult;
 use zenoh_core::{bail, zerror};
 use zenoh_link::{LinkUnicast, LinkUnicastDirection};
@@ -39,6 +40,8 @@ pub(super) struct TransportLinkUnicast {
     pub(super) direction: LinkUnicastDirection,
     // The underlying link
     pub(super) link: LinkUnicast,
+    // Batches are compressed on this link
+    pub(super) is_compression: bool,
     // The transmission pipeline
     pub(super) pipeline: Option<Arc<TransmissionPipeline>>,
     // The transport this link is associated to
@@ -54,11 +57,13 @@ impl TransportLinkUnicast {
         transport: TransportUnicastInner,
         link: LinkUnicast,
         direction: LinkUnicastDirection,
+        is_compression: bool,
     ) -> TransportLinkUnicast {
         TransportLinkUnicast {
             direction,
             transport,
             link,
+            is_compression,
             pipeline: None,
             handle_tx: None,
             signal_rx: Signal::new(),
@@ -76,9 +81,14 @@ impl TransportLinkUnicast {
         conduit_tx: Arc<[TransportConduitTx]>,
     ) {
         if self.handle_tx.is_none() {
+            // Leave room for the compression header so that batches never exceed the link MTU
+            let mut batch_size = batch_size.min(self.link.get_mtu());
+            if self.is_compression {
+                batch_size -= COMPRESSION_BYTES as u16;
+            }
             let config = TransmissionPipelineConf {
                 is_streamed: self.link.is_streamed(),
-                batch_size: batch_size.min(self.link.get_mtu()),
+                batch_size,
                 queue_size: self.transport.config.manager.config.queue_size,
                 backoff: self.transport.config.manager.config.queue_backoff,
             };
@@ -89,11 +99,13 @@ impl TransportLinkUnicast {
             // Spawn the TX task
             let c_link = self.link.clone();
             let c_transport = self.transport.clone();
+            let c_is_compression = self.is_compression;
             let handle = executor.spawn(async move {
                 let res = tx_task(
                     pipeline.clone(),
                     c_link.clone(),
                     keep_alive,
+                    c_is_compression,
                     #[cfg(feature = "stats")]
                     c_transport.stats.clone(),
                 )
@@ -123,6 +135,7 @@ impl TransportLinkUnicast {
             let c_transport = self.transport.clone();
             let c_signal = self.signal_rx.clone();
             let c_rx_buffer_size = self.transport.config.manager.config.link_rx_buffer_size;
+            let c_is_compression = self.is_compression;
 
             let handle = task::spawn(async move {
                 // Start the consume task
@@ -132,6 +145,7 @@ impl TransportLinkUnicast {
                     lease,
                     c_signal.clone(),
                     c_rx_buffer_size,
+                    c_is_compression,
                 )
                 .await;
                 c_signal.trigger();
@@ -177,14 +191,21 @@ async fn tx_task(
     pipeline: Arc<TransmissionPipeline>,
     link: LinkUnicast,
     keep_alive: Duration,
+    is_compression: bool,
     #[cfg(feature = "stats")] stats: Arc<TransportUnicastStatsAtomic>,
 ) -> ZResult<()> {
+    // The buffer to compress the batches onto
+    let mut buffer = vec![];
     loop {
         match pipeline.pull().timeout(keep_alive).await {
             Ok(res) => match res {
                 Some((batch, priority)) => {
                     // Send the buffer on the link
-                    let bytes = batch.as_bytes();
+                    let bytes = if is_compression {
+                        batch.compress(&mut buffer)
+                    } else {
+                        batch.as_bytes()
+                    };
                     let _ = link.write_all(bytes).await?;
 
                     #[cfg(feature = "stats")]
@@ -210,8 +231,13 @@ async fn tx_task(
     // Drain the transmission pipeline and write remaining bytes on the wire
     let mut batches = pipeline.drain();
     for (b, _) in batches.drain(..) {
+        let bytes = if is_compression {
+            b.compress(&mut buffer)
+        } else {
+            b.as_bytes()
+        };
         let _ = link
-            .write_all(b.as_bytes())
+            .write_all(bytes)
             .timeout(keep_alive)
             .await
             .map_err(|_| zerror!("{}: flush failed after {} ms", link, keep_alive.as_millis()))??;
@@ -232,6 +258,7 @@ async fn rx_task_stream(
     lease: Duration,
     signal: Signal,
     rx_buffer_size: usize,
+    is_compression: bool,
 ) -> ZResult<()> {
     enum Action {
         Read(usize),
@@ -276,8 +303,13 @@ async fn rx_task_stream(
             .map_err(|_| zerror!("{}: expired after {} milliseconds", link, lease.as_millis()))??;
         match action {
             Action::Read(n) => {
-                let zs = ZSlice::make(buffer.into(), 0, n)
-                    .map_err(|_| zerror!("{}: decoding error", link))?;
+                let zs = if is_compression {
+                    let output = pool.try_take().unwrap_or_else(|| pool.alloc());
+                    decompress(&link, buffer, n, output)?
+                } else {
+                    ZSlice::make(buffer.into(), 0, n)
+                        .map_err(|_| zerror!("{}: decoding error", link))?
+                };
                 zbuf.append(zs);
 
                 let mut zbuf = zbuf.reader();
@@ -310,6 +342,7 @@ async fn rx_task_dgram(
     lease: Duration,
     signal: Signal,
     rx_buffer_size: usize,
+    is_compression: bool,
 ) -> ZResult<()> {
     enum Action {
         Read(usize),
@@ -358,8 +391,13 @@ async fn rx_task_dgram(
                 transport.stats.inc_rx_bytes(n);
 
                 // Add the received bytes to the ZBuf for deserialization
-                let zs = ZSlice::make(buffer.into(), 0, n)
-                    .map_err(|_| zerror!("{}: decoding error", link))?;
+                let zs = if is_compression {
+                    let output = pool.try_take().unwrap_or_else(|| pool.alloc());
+                    decompress(&link, buffer, n, output)?
+                } else {
+                    ZSlice::make(buffer.into(), 0, n)
+                        .map_err(|_| zerror!("{}: decoding error", link))?
+                };
                 zbuf.append(zs);
                 let mut zbuf = zbuf.reader();
                 // Deserialize all the messages from the current ZBuf
@@ -383,16 +421,50 @@ async fn rx_task_dgram(
     Ok(())
 }
 
+// Get the serialized messages of a batch received on a link where compression has been
+// negotiated, decompressing them onto the output buffer if needed
+fn decompress(
+    link: &LinkUnicast,
+    buffer: RecyclingObject<Box<[u8]>>,
+    n: usize,
+    mut output: RecyclingObject<Box<[u8]>>,
+) -> ZResult<ZSlice> {
+    let zs = match batch::decompress(&buffer[0..n], &mut output)
+        .map_err(|e| zerror!("{}: decoding error: {}", link, e))?
+    {
+        Some(len) => ZSlice::make(output.into(), 0, len),
+        None => ZSlice::make(buffer.into(), COMPRESSION_BYTES, n),
+    };
+    zs.map_err(|_| zerror!("{}: decoding error", link).into())
+}
+
 async fn rx_task(
     link: LinkUnicast,
     transport: TransportUnicastInner,
     lease: Duration,
     signal: Signal,
     rx_buffer_size: usize,
+    is_compression: bool,
 ) -> ZResult<()> {
     if link.is_streamed() {
-        rx_task_stream(link, transport, lease, signal, rx_buffer_size).await
+        rx_task_stream(
+            link,
+            transport,
+            lease,
+            signal,
+            rx_buffer_size,
+            is_compression,
+        )
+        .await
     } else {
-        rx_task_dgram(link, transport, lease, signal, rx_buffer_size).await
+        rx_task_dgram(
+            link,
+            transport,
+            lease,
+            signal,
+            rx_buffer_size,
+            is_compression,
+        )
+        .await
     }
 }
@@ -24,7 +24,7 @@ use async_std::task;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use zenoh_cfg_properties::config::*;
+use zenoh_cfg_properties::{config::*, Properties};
 use zenoh_config::Config;
 use zenoh_core::{
     bail, zasynclock, zasyncread, zasyncwrite, zerror, zlock, zparse, Result as ZResult,
@@ -44,6 +44,7 @@ pub struct TransportManagerConfigUnicast {
     pub max_sessions: usize,
     pub max_links: usize,
     pub is_qos: bool,
+    pub is_compression: bool,
     #[cfg(feature = "shared-memory")]
     pub is_shm: bool,
 }
@@ -79,6 +80,7 @@ pub struct TransportManagerBuilderUnicast {
     pub(super) max_sessions: usize,
     pub(super) max_links: usize,
     pub(super) is_qos: bool,
+    pub(super) is_compression: bool,
     #[cfg(feature = "shared-memory")]
     pub(super) is_shm: bool,
     pub(super) peer_authenticator: HashSet<PeerAuthenticator>,
@@ -131,6 +133,11 @@ impl TransportManagerBuilderUnicast {
         self
     }
 
+    pub fn compression(mut self, is_compression: bool) -> Self {
+        self.is_compression = is_compression;
+        self
+    }
+
     #[cfg(feature = "shared-memory")]
     pub fn shm(mut self, is_shm: bool) -> Self {
         self.is_shm = is_shm;
@@ -151,6 +158,7 @@ impl TransportManagerBuilderUnicast {
         self = self.max_sessions(config.transport().unicast().max_sessions().unwrap());
         self = self.max_links(config.transport().unicast().max_links().unwrap());
         self = self.qos(*config.transport().qos().enabled());
+        self = self.compression(*config.transport().link().compression().enabled());
 
         #[cfg(feature = "shared-memory")]
         {
@@ -174,6 +182,7 @@ impl TransportManagerBuilderUnicast {
             max_sessions: self.max_sessions,
             max_links: self.max_links,
             is_qos: self.is_qos,
+            is_compression: self.is_compression,
             #[cfg(feature = "shared-memory")]
             is_shm: self.is_shm,
         };
@@ -224,6 +233,7 @@ impl Default for TransportManagerBuilderUnicast {
             max_sessions: zparse!(ZN_MAX_SESSIONS_UNICAST_DEFAULT).unwrap(),
             max_links: zparse!(ZN_MAX_LINKS_DEFAULT).unwrap(),
             is_qos: zparse!(ZN_QOS_DEFAULT).unwrap(),
+            is_compression: false,
             #[cfg(feature = "shared-memory")]
             is_shm: zparse!(ZN_SHM_DEFAULT).unwrap(),
             peer_authenticator: HashSet::new(),
@@ -330,6 +340,40 @@ impl TransportManager {
         Ok(())
     }
 
+    pub async fn reload_listeners_unicast(
+        &self,
+        config: &HashMap<String, Properties>,
+    ) -> ZResult<()> {
+        let managers = zlock!(self.state.unicast.protocols)
+            .iter()
+            .map(|(p, lm)| (p.clone(), lm.clone()))
+            .collect::<Vec<(String, LinkManagerUnicast)>>();
+
+        // Reload all the link managers, even if some of them fail
+        let mut res = Ok(());
+        let empty = Properties::default();
+        for (protocol, lm) in managers {
+            if let Err(e) = lm
+                .reload_listeners(config.get(&protocol).unwrap_or(&empty))
+                .await
+            {
+                res = Err(e);
+            }
+        }
+        res
+    }
+
+    pub async fn reload_authenticators_unicast(&self, config: &Config) -> ZResult<()> {
+        // Reload all the peer authenticators, even if some of them fail
+        let mut res = Ok(());
+        for pa in zasyncread!(self.state.unicast.peer_authenticator).iter() {
+            if let Err(e) = pa.reload(config).await {
+                res = Err(e);
+            }
+        }
+        res
+    }
+
     pub fn get_listeners_unicast(&self) -> Vec<EndPoint> {
         let mut vec: Vec<EndPoint> = vec![];
         for p in zlock!(self.state.unicast.protocols).values() {
@@ -426,6 +470,7 @@ impl TransportManager {
                     initial_sn_tx: config.initial_sn_tx,
                     is_shm: config.is_shm,
                     is_qos: config.is_qos,
+                    auth_ids: config.auth_ids,
                 };
                 let a_t = Arc::new(TransportUnicastInner::make(stc)?);
 
@@ -477,6 +522,7 @@ impl TransportManager {
             src: link.get_src().to_owned(),
             dst: link.get_src().to_owned(),
             peer_id: None,
+            auth_ids: vec![],
         };
         super::establishment::open::open_link(&link, self, &mut auth_link).await
     }
@@ -529,9 +575,9 @@ impl TransportManager {
         drop(guard);
 
         let mut peer_id: Option<PeerId> = None;
-        let peer_link = Link::from(&link);
+        let mut auth_ids = vec![];
         for la in zasyncread!(self.state.unicast.link_authenticator).iter() {
-            let res = la.handle_new_link(&peer_link).await;
+            let res = la.handle_new_link(&link).await;
             match res {
                 Ok(pid) => {
                     // Check that all the peer authenticators, eventually return the same PeerId
@@ -556,6 +602,9 @@ impl TransportManager {
                     return;
                 }
             }
+            if let Some(auth_id) = la.get_auth_id(&link).await {
+                auth_ids.push(auth_id);
+            }
         }
 
         // Spawn a task to accept the link
@@ -565,6 +614,7 @@ impl TransportManager {
                 src: link.get_src().to_owned(),
                 dst: link.get_dst().to_owned(),
                 peer_id,
+                auth_ids,
             };
 
             let res = super::establishment::accept::accept_link(&link, &c_manager, &mut auth_link)
@@ -25,6 +25,7 @@ use super::protocol;
 use super::protocol::core::{PeerId, WhatAmI, ZInt};
 use super::protocol::proto::{tmsg, ZenohMessage};
 use super::{TransportPeer, TransportPeerEventHandler};
+use establishment::authenticator::AuthId;
 pub use manager::*;
 use std::fmt;
 use std::sync::{Arc, Weak};
@@ -77,7 +78,7 @@ stats_struct! {
 /*************************************/
 /*        TRANSPORT UNICAST          */
 /*************************************/
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub(crate) struct TransportConfigUnicast {
     pub(crate) peer: PeerId,
     pub(crate) whatami: WhatAmI,
@@ -85,6 +86,7 @@ pub(crate) struct TransportConfigUnicast {
     pub(crate) initial_sn_tx: ZInt,
     pub(crate) is_shm: bool,
     pub(crate) is_qos: bool,
+    pub(crate) auth_ids: Vec<AuthId>,
 }
 
 /// [`TransportUnicast`] is the transport handler returned
@@ -130,6 +132,12 @@ impl TransportUnicast {
         Ok(transport.is_qos())
     }
 
+    #[inline(always)]
+    pub fn get_auth_ids(&self) -> ZResult<Vec<AuthId>> {
+        let transport = self.get_inner()?;
+        Ok(transport.get_auth_ids())
+    }
+
     #[inline(always)]
     pub fn get_callback(&self) -> ZResult<Option<Arc<dyn TransportPeerEventHandler>>> {
         let transport = self.get_inner()?;
@@ -16,6 +16,7 @@ use super::common::{
     conduit::{TransportConduitRx, TransportConduitTx},
     pipeline::TransmissionPipeline,
 };
+use super::establishment::authenticator::AuthId;
 use super::link::TransportLinkUnicast;
 use super::protocol::core::{ConduitSn, PeerId, Priority, WhatAmI, ZInt};
 use super::protocol::proto::{TransportMessage, ZenohMessage};
@@ -57,6 +58,7 @@ pub(crate) struct TransportUnicastConfig {
     pub(crate) initial_sn_tx: ZInt,
     pub(crate) is_shm: bool,
     pub(crate) is_qos: bool,
+    pub(crate) auth_ids: Vec<AuthId>,
 }
 
 #[derive(Clone)]
@@ -205,6 +207,7 @@ impl TransportUnicastInner {
         &self,
         link: LinkUnicast,
         direction: LinkUnicastDirection,
+        is_compression: bool,
     ) -> ZResult<()> {
         // Add the link to the channel
         let mut guard = zwrite!(self.links);
@@ -227,7 +230,7 @@ impl TransportUnicastInner {
         }
 
         // Create a channel link from a link
-        let link = TransportLinkUnicast::new(self.clone(), link, direction);
+        let link = TransportLinkUnicast::new(self.clone(), link, direction, is_compression);
 
         let mut links = Vec::with_capacity(guard.len() + 1);
         links.extend_from_slice(&guard);
@@ -380,6 +383,10 @@ impl TransportUnicastInner {
         self.config.is_qos
     }
 
+    pub(crate) fn get_auth_ids(&self) -> Vec<AuthId> {
+        self.config.auth_ids.clone()
+    }
+
     pub(crate) fn get_callback(&self) -> Option<Arc<dyn TransportPeerEventHandler>> {
         zread!(self.callback).clone()
     }
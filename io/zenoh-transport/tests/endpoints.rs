@@ -503,3 +503,19 @@ AXVFFIgCSluyrolaD6CWD9MqOex4YOfJR2bNxI7lFvuK4AwjyUJzT1U1HXib17mM
     let endpoints = vec![endpoint];
     task::block_on(run(&endpoints));
 }
+
+#[cfg(feature = "transport_ws")]
+#[test]
+fn endpoint_ws() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    // Define the locators
+    let endpoints: Vec<EndPoint> = vec![
+        "ws/127.0.0.1:9454".parse().unwrap(),
+        "ws/[::1]:9454".parse().unwrap(),
+        "ws/localhost:9455".parse().unwrap(),
+    ];
+    task::block_on(run(&endpoints));
+}
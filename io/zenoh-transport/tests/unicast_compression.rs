@@ -0,0 +1,290 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::prelude::*;
+use async_std::sync::Arc;
+use async_std::task;
+use std::any::Any;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use zenoh_buffers::{SplitBuffer, ZBuf};
+use zenoh_core::zasync_executor_init;
+use zenoh_core::Result as ZResult;
+use zenoh_link::{EndPoint, Link};
+use zenoh_protocol::proto::{ZenohBody, ZenohMessage};
+use zenoh_protocol_core::{Channel, CongestionControl, PeerId, Priority, Reliability, WhatAmI};
+use zenoh_transport::{
+    TransportEventHandler, TransportManager, TransportMulticast, TransportMulticastEventHandler,
+    TransportPeer, TransportPeerEventHandler, TransportUnicast,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const SLEEP: Duration = Duration::from_millis(100);
+
+const MSG_COUNT: usize = 100;
+const MSG_SIZE_ALL: [usize; 3] = [8, 1_024, 131_072];
+const MSG_SIZE_NOFRAG: [usize; 2] = [8, 1_024];
+
+macro_rules! ztimeout {
+    ($f:expr) => {
+        $f.timeout(TIMEOUT).await.unwrap()
+    };
+}
+
+// Transport Handler for the router
+struct SHRouter {
+    count: Arc<AtomicUsize>,
+}
+
+impl SHRouter {
+    fn new() -> Self {
+        Self {
+            count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn get_count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    fn reset(&self) {
+        self.count.store(0, Ordering::SeqCst);
+    }
+}
+
+impl TransportEventHandler for SHRouter {
+    fn new_unicast(
+        &self,
+        _peer: TransportPeer,
+        _transport: TransportUnicast,
+    ) -> ZResult<Arc<dyn TransportPeerEventHandler>> {
+        let arc = Arc::new(SCRouter::new(self.count.clone()));
+        Ok(arc)
+    }
+
+    fn new_multicast(
+        &self,
+        _transport: TransportMulticast,
+    ) -> ZResult<Arc<dyn TransportMulticastEventHandler>> {
+        panic!();
+    }
+}
+
+// Transport Callback for the router
+struct SCRouter {
+    count: Arc<AtomicUsize>,
+}
+
+impl SCRouter {
+    fn new(count: Arc<AtomicUsize>) -> Self {
+        Self { count }
+    }
+}
+
+impl TransportPeerEventHandler for SCRouter {
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
+        // Verify that the payload made it through the compression untouched
+        if let ZenohBody::Data(data) = message.body {
+            assert!(data.payload.contiguous().iter().all(|b| *b == 42));
+        }
+        self.count.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn new_link(&self, _link: Link) {}
+    fn del_link(&self, _link: Link) {}
+    fn closing(&self) {}
+    fn closed(&self) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Transport Handler for the client
+struct SHClient;
+
+impl TransportEventHandler for SHClient {
+    fn new_unicast(
+        &self,
+        _peer: TransportPeer,
+        _transport: TransportUnicast,
+    ) -> ZResult<Arc<dyn TransportPeerEventHandler>> {
+        Ok(Arc::new(SCClient))
+    }
+
+    fn new_multicast(
+        &self,
+        _transport: TransportMulticast,
+    ) -> ZResult<Arc<dyn TransportMulticastEventHandler>> {
+        panic!();
+    }
+}
+
+// Transport Callback for the client
+struct SCClient;
+
+impl TransportPeerEventHandler for SCClient {
+    fn handle_message(&self, _message: ZenohMessage) -> ZResult<()> {
+        Ok(())
+    }
+
+    fn new_link(&self, _link: Link) {}
+    fn del_link(&self, _link: Link) {}
+    fn closing(&self) {}
+    fn closed(&self) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+async fn transport_compression(
+    endpoint: &EndPoint,
+    router_compression: bool,
+    client_compression: bool,
+    msg_size: &[usize],
+) {
+    println!(
+        "Transport Compression [router: {}, client: {}] {}",
+        router_compression, client_compression, endpoint
+    );
+
+    // Define client and router IDs
+    let client_id = PeerId::new(1, [0_u8; PeerId::MAX_SIZE]);
+    let router_id = PeerId::new(1, [1_u8; PeerId::MAX_SIZE]);
+
+    // Create the router transport manager
+    let router_handler = Arc::new(SHRouter::new());
+    let unicast = TransportManager::config_unicast().compression(router_compression);
+    let router_manager = TransportManager::builder()
+        .pid(router_id)
+        .whatami(WhatAmI::Router)
+        .unicast(unicast)
+        .build(router_handler.clone())
+        .unwrap();
+
+    // Create the client transport manager
+    let unicast = TransportManager::config_unicast().compression(client_compression);
+    let client_manager = TransportManager::builder()
+        .whatami(WhatAmI::Client)
+        .pid(client_id)
+        .unicast(unicast)
+        .build(Arc::new(SHClient))
+        .unwrap();
+
+    // Create the listener on the router
+    let _ = ztimeout!(router_manager.add_listener(endpoint.clone())).unwrap();
+
+    // Create an empty transport with the client
+    let client_transport = ztimeout!(client_manager.open_transport(endpoint.clone())).unwrap();
+
+    for ms in msg_size.iter() {
+        router_handler.reset();
+
+        // Create a compressible message
+        let key = "/test".into();
+        let payload = ZBuf::from(vec![42_u8; *ms]);
+        let channel = Channel {
+            priority: Priority::default(),
+            reliability: Reliability::Reliable,
+        };
+        let congestion_control = CongestionControl::Block;
+        let data_info = None;
+        let routing_context = None;
+        let reply_context = None;
+        let attachment = None;
+        let message = ZenohMessage::make_data(
+            key,
+            payload,
+            channel,
+            congestion_control,
+            data_info,
+            routing_context,
+            reply_context,
+            attachment,
+        );
+
+        #[cfg(feature = "stats")]
+        let tx_bytes = client_transport.get_stats().unwrap().tx_bytes;
+
+        println!("Sending {} messages of {} bytes", MSG_COUNT, ms);
+        for _ in 0..MSG_COUNT {
+            client_transport.schedule(message.clone()).unwrap();
+        }
+
+        ztimeout!(async {
+            while router_handler.get_count() != MSG_COUNT {
+                task::sleep(SLEEP).await;
+            }
+        });
+
+        // The payloads are highly compressible, so far fewer bytes are sent when compression is used
+        #[cfg(feature = "stats")]
+        if *ms >= 1_024 {
+            let sent = client_transport.get_stats().unwrap().tx_bytes - tx_bytes;
+            println!("Sent {} bytes", sent);
+            assert_eq!(
+                router_compression && client_compression,
+                sent < MSG_COUNT * ms / 2
+            );
+        }
+    }
+
+    // Close the transport
+    ztimeout!(client_transport.close()).unwrap();
+    ztimeout!(async {
+        while !router_manager.get_transports().is_empty() {
+            task::sleep(SLEEP).await;
+        }
+    });
+
+    // Stop the listener and the managers
+    ztimeout!(router_manager.del_listener(endpoint)).unwrap();
+    ztimeout!(router_manager.close());
+    ztimeout!(client_manager.close());
+
+    // Wait a little bit
+    task::sleep(SLEEP).await;
+}
+
+#[cfg(feature = "transport_tcp")]
+#[test]
+fn transport_tcp_compression() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    let endpoint: EndPoint = "tcp/127.0.0.1:17447".parse().unwrap();
+    task::block_on(async {
+        // Compression is used only when both ends enable it
+        for (router, client) in [(true, true), (true, false), (false, true)] {
+            transport_compression(&endpoint, router, client, &MSG_SIZE_ALL).await;
+        }
+    });
+}
+
+#[cfg(feature = "transport_udp")]
+#[test]
+fn transport_udp_compression() {
+    task::block_on(async {
+        zasync_executor_init!();
+    });
+
+    let endpoint: EndPoint = "udp/127.0.0.1:17448".parse().unwrap();
+    task::block_on(async {
+        for (router, client) in [(true, true), (true, false), (false, true)] {
+            transport_compression(&endpoint, router, client, &MSG_SIZE_NOFRAG).await;
+        }
+    });
+}
@@ -0,0 +1,235 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+#[cfg(all(feature = "transport_serial", target_family = "unix"))]
+mod tests {
+    use async_std::prelude::*;
+    use async_std::task;
+    use nix::pty::openpty;
+    use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+    use nix::unistd::{read, ttyname, write};
+    use std::any::Any;
+    use std::os::unix::io::RawFd;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use zenoh_core::zasync_executor_init;
+    use zenoh_core::Result as ZResult;
+    use zenoh_link::{EndPoint, Link};
+    use zenoh_protocol::io::ZBuf;
+    use zenoh_protocol::proto::ZenohMessage;
+    use zenoh_protocol_core::{Channel, CongestionControl, PeerId, Priority, Reliability, WhatAmI};
+    use zenoh_transport::{
+        TransportEventHandler, TransportManager, TransportMulticast,
+        TransportMulticastEventHandler, TransportPeer, TransportPeerEventHandler, TransportUnicast,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const SLEEP: Duration = Duration::from_secs(1);
+    const SLEEP_COUNT: Duration = Duration::from_millis(10);
+
+    const MSG_COUNT: usize = 100;
+    // The second size requires the fragmentation over the serial MTU
+    const MSG_SIZE: [usize; 2] = [1_024, 8_192];
+
+    macro_rules! ztimeout {
+        ($f:expr) => {
+            $f.timeout(TIMEOUT).await.unwrap()
+        };
+    }
+
+    // Transport Handler for the router
+    #[derive(Default)]
+    struct SHRouter {
+        count: Arc<AtomicUsize>,
+    }
+
+    impl TransportEventHandler for SHRouter {
+        fn new_unicast(
+            &self,
+            _peer: TransportPeer,
+            _transport: TransportUnicast,
+        ) -> ZResult<Arc<dyn TransportPeerEventHandler>> {
+            Ok(Arc::new(SC {
+                count: self.count.clone(),
+            }))
+        }
+
+        fn new_multicast(
+            &self,
+            _transport: TransportMulticast,
+        ) -> ZResult<Arc<dyn TransportMulticastEventHandler>> {
+            panic!();
+        }
+    }
+
+    // Transport Handler for the client
+    #[derive(Default)]
+    struct SHClient;
+
+    impl TransportEventHandler for SHClient {
+        fn new_unicast(
+            &self,
+            _peer: TransportPeer,
+            _transport: TransportUnicast,
+        ) -> ZResult<Arc<dyn TransportPeerEventHandler>> {
+            Ok(Arc::new(SC::default()))
+        }
+
+        fn new_multicast(
+            &self,
+            _transport: TransportMulticast,
+        ) -> ZResult<Arc<dyn TransportMulticastEventHandler>> {
+            panic!();
+        }
+    }
+
+    // Transport Callback counting the received messages
+    #[derive(Default)]
+    struct SC {
+        count: Arc<AtomicUsize>,
+    }
+
+    impl TransportPeerEventHandler for SC {
+        fn handle_message(&self, _message: ZenohMessage) -> ZResult<()> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn new_link(&self, _link: Link) {}
+        fn del_link(&self, _link: Link) {}
+        fn closing(&self) {}
+        fn closed(&self) {}
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    // Create a raw pseudo-terminal, returning its master side and the path of its slave side.
+    // The slave side is left open for the master side to remain readable between the links.
+    fn pty() -> (RawFd, String) {
+        let pty = openpty(None, None).unwrap();
+        let mut tio = tcgetattr(pty.slave).unwrap();
+        cfmakeraw(&mut tio);
+        tcsetattr(pty.slave, SetArg::TCSANOW, &tio).unwrap();
+        let path = ttyname(pty.slave).unwrap().display().to_string();
+        (pty.master, path)
+    }
+
+    // Copy the bytes written on a pseudo-terminal to the other one.
+    fn forward(from: RawFd, to: RawFd) {
+        std::thread::spawn(move || {
+            let mut buffer = [0_u8; 4_096];
+            while let Ok(n) = read(from, &mut buffer) {
+                let mut written = 0;
+                while written < n {
+                    written += write(to, &buffer[written..n]).unwrap();
+                }
+            }
+        });
+    }
+
+    // Create a pair of pseudo-terminals connected to each other as a serial line would,
+    // returning the paths of their slave sides.
+    fn serial_line() -> (String, String) {
+        let (master_a, path_a) = pty();
+        let (master_b, path_b) = pty();
+        // Some noise on the line before the first frame, to be dropped by the receiver
+        write(master_b, b"noise\x00").unwrap();
+        forward(master_a, master_b);
+        forward(master_b, master_a);
+        (path_a, path_b)
+    }
+
+    async fn run(router_endpoint: EndPoint, client_endpoint: EndPoint) {
+        let client_id = PeerId::new(1, [0_u8; PeerId::MAX_SIZE]);
+        let router_id = PeerId::new(1, [1_u8; PeerId::MAX_SIZE]);
+
+        let router_handler = Arc::new(SHRouter::default());
+        let router_manager = TransportManager::builder()
+            .pid(router_id)
+            .whatami(WhatAmI::Router)
+            .build(router_handler.clone())
+            .unwrap();
+        let client_manager = TransportManager::builder()
+            .whatami(WhatAmI::Client)
+            .pid(client_id)
+            .build(Arc::new(SHClient))
+            .unwrap();
+
+        println!("Add endpoint: {}", router_endpoint);
+        let _ = ztimeout!(router_manager.add_listener(router_endpoint.clone())).unwrap();
+
+        // The listener accepts a new transport once the previous one is closed
+        for msg_size in MSG_SIZE {
+            println!("Opening transport with {}", client_endpoint);
+            let client_transport =
+                ztimeout!(client_manager.open_transport(client_endpoint.clone())).unwrap();
+
+            let message = ZenohMessage::make_data(
+                "/test".into(),
+                ZBuf::from(vec![0_u8; msg_size]),
+                Channel {
+                    priority: Priority::default(),
+                    reliability: Reliability::Reliable,
+                },
+                CongestionControl::Block,
+                None,
+                None,
+                None,
+                None,
+            );
+            println!("Sending {} messages of {} bytes", MSG_COUNT, msg_size);
+            router_handler.count.store(0, Ordering::SeqCst);
+            for _ in 0..MSG_COUNT {
+                client_transport.schedule(message.clone()).unwrap();
+            }
+            ztimeout!(async {
+                while router_handler.count.load(Ordering::SeqCst) != MSG_COUNT {
+                    task::sleep(SLEEP_COUNT).await;
+                }
+            });
+
+            println!("Closing transport with {}", client_endpoint);
+            ztimeout!(client_transport.close()).unwrap();
+            ztimeout!(async {
+                while !router_manager.get_transports().is_empty() {
+                    task::sleep(SLEEP).await;
+                }
+            });
+        }
+
+        println!("Del endpoint: {}", router_endpoint);
+        ztimeout!(router_manager.del_listener(&router_endpoint)).unwrap();
+
+        ztimeout!(router_manager.close());
+        ztimeout!(client_manager.close());
+    }
+
+    #[test]
+    fn transport_unicast_serial_only() {
+        task::block_on(async {
+            zasync_executor_init!();
+        });
+
+        let (router_path, client_path) = serial_line();
+        let router_endpoint: EndPoint = format!("serial/{}?baudrate=921600", router_path)
+            .parse()
+            .unwrap();
+        let client_endpoint: EndPoint = format!("serial/{}?baudrate=921600", client_path)
+            .parse()
+            .unwrap();
+        task::block_on(run(router_endpoint, client_endpoint));
+    }
+}
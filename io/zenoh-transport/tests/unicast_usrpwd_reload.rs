@@ -0,0 +1,193 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+#[cfg(all(feature = "auth_usrpwd", feature = "transport_tcp"))]
+mod tests {
+    use async_std::prelude::*;
+    use async_std::sync::Arc;
+    use async_std::task;
+    use std::collections::{HashMap, HashSet};
+    use std::iter::FromIterator;
+    use std::path::Path;
+    use std::time::Duration;
+    use zenoh_config::{Config, ValidatedMap};
+    use zenoh_core::zasync_executor_init;
+    use zenoh_core::Result as ZResult;
+    use zenoh_link::EndPoint;
+    use zenoh_protocol_core::{PeerId, WhatAmI};
+    use zenoh_transport::unicast::establishment::authenticator::UserPasswordAuthenticator;
+    use zenoh_transport::{
+        DummyTransportPeerEventHandler, TransportEventHandler, TransportManager,
+        TransportMulticast, TransportMulticastEventHandler, TransportPeer,
+        TransportPeerEventHandler, TransportUnicast,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const SLEEP: Duration = Duration::from_millis(100);
+
+    macro_rules! ztimeout {
+        ($f:expr) => {
+            $f.timeout(TIMEOUT).await.unwrap()
+        };
+    }
+
+    // NOTE: these are the stored passwords of "password01", "password02" and "password03", as
+    //       derived by `UserPasswordAuthenticator::stored_password`, with parameters as weak as
+    //       possible to keep the test fast.
+    const ARGON2_PASSWORD01: &str =
+        "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHRzYWx0$Z7Y+AXvQMzXqOX/A1EKlkkytllubckr6FmTeKuXYlJ8";
+    const ARGON2_PASSWORD02: &str =
+        "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHRzYWx0$9XwFaqpFv/q1mBX0ZMYuJdh2XQxHv/F7uKFWkGE8rqk";
+    const PBKDF2_PASSWORD01: &str =
+        "$pbkdf2-sha256$i=1000,l=32$c2FsdHNhbHRzYWx0$8HOdUlRKMLuZxhLm/HNRQkmzCV38KOIyRcmAmIhEt2c";
+    const PBKDF2_PASSWORD03: &str =
+        "$pbkdf2-sha512$i=1000,l=64$c2FsdHNhbHRzYWx0$iILCIVMOCtaT/oRVr9Jm28SBX0bZ97jr7gy2sdrraCs";
+
+    // Transport Handler
+    struct SH;
+
+    impl TransportEventHandler for SH {
+        fn new_unicast(
+            &self,
+            _peer: TransportPeer,
+            _transport: TransportUnicast,
+        ) -> ZResult<Arc<dyn TransportPeerEventHandler>> {
+            Ok(Arc::new(DummyTransportPeerEventHandler))
+        }
+
+        fn new_multicast(
+            &self,
+            _transport: TransportMulticast,
+        ) -> ZResult<Arc<dyn TransportMulticastEventHandler>> {
+            panic!();
+        }
+    }
+
+    fn new_client(id: u8, user: &str, password: &str) -> TransportManager {
+        let peer_auth = UserPasswordAuthenticator::new(
+            HashMap::new(),
+            Some((user.as_bytes().to_vec(), password.as_bytes().to_vec())),
+        );
+        let unicast = TransportManager::config_unicast()
+            .peer_authenticator(HashSet::from_iter(vec![peer_auth.into()]));
+        TransportManager::builder()
+            .whatami(WhatAmI::Client)
+            .pid(PeerId::new(1, [id; PeerId::MAX_SIZE]))
+            .unicast(unicast)
+            .build(Arc::new(SH))
+            .unwrap()
+    }
+
+    async fn open(manager: &TransportManager, endpoint: &EndPoint) -> ZResult<TransportUnicast> {
+        let res = ztimeout!(manager.open_transport(endpoint.clone()));
+        if let Ok(transport) = res.as_ref() {
+            // Only check the handshake, do not keep the transport around
+            ztimeout!(transport.close()).unwrap();
+        }
+        res
+    }
+
+    fn usrpwd_config(dictionary: &Path, users: &str) -> Config {
+        let mut config = Config::default();
+        config
+            .insert_json5(
+                "transport/auth/usrpwd/dictionary_file",
+                &format!("{:?}", dictionary.to_str().unwrap()),
+            )
+            .unwrap();
+        config
+            .insert_json5("transport/auth/usrpwd/users", users)
+            .unwrap();
+        config
+    }
+
+    async fn reload_users(locator: &str) {
+        let dir = std::env::temp_dir().join(format!("zenoh-usrpwd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dictionary = dir.join("dictionary.txt");
+        std::fs::write(&dictionary, format!("user01:{}", PBKDF2_PASSWORD01)).unwrap();
+
+        // Create the router transport manager with a hashed dictionary and configured user
+        let users = format!("{{ user02: {:?} }}", ARGON2_PASSWORD02);
+        let config = usrpwd_config(&dictionary, &users);
+        let peer_auth_router = UserPasswordAuthenticator::from_config(&config)
+            .await
+            .unwrap()
+            .unwrap();
+        let unicast = TransportManager::config_unicast()
+            .peer_authenticator(HashSet::from_iter(vec![peer_auth_router.into()]));
+        let router_manager = TransportManager::builder()
+            .whatami(WhatAmI::Router)
+            .pid(PeerId::new(1, [0_u8; PeerId::MAX_SIZE]))
+            .unicast(unicast)
+            .build(Arc::new(SH))
+            .unwrap();
+        let endpoint: EndPoint = locator.parse().unwrap();
+        let _ = ztimeout!(router_manager.add_listener(endpoint.clone())).unwrap();
+
+        let client01 = new_client(1, "user01", "password01");
+        let client02 = new_client(2, "user02", "password02");
+        let client03 = new_client(3, "user03", "password03");
+        let invalid = new_client(4, "user01", "password02");
+
+        // The passwords are checked against their hashes
+        open(&client01, &endpoint).await.unwrap();
+        open(&client02, &endpoint).await.unwrap();
+        assert!(open(&client03, &endpoint).await.is_err());
+        assert!(open(&invalid, &endpoint).await.is_err());
+
+        // Add and remove users through the configuration
+        let users = format!("{{ user03: {:?} }}", PBKDF2_PASSWORD03);
+        let config = usrpwd_config(&dictionary, &users);
+        ztimeout!(router_manager.reload_authenticators(&config)).unwrap();
+        open(&client03, &endpoint).await.unwrap();
+        assert!(open(&client02, &endpoint).await.is_err());
+        open(&client01, &endpoint).await.unwrap();
+
+        // Rewrite the dictionary file, the router picks it up for new handshakes
+        std::fs::write(&dictionary, format!("user02:{}", ARGON2_PASSWORD01)).unwrap();
+        ztimeout!(async {
+            while open(&client01, &endpoint).await.is_ok() {
+                task::sleep(SLEEP).await;
+            }
+        });
+        let client05 = new_client(5, "user02", "password01");
+        open(&client05, &endpoint).await.unwrap();
+        open(&client03, &endpoint).await.unwrap();
+
+        // Close everything
+        ztimeout!(async {
+            while !router_manager.get_transports().is_empty() {
+                task::sleep(SLEEP).await;
+            }
+        });
+        ztimeout!(router_manager.del_listener(&endpoint)).unwrap();
+        ztimeout!(router_manager.close());
+        for c in [client01, client02, client03, invalid, client05] {
+            ztimeout!(c.close());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // Wait a little bit
+        task::sleep(SLEEP).await;
+    }
+
+    #[test]
+    fn transport_tcp_reload_users() {
+        task::block_on(async {
+            zasync_executor_init!();
+        });
+
+        task::block_on(reload_users("tcp/127.0.0.1:17451"));
+    }
+}
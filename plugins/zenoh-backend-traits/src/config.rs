@@ -1,6 +1,7 @@
 use derive_more::{AsMut, AsRef};
 use serde_json::{Map, Value};
 use std::convert::TryFrom;
+use std::time::Duration;
 use zenoh::Result as ZResult;
 use zenoh_core::{bail, zerror, Error};
 
@@ -32,10 +33,30 @@ pub struct StorageConfig {
     pub strip_prefix: String,
     pub volume_id: String,
     pub volume_cfg: Value,
+    pub replication: Option<ReplicationConfig>,
     // #[as_ref]
     // #[as_mut]
     // pub rest: Map<String, Value>,
 }
+/// The configuration of the anti-entropy replication between the storages sharing a key expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicationConfig {
+    /// The period between two publications of the digest of a storage.
+    pub interval: Duration,
+    /// The duration the deletions are kept in the replication index, for the other replicas
+    /// to learn about them. It must exceed the time needed by the replicas to converge.
+    pub tombstone_retention: Duration,
+}
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        ReplicationConfig {
+            interval: Duration::from_secs(DEFAULT_REPLICATION_INTERVAL_SECS),
+            tombstone_retention: Duration::from_secs(DEFAULT_TOMBSTONE_RETENTION_SECS),
+        }
+    }
+}
+pub const DEFAULT_REPLICATION_INTERVAL_SECS: u64 = 10;
+pub const DEFAULT_TOMBSTONE_RETENTION_SECS: u64 = 3600;
 #[derive(Debug)]
 pub enum ConfigDiff {
     DeleteVolume(VolumeConfig),
@@ -265,6 +286,15 @@ impl StorageConfig {
                 _ => unreachable!(),
             },
         );
+        if let Some(replication) = &self.replication {
+            let mut r = serde_json::Map::new();
+            r.insert("interval".into(), replication.interval.as_secs_f64().into());
+            r.insert(
+                "tombstone_retention".into(),
+                replication.tombstone_retention.as_secs_f64().into(),
+            );
+            result.insert("replication".into(), Value::Object(r));
+        }
         Value::Object(result)
     }
     fn try_from<V: AsObject>(plugin_name: &str, storage_name: &str, config: &V) -> ZResult<Self> {
@@ -307,12 +337,38 @@ impl StorageConfig {
             ),
             _ => bail!("Invalid type for field `volume` of storage `{}`. Only strings or objects with at least the `id` field are accepted.", storage_name)
         };
+        let replication = match config.get("replication") {
+            None => None,
+            Some(Value::Object(replication)) => {
+                let mut result = ReplicationConfig::default();
+                for (key, value) in replication {
+                    let duration = value
+                        .as_f64()
+                        .filter(|secs| *secs > 0.0)
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+                    match (key.as_str(), duration) {
+                        ("interval", Some(interval)) => result.interval = interval,
+                        ("tombstone_retention", Some(retention)) => {
+                            result.tombstone_retention = retention
+                        }
+                        ("interval", _) | ("tombstone_retention", _) => bail!("`replication.{}` field of storage `{}` must be a positive number of seconds", key, storage_name),
+                        _ => bail!("Unknown `replication.{}` field for storage `{}`", key, storage_name),
+                    }
+                }
+                Some(result)
+            }
+            _ => bail!(
+                "Invalid type for field `replication` of storage `{}`. Only objects are accepted.",
+                storage_name
+            ),
+        };
         Ok(StorageConfig {
             name: storage_name.into(),
             key_expr,
             strip_prefix,
             volume_id,
             volume_cfg,
+            replication,
         })
     }
 }
@@ -120,7 +120,7 @@
 //!         //  - if not: just get the sample with key==key_selector and call: query.reply(sample.clone()).await;
 //!         //  - if yes: get all the samples with key matching key_selector and call for each: query.reply(sample.clone()).await;
 //!         //
-//!         // NOTE: in case query.value_selector() is not empty something smarter should be done with returned samples...
+//!         // NOTE: the filter and fragment of query.value_selector() are applied by query.reply()
 //!         Ok(())
 //!     }
 //! }
@@ -128,7 +128,10 @@
 
 use async_std::sync::Arc;
 use async_trait::async_trait;
-use zenoh::prelude::{KeyExpr, Sample, Selector};
+use std::convert::TryFrom;
+use zenoh::prelude::{KeyExpr, Sample, Selector, Value, ValueSelector};
+use zenoh::selector::ValueFilter;
+use zenoh::time::Timestamp;
 pub use zenoh::Result as ZResult;
 
 pub mod config;
@@ -171,14 +174,25 @@ pub trait Storage: Send + Sync {
 
     /// Function called for each incoming query matching this storage's keys exp.
     /// This storage should reply with data matching the query calling [`Query::reply()`].
+    /// An error returned by this function is sent to the querier as an error reply.
     async fn on_query(&mut self, query: Query) -> ZResult<()>;
+
+    /// Returns the key and the timestamp of the latest sample stored for each key, and whether
+    /// it's a deletion (for the deletions still known by this storage).
+    /// It's called at the start of a replicated storage to index the content it already holds,
+    /// e.g. when recovered from a persistent volume. By default, no content is reported.
+    async fn get_all_entries(&self) -> ZResult<Vec<(String, Timestamp, bool)>> {
+        Ok(vec![])
+    }
 }
 
 /// A wrapper around the [`zenoh::queryable::Query`] allowing to call the
-/// OutgoingDataInterceptor (if any) before to send the reply
+/// OutgoingDataInterceptor (if any) before to send the reply, and applying
+/// the filter and fragment of the query's value selector on the replies.
 pub struct Query {
     q: zenoh::queryable::Query,
     interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
+    value_filter: ValueFilter,
 }
 
 impl Query {
@@ -186,7 +200,14 @@ impl Query {
         q: zenoh::queryable::Query,
         interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
     ) -> Query {
-        Query { q, interceptor }
+        let value_filter = ValueSelector::try_from(q.value_selector())
+            .map(|value_selector| value_selector.value_filter())
+            .unwrap_or_default();
+        Query {
+            q,
+            interceptor,
+            value_filter,
+        }
     }
 
     /// The full [`Selector`] of this Query.
@@ -207,7 +228,10 @@ impl Query {
         self.q.value_selector()
     }
 
-    /// Sends a Sample as a reply to this Query
+    /// Sends a Sample as a reply to this Query.
+    ///
+    /// The Sample is dropped if its value doesn't satisfy the filter of the value selector,
+    /// and only the fields selected by the fragment (if any) are sent.
     pub async fn reply(&self, sample: Sample) {
         // Call outgoing intercerceptor
         let sample = if let Some(ref interceptor) = self.interceptor {
@@ -215,7 +239,17 @@ impl Query {
         } else {
             sample
         };
-        // Send reply
-        self.q.reply_async(sample).await
+        // Apply filter and fragment
+        if let Some(sample) = self.value_filter.apply(sample) {
+            // Send reply
+            self.q.reply_async(sample).await
+        }
+    }
+
+    /// Sends an error reply to this Query, e.g. when the backend fails to retrieve the data.
+    ///
+    /// The error is neither intercepted nor filtered.
+    pub fn reply_err(&self, value: Value) {
+        self.q.reply_err(value)
     }
 }
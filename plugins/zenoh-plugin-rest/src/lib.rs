@@ -68,11 +68,11 @@ fn sample_to_json(sample: Sample) -> String {
     )
 }
 
-async fn to_json(results: ReplyReceiver) -> String {
-    let values = results
-        .filter_map(move |reply| async move { Some(sample_to_json(reply.sample)) })
+fn to_json(samples: Vec<Sample>) -> String {
+    let values = samples
+        .into_iter()
+        .map(sample_to_json)
         .collect::<Vec<String>>()
-        .await
         .join(",\n");
     format!("[\n{}\n]\n", values)
 }
@@ -85,15 +85,36 @@ fn sample_to_html(sample: Sample) -> String {
     )
 }
 
-async fn to_html(results: ReplyReceiver) -> String {
-    let values = results
-        .filter_map(move |reply| async move { Some(sample_to_html(reply.sample)) })
+fn to_html(samples: Vec<Sample>) -> String {
+    let values = samples
+        .into_iter()
+        .map(sample_to_html)
         .collect::<Vec<String>>()
-        .await
         .join("\n");
     format!("<dl>\n{}\n</dl>\n", values)
 }
 
+// Collect the samples of the replies, or the errors if any replier failed
+async fn collect_replies(mut results: ReplyReceiver) -> Result<Vec<Sample>, String> {
+    let mut samples = vec![];
+    let mut errors = vec![];
+    while let Some(reply) = results.next().await {
+        match reply.sample {
+            Ok(sample) => samples.push(sample),
+            Err(value) => errors.push(format!(
+                "{}: {}",
+                reply.replier_id,
+                String::from_utf8_lossy(&value.payload.contiguous())
+            )),
+        }
+    }
+    if errors.is_empty() {
+        Ok(samples)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
 fn method_to_kind(method: Method) -> SampleKind {
     match method {
         Method::Put => SampleKind::Put,
@@ -289,21 +310,28 @@ async fn query(req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
             .consolidation(consolidation)
             .await
         {
-            Ok(receiver) => {
-                if first_accept == "text/html" {
-                    Ok(response(
-                        StatusCode::Ok,
-                        Mime::from_str("text/html").unwrap(),
-                        &to_html(receiver).await,
-                    ))
-                } else {
-                    Ok(response(
-                        StatusCode::Ok,
-                        Mime::from_str("application/json").unwrap(),
-                        &to_json(receiver).await,
-                    ))
+            Ok(receiver) => match collect_replies(receiver).await {
+                Ok(samples) => {
+                    if first_accept == "text/html" {
+                        Ok(response(
+                            StatusCode::Ok,
+                            Mime::from_str("text/html").unwrap(),
+                            &to_html(samples),
+                        ))
+                    } else {
+                        Ok(response(
+                            StatusCode::Ok,
+                            Mime::from_str("application/json").unwrap(),
+                            &to_json(samples),
+                        ))
+                    }
                 }
-            }
+                Err(errors) => Ok(response(
+                    StatusCode::InternalServerError,
+                    Mime::from_str("text/plain").unwrap(),
+                    &errors,
+                )),
+            },
             Err(e) => Ok(response(
                 StatusCode::InternalServerError,
                 Mime::from_str("text/plain").unwrap(),
@@ -313,6 +341,38 @@ async fn query(req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
     }
 }
 
+async fn metrics(req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
+    log::trace!("Incoming metrics request: {:?}", req);
+    let key_expr = format!("/@/router/{}/metrics", req.state().1);
+    match req.state().0.get(&key_expr).await {
+        Ok(mut receiver) => match receiver.next().await {
+            Some(reply) => match reply.sample {
+                Ok(sample) => Ok(response(
+                    StatusCode::Ok,
+                    Mime::from_str("application/openmetrics-text; version=1.0.0; charset=utf-8")
+                        .unwrap(),
+                    &String::from_utf8_lossy(&sample.value.payload.contiguous()),
+                )),
+                Err(value) => Ok(response(
+                    StatusCode::InternalServerError,
+                    Mime::from_str("text/plain").unwrap(),
+                    &String::from_utf8_lossy(&value.payload.contiguous()),
+                )),
+            },
+            None => Ok(response(
+                StatusCode::InternalServerError,
+                Mime::from_str("text/plain").unwrap(),
+                "No metrics available",
+            )),
+        },
+        Err(e) => Ok(response(
+            StatusCode::InternalServerError,
+            Mime::from_str("text/plain").unwrap(),
+            &e.to_string(),
+        )),
+    }
+}
+
 async fn write(mut req: Request<(Arc<Session>, String)>) -> tide::Result<Response> {
     log::trace!("Incoming PUT request: {:?}", req);
     match req.body_bytes().await {
@@ -369,6 +429,9 @@ pub async fn run(runtime: Runtime, conf: Config) {
             .allow_credentials(false),
     );
 
+    if let Some(metrics_path) = &conf.metrics_path {
+        app.at(metrics_path).get(metrics);
+    }
     app.at("/").get(query).put(write).patch(write).delete(write);
     app.at("*").get(query).put(write).patch(write).delete(write);
 
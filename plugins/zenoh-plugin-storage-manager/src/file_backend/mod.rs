@@ -0,0 +1,610 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use crate::memory_backend::{
+    apply_patch, NonJsonPatch, Present, Removed, StoredValue, TimedCleanup, CLEANUP_TIMEOUT_MS,
+};
+use async_std::fs::{self, File, OpenOptions};
+use async_std::io::WriteExt;
+use async_std::path::{Component, Path, PathBuf};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use log::{debug, trace, warn};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use zenoh::prelude::*;
+use zenoh::time::Timestamp;
+use zenoh::utils::key_expr;
+use zenoh_backend_traits::config::{StorageConfig, VolumeConfig};
+use zenoh_backend_traits::*;
+use zenoh_collections::{TimedEvent, TimedHandle, Timer};
+use zenoh_core::{bail, zerror, Result as ZResult};
+
+mod record;
+use record::Record;
+
+// The volume option giving the directory where the storages are persisted.
+const PROP_ROOT: &str = "root";
+// The volume option giving the number of records appended to a storage's log
+// after which the log is compacted into the storage's snapshot.
+const PROP_COMPACTION_THRESHOLD: &str = "compaction_threshold";
+// The volume option making each write to a storage's log synced to the disk.
+const PROP_FSYNC: &str = "fsync";
+// The storage option giving the sub-directory of the root where the storage is persisted.
+const PROP_DIR: &str = "dir";
+
+const DEFAULT_COMPACTION_THRESHOLD: u64 = 1000;
+
+const LOG_FILE: &str = "log";
+const SNAPSHOT_FILE: &str = "snapshot";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.tmp";
+
+pub fn create_file_backend(config: VolumeConfig) -> ZResult<Box<dyn Volume>> {
+    let root = match config.rest.get(PROP_ROOT) {
+        Some(serde_json::Value::String(root)) => PathBuf::from(root),
+        _ => bail!(
+            "`{}` volume configuration requires a string-typed `{}` field",
+            config.name,
+            PROP_ROOT
+        ),
+    };
+    let compaction_threshold = match config.rest.get(PROP_COMPACTION_THRESHOLD) {
+        None => DEFAULT_COMPACTION_THRESHOLD,
+        Some(v) => match v.as_u64() {
+            Some(threshold) if threshold > 0 => threshold,
+            _ => bail!(
+                "`{}` field of `{}` volume configuration must be a positive integer",
+                PROP_COMPACTION_THRESHOLD,
+                config.name
+            ),
+        },
+    };
+    let fsync = match config.rest.get(PROP_FSYNC) {
+        None => false,
+        Some(serde_json::Value::Bool(b)) => *b,
+        _ => bail!(
+            "`{}` field of `{}` volume configuration must be a boolean",
+            PROP_FSYNC,
+            config.name
+        ),
+    };
+    Ok(Box::new(FileBackend {
+        config,
+        root,
+        compaction_threshold,
+        fsync,
+    }))
+}
+
+pub struct FileBackend {
+    config: VolumeConfig,
+    root: PathBuf,
+    compaction_threshold: u64,
+    fsync: bool,
+}
+
+#[async_trait]
+impl Volume for FileBackend {
+    fn get_admin_status(&self) -> serde_json::Value {
+        self.config.to_json_value()
+    }
+
+    async fn create_storage(&mut self, properties: StorageConfig) -> ZResult<Box<dyn Storage>> {
+        debug!("Create File Storage with configuration: {:?}", properties);
+        let dir = match properties.volume_cfg.get(PROP_DIR) {
+            None => properties.name.clone(),
+            Some(serde_json::Value::String(dir)) => dir.clone(),
+            Some(_) => bail!(
+                "`{}` field of storage `{}` must be a string",
+                PROP_DIR,
+                properties.name
+            ),
+        };
+        if !Path::new(&dir)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            bail!(
+                "`{}` field of storage `{}` must be a relative path without '..' components",
+                PROP_DIR,
+                properties.name
+            )
+        }
+        Ok(Box::new(
+            FileStorage::open(
+                properties,
+                self.root.join(dir),
+                self.compaction_threshold,
+                self.fsync,
+            )
+            .await?,
+        ))
+    }
+
+    fn incoming_data_interceptor(&self) -> Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>> {
+        None
+    }
+
+    fn outgoing_data_interceptor(&self) -> Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>> {
+        None
+    }
+}
+
+/// A storage keeping its values in memory as a [`MemoryStorage`](crate::memory_backend),
+/// and persisting them in a directory as an append-only log of the changes, periodically
+/// compacted into a snapshot of the values.
+///
+/// A compaction writes the snapshot into a temporary file that atomically replaces the
+/// previous snapshot before the log is truncated. After a crash, the storage thus recovers
+/// its values from the last snapshot and the valid part of the log, replaying the changes
+/// with the same timestamp ordering as when they were received.
+struct FileStorage {
+    config: StorageConfig,
+    map: Arc<RwLock<HashMap<String, StoredValue>>>,
+    timer: Timer,
+    non_json_patch: NonJsonPatch,
+    dir: PathBuf,
+    log: File,
+    log_records: u64,
+    compaction_threshold: u64,
+    fsync: bool,
+}
+
+impl FileStorage {
+    async fn open(
+        config: StorageConfig,
+        dir: PathBuf,
+        compaction_threshold: u64,
+        fsync: bool,
+    ) -> ZResult<FileStorage> {
+        let non_json_patch = NonJsonPatch::from_config(&config)?;
+        fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| zerror!("Failed to create directory {}: {}", dir.display(), e))?;
+
+        // A left-over temporary snapshot is the result of an interrupted compaction:
+        // the previous snapshot and the log are still valid.
+        let tmp = dir.join(SNAPSHOT_TMP_FILE);
+        if tmp.exists().await {
+            fs::remove_file(&tmp).await?;
+        }
+
+        let mut records = vec![];
+        let snapshot = dir.join(SNAPSHOT_FILE);
+        if snapshot.exists().await {
+            let (mut recs, valid) = read_file(&snapshot).await?;
+            if valid.is_some() {
+                bail!("Corrupted snapshot file {}", snapshot.display())
+            }
+            records.append(&mut recs);
+        }
+        let log_path = dir.join(LOG_FILE);
+        let mut log_records = 0;
+        if log_path.exists().await {
+            let (mut recs, valid) = read_file(&log_path).await?;
+            if let Some(valid) = valid {
+                warn!(
+                    "Truncating log file {} after {} valid records",
+                    log_path.display(),
+                    recs.len()
+                );
+                let log = OpenOptions::new().write(true).open(&log_path).await?;
+                log.set_len(valid).await?;
+                log.sync_all().await?;
+            }
+            log_records = recs.len() as u64;
+            records.append(&mut recs);
+        }
+        let mut log = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&log_path)
+            .await
+            .map_err(|e| zerror!("Failed to open log file {}: {}", log_path.display(), e))?;
+        if log.metadata().await?.len() == 0 {
+            log.write_all(record::MAGIC).await?;
+            log.sync_all().await?;
+        }
+
+        let storage = FileStorage {
+            config,
+            map: Arc::new(RwLock::new(HashMap::new())),
+            timer: Timer::new(false),
+            non_json_patch,
+            dir,
+            log,
+            log_records,
+            compaction_threshold,
+            fsync,
+        };
+        let count = records.len();
+        for record in records {
+            storage.replay(record).await;
+        }
+        debug!(
+            "File Storage {} recovered {} records from {}",
+            storage.config.name,
+            count,
+            storage.dir.display()
+        );
+        Ok(storage)
+    }
+
+    // Apply a record read from the disk to the map.
+    async fn replay(&self, record: Record) {
+        let mut map = self.map.write().await;
+        let (key, ts) = match &record {
+            Record::Put { key, ts, .. } | Record::Delete { key, ts } => (key.clone(), *ts),
+        };
+        if let Some(old_val) = map.get(&key) {
+            if old_val.ts() >= &ts {
+                return;
+            }
+        }
+        let value = match record {
+            Record::Put {
+                encoding, payload, ..
+            } => {
+                let value = Value::from(payload).encoding(Encoding::from(encoding));
+                Present {
+                    sample: Sample::new(self.full_key(&key), value).with_timestamp(ts),
+                    ts,
+                }
+            }
+            Record::Delete { .. } => Removed {
+                ts,
+                cleanup_handle: self.schedule_cleanup(key.clone()).await,
+            },
+        };
+        replace(&mut map, key, value);
+    }
+
+    #[inline]
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{}", self.config.strip_prefix, key)
+    }
+
+    async fn schedule_cleanup(&self, key: String) -> TimedHandle {
+        let event = TimedEvent::once(
+            Instant::now() + Duration::from_millis(CLEANUP_TIMEOUT_MS),
+            TimedCleanup {
+                map: self.map.clone(),
+                key,
+            },
+        );
+        let handle = event.get_handle();
+        self.timer.add_async(event).await;
+        handle
+    }
+
+    async fn append(&mut self, buf: &[u8]) -> ZResult<()> {
+        self.log.write_all(buf).await?;
+        if self.fsync {
+            self.log.sync_data().await?;
+        } else {
+            self.log.flush().await?;
+        }
+        self.log_records += 1;
+        Ok(())
+    }
+
+    async fn compact(&mut self, map: &HashMap<String, StoredValue>) -> ZResult<()> {
+        trace!(
+            "Compact File Storage {} with {} values",
+            self.config.name,
+            map.len()
+        );
+        let mut buf = record::MAGIC.to_vec();
+        for (key, value) in map {
+            encode(&mut buf, key, value);
+        }
+        let tmp = self.dir.join(SNAPSHOT_TMP_FILE);
+        let mut file = File::create(&tmp).await?;
+        file.write_all(&buf).await?;
+        file.sync_all().await?;
+        fs::rename(&tmp, self.dir.join(SNAPSHOT_FILE)).await?;
+        // make the rename durable before truncating the log
+        if let Ok(dir) = File::open(&self.dir).await {
+            let _ = dir.sync_all().await;
+        }
+        self.log.set_len(record::MAGIC.len() as u64).await?;
+        self.log.sync_all().await?;
+        self.log_records = 0;
+        Ok(())
+    }
+}
+
+// Append the record corresponding to a stored value to `buf`.
+fn encode(buf: &mut Vec<u8>, key: &str, value: &StoredValue) {
+    match value {
+        Present { sample, ts } => record::encode_put(
+            buf,
+            key,
+            ts,
+            &sample.value.encoding.to_string(),
+            &sample.value.payload.contiguous(),
+        ),
+        Removed { ts, .. } => record::encode_delete(buf, key, ts),
+    }
+}
+
+// Insert a value in the map, cancelling the timed cleanup of the replaced tombstone (if any).
+fn replace(map: &mut HashMap<String, StoredValue>, key: String, value: StoredValue) {
+    if let Some(Removed { cleanup_handle, .. }) = map.insert(key, value) {
+        cleanup_handle.defuse();
+    }
+}
+
+// Read the records of a log or snapshot file. Also returns the length of its valid part
+// if it ends with a truncated or corrupted record.
+async fn read_file(path: &Path) -> ZResult<(Vec<Record>, Option<u64>)> {
+    let bytes = fs::read(path)
+        .await
+        .map_err(|e| zerror!("Failed to read {}: {}", path.display(), e))?;
+    if bytes.len() < record::MAGIC.len() {
+        // the file was being created when crashing
+        return Ok((vec![], (!bytes.is_empty()).then_some(0)));
+    }
+    if !bytes.starts_with(record::MAGIC) {
+        bail!("{} is not a storage file", path.display())
+    }
+    let content = &bytes[record::MAGIC.len()..];
+    let (records, valid) = record::decode(content);
+    let valid = (valid < content.len()).then(|| (record::MAGIC.len() + valid) as u64);
+    Ok((records, valid))
+}
+
+#[async_trait]
+impl Storage for FileStorage {
+    fn get_admin_status(&self) -> serde_json::Value {
+        self.config.to_json_value()
+    }
+
+    async fn on_sample(&mut self, mut sample: Sample) -> ZResult<()> {
+        trace!("on_sample for {}", sample.key_expr);
+        sample.ensure_timestamp();
+        let timestamp = sample.timestamp.unwrap();
+        let key = match sample
+            .key_expr
+            .as_str()
+            .strip_prefix(&self.config.strip_prefix)
+        {
+            Some(key) => key.to_string(),
+            None => bail!(
+                "Received a sample for {} that doesn't start with strip_prefix {}",
+                sample.key_expr,
+                self.config.strip_prefix
+            ),
+        };
+        let map = self.map.clone();
+        let mut map = map.write().await;
+        let stored = map.get(&key);
+        if let Some(old_val) = stored {
+            if old_val.ts() >= &timestamp {
+                debug!(
+                    "{} on {} dropped: out-of-date",
+                    sample.kind, sample.key_expr
+                );
+                return Ok(());
+            }
+        }
+        let value = match sample.kind {
+            SampleKind::Put => Present {
+                sample,
+                ts: timestamp,
+            },
+            SampleKind::Patch => {
+                let stored = match stored {
+                    Some(Present { sample, ts: _ }) => Some(sample),
+                    _ => None,
+                };
+                Present {
+                    sample: apply_patch(self.non_json_patch, stored, sample)?,
+                    ts: timestamp,
+                }
+            }
+            SampleKind::Delete => Removed {
+                ts: timestamp,
+                cleanup_handle: self.schedule_cleanup(key.clone()).await,
+            },
+        };
+        // write the change ahead of applying it
+        let mut buf = vec![];
+        encode(&mut buf, &key, &value);
+        if let Err(e) = self.append(&buf).await {
+            if let Removed { cleanup_handle, .. } = value {
+                cleanup_handle.defuse();
+            }
+            return Err(e);
+        }
+        replace(&mut map, key, value);
+        if self.log_records >= self.compaction_threshold {
+            // the change is already in the log: a failed compaction is retried on the next change
+            if let Err(e) = self.compact(&map).await {
+                warn!(
+                    "Compaction of File Storage {} failed: {}",
+                    self.config.name, e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_query(&mut self, query: Query) -> ZResult<()> {
+        trace!("on_query for {}", query.key_selector());
+        let key_selector = query.key_selector().as_str();
+        let map = self.map.read().await;
+        if !key_selector.contains('*') {
+            if let Some(Present { sample, ts: _ }) = key_selector
+                .strip_prefix(&self.config.strip_prefix)
+                .and_then(|key| map.get(key))
+            {
+                query.reply(sample.clone()).await;
+            }
+        } else {
+            for stored_value in map.values() {
+                if let Present { sample, ts: _ } = stored_value {
+                    if key_expr::intersect(key_selector, sample.key_expr.as_str()) {
+                        query.reply(sample.clone()).await;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_all_entries(&self) -> ZResult<Vec<(String, Timestamp, bool)>> {
+        Ok(self
+            .map
+            .read()
+            .await
+            .iter()
+            .map(|(key, stored_value)| {
+                let deleted = matches!(stored_value, Removed { .. });
+                (self.full_key(key), *stored_value.ts(), deleted)
+            })
+            .collect())
+    }
+}
+
+impl Drop for FileStorage {
+    fn drop(&mut self) {
+        // nothing to do: all the changes are already in the log
+        trace!("FileStorage::drop()");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn storage_config(strip_prefix: &str) -> StorageConfig {
+        StorageConfig {
+            name: "test".into(),
+            key_expr: "/test/**".into(),
+            strip_prefix: strip_prefix.into(),
+            volume_id: "file".into(),
+            volume_cfg: serde_json::Value::Null,
+            replication: None,
+        }
+    }
+
+    fn sample(key: &str, value: &str, kind: SampleKind) -> Sample {
+        let mut sample = Sample::new(key.to_string(), value);
+        sample.kind = kind;
+        sample.timestamp = Some(zenoh::time::new_reception_timestamp());
+        sample
+    }
+
+    async fn stored(storage: &FileStorage, key: &str) -> Option<String> {
+        match storage.map.read().await.get(key) {
+            Some(Present { sample, ts: _ }) => {
+                assert_eq!(sample.key_expr.as_str(), storage.full_key(key));
+                Some(String::from_utf8(sample.value.payload.contiguous().to_vec()).unwrap())
+            }
+            Some(Removed { .. }) => Some("<removed>".into()),
+            None => None,
+        }
+    }
+
+    #[test]
+    fn file_storage_recovery() {
+        async_std::task::block_on(async {
+            let dir = PathBuf::from(std::env::temp_dir())
+                .join(format!("zenoh-file-storage-test-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir).await;
+
+            let mut storage = FileStorage::open(storage_config("/test/"), dir.clone(), 3, false)
+                .await
+                .unwrap();
+            let late = sample("/test/a", "late", SampleKind::Put);
+            storage
+                .on_sample(sample("/test/a", "a1", SampleKind::Put))
+                .await
+                .unwrap();
+            storage
+                .on_sample(sample("/test/b", "b1", SampleKind::Put))
+                .await
+                .unwrap();
+            storage
+                .on_sample(sample("/test/c", "c1", SampleKind::Put))
+                .await
+                .unwrap();
+            // the 3rd record triggered a compaction
+            assert_eq!(storage.log_records, 0);
+            storage
+                .on_sample(sample("/test/b", "", SampleKind::Delete))
+                .await
+                .unwrap();
+            storage.on_sample(late).await.unwrap();
+            assert!(storage
+                .on_sample(sample("/other/a", "x", SampleKind::Put))
+                .await
+                .is_err());
+            drop(storage);
+
+            // simulate a crash in the middle of a write
+            let mut log = std::fs::OpenOptions::new()
+                .append(true)
+                .open(dir.join(LOG_FILE))
+                .unwrap();
+            log.write_all(&[42, 0, 0, 0, 1, 2]).unwrap();
+            drop(log);
+
+            let mut storage = FileStorage::open(storage_config("/test/"), dir.clone(), 3, false)
+                .await
+                .unwrap();
+            assert_eq!(storage.log_records, 1);
+            assert_eq!(stored(&storage, "a").await.as_deref(), Some("a1"));
+            assert_eq!(stored(&storage, "b").await.as_deref(), Some("<removed>"));
+            assert_eq!(stored(&storage, "c").await.as_deref(), Some("c1"));
+            storage
+                .on_sample(sample("/test/c", "c2", SampleKind::Put))
+                .await
+                .unwrap();
+            drop(storage);
+
+            // an interrupted compaction leaves the previous snapshot and the log untouched
+            std::fs::write(dir.join(SNAPSHOT_TMP_FILE), b"garbage").unwrap();
+            let storage = FileStorage::open(storage_config("/test/"), dir.clone(), 3, false)
+                .await
+                .unwrap();
+            assert_eq!(storage.log_records, 2);
+            assert_eq!(stored(&storage, "c").await.as_deref(), Some("c2"));
+            assert!(!dir.join(SNAPSHOT_TMP_FILE).exists().await);
+            drop(storage);
+
+            // a failed compaction doesn't fail the write, and is retried on the next one
+            let mut storage = FileStorage::open(storage_config("/test/"), dir.clone(), 3, false)
+                .await
+                .unwrap();
+            fs::create_dir(dir.join(SNAPSHOT_TMP_FILE)).await.unwrap();
+            storage
+                .on_sample(sample("/test/d", "d1", SampleKind::Put))
+                .await
+                .unwrap();
+            assert_eq!(storage.log_records, 3);
+            assert_eq!(stored(&storage, "d").await.as_deref(), Some("d1"));
+            fs::remove_dir(dir.join(SNAPSHOT_TMP_FILE)).await.unwrap();
+            storage
+                .on_sample(sample("/test/d", "d2", SampleKind::Put))
+                .await
+                .unwrap();
+            assert_eq!(storage.log_records, 0);
+            drop(storage);
+
+            fs::remove_dir_all(&dir).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,213 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! The on-disk format of the file storages.
+//!
+//! Both the log and the snapshot files start with [`MAGIC`] followed by a sequence of records.
+//! Each record is framed as:
+//! ```text
+//! +---------------+---------------+------------------+
+//! | len: u32 (LE) | crc: u32 (LE) | body: [u8; len]  |
+//! +---------------+---------------+------------------+
+//! ```
+//! where `crc` is the CRC-32 of `body`, and `body` is made of:
+//! ```text
+//! op: u8 | ts_len: u8 | ts | key_len: u32 | key [| enc_len: u32 | enc | len: u32 | payload]
+//! ```
+//! with the encoding and payload only present for PUT records.
+use std::convert::TryInto;
+use std::str::FromStr;
+use zenoh::time::Timestamp;
+
+/// The header of log and snapshot files.
+pub(super) const MAGIC: &[u8] = b"ZSTORE01";
+
+const OP_PUT: u8 = 0;
+const OP_DELETE: u8 = 1;
+
+const FRAME_HEADER_LEN: usize = 8;
+
+/// A decoded record.
+#[derive(Debug, PartialEq)]
+pub(super) enum Record {
+    Put {
+        key: String,
+        ts: Timestamp,
+        encoding: String,
+        payload: Vec<u8>,
+    },
+    Delete {
+        key: String,
+        ts: Timestamp,
+    },
+}
+
+#[inline]
+fn push_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn encode(buf: &mut Vec<u8>, op: u8, key: &str, ts: &Timestamp, value: Option<(&str, &[u8])>) {
+    let start = buf.len();
+    // reserve the frame header, written once the body is known
+    buf.extend_from_slice(&[0u8; FRAME_HEADER_LEN]);
+    buf.push(op);
+    let ts = ts.to_string();
+    buf.push(ts.len() as u8);
+    buf.extend_from_slice(ts.as_bytes());
+    push_bytes(buf, key.as_bytes());
+    if let Some((encoding, payload)) = value {
+        push_bytes(buf, encoding.as_bytes());
+        push_bytes(buf, payload);
+    }
+    let body = &buf[start + FRAME_HEADER_LEN..];
+    let len = (body.len() as u32).to_le_bytes();
+    let crc = crc32fast::hash(body).to_le_bytes();
+    buf[start..start + 4].copy_from_slice(&len);
+    buf[start + 4..start + FRAME_HEADER_LEN].copy_from_slice(&crc);
+}
+
+/// Append a PUT record to `buf`.
+#[inline]
+pub(super) fn encode_put(
+    buf: &mut Vec<u8>,
+    key: &str,
+    ts: &Timestamp,
+    encoding: &str,
+    payload: &[u8],
+) {
+    encode(buf, OP_PUT, key, ts, Some((encoding, payload)))
+}
+
+/// Append a DELETE record to `buf`.
+#[inline]
+pub(super) fn encode_delete(buf: &mut Vec<u8>, key: &str, ts: &Timestamp) {
+    encode(buf, OP_DELETE, key, ts, None)
+}
+
+// A cursor over a record body, returning None when running out of bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?.to_vec()).ok()
+    }
+}
+
+fn decode_body(body: &[u8]) -> Option<Record> {
+    let mut reader = Reader(body);
+    let op = reader.u8()?;
+    let ts_len = reader.u8()? as usize;
+    let ts = Timestamp::from_str(std::str::from_utf8(reader.take(ts_len)?).ok()?).ok()?;
+    let key = reader.string()?;
+    let record = match op {
+        OP_PUT => Record::Put {
+            key,
+            ts,
+            encoding: reader.string()?,
+            payload: reader.bytes()?.to_vec(),
+        },
+        OP_DELETE => Record::Delete { key, ts },
+        _ => return None,
+    };
+    reader.0.is_empty().then_some(record)
+}
+
+/// Decode the records of a file's content (excluding its [`MAGIC`] header).
+///
+/// Decoding stops at the first truncated or corrupted record, as left by a crash in the middle
+/// of a write. Returns the decoded records and the length of the valid part of `bytes`.
+pub(super) fn decode(bytes: &[u8]) -> (Vec<Record>, usize) {
+    let mut records = vec![];
+    let mut offset = 0;
+    while bytes.len() - offset >= FRAME_HEADER_LEN {
+        let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap());
+        let body = match bytes.get(offset + FRAME_HEADER_LEN..offset + FRAME_HEADER_LEN + len) {
+            Some(body) if crc32fast::hash(body) == crc => body,
+            _ => break,
+        };
+        match decode_body(body) {
+            Some(record) => records.push(record),
+            None => break,
+        }
+        offset += FRAME_HEADER_LEN + len;
+    }
+    (records, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh::time::new_reception_timestamp;
+
+    #[test]
+    fn records_roundtrip_and_truncation() {
+        let ts1 = new_reception_timestamp();
+        let ts2 = new_reception_timestamp();
+        let mut buf = vec![];
+        encode_put(&mut buf, "a/b", &ts1, "application/json", b"{\"x\":1}");
+        encode_delete(&mut buf, "c", &ts2);
+        let expected = vec![
+            Record::Put {
+                key: "a/b".into(),
+                ts: ts1,
+                encoding: "application/json".into(),
+                payload: b"{\"x\":1}".to_vec(),
+            },
+            Record::Delete {
+                key: "c".into(),
+                ts: ts2,
+            },
+        ];
+        assert_eq!(decode(&buf), (expected, buf.len()));
+
+        // a record truncated by a crash is ignored
+        let (records, first_len) = decode(&buf[..buf.len() - 1]);
+        assert_eq!(records.len(), 1);
+        let (records, valid) = decode(&buf[..buf.len() + 3 - FRAME_HEADER_LEN]);
+        assert_eq!((records.len(), valid), (1, first_len));
+
+        // a corrupted record is detected by its checksum, and ends the decoding
+        let mut corrupted = buf.clone();
+        corrupted[first_len + FRAME_HEADER_LEN + 2] ^= 0xFF;
+        encode_put(&mut corrupted, "d", &ts2, "", b"");
+        let (records, valid) = decode(&corrupted);
+        assert_eq!((records.len(), valid), (1, first_len));
+    }
+}
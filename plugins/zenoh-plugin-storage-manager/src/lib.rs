@@ -16,6 +16,7 @@
 use async_std::channel::Sender;
 use async_std::sync::Arc;
 use async_std::task;
+use file_backend::create_file_backend;
 use libloading::Library;
 use memory_backend::create_memory_backend;
 use std::collections::HashMap;
@@ -37,7 +38,9 @@ use zenoh_util::LibLoader;
 
 mod backends_mgt;
 use backends_mgt::*;
+mod file_backend;
 mod memory_backend;
+mod replication;
 mod storages_mgt;
 
 zenoh_plugin_trait::declare_plugin!(StoragesPlugin);
@@ -150,6 +153,18 @@ impl StorageRuntimeInner {
                 }
                 Err(e) => bail!("{}", e),
             }
+        } else if config.paths.is_none()
+            && config.backend.as_deref().unwrap_or(&volume_id) == FILE_BACKEND_NAME
+        {
+            match create_file_backend(config) {
+                Ok(backend) => {
+                    self.volumes.insert(
+                        volume_id,
+                        VolumeHandle::new(backend, None, "<static-file>".into()),
+                    );
+                }
+                Err(e) => bail!("{}", e),
+            }
         } else {
             match config.backend_search_method() {
                 BackendSearchMethod::ByPaths(paths) => {
@@ -366,6 +381,7 @@ impl RunningPluginTrait for StorageRuntime {
 
 const BACKEND_LIB_PREFIX: &str = "zbackend_";
 const MEMORY_BACKEND_NAME: &str = "memory";
+const FILE_BACKEND_NAME: &str = "file";
 
 fn with_extended_string<R, F: FnMut(&mut String) -> R>(
     prefix: &mut String,
@@ -0,0 +1,314 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use super::CLEANUP_TIMEOUT_MS;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+use zenoh::prelude::*;
+use zenoh::selector::TimeRange;
+use zenoh::time::Timestamp;
+use zenoh_backend_traits::config::StorageConfig;
+use zenoh_core::{bail, Result as ZResult};
+
+// The storage option enabling the history mode, with its retention bounds.
+const PROP_HISTORY: &str = "history";
+const PROP_MAX_SAMPLES: &str = "max_samples";
+const PROP_MAX_AGE: &str = "max_age";
+const PROP_MAX_BYTES: &str = "max_bytes";
+
+/// The retention bounds of the samples history of each key. A missing bound is not enforced,
+/// but at least one bound is required.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct HistoryConfig {
+    max_samples: Option<usize>,
+    max_age: Option<Duration>,
+    max_bytes: Option<usize>,
+}
+
+impl HistoryConfig {
+    /// Parses the `history` option of a storage, if any.
+    pub(crate) fn from_config(config: &StorageConfig) -> ZResult<Option<HistoryConfig>> {
+        let history = match config.volume_cfg.get(PROP_HISTORY) {
+            None => return Ok(None),
+            Some(serde_json::Value::Object(history)) => history,
+            Some(_) => bail!(
+                "`{}` option of storage `{}` must be an object",
+                PROP_HISTORY,
+                config.name
+            ),
+        };
+        let mut result = HistoryConfig::default();
+        for (key, value) in history {
+            match (key.as_str(), value.as_u64(), value.as_f64()) {
+                (PROP_MAX_SAMPLES, Some(n), _) if n > 0 => result.max_samples = Some(n as usize),
+                (PROP_MAX_BYTES, Some(n), _) => result.max_bytes = Some(n as usize),
+                (PROP_MAX_AGE, _, Some(secs)) if secs > 0.0 => {
+                    match Duration::try_from_secs_f64(secs) {
+                        Ok(max_age) => result.max_age = Some(max_age),
+                        Err(e) => bail!(
+                            "`{}.{}` option of storage `{}` is out of range: {}",
+                            PROP_HISTORY,
+                            key,
+                            config.name,
+                            e
+                        ),
+                    }
+                }
+                (PROP_MAX_SAMPLES, _, _) | (PROP_MAX_BYTES, _, _) | (PROP_MAX_AGE, _, _) => bail!(
+                    "`{}.{}` option of storage `{}` must be a positive number",
+                    PROP_HISTORY,
+                    key,
+                    config.name
+                ),
+                _ => bail!(
+                    "Unknown `{}.{}` option for storage `{}`",
+                    PROP_HISTORY,
+                    key,
+                    config.name
+                ),
+            }
+        }
+        if result == HistoryConfig::default() {
+            bail!(
+                "`{}` option of storage `{}` requires at least one of the `{}`, `{}` or `{}` bounds",
+                PROP_HISTORY,
+                config.name,
+                PROP_MAX_SAMPLES,
+                PROP_MAX_AGE,
+                PROP_MAX_BYTES
+            )
+        }
+        Ok(Some(result))
+    }
+}
+
+#[derive(Default)]
+struct KeyHistory {
+    // ordered by timestamp
+    samples: VecDeque<(Timestamp, Sample)>,
+    bytes: usize,
+    // the timestamp of the latest deletion of the key, if any
+    deleted: Option<Timestamp>,
+}
+
+impl KeyHistory {
+    fn pop_oldest(&mut self) {
+        if let Some((_, sample)) = self.samples.pop_front() {
+            self.bytes -= sample.value.payload.len();
+        }
+    }
+
+    fn prune(&mut self, config: &HistoryConfig, now: SystemTime) {
+        if let Some(max_samples) = config.max_samples {
+            while self.samples.len() > max_samples {
+                self.pop_oldest();
+            }
+        }
+        if let Some(max_bytes) = config.max_bytes {
+            while self.bytes > max_bytes {
+                self.pop_oldest();
+            }
+        }
+        if let Some(oldest) = config.max_age.and_then(|age| now.checked_sub(age)) {
+            while matches!(self.samples.front(), Some((ts, _)) if ts.get_time().to_system_time() < oldest)
+            {
+                self.pop_oldest();
+            }
+        }
+    }
+}
+
+/// The samples history of each key of a storage, ordered by timestamp.
+pub(crate) struct History {
+    config: HistoryConfig,
+    keys: HashMap<String, KeyHistory>,
+}
+
+impl History {
+    pub(crate) fn new(config: HistoryConfig) -> History {
+        History {
+            config,
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Inserts a timestamped sample in the history of its key, whatever its arrival order.
+    /// Samples with an already known timestamp or older than the latest deletion of their key
+    /// are ignored.
+    pub(crate) fn insert(&mut self, key: String, ts: Timestamp, sample: Sample) {
+        let history = self.keys.entry(key).or_default();
+        if history.deleted.is_some_and(|deleted| ts <= deleted) {
+            return;
+        }
+        if let Err(i) = history.samples.binary_search_by(|(t, _)| t.cmp(&ts)) {
+            history.bytes += sample.value.payload.len();
+            history.samples.insert(i, (ts, sample));
+            history.prune(&self.config, SystemTime::now());
+        }
+    }
+
+    /// Records the deletion of a key, discarding the samples of its history older than `ts`.
+    pub(crate) fn delete(&mut self, key: String, ts: Timestamp) {
+        let history = self.keys.entry(key).or_default();
+        if history.deleted.is_some_and(|deleted| ts <= deleted) {
+            return;
+        }
+        history.deleted = Some(ts);
+        while matches!(history.samples.front(), Some((t, _)) if *t < ts) {
+            history.pop_oldest();
+        }
+    }
+
+    /// Returns the samples whose key intersects `key_selector` and whose timestamp is
+    /// in `time_range`, after having discarded the samples older than the maximum age.
+    /// The deletions are kept for [`CLEANUP_TIMEOUT_MS`], as the tombstones of the storage.
+    pub(crate) fn get(&mut self, key_selector: &str, time_range: &TimeRange) -> Vec<Sample> {
+        let now = SystemTime::now();
+        let config = &self.config;
+        let cleanup = now.checked_sub(Duration::from_millis(CLEANUP_TIMEOUT_MS));
+        self.keys.retain(|_, history| {
+            history.prune(config, now);
+            !history.samples.is_empty()
+                || history
+                    .deleted
+                    .zip(cleanup)
+                    .is_some_and(|(deleted, cleanup)| {
+                        deleted.get_time().to_system_time() >= cleanup
+                    })
+        });
+        self.keys
+            .iter()
+            .filter(|(key, _)| zenoh::utils::key_expr::intersect(key_selector, key))
+            .flat_map(|(_, history)| history.samples.iter())
+            .filter(|(ts, _)| time_range.contains_timestamp(ts))
+            .map(|(_, sample)| sample.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use zenoh::time::{new_reception_timestamp, NTP64};
+
+    fn sample(ts: Timestamp, payload: &str) -> (Timestamp, Sample) {
+        (ts, Sample::new("/test/a", payload).with_timestamp(ts))
+    }
+
+    fn payloads(samples: Vec<Sample>) -> Vec<String> {
+        samples
+            .into_iter()
+            .map(|s| String::from_utf8(s.value.payload.contiguous().to_vec()).unwrap())
+            .collect()
+    }
+
+    fn range(value_selector: &str) -> TimeRange {
+        ValueSelector::try_from(value_selector)
+            .unwrap()
+            .time_range()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn history_config() {
+        let config = |history: serde_json::Value| StorageConfig {
+            name: "test".into(),
+            key_expr: "/test/**".into(),
+            strip_prefix: "".into(),
+            volume_id: "memory".into(),
+            volume_cfg: serde_json::json!({ PROP_HISTORY: history }),
+            replication: None,
+        };
+        let history = HistoryConfig::from_config(&config(serde_json::json!({"max_age": 1.5})))
+            .unwrap()
+            .unwrap();
+        assert_eq!(history.max_age, Some(Duration::from_millis(1500)));
+        assert!(HistoryConfig::from_config(&config(serde_json::json!({"max_age": 1e30}))).is_err());
+        assert!(HistoryConfig::from_config(&config(serde_json::json!({"max_age": -1}))).is_err());
+        // an unbounded history is rejected
+        assert!(HistoryConfig::from_config(&config(serde_json::json!({}))).is_err());
+    }
+
+    #[test]
+    fn history_retention() {
+        let ts: Vec<Timestamp> = (0..5).map(|_| new_reception_timestamp()).collect();
+        let all = range("?(starttime=0)");
+
+        let mut history = History::new(HistoryConfig {
+            max_samples: Some(3),
+            ..Default::default()
+        });
+        // samples are ordered by timestamp whatever their arrival order, without duplicates
+        for i in [1, 0, 3, 2, 3] {
+            let (ts, sample) = sample(ts[i], &i.to_string());
+            history.insert("/test/a".into(), ts, sample);
+        }
+        assert_eq!(payloads(history.get("/test/*", &all)), ["1", "2", "3"]);
+        assert!(history.get("/other/**", &all).is_empty());
+
+        let mut history = History::new(HistoryConfig {
+            max_bytes: Some(4),
+            ..Default::default()
+        });
+        for (i, payload) in ["aa", "bb", "c", "dd"].iter().enumerate() {
+            let (ts, sample) = sample(ts[i], payload);
+            history.insert("/test/a".into(), ts, sample);
+        }
+        assert_eq!(payloads(history.get("/test/a", &all)), ["c", "dd"]);
+
+        let mut history = History::new(HistoryConfig {
+            max_age: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        });
+        let old = SystemTime::now() - Duration::from_secs(7200);
+        let old = Timestamp::new(
+            NTP64::from(old.duration_since(std::time::UNIX_EPOCH).unwrap()),
+            *ts[0].get_id(),
+        );
+        for (ts, payload) in [(old, "old"), (ts[4], "new")] {
+            let (ts, sample) = sample(ts, payload);
+            history.insert("/test/a".into(), ts, sample);
+        }
+        assert_eq!(payloads(history.get("/test/a", &all)), ["new"]);
+        assert!(history
+            .get("/test/a", &range("?(stoptime=now()-1h)"))
+            .is_empty());
+    }
+
+    #[test]
+    fn history_deletions() {
+        let ts: Vec<Timestamp> = (0..5).map(|_| new_reception_timestamp()).collect();
+        let all = range("?(starttime=0)");
+
+        let mut history = History::new(HistoryConfig {
+            max_samples: Some(10),
+            ..Default::default()
+        });
+        for i in [0, 1, 3] {
+            let (ts, sample) = sample(ts[i], &i.to_string());
+            history.insert("/test/a".into(), ts, sample);
+        }
+        // a deletion discards the older samples, even those received after it
+        history.delete("/test/a".into(), ts[2]);
+        let (_, late) = sample(ts[1], "late");
+        history.insert("/test/a".into(), ts[1], late);
+        assert_eq!(payloads(history.get("/test/a", &all)), ["3"]);
+        history.delete("/test/a".into(), ts[4]);
+        assert!(history.get("/test/**", &all).is_empty());
+        let (_, late) = sample(ts[3], "late");
+        history.insert("/test/a".into(), ts[3], late);
+        assert!(history.get("/test/**", &all).is_empty());
+    }
+}
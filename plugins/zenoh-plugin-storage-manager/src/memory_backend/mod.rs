@@ -13,9 +13,10 @@
 //
 use async_std::sync::{Arc, RwLock};
 use async_trait::async_trait;
-use log::{debug, trace, warn};
+use log::{debug, trace};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::time::{Duration, Instant};
 use zenoh::prelude::*;
 use zenoh::time::Timestamp;
@@ -23,7 +24,10 @@ use zenoh::utils::key_expr;
 use zenoh_backend_traits::config::{StorageConfig, VolumeConfig};
 use zenoh_backend_traits::*;
 use zenoh_collections::{Timed, TimedEvent, TimedHandle, Timer};
-use zenoh_core::Result as ZResult;
+use zenoh_core::{bail, zerror, Result as ZResult};
+
+mod history;
+use history::{History, HistoryConfig};
 
 pub fn create_memory_backend(config: VolumeConfig) -> ZResult<Box<dyn Volume>> {
     Ok(Box::new(MemoryBackend { config }))
@@ -73,7 +77,7 @@ impl Drop for MemoryBackend {
 }
 
 #[allow(clippy::large_enum_variant)]
-enum StoredValue {
+pub(crate) enum StoredValue {
     Present {
         ts: Timestamp,
         sample: Sample,
@@ -86,7 +90,7 @@ enum StoredValue {
 }
 
 impl StoredValue {
-    fn ts(&self) -> &Timestamp {
+    pub(crate) fn ts(&self) -> &Timestamp {
         match self {
             Present { ts, sample: _ } => ts,
             Removed {
@@ -96,20 +100,56 @@ impl StoredValue {
         }
     }
 }
-use StoredValue::{Present, Removed};
+pub(crate) use StoredValue::{Present, Removed};
+
+// The storage option defining how PATCH samples are applied when the stored value
+// or the patch are not JSON.
+pub(crate) const PROP_NON_JSON_PATCH: &str = "non_json_patch";
+
+/// The policy applied to PATCH samples that can't be merged as JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NonJsonPatch {
+    /// Drop the patch.
+    Reject,
+    /// Replace the stored value with the patch, as a PUT would.
+    Replace,
+}
+
+impl NonJsonPatch {
+    pub(crate) fn from_config(config: &StorageConfig) -> ZResult<NonJsonPatch> {
+        match config.volume_cfg.get(PROP_NON_JSON_PATCH) {
+            None => Ok(NonJsonPatch::Reject),
+            Some(serde_json::Value::String(s)) if s == "reject" => Ok(NonJsonPatch::Reject),
+            Some(serde_json::Value::String(s)) if s == "replace" => Ok(NonJsonPatch::Replace),
+            Some(v) => bail!(
+                "Invalid value for `{}` option of storage `{}`: {} (only \"reject\" or \"replace\" are accepted)",
+                PROP_NON_JSON_PATCH,
+                config.name,
+                v
+            ),
+        }
+    }
+}
 
 struct MemoryStorage {
     config: StorageConfig,
     map: Arc<RwLock<HashMap<String, StoredValue>>>,
     timer: Timer,
+    non_json_patch: NonJsonPatch,
+    // the samples history of each key, if the storage is in history mode
+    history: Option<History>,
 }
 
 impl MemoryStorage {
     async fn new(properties: StorageConfig) -> ZResult<MemoryStorage> {
+        let non_json_patch = NonJsonPatch::from_config(&properties)?;
+        let history = HistoryConfig::from_config(&properties)?.map(History::new);
         Ok(MemoryStorage {
             config: properties,
             map: Arc::new(RwLock::new(HashMap::new())),
             timer: Timer::new(false),
+            non_json_patch,
+            history,
         })
     }
 }
@@ -129,6 +169,62 @@ impl MemoryStorage {
     }
 }
 
+/// Compute the sample resulting from the application of the `patch` sample on the `stored`
+/// sample (if any) according to the RFC 7396 JSON merge-patch semantics, falling back on the
+/// `non_json_patch` policy when the stored value or the patch are not JSON.
+pub(crate) fn apply_patch(
+    non_json_patch: NonJsonPatch,
+    stored: Option<&Sample>,
+    patch: Sample,
+) -> ZResult<Sample> {
+    let target = match stored {
+        Some(sample) => sample.value.as_json(),
+        None => Some(serde_json::Value::Null),
+    };
+    let value = match (target, patch.value.as_json()) {
+        (Some(mut target), Some(json)) => {
+            merge_patch(&mut target, json);
+            match stored {
+                Some(stored) => Value::from(target).encoding(stored.value.encoding.clone()),
+                None => Value::from(target),
+            }
+        }
+        _ => match non_json_patch {
+            NonJsonPatch::Replace => patch.value,
+            NonJsonPatch::Reject => {
+                return Err(zerror!(
+                    "PATCH on {} rejected: the stored value or the patch is not JSON",
+                    patch.key_expr
+                )
+                .into())
+            }
+        },
+    };
+    let mut sample = Sample::new(patch.key_expr, value).with_source_info(patch.source_info);
+    sample.timestamp = patch.timestamp;
+    Ok(sample)
+}
+
+// Apply the `patch` on the `target` JSON value as specified by RFC 7396.
+fn merge_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+    match patch {
+        serde_json::Value::Object(patch) => {
+            if !target.is_object() {
+                *target = serde_json::Value::Object(serde_json::Map::new());
+            }
+            let map = target.as_object_mut().unwrap();
+            for (name, value) in patch {
+                if value.is_null() {
+                    map.remove(&name);
+                } else {
+                    merge_patch(map.entry(name).or_insert(serde_json::Value::Null), value);
+                }
+            }
+        }
+        patch => *target = patch,
+    }
+}
+
 #[async_trait]
 impl Storage for MemoryStorage {
     fn get_admin_status(&self) -> serde_json::Value {
@@ -139,6 +235,16 @@ impl Storage for MemoryStorage {
         trace!("on_sample for {}", sample.key_expr);
         sample.ensure_timestamp();
         let timestamp = sample.timestamp.unwrap();
+        // samples and deletions are kept in the history even if out-of-date for the latest value
+        match (&mut self.history, &sample.kind) {
+            (Some(history), SampleKind::Put) => {
+                history.insert(sample.key_expr.to_string(), timestamp, sample.clone())
+            }
+            (Some(history), SampleKind::Delete) => {
+                history.delete(sample.key_expr.to_string(), timestamp)
+            }
+            _ => (),
+        }
         match sample.kind {
             SampleKind::Put => match self.map.write().await.entry(sample.key_expr.to_string()) {
                 Entry::Vacant(v) => {
@@ -199,25 +305,75 @@ impl Storage for MemoryStorage {
                     }
                 }
             },
-            SampleKind::Patch => {
-                warn!("Received PATCH for {}: not yet supported", sample.key_expr);
-            }
+            SampleKind::Patch => match self.map.write().await.entry(sample.key_expr.to_string()) {
+                Entry::Vacant(v) => {
+                    let sample = apply_patch(self.non_json_patch, None, sample)?;
+                    if let Some(history) = &mut self.history {
+                        history.insert(sample.key_expr.to_string(), timestamp, sample.clone());
+                    }
+                    v.insert(Present {
+                        sample,
+                        ts: timestamp,
+                    });
+                }
+                Entry::Occupied(mut o) => {
+                    let old_val = o.get();
+                    if old_val.ts() < &timestamp {
+                        let sample = match old_val {
+                            Present {
+                                sample: stored,
+                                ts: _,
+                            } => apply_patch(self.non_json_patch, Some(stored), sample)?,
+                            Removed {
+                                ts: _,
+                                cleanup_handle,
+                            } => {
+                                let sample = apply_patch(self.non_json_patch, None, sample)?;
+                                // cancel timed cleanup
+                                cleanup_handle.clone().defuse();
+                                sample
+                            }
+                        };
+                        if let Some(history) = &mut self.history {
+                            history.insert(sample.key_expr.to_string(), timestamp, sample.clone());
+                        }
+                        o.insert(Present {
+                            sample,
+                            ts: timestamp,
+                        });
+                    } else {
+                        debug!("PATCH on {} dropped: out-of-date", sample.key_expr);
+                    }
+                }
+            },
         }
         Ok(())
     }
 
     async fn on_query(&mut self, query: Query) -> ZResult<()> {
         trace!("on_query for {}", query.key_selector());
+        let time_range = ValueSelector::try_from(query.value_selector())?.time_range()?;
+        if let (Some(history), Some(time_range)) = (&mut self.history, &time_range) {
+            for sample in history.get(query.key_selector().as_str(), time_range) {
+                query.reply(sample).await;
+            }
+            return Ok(());
+        }
+        // without history, only the latest value of each key is selected by a time range
+        let in_time_range = |ts: &Timestamp| time_range.is_none_or(|r| r.contains_timestamp(ts));
         if !query.key_selector().as_str().contains('*') {
-            if let Some(Present { sample, ts: _ }) =
+            if let Some(Present { sample, ts }) =
                 self.map.read().await.get(query.key_selector().as_str())
             {
-                query.reply(sample.clone()).await;
+                if in_time_range(ts) {
+                    query.reply(sample.clone()).await;
+                }
             }
         } else {
             for (_, stored_value) in self.map.read().await.iter() {
-                if let Present { sample, ts: _ } = stored_value {
+                if let Present { sample, ts } = stored_value {
                     if key_expr::intersect(query.key_selector().as_str(), sample.key_expr.as_str())
+                        && in_time_range(ts)
                     {
                         let s: Sample = sample.clone();
                         query.reply(s).await;
@@ -227,6 +383,19 @@ impl Storage for MemoryStorage {
         }
         Ok(())
     }
+
+    async fn get_all_entries(&self) -> ZResult<Vec<(String, Timestamp, bool)>> {
+        Ok(self
+            .map
+            .read()
+            .await
+            .iter()
+            .map(|(key, stored_value)| {
+                let deleted = matches!(stored_value, Removed { .. });
+                (key.clone(), *stored_value.ts(), deleted)
+            })
+            .collect())
+    }
 }
 
 impl Drop for MemoryStorage {
@@ -236,11 +405,11 @@ impl Drop for MemoryStorage {
     }
 }
 
-const CLEANUP_TIMEOUT_MS: u64 = 5000;
+pub(crate) const CLEANUP_TIMEOUT_MS: u64 = 5000;
 
-struct TimedCleanup {
-    map: Arc<RwLock<HashMap<String, StoredValue>>>,
-    key: String,
+pub(crate) struct TimedCleanup {
+    pub(crate) map: Arc<RwLock<HashMap<String, StoredValue>>>,
+    pub(crate) key: String,
 }
 
 #[async_trait]
@@ -249,3 +418,60 @@ impl Timed for TimedCleanup {
         self.map.write().await.remove(&self.key);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_patch, merge_patch, NonJsonPatch};
+    use serde_json::json;
+    use zenoh::prelude::*;
+
+    #[test]
+    fn merge_patch_rfc7396() {
+        // Test cases from RFC 7396, Appendix A
+        let cases = [
+            (json!({"a":"b"}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"b"}), json!({"b":"c"}), json!({"a":"b","b":"c"})),
+            (json!({"a":"b"}), json!({"a":null}), json!({})),
+            (
+                json!({"a":"b","b":"c"}),
+                json!({"a":null}),
+                json!({"b":"c"}),
+            ),
+            (json!({"a":["b"]}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"c"}), json!({"a":["b"]}), json!({"a":["b"]})),
+            (
+                json!({"a":{"b":"c"}}),
+                json!({"a":{"b":"d","c":null}}),
+                json!({"a":{"b":"d"}}),
+            ),
+            (json!({"a":[{"b":"c"}]}), json!({"a":[1]}), json!({"a":[1]})),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a":"b"}), json!(["c"]), json!(["c"])),
+            (json!({"a":"foo"}), json!(null), json!(null)),
+            (json!({"a":"foo"}), json!("bar"), json!("bar")),
+            (json!({"e":null}), json!({"a":1}), json!({"e":null,"a":1})),
+            (json!([1, 2]), json!({"a":"b","c":null}), json!({"a":"b"})),
+            (
+                json!({}),
+                json!({"a":{"bb":{"ccc":null}}}),
+                json!({"a":{"bb":{}}}),
+            ),
+        ];
+        for (mut target, patch, expected) in cases {
+            merge_patch(&mut target, patch);
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn apply_patch_keeps_stored_encoding() {
+        let stored = Sample::new(
+            "/test/a",
+            Value::from(r#"{"a":"b"}"#.to_string()).encoding(Encoding::TEXT_JSON),
+        );
+        let patch = Sample::new("/test/a", Value::from(json!({"c":"d"})));
+        let sample = apply_patch(NonJsonPatch::Reject, Some(&stored), patch).unwrap();
+        assert_eq!(sample.value.encoding, Encoding::TEXT_JSON);
+        assert_eq!(sample.value.as_json(), Some(json!({"a":"b","c":"d"})));
+    }
+}
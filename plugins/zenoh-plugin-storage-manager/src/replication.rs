@@ -0,0 +1,620 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! Anti-entropy replication between the storages sharing a key expression.
+//!
+//! Each replicated storage maintains an index of the timestamp of the latest sample (or
+//! deletion) it received for each key. The keys are partitioned in [`BUCKETS`] buckets whose
+//! hash is the XOR of the hashes of their entries. Periodically, each replica publishes the
+//! digest made of its non-empty buckets' hashes. On reception of another replica's digest,
+//! a replica queries the entries of the buckets that differ from its own, and then retrieves
+//! the samples that are missing or newer than its own from the storages. Since the samples
+//! are then stored with their original timestamps, the replicas converge on the
+//! last-writer-wins semantics of the storages.
+//!
+//! The replicas of a key expression communicate on the following keys:
+//!  * `/@/replication/<key expr hash>/digest/<replica>`: the digests publications.
+//!  * `/@/replication/<key expr hash>/entries/<replica>?(buckets=<b1>,<b2>...)`: the queryable
+//!    replying the entries of the given buckets.
+use async_std::channel::{Receiver, Sender};
+use async_std::sync::Arc;
+use async_std::task;
+use futures::select;
+use futures::stream::StreamExt;
+use futures::FutureExt;
+use log::{debug, trace, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use zenoh::prelude::*;
+use zenoh::query::{QueryConsolidation, QueryTarget, Target};
+use zenoh::queryable;
+use zenoh::time::Timestamp;
+use zenoh::Session;
+use zenoh_backend_traits::config::ReplicationConfig;
+use zenoh_core::zlock;
+
+/// The number of buckets the keys are partitioned in.
+pub(crate) const BUCKETS: u64 = 64;
+
+// FNV-1a: a stable hash, identical on all the replicas.
+fn fnv1a(bytes: &[u8], mut hash: u64) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+#[inline]
+fn bucket(key: &str) -> u64 {
+    fnv1a(key.as_bytes(), FNV_OFFSET) % BUCKETS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Entry {
+    pub(crate) ts: Timestamp,
+    pub(crate) deleted: bool,
+}
+
+impl Entry {
+    fn hash(&self, key: &str) -> u64 {
+        let hash = fnv1a(key.as_bytes(), FNV_OFFSET);
+        let hash = fnv1a(self.ts.to_string().as_bytes(), hash);
+        fnv1a(&[self.deleted as u8], hash)
+    }
+
+    // Returns true if this entry is a deletion older than the `retention`.
+    fn is_expired(&self, retention: Duration, now: SystemTime) -> bool {
+        self.deleted
+            && now
+                .checked_sub(retention)
+                .is_some_and(|oldest| self.ts.get_time().to_system_time() < oldest)
+    }
+}
+
+/// The timestamp of the latest sample or deletion received by a storage for each key.
+///
+/// The deletions are only kept for the `tombstone_retention` duration after their timestamp.
+pub(crate) struct ReplicationIndex {
+    entries: HashMap<String, Entry>,
+    tombstone_retention: Duration,
+}
+
+impl ReplicationIndex {
+    pub(crate) fn new(tombstone_retention: Duration) -> ReplicationIndex {
+        ReplicationIndex {
+            entries: HashMap::new(),
+            tombstone_retention,
+        }
+    }
+
+    /// Records the entry of a sample successfully stored, if it's newer than the known one
+    /// for its key. An expired deletion only removes the known entry.
+    pub(crate) fn update(&mut self, key: &str, entry: Entry) {
+        if self.entries.get(key).is_some_and(|e| e.ts >= entry.ts) {
+            return;
+        }
+        if entry.is_expired(self.tombstone_retention, SystemTime::now()) {
+            self.entries.remove(key);
+        } else {
+            self.entries.insert(key.to_string(), entry);
+        }
+    }
+
+    /// Removes the deletions older than the tombstone retention.
+    pub(crate) fn evict_tombstones(&mut self) {
+        let (retention, now) = (self.tombstone_retention, SystemTime::now());
+        self.entries
+            .retain(|_, entry| !entry.is_expired(retention, now));
+    }
+
+    /// Returns true if `ts` is newer than the known timestamp for `key`.
+    pub(crate) fn is_newer(&self, key: &str, ts: &Timestamp) -> bool {
+        self.entries.get(key).is_none_or(|e| e.ts < *ts)
+    }
+
+    /// Returns the hash of each non-empty bucket.
+    pub(crate) fn digest(&self) -> BTreeMap<u64, u64> {
+        let mut digest = BTreeMap::new();
+        for (key, entry) in &self.entries {
+            *digest.entry(bucket(key)).or_insert(0) ^= entry.hash(key);
+        }
+        digest
+    }
+
+    /// Returns the entries of the given buckets.
+    pub(crate) fn entries(&self, buckets: &[u64]) -> Vec<(String, Entry)> {
+        self.entries
+            .iter()
+            .filter(|(key, _)| buckets.contains(&bucket(key)))
+            .map(|(key, entry)| (key.clone(), *entry))
+            .collect()
+    }
+}
+
+/// Returns the buckets whose hash differs between two digests.
+pub(crate) fn diff(local: &BTreeMap<u64, u64>, remote: &BTreeMap<u64, u64>) -> Vec<u64> {
+    let mut buckets: Vec<u64> = local
+        .keys()
+        .chain(remote.keys())
+        .filter(|b| local.get(b) != remote.get(b))
+        .copied()
+        .collect();
+    buckets.sort_unstable();
+    buckets.dedup();
+    buckets
+}
+
+fn encode_digest(key_expr: &str, digest: &BTreeMap<u64, u64>) -> serde_json::Value {
+    let buckets: serde_json::Map<String, serde_json::Value> = digest
+        .iter()
+        .map(|(b, h)| (b.to_string(), format!("{:016x}", h).into()))
+        .collect();
+    serde_json::json!({ "key_expr": key_expr, "buckets": buckets })
+}
+
+fn decode_digest(value: &Value, key_expr: &str) -> Option<BTreeMap<u64, u64>> {
+    let json = value.as_json()?;
+    if json.get("key_expr")?.as_str()? != key_expr {
+        // another key expression with the same hash
+        return None;
+    }
+    json.get("buckets")?
+        .as_object()?
+        .iter()
+        .map(|(b, h)| Some((b.parse().ok()?, u64::from_str_radix(h.as_str()?, 16).ok()?)))
+        .collect()
+}
+
+fn encode_entries(entries: &[(String, Entry)]) -> serde_json::Value {
+    entries
+        .iter()
+        .map(|(key, entry)| {
+            serde_json::json!({
+                "key": key,
+                "ts": entry.ts.to_string(),
+                "deleted": entry.deleted,
+            })
+        })
+        .collect()
+}
+
+fn decode_entries(value: &Value) -> Option<Vec<(String, Entry)>> {
+    value
+        .as_json()?
+        .as_array()?
+        .iter()
+        .map(|e| {
+            Some((
+                e.get("key")?.as_str()?.to_string(),
+                Entry {
+                    ts: Timestamp::from_str(e.get("ts")?.as_str()?).ok()?,
+                    deleted: e.get("deleted")?.as_bool()?,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Starts the replication of a storage on `key_expr`.
+///
+/// The samples to be stored are sent on `samples`, and the replication stops once the
+/// `stop` channel is closed.
+pub(crate) async fn start_replication(
+    zenoh: Arc<Session>,
+    name: String,
+    key_expr: String,
+    config: ReplicationConfig,
+    index: Arc<Mutex<ReplicationIndex>>,
+    samples: Sender<Sample>,
+    stop: Receiver<()>,
+) {
+    let prefix = format!(
+        "/@/replication/{:016x}",
+        fnv1a(key_expr.as_bytes(), FNV_OFFSET)
+    );
+    let replica = format!("{}-{}", zenoh.id().await, name);
+    debug!(
+        "Start replication of storage {} on {} as {}",
+        name, prefix, replica
+    );
+
+    // Publish the digest and answer the entries queries: this task never waits for other replicas.
+    task::spawn({
+        let zenoh = zenoh.clone();
+        let index = index.clone();
+        let stop = stop.clone();
+        let key_expr = key_expr.clone();
+        let digest_key = format!("{}/digest/{}", prefix, replica);
+        let entries_key = format!("{}/entries/{}", prefix, replica);
+        async move {
+            let mut entries_queryable = match zenoh.queryable(&entries_key).await {
+                Ok(queryable) => queryable,
+                Err(e) => {
+                    warn!("Error starting replication of storage {}: {}", name, e);
+                    return;
+                }
+            };
+            loop {
+                select!(
+                    _ = task::sleep(config.interval).fuse() => {
+                        let digest = {
+                            let mut index = zlock!(index);
+                            index.evict_tombstones();
+                            encode_digest(&key_expr, &index.digest())
+                        };
+                        if let Err(e) = zenoh.put(&digest_key, digest).await {
+                            warn!("Error publishing digest of storage {}: {}", name, e);
+                        }
+                    },
+                    query = entries_queryable.next() => {
+                        let query = query.unwrap();
+                        let buckets: Vec<u64> = ValueSelector::try_from(query.value_selector())
+                            .ok()
+                            .and_then(|vs| vs.properties.get("buckets").cloned())
+                            .map(|b| b.split(',').filter_map(|b| b.parse().ok()).collect())
+                            .unwrap_or_default();
+                        let entries = encode_entries(&zlock!(index).entries(&buckets));
+                        query.reply_async(Sample::new(entries_key.clone(), entries)).await;
+                    },
+                    _ = stop.recv().fuse() => return,
+                );
+            }
+        }
+    });
+
+    // Compare the digests of the other replicas and retrieve the missing or newer samples.
+    task::spawn(async move {
+        let mut digests = match zenoh.subscribe(format!("{}/digest/*", prefix)).await {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                warn!("Error starting replication of storage {}: {}", replica, e);
+                return;
+            }
+        };
+        let own_digest_key = format!("{}/digest/{}", prefix, replica);
+        loop {
+            select!(
+                sample = digests.next() => {
+                    let sample = sample.unwrap();
+                    if sample.key_expr.as_str() == own_digest_key {
+                        continue;
+                    }
+                    if let Some(remote) = decode_digest(&sample.value, &key_expr) {
+                        let buckets = diff(&zlock!(index).digest(), &remote);
+                        if !buckets.is_empty() {
+                            let peer = sample
+                                .key_expr
+                                .as_str()
+                                .replacen("/digest/", "/entries/", 1);
+                            align(&zenoh, &peer, &buckets, &index, &samples).await;
+                        }
+                    }
+                },
+                _ = stop.recv().fuse() => return,
+            );
+        }
+    });
+}
+
+// Retrieve the entries of the given buckets from the `peer` replica, and the samples for
+// those that are missing or newer than the local ones.
+async fn align(
+    zenoh: &Session,
+    peer: &str,
+    buckets: &[u64],
+    index: &Mutex<ReplicationIndex>,
+    samples: &Sender<Sample>,
+) {
+    let buckets: Vec<String> = buckets.iter().map(u64::to_string).collect();
+    let selector = format!("{}?(buckets={})", peer, buckets.join(","));
+    trace!("Replication: query {}", selector);
+    let mut replies = match zenoh.get(&selector).await {
+        Ok(replies) => replies,
+        Err(e) => {
+            warn!("Replication: error querying {}: {}", selector, e);
+            return;
+        }
+    };
+    let mut entries = vec![];
+    while let Some(reply) = replies.next().await {
+        match reply.sample {
+            Ok(sample) => match decode_entries(&sample.value) {
+                Some(mut e) => entries.append(&mut e),
+                None => warn!("Replication: received invalid entries from {}", peer),
+            },
+            Err(e) => warn!("Replication: received an error from {}: {}", peer, e),
+        }
+    }
+    let entries: Vec<(String, Entry)> = {
+        let index = zlock!(index);
+        entries
+            .into_iter()
+            .filter(|(key, entry)| index.is_newer(key, &entry.ts))
+            .collect()
+    };
+    debug!(
+        "Replication: {} entries to align from {}",
+        entries.len(),
+        peer
+    );
+    for (key, entry) in entries {
+        if entry.deleted {
+            let mut sample = Sample::new(key, Value::empty()).with_timestamp(entry.ts);
+            sample.kind = SampleKind::Delete;
+            if samples.send(sample).await.is_err() {
+                return;
+            }
+            continue;
+        }
+        let query_target = QueryTarget {
+            kind: queryable::STORAGE,
+            target: Target::All,
+        };
+        let mut replies = match zenoh
+            .get(Selector::from(&key))
+            .target(query_target)
+            .consolidation(QueryConsolidation::none())
+            .await
+        {
+            Ok(replies) => replies,
+            Err(e) => {
+                warn!("Replication: error querying {}: {}", key, e);
+                continue;
+            }
+        };
+        while let Some(reply) = replies.next().await {
+            let sample = match reply.sample {
+                Ok(sample) => sample,
+                Err(e) => {
+                    warn!("Replication: received an error querying {}: {}", key, e);
+                    continue;
+                }
+            };
+            let newer = match &sample.timestamp {
+                Some(ts) => zlock!(index).is_newer(sample.key_expr.as_str(), ts),
+                None => false,
+            };
+            if newer && samples.send(sample).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storages_mgt::{start_storage, StorageMessage};
+    use async_trait::async_trait;
+    use zenoh::time::{new_reception_timestamp, NTP64};
+    use zenoh::utils::key_expr;
+    use zenoh_backend_traits::{Query, Storage};
+    use zenoh_core::Result as ZResult;
+
+    const RETENTION: Duration = Duration::from_secs(3600);
+
+    fn entry(deleted: bool) -> Entry {
+        Entry {
+            ts: new_reception_timestamp(),
+            deleted,
+        }
+    }
+
+    fn old_entry(age: Duration, deleted: bool) -> Entry {
+        let time = SystemTime::now() - age;
+        let time = time.duration_since(std::time::UNIX_EPOCH).unwrap();
+        Entry {
+            ts: Timestamp::new(NTP64::from(time), *new_reception_timestamp().get_id()),
+            deleted,
+        }
+    }
+
+    #[test]
+    fn digests_converge() {
+        let mut a = ReplicationIndex::new(RETENTION);
+        let mut b = ReplicationIndex::new(RETENTION);
+        let put1 = ("/test/1", entry(false));
+        let put2 = ("/test/2", entry(false));
+        let del1 = ("/test/1", entry(true));
+        for (key, e) in [put1, put2, del1] {
+            a.update(key, e);
+        }
+        // entries received in a different order, and duplicated
+        for (key, e) in [put2, del1, put1, put2] {
+            b.update(key, e);
+        }
+        assert_eq!(a.digest(), b.digest());
+        assert!(diff(&a.digest(), &b.digest()).is_empty());
+
+        let put3 = entry(false);
+        b.update("/test/3", put3);
+        let buckets = diff(&a.digest(), &b.digest());
+        assert_eq!(buckets, [bucket("/test/3")]);
+        let entries = b.entries(&buckets);
+        assert!(entries.contains(&("/test/3".to_string(), put3)));
+        assert!(a.is_newer("/test/3", &put3.ts));
+        assert!(!a.is_newer("/test/1", &put1.1.ts));
+
+        // digests and entries survive their encoding
+        let digest = b.digest();
+        let encoded = Value::from(encode_digest("/test/**", &digest));
+        assert_eq!(decode_digest(&encoded, "/test/**"), Some(digest));
+        assert_eq!(decode_digest(&encoded, "/other/**"), None);
+        let encoded = Value::from(encode_entries(&entries));
+        assert_eq!(decode_entries(&encoded), Some(entries));
+    }
+
+    #[test]
+    fn tombstones_expire() {
+        let mut index = ReplicationIndex::new(RETENTION);
+        // an expired deletion removes the older entry of its key
+        index.update("/test/1", old_entry(RETENTION * 3, false));
+        index.update("/test/1", old_entry(RETENTION * 2, true));
+        assert!(index.digest().is_empty());
+
+        let put = entry(false);
+        index.update("/test/2", put);
+        index.update("/test/3", entry(true));
+        assert_eq!(index.entries(&[bucket("/test/3")]).len(), 1);
+        index.tombstone_retention = Duration::ZERO;
+        index.evict_tombstones();
+        let mut expected = ReplicationIndex::new(RETENTION);
+        expected.update("/test/2", put);
+        assert_eq!(index.digest(), expected.digest());
+    }
+
+    // A storage keeping the latest sample of each key (deletions included) in a map shared
+    // with the test.
+    #[derive(Clone, Default)]
+    struct SharedStorage(Arc<Mutex<HashMap<String, Sample>>>);
+
+    impl SharedStorage {
+        fn store(&self, mut sample: Sample) {
+            sample.ensure_timestamp();
+            let mut map = zlock!(self.0);
+            let key = sample.key_expr.to_string();
+            if map.get(&key).is_none_or(|s| s.timestamp < sample.timestamp) {
+                map.insert(key, sample);
+            }
+        }
+
+        // The payload of each key, or None if deleted.
+        fn content(&self) -> BTreeMap<String, Option<String>> {
+            zlock!(self.0)
+                .iter()
+                .map(|(key, sample)| {
+                    let payload = sample.value.payload.contiguous().to_vec();
+                    let value = (sample.kind != SampleKind::Delete)
+                        .then(|| String::from_utf8(payload).unwrap());
+                    (key.clone(), value)
+                })
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl Storage for SharedStorage {
+        fn get_admin_status(&self) -> serde_json::Value {
+            serde_json::Value::Null
+        }
+
+        async fn on_sample(&mut self, sample: Sample) -> ZResult<()> {
+            self.store(sample);
+            Ok(())
+        }
+
+        async fn on_query(&mut self, query: Query) -> ZResult<()> {
+            let samples: Vec<Sample> = zlock!(self.0)
+                .values()
+                .filter(|s| s.kind != SampleKind::Delete)
+                .filter(|s| key_expr::intersect(query.key_selector().as_str(), s.key_expr.as_str()))
+                .cloned()
+                .collect();
+            for sample in samples {
+                query.reply(sample).await;
+            }
+            Ok(())
+        }
+
+        async fn get_all_entries(&self) -> ZResult<Vec<(String, Timestamp, bool)>> {
+            Ok(zlock!(self.0)
+                .iter()
+                .map(|(key, s)| {
+                    (
+                        key.clone(),
+                        s.timestamp.unwrap(),
+                        s.kind == SampleKind::Delete,
+                    )
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn storages_converge() {
+        task::block_on(async {
+            let _ = env_logger::try_init();
+            let mut config = zenoh::config::peer();
+            config
+                .insert_json5("scouting/multicast/enabled", "false")
+                .unwrap();
+            let zenoh = Arc::new(zenoh::open(config).await.unwrap());
+
+            // the storages hold diverging content before their start
+            let (a, b) = (SharedStorage::default(), SharedStorage::default());
+            let sample = |key: &str, value: &str, kind: SampleKind| {
+                let mut sample =
+                    Sample::new(key.to_string(), value).with_timestamp(new_reception_timestamp());
+                sample.kind = kind;
+                sample
+            };
+            a.store(sample("/test/1", "a1", SampleKind::Put));
+            a.store(sample("/test/2", "a2", SampleKind::Put));
+            b.store(sample("/test/1", "b1", SampleKind::Put));
+            b.store(sample("/test/3", "b3", SampleKind::Put));
+            a.store(sample("/test/3", "", SampleKind::Delete));
+            b.store(sample("/test/4", "b4", SampleKind::Put));
+
+            let config = ReplicationConfig {
+                interval: Duration::from_millis(100),
+                ..Default::default()
+            };
+            let mut handles = vec![];
+            for (name, storage) in [("a", &a), ("b", &b)] {
+                let handle = start_storage(
+                    Box::new(storage.clone()),
+                    format!(
+                        "/@/router/test/status/plugins/storage_manager/storages/{}",
+                        name
+                    ),
+                    "/test/**".into(),
+                    Some(config.clone()),
+                    None,
+                    None,
+                    zenoh.clone(),
+                )
+                .await
+                .unwrap();
+                handles.push(handle);
+            }
+
+            let expected: BTreeMap<String, Option<String>> = [
+                ("/test/1", Some("b1")),
+                ("/test/2", Some("a2")),
+                ("/test/3", None),
+                ("/test/4", Some("b4")),
+            ]
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.map(str::to_string)))
+            .collect();
+            for _ in 0..50 {
+                if a.content() == expected && b.content() == expected {
+                    break;
+                }
+                task::sleep(Duration::from_millis(100)).await;
+            }
+            assert_eq!(a.content(), expected);
+            assert_eq!(b.content(), expected);
+            for handle in handles {
+                handle.send(StorageMessage::Stop).await.unwrap();
+            }
+        });
+    }
+}
@@ -11,6 +11,7 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+use crate::replication::{start_replication, Entry, ReplicationIndex};
 use async_std::channel::{bounded, Sender};
 use async_std::sync::Arc;
 use async_std::task;
@@ -22,18 +23,43 @@ use zenoh::prelude::*;
 use zenoh::query::{QueryConsolidation, QueryTarget, Target};
 use zenoh::queryable;
 use zenoh::Session;
-use zenoh_backend_traits::Query;
-use zenoh_core::Result as ZResult;
+use zenoh_backend_traits::config::ReplicationConfig;
+use zenoh_backend_traits::{Query, Storage};
+use zenoh_core::{zlock, Result as ZResult};
 
 pub(crate) enum StorageMessage {
     Stop,
     GetStatus(Sender<serde_json::Value>),
 }
 
+// Store a sample, recording it in the replication index (if any) once stored.
+async fn store(
+    storage: &mut Box<dyn Storage>,
+    index: &Option<Arc<std::sync::Mutex<ReplicationIndex>>>,
+    mut sample: Sample,
+) -> ZResult<()> {
+    match index {
+        Some(index) => {
+            // the timestamp must be the same in the storage and in the index
+            sample.ensure_timestamp();
+            let key = sample.key_expr.to_string();
+            let entry = Entry {
+                ts: sample.timestamp.unwrap(),
+                deleted: sample.kind == SampleKind::Delete,
+            };
+            storage.on_sample(sample).await?;
+            zlock!(index).update(&key, entry);
+            Ok(())
+        }
+        None => storage.on_sample(sample).await,
+    }
+}
+
 pub(crate) async fn start_storage(
     mut storage: Box<dyn zenoh_backend_traits::Storage>,
     admin_key: String,
     key_expr: String,
+    replication: Option<ReplicationConfig>,
     in_interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
     out_interceptor: Option<Arc<dyn Fn(Sample) -> Sample + Send + Sync>>,
     zenoh: Arc<Session>,
@@ -42,6 +68,25 @@ pub(crate) async fn start_storage(
 
     let (tx, rx) = bounded(1);
     task::spawn(async move {
+        let index = replication.as_ref().map(|config| {
+            Arc::new(std::sync::Mutex::new(ReplicationIndex::new(
+                config.tombstone_retention,
+            )))
+        });
+
+        // index the content the storage already holds (e.g. recovered from a persistent volume)
+        if let Some(index) = &index {
+            match storage.get_all_entries().await {
+                Ok(entries) => {
+                    let mut index = zlock!(index);
+                    for (key, ts, deleted) in entries {
+                        index.update(&key, Entry { ts, deleted });
+                    }
+                }
+                Err(e) => warn!("Error indexing the content of storage {}: {}", admin_key, e),
+            }
+        }
+
         // subscribe on key_expr
         let mut storage_sub = match zenoh.subscribe(&key_expr).await {
             Ok(storage_sub) => storage_sub,
@@ -70,19 +115,25 @@ pub(crate) async fn start_storage(
             }
         };
         while let Some(reply) = replies.next().await {
-            log::trace!(
-                "Storage {} aligns data {}",
-                admin_key,
-                reply.sample.key_expr
-            );
+            let sample = match reply.sample {
+                Ok(sample) => sample,
+                Err(e) => {
+                    warn!(
+                        "Storage {} received an error aligning with {}: {}",
+                        admin_key, reply.replier_id, e
+                    );
+                    continue;
+                }
+            };
+            log::trace!("Storage {} aligns data {}", admin_key, sample.key_expr);
             // Call incoming data interceptor (if any)
             let sample = if let Some(ref interceptor) = in_interceptor {
-                interceptor(reply.sample)
+                interceptor(sample)
             } else {
-                reply.sample
+                sample
             };
             // Call storage
-            if let Err(e) = storage.on_sample(sample).await {
+            if let Err(e) = store(&mut storage, &index, sample).await {
                 warn!(
                     "Storage {} raised an error aligning a sample: {}",
                     admin_key, e
@@ -90,6 +141,26 @@ pub(crate) async fn start_storage(
             }
         }
 
+        // start the anti-entropy replication with the other storages on key_expr (if configured).
+        // replication_tx is kept for replication_rx to never be closed,
+        // even if the replication stops.
+        let (replication_tx, replication_rx) = bounded(1);
+        let (_replication_stop, replication_stop_rx) = bounded::<()>(1);
+        if let (Some(config), Some(index)) = (replication, &index) {
+            // the last chunk of the admin key is the storage name
+            let name = admin_key.rsplit('/').next().unwrap_or_default().to_string();
+            start_replication(
+                zenoh.clone(),
+                name,
+                key_expr.clone(),
+                config,
+                index.clone(),
+                replication_tx.clone(),
+                replication_stop_rx,
+            )
+            .await;
+        }
+
         // answer to queries on key_expr
         let mut storage_queryable = match zenoh.queryable(&key_expr).kind(queryable::STORAGE).await
         {
@@ -111,18 +182,41 @@ pub(crate) async fn start_storage(
                         sample.unwrap()
                     };
                     // Call storage
-                    if let Err(e) = storage.on_sample(sample).await {
+                    if let Err(e) = store(&mut storage, &index, sample).await {
                         warn!("Storage {} raised an error receiving a sample: {}", admin_key, e);
                     }
                 },
+                // on sample retrieved from another storage by the replication
+                sample = replication_rx.recv().fuse() => {
+                    if let Ok(sample) = sample {
+                        // Call incoming data interceptor (if any)
+                        let sample = if let Some(ref interceptor) = in_interceptor {
+                            interceptor(sample)
+                        } else {
+                            sample
+                        };
+                        if let Err(e) = store(&mut storage, &index, sample).await {
+                            warn!(
+                                "Storage {} raised an error replicating a sample: {}",
+                                admin_key, e
+                            );
+                        }
+                    }
+                },
                 // on query on key_expr
                 query = storage_queryable.next() => {
                     let q = query.unwrap();
+                    // keep a sender to report the error of the backend (if any) to the querier
+                    let replies_sender = q.replies_sender.clone();
                     // wrap zenoh::Query in zenoh_backend_traits::Query
                     // with outgoing interceptor
                     let query = Query::new(q, out_interceptor.clone());
                     if let Err(e) = storage.on_query(query).await {
                         warn!("Storage {} raised an error receiving a query: {}", admin_key, e);
+                        replies_sender.send_err(Value::from(format!(
+                            "Storage {} raised an error: {}",
+                            admin_key, e
+                        )));
                     }
                 },
                 // on storage handle drop
@@ -245,8 +245,15 @@ async fn net_event_handler(z: Arc<Session>, state: Arc<GroupState>) {
                                 let mut receiver = z.get(&qres).consolidation(qc).await.unwrap();
 
                                 while let Some(reply) = receiver.next().await {
+                                    let sample = match reply.sample {
+                                        Ok(sample) => sample,
+                                        Err(e) => {
+                                            log::debug!("Received error reply: {}", e);
+                                            continue;
+                                        }
+                                    };
                                     match bincode::deserialize::<Member>(
-                                        &reply.sample.value.payload.contiguous(),
+                                        &sample.value.payload.contiguous(),
                                     ) {
                                         Ok(m) => {
                                             let mut expiry = Instant::now();
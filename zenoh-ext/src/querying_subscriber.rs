@@ -416,11 +416,14 @@ impl Stream for InnerState {
             while i < mself.replies_recv_queue.len() {
                 loop {
                     match mself.replies_recv_queue[i].poll_next(cx) {
-                        Poll::Ready(Some(mut reply)) => {
-                            log::trace!("Reply received: {}", reply.sample.key_expr);
-                            reply.sample.ensure_timestamp();
-                            mself.merge_queue.push(reply.sample);
-                        }
+                        Poll::Ready(Some(reply)) => match reply.sample {
+                            Ok(mut sample) => {
+                                log::trace!("Reply received: {}", sample.key_expr);
+                                sample.ensure_timestamp();
+                                mself.merge_queue.push(sample);
+                            }
+                            Err(e) => log::warn!("Received error reply: {}", e),
+                        },
                         Poll::Ready(None) => {
                             // query completed - remove the receiver and break loop
                             mself.replies_recv_queue.remove(i);
@@ -490,10 +493,15 @@ impl InnerState {
         if !self.replies_recv_queue.is_empty() {
             // get all replies and add them to merge_queue
             for recv in self.replies_recv_queue.drain(..) {
-                while let Ok(mut reply) = recv.recv() {
-                    log::trace!("Reply received: {}", reply.sample.key_expr);
-                    reply.sample.ensure_timestamp();
-                    self.merge_queue.push(reply.sample);
+                while let Ok(reply) = recv.recv() {
+                    match reply.sample {
+                        Ok(mut sample) => {
+                            log::trace!("Reply received: {}", sample.key_expr);
+                            sample.ensure_timestamp();
+                            self.merge_queue.push(sample);
+                        }
+                        Err(e) => log::warn!("Received error reply: {}", e),
+                    }
                 }
             }
             log::debug!(
@@ -542,11 +550,14 @@ impl InnerState {
             while i < self.replies_recv_queue.len() {
                 loop {
                     match self.replies_recv_queue[i].try_recv() {
-                        Ok(mut reply) => {
-                            log::trace!("Reply received: {}", reply.sample.key_expr);
-                            reply.sample.ensure_timestamp();
-                            self.merge_queue.push(reply.sample);
-                        }
+                        Ok(reply) => match reply.sample {
+                            Ok(mut sample) => {
+                                log::trace!("Reply received: {}", sample.key_expr);
+                                sample.ensure_timestamp();
+                                self.merge_queue.push(sample);
+                            }
+                            Err(e) => log::warn!("Received error reply: {}", e),
+                        },
                         Err(TryRecvError::Disconnected) => {
                             // query completed - remove the receiver and break loop
                             self.replies_recv_queue.remove(i);
@@ -613,11 +624,14 @@ impl InnerState {
             while i < self.replies_recv_queue.len() {
                 loop {
                     match self.replies_recv_queue[i].recv_deadline(deadline) {
-                        Ok(mut reply) => {
-                            log::trace!("Reply received: {}", reply.sample.key_expr);
-                            reply.sample.ensure_timestamp();
-                            self.merge_queue.push(reply.sample);
-                        }
+                        Ok(reply) => match reply.sample {
+                            Ok(mut sample) => {
+                                log::trace!("Reply received: {}", sample.key_expr);
+                                sample.ensure_timestamp();
+                                self.merge_queue.push(sample);
+                            }
+                            Err(e) => log::warn!("Received error reply: {}", e),
+                        },
                         Err(RecvTimeoutError::Disconnected) => {
                             // query completed - remove the receiver and break loop
                             self.replies_recv_queue.remove(i);
@@ -89,6 +89,7 @@ fn tables_bench(c: &mut Criterion) {
                     None,
                     payload.clone(),
                     None,
+                    None,
                 );
             })
         });
@@ -104,6 +105,7 @@ fn tables_bench(c: &mut Criterion) {
                     None,
                     payload.clone(),
                     None,
+                    None,
                 );
             })
         });
@@ -119,6 +121,7 @@ fn tables_bench(c: &mut Criterion) {
                     None,
                     payload.clone(),
                     None,
+                    None,
                 );
             })
         });
@@ -69,7 +69,10 @@
 //!     let session = zenoh::open(config::default()).await.unwrap();
 //!     let mut replies = session.get("/key/expression").await.unwrap();
 //!     while let Some(reply) = replies.next().await {
-//!         println!(">> Received {}", reply.sample);
+//!         match reply.sample {
+//!             Ok(sample) => println!(">> Received {}", sample),
+//!             Err(value) => println!(">> Received an error: {}", value),
+//!         }
 //!     }
 //! }
 //! ```
@@ -110,10 +113,12 @@ pub mod net;
 #[deprecated = "This module is now a separate crate. Use the crate directly for shorter compile-times"]
 pub use zenoh_config as config;
 pub mod info;
+pub mod liveliness;
 pub mod prelude;
 pub mod publication;
 pub mod query;
 pub mod queryable;
+pub mod selector;
 pub mod subscriber;
 pub mod utils;
 
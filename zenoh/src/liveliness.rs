@@ -0,0 +1,338 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! Liveliness primitives.
+//!
+//! A [`LivelinessToken`] tells the other zenoh applications that the entity owning it is alive.
+//! Tokens have their own declarations in the routing infrastructure, propagated like
+//! subscriptions but kept apart from them: routers retract them as soon as the session owning
+//! them closes or loses its connection, with no keep-alive traffic of their own.
+use crate::prelude::{Id, KeyExpr};
+use crate::query::{Reply, ReplyReceiver};
+use crate::subscriber::{
+    CallbackSubscriber, DataHandler, SampleReceiver, Subscriber, SubscriberInvoker,
+};
+use crate::sync::ZFuture;
+use crate::API_DATA_RECEPTION_CHANNEL_SIZE;
+use crate::{Result as ZResult, SessionRef};
+use async_std::sync::Arc;
+use flume::bounded;
+use std::fmt;
+use std::sync::RwLock;
+use zenoh_sync::{derive_zfuture, Runnable};
+
+/// The liveliness primitives of a [`Session`](crate::Session), returned by
+/// [`Session::liveliness()`](crate::Session::liveliness).
+///
+/// # Examples
+/// ```
+/// # async_std::task::block_on(async {
+/// use zenoh::prelude::*;
+///
+/// let session = zenoh::open(config::peer()).await.unwrap();
+/// let token = session.liveliness().declare_token("/node/1").await.unwrap();
+/// # })
+/// ```
+pub struct Liveliness<'a> {
+    pub(crate) session: SessionRef<'a>,
+}
+
+impl<'a> Liveliness<'a> {
+    /// Declare a [`LivelinessToken`] for the given key expression.
+    ///
+    /// The token is alive until it is undeclared or dropped, or until the session closes.
+    /// The key expression must not contain wildcards.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap();
+    /// let token = session.liveliness().declare_token("/node/1").await.unwrap();
+    /// token.undeclare().await.unwrap();
+    /// # })
+    /// ```
+    pub fn declare_token<'b, IntoKeyExpr>(
+        &self,
+        key_expr: IntoKeyExpr,
+    ) -> LivelinessTokenBuilder<'a, 'b>
+    where
+        IntoKeyExpr: Into<KeyExpr<'b>>,
+    {
+        LivelinessTokenBuilder {
+            session: self.session.clone(),
+            key_expr: key_expr.into(),
+        }
+    }
+
+    /// Create a [`Subscriber`] receiving a [`Put`](crate::prelude::SampleKind::Put) sample when a
+    /// token matching the given key expression appears, and a [`Delete`](crate::prelude::SampleKind::Delete)
+    /// sample when it disappears.
+    ///
+    /// The tokens already alive are not notified, use [`get`](Liveliness::get) to retrieve them.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # async_std::task::block_on(async {
+    /// use futures::prelude::*;
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap();
+    /// let mut subscriber = session.liveliness().subscribe("/node/**").await.unwrap();
+    /// while let Some(sample) = subscriber.next().await {
+    ///     println!(">> {} is {:?}", sample.key_expr, sample.kind);
+    /// }
+    /// # })
+    /// ```
+    pub fn subscribe<'b, IntoKeyExpr>(
+        &self,
+        key_expr: IntoKeyExpr,
+    ) -> LivelinessSubscriberBuilder<'a, 'b>
+    where
+        IntoKeyExpr: Into<KeyExpr<'b>>,
+    {
+        LivelinessSubscriberBuilder {
+            session: self.session.clone(),
+            key_expr: key_expr.into(),
+        }
+    }
+
+    /// Retrieve the tokens currently alive that match the given key expression.
+    ///
+    /// The query is answered by the session itself from the tokens known to its routing tables,
+    /// with a [`Reply`] per token whose `replier_id` is the id of this session.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use futures::prelude::*;
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap();
+    /// let mut replies = session.liveliness().get("/node/**").await.unwrap();
+    /// while let Some(reply) = replies.next().await {
+    ///     if let Ok(sample) = reply.sample {
+    ///         println!(">> {} is alive", sample.key_expr);
+    ///     }
+    /// }
+    /// # })
+    /// ```
+    pub fn get<'b, IntoKeyExpr>(&self, key_expr: IntoKeyExpr) -> LivelinessGetter<'a, 'b>
+    where
+        IntoKeyExpr: Into<KeyExpr<'b>>,
+    {
+        LivelinessGetter {
+            session: self.session.clone(),
+            key_expr: key_expr.into(),
+        }
+    }
+}
+
+/// A token telling the other zenoh applications that its owner is alive.
+///
+/// Tokens are automatically undeclared when dropped.
+pub struct LivelinessToken<'a> {
+    pub(crate) session: SessionRef<'a>,
+    pub(crate) id: Id,
+    pub(crate) key_expr: String,
+    pub(crate) alive: bool,
+}
+
+impl LivelinessToken<'_> {
+    /// The key expression of this token.
+    pub fn key_expr(&self) -> &str {
+        &self.key_expr
+    }
+
+    /// Undeclare a [`LivelinessToken`].
+    ///
+    /// Tokens are automatically undeclared when dropped, but you may want to use this function to handle errors or
+    /// undeclare the token asynchronously.
+    #[inline]
+    #[must_use = "ZFutures do nothing unless you `.wait()`, `.await` or poll them"]
+    pub fn undeclare(mut self) -> impl ZFuture<Output = ZResult<()>> {
+        self.alive = false;
+        self.session.undeclare_token(self.id)
+    }
+}
+
+impl Drop for LivelinessToken<'_> {
+    fn drop(&mut self) {
+        if self.alive {
+            let _ = self.session.undeclare_token(self.id).wait();
+        }
+    }
+}
+
+impl fmt::Debug for LivelinessToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LivelinessToken{{ id:{}, key_expr:{} }}",
+            self.id, self.key_expr
+        )
+    }
+}
+
+derive_zfuture! {
+    /// A builder for initializing a [`LivelinessToken`].
+    ///
+    /// The result of this builder can be accessed synchronously via [`wait()`](ZFuture::wait())
+    /// or asynchronously via `.await`.
+    #[derive(Debug, Clone)]
+    pub struct LivelinessTokenBuilder<'a, 'b> {
+        pub(crate) session: SessionRef<'a>,
+        pub(crate) key_expr: KeyExpr<'b>,
+    }
+}
+
+impl<'a> Runnable for LivelinessTokenBuilder<'a, '_> {
+    type Output = ZResult<LivelinessToken<'a>>;
+
+    fn run(&mut self) -> Self::Output {
+        log::trace!("declare_token({:?})", self.key_expr);
+        self.session
+            .declare_token(&self.key_expr)
+            .map(|(id, key_expr)| LivelinessToken {
+                session: self.session.clone(),
+                id,
+                key_expr,
+                alive: true,
+            })
+    }
+}
+
+derive_zfuture! {
+    /// A builder for initializing a liveliness [`Subscriber`].
+    ///
+    /// The result of this builder can be accessed synchronously via [`wait()`](ZFuture::wait())
+    /// or asynchronously via `.await`.
+    #[derive(Debug, Clone)]
+    pub struct LivelinessSubscriberBuilder<'a, 'b> {
+        pub(crate) session: SessionRef<'a>,
+        pub(crate) key_expr: KeyExpr<'b>,
+    }
+}
+
+impl<'a, 'b> LivelinessSubscriberBuilder<'a, 'b> {
+    /// Make the built liveliness subscriber a [`CallbackSubscriber`].
+    #[inline]
+    pub fn callback<DataHandler>(
+        self,
+        handler: DataHandler,
+    ) -> CallbackLivelinessSubscriberBuilder<'a, 'b>
+    where
+        DataHandler: FnMut(crate::Sample) + Send + Sync + 'static,
+    {
+        CallbackLivelinessSubscriberBuilder {
+            session: self.session,
+            key_expr: self.key_expr,
+            handler: Arc::new(RwLock::new(handler)),
+        }
+    }
+}
+
+impl<'a> Runnable for LivelinessSubscriberBuilder<'a, '_> {
+    type Output = ZResult<Subscriber<'a>>;
+
+    fn run(&mut self) -> Self::Output {
+        log::trace!("liveliness subscribe({:?})", self.key_expr);
+        let (sender, receiver) = bounded(*API_DATA_RECEPTION_CHANNEL_SIZE);
+        self.session
+            .declare_liveliness_subscriber(&self.key_expr, SubscriberInvoker::Sender(sender))
+            .map(|sub_state| {
+                Subscriber::new(
+                    self.session.clone(),
+                    sub_state,
+                    true,
+                    SampleReceiver::new(receiver.clone()),
+                    receiver,
+                )
+            })
+    }
+}
+
+derive_zfuture! {
+    /// A builder for initializing a liveliness [`CallbackSubscriber`].
+    ///
+    /// The result of this builder can be accessed synchronously via [`wait()`](ZFuture::wait())
+    /// or asynchronously via `.await`.
+    #[derive(Clone)]
+    pub struct CallbackLivelinessSubscriberBuilder<'a, 'b> {
+        session: SessionRef<'a>,
+        key_expr: KeyExpr<'b>,
+        handler: Arc<RwLock<DataHandler>>,
+    }
+}
+
+impl fmt::Debug for CallbackLivelinessSubscriberBuilder<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackLivelinessSubscriberBuilder")
+            .field("session", &self.session)
+            .field("key_expr", &self.key_expr)
+            .finish()
+    }
+}
+
+impl<'a> Runnable for CallbackLivelinessSubscriberBuilder<'a, '_> {
+    type Output = ZResult<CallbackSubscriber<'a>>;
+
+    fn run(&mut self) -> Self::Output {
+        log::trace!(
+            "declare_callback_liveliness_subscriber({:?})",
+            self.key_expr
+        );
+        self.session
+            .declare_liveliness_subscriber(
+                &self.key_expr,
+                SubscriberInvoker::Handler(self.handler.clone()),
+            )
+            .map(|sub_state| CallbackSubscriber {
+                session: self.session.clone(),
+                state: sub_state,
+                alive: true,
+            })
+    }
+}
+
+derive_zfuture! {
+    /// A builder for initializing a liveliness query.
+    ///
+    /// The result of the query is provided as a [`ReplyReceiver`] and can be
+    /// accessed synchronously via [`wait()`](ZFuture::wait()) or asynchronously via `.await`.
+    #[derive(Debug, Clone)]
+    pub struct LivelinessGetter<'a, 'b> {
+        pub(crate) session: SessionRef<'a>,
+        pub(crate) key_expr: KeyExpr<'b>,
+    }
+}
+
+impl Runnable for LivelinessGetter<'_, '_> {
+    type Output = ZResult<ReplyReceiver>;
+
+    fn run(&mut self) -> Self::Output {
+        log::trace!("liveliness get({:?})", self.key_expr);
+        let samples = self.session.alive_tokens(&self.key_expr)?;
+        let (rep_sender, rep_receiver) = flume::unbounded();
+        for sample in samples {
+            let _ = rep_sender.send(Reply {
+                sample: Ok(sample),
+                replier_kind: crate::queryable::EVAL,
+                replier_id: self.session.runtime.pid,
+            });
+        }
+        Ok(ReplyReceiver::new(rep_receiver))
+    }
+}
@@ -0,0 +1,415 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use super::face::FaceState;
+use super::resource::Resource;
+use super::router::Tables;
+use async_std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use zenoh_config::{AclAction, AclRule, Config, Permission};
+use zenoh_core::{zerror, Result as ZResult};
+use zenoh_protocol::io::ZBuf;
+use zenoh_protocol::proto::{data_kind, DataInfo};
+use zenoh_protocol_core::key_expr;
+use zenoh_protocol_core::PeerId;
+use zenoh_transport::unicast::establishment::authenticator::AuthId;
+
+const ACTIONS: [AclAction; 6] = [
+    AclAction::Put,
+    AclAction::Delete,
+    AclAction::Subscribe,
+    AclAction::Query,
+    AclAction::Reply,
+    AclAction::Token,
+];
+
+#[inline]
+fn action_index(action: AclAction) -> usize {
+    match action {
+        AclAction::Put => 0,
+        AclAction::Delete => 1,
+        AclAction::Subscribe => 2,
+        AclAction::Query => 3,
+        AclAction::Reply => 4,
+        AclAction::Token => 5,
+    }
+}
+
+#[inline]
+fn action_name(action: AclAction) -> &'static str {
+    match action {
+        AclAction::Put => "put",
+        AclAction::Delete => "delete",
+        AclAction::Subscribe => "subscribe",
+        AclAction::Query => "query",
+        AclAction::Reply => "reply",
+        AclAction::Token => "token",
+    }
+}
+
+// PEM encodings are compared regardless of their line wrapping.
+fn normalize_pem(pem: &str) -> String {
+    pem.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+enum Subject {
+    User(String),
+    PublicKey(String),
+    Peer(PeerId),
+}
+
+impl Subject {
+    fn matches(&self, face: &FaceState) -> bool {
+        match self {
+            Subject::User(user) => face
+                .auth_ids
+                .iter()
+                .any(|id| matches!(id, AuthId::Username(u) if u == user)),
+            Subject::PublicKey(key) => face
+                .auth_ids
+                .iter()
+                .any(|id| matches!(id, AuthId::PublicKey(k) if normalize_pem(k) == *key)),
+            Subject::Peer(pid) => face.pid == *pid,
+        }
+    }
+}
+
+/// A set of identities remote faces may have been authenticated with.
+pub(crate) struct Subjects(Vec<Subject>);
+
+impl Subjects {
+    pub(crate) fn new(
+        users: &[String],
+        public_keys: &[String],
+        peer_ids: &[String],
+    ) -> ZResult<Subjects> {
+        let mut subjects = vec![];
+        subjects.extend(users.iter().cloned().map(Subject::User));
+        subjects.extend(
+            public_keys
+                .iter()
+                .map(|key| Subject::PublicKey(normalize_pem(key))),
+        );
+        for pid in peer_ids {
+            let pid = pid
+                .parse()
+                .map_err(|e| zerror!("Invalid peer id '{}': {}", pid, e))?;
+            subjects.push(Subject::Peer(pid));
+        }
+        Ok(Subjects(subjects))
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check if the given `face` has one of these identities.
+    #[inline]
+    pub(crate) fn matches(&self, face: &FaceState) -> bool {
+        self.0.iter().any(|s| s.matches(face))
+    }
+}
+
+/// Describe the peer id and authenticated identities of the given `face`.
+pub(crate) fn identity(face: &FaceState) -> String {
+    if face.auth_ids.is_empty() {
+        face.pid.to_string()
+    } else {
+        let ids: Vec<String> = face.auth_ids.iter().map(|id| id.to_string()).collect();
+        format!("{} ({})", face.pid, ids.join(", "))
+    }
+}
+
+struct Rule {
+    subjects: Subjects,
+    key_exprs: Vec<String>,
+    actions: Vec<AclAction>,
+    permission: Permission,
+}
+
+impl Rule {
+    fn new(rule: &AclRule) -> ZResult<Rule> {
+        let conf = &rule.subjects;
+        let subjects = Subjects::new(&conf.users, &conf.public_keys, &conf.peer_ids)
+            .map_err(|e| zerror!("Invalid access control rule: {}", e))?;
+        Ok(Rule {
+            subjects,
+            key_exprs: rule.key_exprs.clone(),
+            actions: rule.actions.clone(),
+            permission: rule.permission,
+        })
+    }
+
+    #[inline]
+    fn applies_to(&self, face: &FaceState, action: AclAction) -> bool {
+        self.actions.contains(&action) && (self.subjects.is_empty() || self.subjects.matches(face))
+    }
+}
+
+/// The access control policy enforced by the routing [`Tables`] on the declarations and
+/// messages received from remote faces.
+///
+/// A rule denying an action applies to every key expression intersecting one of its key
+/// expressions while a rule allowing an action only applies to the key expressions included
+/// in one of them. Denying rules take precedence over allowing rules and the default
+/// permission applies when no rule matches.
+pub struct AccessControl {
+    default_permission: Permission,
+    rules: Vec<Rule>,
+    denials: [AtomicUsize; 6],
+}
+
+impl AccessControl {
+    pub fn from_config(config: &Config) -> ZResult<Option<AccessControl>> {
+        let conf = config.access_control();
+        if !conf.enabled() {
+            return Ok(None);
+        }
+        for rule in conf.rules() {
+            if rule.permission == Permission::Allow && !rule.subjects.peer_ids.is_empty() {
+                log::warn!(
+                    "Access control rule allowing {:?} on {:?} to peer_ids {:?}: the zenoh ids are not authenticated, any remote peer may claim them",
+                    rule.actions,
+                    rule.key_exprs,
+                    rule.subjects.peer_ids
+                );
+            }
+        }
+        let rules = conf
+            .rules()
+            .iter()
+            .map(Rule::new)
+            .collect::<ZResult<Vec<Rule>>>()?;
+        log::debug!("Access control enabled with {} rules", rules.len());
+        Ok(Some(AccessControl {
+            default_permission: conf.default_permission().unwrap_or(Permission::Allow),
+            rules,
+            denials: Default::default(),
+        }))
+    }
+
+    /// Check if the given `face` is granted the given `action` on the given key expression.
+    pub fn is_allowed(&self, face: &FaceState, action: AclAction, expr: &str) -> bool {
+        let mut allowed = false;
+        for rule in self.rules.iter().filter(|r| r.applies_to(face, action)) {
+            match rule.permission {
+                Permission::Deny => {
+                    if rule
+                        .key_exprs
+                        .iter()
+                        .any(|ke| key_expr::intersect(ke, expr))
+                    {
+                        return false;
+                    }
+                }
+                Permission::Allow => {
+                    if rule.key_exprs.iter().any(|ke| key_expr::include(ke, expr)) {
+                        allowed = true;
+                    }
+                }
+            }
+        }
+        allowed || self.default_permission == Permission::Allow
+    }
+
+    /// The number of denied messages and declarations for each action.
+    pub fn denials(&self) -> Vec<(&'static str, usize)> {
+        ACTIONS
+            .iter()
+            .map(|action| {
+                (
+                    action_name(*action),
+                    self.denials[action_index(*action)].load(Ordering::Relaxed),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Return the action performed by a data message with the given `info`.
+#[inline]
+pub(crate) fn data_action(info: &Option<DataInfo>) -> AclAction {
+    match info.as_ref().and_then(|info| info.kind) {
+        Some(data_kind::DELETE) => AclAction::Delete,
+        _ => AclAction::Put,
+    }
+}
+
+/// Check the access control policy of the given `tables` (if any) for the given `face` on the
+/// key expression made of `prefix` and `suffix`, logging and counting denials.
+/// Local faces (see [`Tables::open_face`]) are always granted access.
+#[inline]
+pub(crate) fn check(
+    tables: &Tables,
+    face: &FaceState,
+    action: AclAction,
+    prefix: &Resource,
+    suffix: &str,
+) -> bool {
+    match &tables.acl {
+        Some(acl) if !face.is_local() => {
+            let expr = prefix.expr() + suffix;
+            if acl.is_allowed(face, action, &expr) {
+                true
+            } else {
+                acl.denials[action_index(action)].fetch_add(1, Ordering::Relaxed);
+                log::warn!(
+                    "Access control: {} denied on {} for {}",
+                    action_name(action),
+                    expr,
+                    face
+                );
+                false
+            }
+        }
+        _ => true,
+    }
+}
+
+// Return the first chunk of the key expression made of `prefix` and `suffix`.
+#[inline]
+fn first_chunk<'a>(prefix: &'a Resource, suffix: &'a str) -> &'a str {
+    let mut res = prefix;
+    let mut chunk = suffix;
+    while let Some(parent) = &res.parent {
+        chunk = &res.suffix;
+        res = parent;
+    }
+    chunk
+}
+
+/// The policy restricting the configuration changes sent to the adminspace of this router.
+pub struct AdminConfigPolicy {
+    write: bool,
+    writers: Subjects,
+}
+
+impl AdminConfigPolicy {
+    pub fn from_config(config: &Config) -> ZResult<AdminConfigPolicy> {
+        let conf = config.adminspace();
+        let writers = &conf.writers().subjects;
+        if *conf.write() && !writers.peer_ids.is_empty() {
+            log::warn!(
+                "Adminspace writers peer_ids {:?}: the zenoh ids are not authenticated, any remote peer may claim them",
+                writers.peer_ids
+            );
+        }
+        let writers = Subjects::new(&writers.users, &writers.public_keys, &writers.peer_ids)
+            .map_err(|e| zerror!("Invalid adminspace writers: {}", e))?;
+        Ok(AdminConfigPolicy {
+            write: *conf.write(),
+            writers,
+        })
+    }
+
+    /// A policy rejecting every configuration change.
+    pub fn read_only() -> AdminConfigPolicy {
+        AdminConfigPolicy {
+            write: false,
+            writers: Subjects(vec![]),
+        }
+    }
+}
+
+impl Default for AdminConfigPolicy {
+    fn default() -> Self {
+        AdminConfigPolicy {
+            write: true,
+            writers: Subjects(vec![]),
+        }
+    }
+}
+
+/// The handler applying the configuration changes accepted by the [`AdminConfigPolicy`] of a router.
+pub trait AdminConfigHandler: Send + Sync {
+    /// Apply the change of the configuration on `key_expr` issued by `who`: the deletion of the
+    /// configuration value if `data_info` is one of a deletion, the insertion of `payload` otherwise.
+    fn config_change(&self, who: &str, key_expr: &str, payload: ZBuf, data_info: Option<DataInfo>);
+}
+
+/// A configuration change accepted by [`check_admin_config_write`], applied once routed.
+pub(crate) struct AdminConfigChange {
+    handler: Arc<dyn AdminConfigHandler>,
+    who: String,
+    key_expr: String,
+}
+
+impl AdminConfigChange {
+    pub(crate) fn apply(self, payload: ZBuf, data_info: Option<DataInfo>) {
+        self.handler
+            .config_change(&self.who, &self.key_expr, payload, data_info)
+    }
+}
+
+/// What is done with a message according to [`check_admin_config_write`].
+pub(crate) enum AdminConfigWrite {
+    /// The message is routed as any other one.
+    Route,
+    /// The message is routed and the configuration change it carries is applied.
+    Apply(AdminConfigChange),
+    /// The message is a rejected configuration change and is dropped.
+    Reject,
+}
+
+/// Check if the configuration change sent by the given `face` to the adminspace of this router
+/// on the key expression made of `prefix` and `suffix` (if it is one) is permitted, logging
+/// the rejected ones.
+/// Changes from local faces are accepted unless the adminspace is read-only, while the changes
+/// from remote faces must come from one of the configured writers (if any). The accepted changes
+/// are applied by the [`AdminConfigHandler`] of the tables, given the identity of their issuer.
+#[inline]
+pub(crate) fn check_admin_config_write(
+    tables: &Tables,
+    face: &FaceState,
+    prefix: &Resource,
+    suffix: &str,
+) -> AdminConfigWrite {
+    if !first_chunk(prefix, suffix).starts_with("/@") {
+        return AdminConfigWrite::Route;
+    }
+    let expr = prefix.expr() + suffix;
+    if !expr.starts_with(&format!("/@/router/{}/config/", tables.pid)) {
+        return AdminConfigWrite::Route;
+    }
+    let policy = &tables.admin_config_policy;
+    let who = if face.is_local() {
+        format!("local {}", face)
+    } else {
+        identity(face)
+    };
+    if !policy.write {
+        log::warn!(
+            "Rejected config change on {} from {}: the adminspace is read-only",
+            expr,
+            who
+        );
+        AdminConfigWrite::Reject
+    } else if face.is_local() || policy.writers.is_empty() || policy.writers.matches(face) {
+        match &tables.admin_config_handler {
+            Some(handler) => AdminConfigWrite::Apply(AdminConfigChange {
+                handler: handler.clone(),
+                who,
+                key_expr: expr,
+            }),
+            None => AdminConfigWrite::Route,
+        }
+    } else {
+        log::warn!(
+            "Rejected config change on {} from {}: not an authorized writer",
+            expr,
+            who
+        );
+        AdminConfigWrite::Reject
+    }
+}
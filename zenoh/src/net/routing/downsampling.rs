@@ -0,0 +1,544 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use super::acl::Subjects;
+use super::face::FaceState;
+use async_std::sync::Arc;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zenoh_buffers::ZBuf;
+use zenoh_collections::{Timed, TimedEvent, Timer};
+use zenoh_config::{Config, DownsamplingRule, DownsamplingStrategy};
+use zenoh_core::{bail, zerror, zlock, Result as ZResult};
+use zenoh_protocol::proto::{Attachment, DataInfo, RoutingContext};
+use zenoh_protocol_core::key_expr;
+use zenoh_protocol_core::{Channel, CongestionControl, KeyExpr};
+use zenoh_transport::Primitives;
+
+// The minimum number of key expressions tracked by a face before the idle ones are evicted
+const KEYS_SWEEP_THRESHOLD: usize = 1_024;
+
+struct Rule {
+    subjects: Subjects,
+    interfaces: Vec<IpAddr>,
+    key_exprs: Vec<String>,
+    period: Duration,
+    strategy: DownsamplingStrategy,
+    drops: AtomicUsize,
+}
+
+impl Rule {
+    fn new(rule: &DownsamplingRule) -> ZResult<Rule> {
+        let conf = &rule.subjects;
+        let subjects = Subjects::new(&conf.users, &conf.public_keys, &conf.peer_ids)
+            .map_err(|e| zerror!("Invalid downsampling rule: {}", e))?;
+        let mut interfaces = vec![];
+        for iface in &rule.interfaces {
+            match iface.parse::<IpAddr>() {
+                Ok(addr) => interfaces.push(addr),
+                Err(_) => match zenoh_util::net::get_interface(iface)? {
+                    Some(addr) => interfaces.push(addr),
+                    None => log::warn!("Downsampling: unable to find interface {}", iface),
+                },
+            }
+        }
+        if !(rule.max_rate > 0.0 && rule.max_rate.is_finite()) {
+            bail!(
+                "Invalid downsampling rule: max_rate must be positive, got {}",
+                rule.max_rate
+            );
+        }
+        let period = Duration::try_from_secs_f64(1.0 / rule.max_rate).map_err(|e| {
+            zerror!(
+                "Invalid downsampling rule: max_rate {} is out of range: {}",
+                rule.max_rate,
+                e
+            )
+        })?;
+        Ok(Rule {
+            subjects,
+            interfaces,
+            key_exprs: rule.key_exprs.clone(),
+            period,
+            strategy: rule.strategy,
+            drops: AtomicUsize::new(0),
+        })
+    }
+
+    fn applies_to(&self, face: &FaceState, addrs: &[IpAddr]) -> bool {
+        (self.subjects.is_empty() && self.interfaces.is_empty())
+            || self.subjects.matches(face)
+            || addrs.iter().any(|addr| self.interfaces.contains(addr))
+    }
+}
+
+/// The downsampling policy enforced by the routing [`Tables`](super::router::Tables) on the
+/// publications routed to remote faces.
+///
+/// For each face, the first rule applying to it and including a key expression limits the
+/// rate of the publications routed to this face on this key expression.
+pub struct Downsampling {
+    rules: Vec<Arc<Rule>>,
+}
+
+impl Downsampling {
+    pub fn from_config(config: &Config) -> ZResult<Option<Downsampling>> {
+        let rules = config
+            .downsampling()
+            .rules()
+            .iter()
+            .map(|rule| Rule::new(rule).map(Arc::new))
+            .collect::<ZResult<Vec<Arc<Rule>>>>()?;
+        if rules.is_empty() {
+            return Ok(None);
+        }
+        log::debug!("Downsampling enabled with {} rules", rules.len());
+        Ok(Some(Downsampling { rules }))
+    }
+
+    /// The downsampler of the given `face`, reached through the given local addresses,
+    /// if some rules apply to it.
+    pub(crate) fn face_downsampler(
+        &self,
+        face: &FaceState,
+        addrs: &[IpAddr],
+        timer: &Timer,
+    ) -> Option<Arc<FaceDownsampler>> {
+        let rules: Vec<Arc<Rule>> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to(face, addrs))
+            .cloned()
+            .collect();
+        if rules.is_empty() {
+            None
+        } else {
+            Some(Arc::new(FaceDownsampler {
+                rules,
+                timer: timer.clone(),
+                keys: Mutex::new(Keys::default()),
+            }))
+        }
+    }
+
+    /// The key expressions of each rule with the number of publications it dropped.
+    pub fn drops(&self) -> Vec<(&[String], usize)> {
+        self.rules
+            .iter()
+            .map(|rule| (&rule.key_exprs[..], rule.drops.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+struct Sample {
+    key_expr: KeyExpr<'static>,
+    payload: ZBuf,
+    channel: Channel,
+    congestion_control: CongestionControl,
+    data_info: Option<DataInfo>,
+    routing_context: Option<RoutingContext>,
+    attachment: Option<Attachment>,
+}
+
+struct KeyState {
+    rule: Arc<Rule>,
+    next: Instant,
+    pending: Option<Sample>,
+}
+
+impl KeyState {
+    // A key expression is idle once its period elapsed with no publication kept:
+    // its state is then the one of a key expression never published on.
+    fn is_idle(&self, now: Instant) -> bool {
+        self.pending.is_none() && now >= self.next
+    }
+}
+
+// The key expressions matching a rule the face was recently sent publications on
+struct Keys {
+    states: HashMap<String, KeyState>,
+    sweep_threshold: usize,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            states: HashMap::new(),
+            sweep_threshold: KEYS_SWEEP_THRESHOLD,
+        }
+    }
+}
+
+impl Keys {
+    // Evict the idle key expressions whenever the number of tracked ones doubled since the
+    // last eviction, so that the memory is bounded by the publications of the last period.
+    fn sweep(&mut self, now: Instant) {
+        if self.states.len() >= self.sweep_threshold {
+            self.states.retain(|_, state| !state.is_idle(now));
+            self.sweep_threshold = KEYS_SWEEP_THRESHOLD.max(2 * self.states.len());
+        }
+    }
+}
+
+/// Limits the rate of the publications routed to a face.
+pub(crate) struct FaceDownsampler {
+    rules: Vec<Arc<Rule>>,
+    timer: Timer,
+    keys: Mutex<Keys>,
+}
+
+impl FaceDownsampler {
+    /// Send the given publication on `key` to the face through `primitives` if the rate allows it.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn send_data(
+        self: &Arc<Self>,
+        primitives: &Arc<dyn Primitives + Send + Sync>,
+        key: String,
+        key_expr: &KeyExpr,
+        payload: ZBuf,
+        channel: Channel,
+        congestion_control: CongestionControl,
+        data_info: Option<DataInfo>,
+        routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
+    ) {
+        let mut guard = zlock!(self.keys);
+        let keys = &mut *guard;
+        let now = Instant::now();
+        // Only the key expressions matching a rule are tracked
+        if !keys.states.contains_key(&key) {
+            let rule = self
+                .rules
+                .iter()
+                .find(|rule| rule.key_exprs.iter().any(|ke| key_expr::include(ke, &key)));
+            match rule {
+                Some(rule) => {
+                    keys.sweep(now);
+                    keys.states.insert(
+                        key.clone(),
+                        KeyState {
+                            rule: rule.clone(),
+                            next: now,
+                            pending: None,
+                        },
+                    );
+                }
+                None => {
+                    drop(guard);
+                    primitives.send_data(
+                        key_expr,
+                        payload,
+                        channel,
+                        congestion_control,
+                        data_info,
+                        routing_context,
+                        attachment,
+                    );
+                    return;
+                }
+            }
+        }
+        // The state was just inserted if it was missing
+        let state = keys.states.get_mut(&key).unwrap();
+        let rule = state.rule.clone();
+
+        if state.is_idle(now) {
+            state.next = now + rule.period;
+            drop(guard);
+            primitives.send_data(
+                key_expr,
+                payload,
+                channel,
+                congestion_control,
+                data_info,
+                routing_context,
+                attachment,
+            );
+            return;
+        }
+
+        match rule.strategy {
+            DownsamplingStrategy::Drop => {
+                rule.drops.fetch_add(1, Ordering::Relaxed);
+            }
+            DownsamplingStrategy::Latest => {
+                let sample = Sample {
+                    key_expr: key_expr.to_owned(),
+                    payload,
+                    channel,
+                    congestion_control,
+                    data_info,
+                    routing_context,
+                    attachment,
+                };
+                if state.pending.replace(sample).is_some() {
+                    rule.drops.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    let when = state.next;
+                    drop(guard);
+                    self.timer.add(TimedEvent::once(
+                        when,
+                        SendLatest {
+                            downsampler: self.clone(),
+                            primitives: primitives.clone(),
+                            key,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// Sends the latest publication kept on a key expression once the rate allows it.
+#[derive(Clone)]
+struct SendLatest {
+    downsampler: Arc<FaceDownsampler>,
+    primitives: Arc<dyn Primitives + Send + Sync>,
+    key: String,
+}
+
+#[async_trait]
+impl Timed for SendLatest {
+    async fn run(&mut self) {
+        let sample = {
+            let mut keys = zlock!(self.downsampler.keys);
+            match keys.states.get_mut(&self.key) {
+                Some(state) => {
+                    state.next = Instant::now() + state.rule.period;
+                    state.pending.take()
+                }
+                None => None,
+            }
+        };
+        if let Some(sample) = sample {
+            self.primitives.send_data(
+                &sample.key_expr,
+                sample.payload,
+                sample.channel,
+                sample.congestion_control,
+                sample.data_info,
+                sample.routing_context,
+                sample.attachment,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh_config::SubjectsConf;
+    use zenoh_protocol::proto::QueryBody;
+    use zenoh_protocol_core::{
+        ConsolidationStrategy, PeerId, QueryTarget, QueryableInfo, SubInfo, ZInt,
+    };
+
+    #[derive(Default)]
+    struct DataRecorder(Mutex<Vec<String>>);
+
+    impl Primitives for DataRecorder {
+        fn decl_resource(&self, _expr_id: ZInt, _key_expr: &KeyExpr) {}
+        fn forget_resource(&self, _expr_id: ZInt) {}
+        fn decl_publisher(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+        fn forget_publisher(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+        fn decl_subscriber(
+            &self,
+            _key_expr: &KeyExpr,
+            _sub_info: &SubInfo,
+            _routing_context: Option<RoutingContext>,
+        ) {
+        }
+        fn forget_subscriber(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        }
+        fn decl_queryable(
+            &self,
+            _key_expr: &KeyExpr,
+            _kind: ZInt,
+            _qabl_info: &QueryableInfo,
+            _routing_context: Option<RoutingContext>,
+        ) {
+        }
+        fn forget_queryable(
+            &self,
+            _key_expr: &KeyExpr,
+            _kind: ZInt,
+            _routing_context: Option<RoutingContext>,
+        ) {
+        }
+        fn decl_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+        fn forget_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+        fn send_data(
+            &self,
+            key_expr: &KeyExpr,
+            payload: ZBuf,
+            _channel: Channel,
+            _congestion_control: CongestionControl,
+            _data_info: Option<DataInfo>,
+            _routing_context: Option<RoutingContext>,
+            _attachment: Option<Attachment>,
+        ) {
+            use zenoh_buffers::SplitBuffer;
+            zlock!(self.0).push(format!(
+                "{}={}",
+                key_expr,
+                String::from_utf8_lossy(&payload.contiguous())
+            ));
+        }
+        fn send_query(
+            &self,
+            _key_expr: &KeyExpr,
+            _value_selector: &str,
+            _qid: ZInt,
+            _target: QueryTarget,
+            _consolidation: ConsolidationStrategy,
+            _body: Option<QueryBody>,
+            _routing_context: Option<RoutingContext>,
+            _attachment: Option<Attachment>,
+        ) {
+        }
+        fn send_reply_data(
+            &self,
+            _qid: ZInt,
+            _replier_kind: ZInt,
+            _replier_id: PeerId,
+            _is_error: bool,
+            _key_expr: KeyExpr,
+            _info: Option<DataInfo>,
+            _payload: ZBuf,
+            _attachment: Option<Attachment>,
+        ) {
+        }
+        fn send_reply_final(&self, _qid: ZInt) {}
+        fn send_pull(
+            &self,
+            _is_final: bool,
+            _key_expr: &KeyExpr,
+            _pull_id: ZInt,
+            _max_samples: &Option<ZInt>,
+        ) {
+        }
+        fn send_close(&self) {}
+    }
+
+    fn downsampler(max_rate: f64, strategy: DownsamplingStrategy) -> Arc<FaceDownsampler> {
+        let rule = Rule::new(&DownsamplingRule {
+            subjects: SubjectsConf::default(),
+            interfaces: vec![],
+            key_exprs: vec!["/sensor/**".to_string()],
+            max_rate,
+            strategy,
+        })
+        .unwrap();
+        Arc::new(FaceDownsampler {
+            rules: vec![Arc::new(rule)],
+            timer: Timer::new(false),
+            keys: Mutex::new(Keys::default()),
+        })
+    }
+
+    fn publish(
+        downsampler: &Arc<FaceDownsampler>,
+        primitives: &Arc<dyn Primitives + Send + Sync>,
+        key: &str,
+        value: &str,
+    ) {
+        downsampler.send_data(
+            primitives,
+            key.to_string(),
+            &key.into(),
+            ZBuf::from(value.as_bytes().to_vec()),
+            Channel::default(),
+            CongestionControl::default(),
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn downsampling_rate() {
+        for max_rate in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+            let rule = DownsamplingRule {
+                subjects: SubjectsConf::default(),
+                interfaces: vec![],
+                key_exprs: vec!["/sensor/**".to_string()],
+                max_rate,
+                strategy: DownsamplingStrategy::Drop,
+            };
+            assert!(Rule::new(&rule).is_err());
+        }
+    }
+
+    #[test]
+    fn downsampling_drop() {
+        let recorder = Arc::new(DataRecorder::default());
+        let primitives: Arc<dyn Primitives + Send + Sync> = recorder.clone();
+        let downsampler = downsampler(1.0, DownsamplingStrategy::Drop);
+        for i in 0..10 {
+            publish(&downsampler, &primitives, "/sensor/a", &i.to_string());
+            publish(&downsampler, &primitives, "/sensor/b", &i.to_string());
+            publish(&downsampler, &primitives, "/other", &i.to_string());
+        }
+        let sent = zlock!(recorder.0).clone();
+        assert_eq!(sent.iter().filter(|s| s.starts_with("/other")).count(), 10);
+        assert!(sent.contains(&"/sensor/a=0".to_string()));
+        assert!(sent.contains(&"/sensor/b=0".to_string()));
+        assert_eq!(sent.len(), 12);
+        assert_eq!(downsampler.rules[0].drops.load(Ordering::Relaxed), 18);
+        // Only the key expressions matching a rule are tracked
+        assert_eq!(zlock!(downsampler.keys).states.len(), 2);
+    }
+
+    #[test]
+    fn downsampling_keys() {
+        let recorder = Arc::new(DataRecorder::default());
+        let primitives: Arc<dyn Primitives + Send + Sync> = recorder.clone();
+        let downsampler = downsampler(1_000.0, DownsamplingStrategy::Drop);
+        for i in 0..10 * KEYS_SWEEP_THRESHOLD {
+            publish(&downsampler, &primitives, &format!("/other/{}", i), "");
+        }
+        assert!(zlock!(downsampler.keys).states.is_empty());
+
+        // The idle key expressions are evicted as new ones are published on
+        for i in 0..10 * KEYS_SWEEP_THRESHOLD {
+            publish(&downsampler, &primitives, &format!("/sensor/{}", i), "");
+            if i % KEYS_SWEEP_THRESHOLD == 0 {
+                std::thread::sleep(Duration::from_millis(2));
+            }
+        }
+        assert!(zlock!(downsampler.keys).states.len() <= 2 * KEYS_SWEEP_THRESHOLD);
+        assert_eq!(zlock!(recorder.0).len(), 20 * KEYS_SWEEP_THRESHOLD);
+    }
+
+    #[test]
+    fn downsampling_latest() {
+        let recorder = Arc::new(DataRecorder::default());
+        let primitives: Arc<dyn Primitives + Send + Sync> = recorder.clone();
+        let downsampler = downsampler(10.0, DownsamplingStrategy::Latest);
+        for i in 0..5 {
+            publish(&downsampler, &primitives, "/sensor/a", &i.to_string());
+        }
+        assert_eq!(*zlock!(recorder.0), vec!["/sensor/a=0".to_string()]);
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(
+            *zlock!(recorder.0),
+            vec!["/sensor/a=0".to_string(), "/sensor/a=4".to_string()]
+        );
+        assert_eq!(downsampler.rules[0].drops.load(Ordering::Relaxed), 3);
+    }
+}
@@ -11,33 +11,53 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+use super::downsampling::FaceDownsampler;
 use super::router::*;
 use async_std::sync::Arc;
+use flume::Sender;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::RwLock;
+use std::time::Duration;
 use zenoh_protocol::io::ZBuf;
-use zenoh_protocol::proto::{DataInfo, RoutingContext};
+use zenoh_protocol::proto::{Attachment, DataInfo, QueryBody, RoutingContext};
 use zenoh_protocol_core::{
     Channel, CongestionControl, ConsolidationStrategy, KeyExpr, PeerId, QueryTarget, QueryableInfo,
     SubInfo, WhatAmI, ZInt,
 };
+use zenoh_transport::unicast::establishment::authenticator::AuthId;
 use zenoh_transport::Primitives;
 
+/// How a face is connected to this router.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FaceKind {
+    /// Primitives of this process (e.g. sessions or the adminspace).
+    Local,
+    /// A unicast transport to a remote peer.
+    Unicast,
+    /// A multicast transport, shared by all the peers of its group.
+    Multicast,
+}
+
 pub struct FaceState {
     pub(super) id: usize,
     pub(super) pid: PeerId,
     pub(super) whatami: WhatAmI,
     pub(super) primitives: Arc<dyn Primitives + Send + Sync>,
     pub(super) link_id: usize,
+    pub(super) auth_ids: Vec<AuthId>,
+    pub(super) kind: FaceKind,
     pub(super) local_mappings: HashMap<ZInt, Arc<Resource>>,
     pub(super) remote_mappings: HashMap<ZInt, Arc<Resource>>,
     pub(super) local_subs: HashSet<Arc<Resource>>,
     pub(super) remote_subs: HashSet<Arc<Resource>>,
     pub(super) local_qabls: HashMap<(Arc<Resource>, ZInt), QueryableInfo>,
     pub(super) remote_qabls: HashSet<(Arc<Resource>, ZInt)>,
+    pub(super) local_tokens: HashSet<Arc<Resource>>,
+    pub(super) remote_tokens: HashSet<Arc<Resource>>,
     pub(super) next_qid: ZInt,
     pub(super) pending_queries: HashMap<ZInt, Arc<Query>>,
+    pub(super) downsampler: Option<Arc<FaceDownsampler>>,
 }
 
 impl FaceState {
@@ -47,6 +67,8 @@ impl FaceState {
         whatami: WhatAmI,
         primitives: Arc<dyn Primitives + Send + Sync>,
         link_id: usize,
+        auth_ids: Vec<AuthId>,
+        kind: FaceKind,
     ) -> Arc<FaceState> {
         Arc::new(FaceState {
             id,
@@ -54,17 +76,28 @@ impl FaceState {
             whatami,
             primitives,
             link_id,
+            auth_ids,
+            kind,
             local_mappings: HashMap::new(),
             remote_mappings: HashMap::new(),
             local_subs: HashSet::new(),
             remote_subs: HashSet::new(),
             local_qabls: HashMap::new(),
             remote_qabls: HashSet::new(),
+            local_tokens: HashSet::new(),
+            remote_tokens: HashSet::new(),
             next_qid: 0,
             pending_queries: HashMap::new(),
+            downsampler: None,
         })
     }
 
+    /// Whether the face was opened for primitives of this process rather than for a transport.
+    #[inline]
+    pub(super) fn is_local(&self) -> bool {
+        self.kind == FaceKind::Local
+    }
+
     #[inline]
     #[allow(clippy::trivially_copy_pass_by_ref)]
     pub(super) fn get_mapping(&self, prefixid: &ZInt) -> Option<&std::sync::Arc<Resource>> {
@@ -163,6 +196,69 @@ pub struct Face {
     pub(crate) state: Arc<FaceState>,
 }
 
+impl Face {
+    /// Route a query issued on this face, overriding the routers' default timeout
+    /// for the pending state it creates on the outgoing faces.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn send_query_with_timeout(
+        &self,
+        key_expr: &KeyExpr,
+        value_selector: &str,
+        qid: ZInt,
+        target: QueryTarget,
+        consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
+        routing_context: Option<RoutingContext>,
+        timeout: Duration,
+        attachment: Option<Attachment>,
+    ) {
+        route_query(
+            &self.tables,
+            &self.state,
+            key_expr,
+            value_selector,
+            qid,
+            target,
+            consolidation,
+            body,
+            routing_context,
+            Some(timeout),
+            attachment,
+        );
+    }
+
+    /// Whether the data published on this face for the given key expression reaches at least
+    /// one subscriber. The subscribers of this face only count when `local` is set.
+    pub(crate) fn matching_status(&self, key_expr: &KeyExpr, local: bool) -> Option<bool> {
+        let tables = zread!(self.tables);
+        matching_status(&tables, &self.state, key_expr, local)
+    }
+
+    /// Register a listener notified through `sender` of the changes of the matching status
+    /// of the given key expression. Returns the id of the listener and the current status.
+    pub(crate) fn declare_matching_listener(
+        &self,
+        key_expr: &KeyExpr,
+        local: bool,
+        sender: Sender<bool>,
+    ) -> Option<(usize, bool)> {
+        let mut tables = zwrite!(self.tables);
+        declare_matching_listener(&mut tables, &self.state, key_expr, local, sender)
+    }
+
+    pub(crate) fn undeclare_matching_listener(&self, id: usize) {
+        let mut tables = zwrite!(self.tables);
+        undeclare_matching_listener(&mut tables, id)
+    }
+
+    /// The key expressions of the liveliness tokens known to the routing tables
+    /// intersecting the given key expression.
+    pub(crate) fn alive_tokens(&self, key_expr: &KeyExpr) -> Option<Vec<String>> {
+        let tables = zread!(self.tables);
+        alive_tokens(&tables, &self.state, key_expr)
+    }
+}
+
 impl Primitives for Face {
     fn decl_resource(&self, expr_id: ZInt, key_expr: &KeyExpr) {
         let mut tables = zwrite!(self.tables);
@@ -324,6 +420,44 @@ impl Primitives for Face {
         }
     }
 
+    fn decl_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        let mut tables = zwrite!(self.tables);
+        match (tables.whatami, self.state.whatami) {
+            (WhatAmI::Router, WhatAmI::Router) => {
+                if let Some(router) = self.state.get_router(&tables, routing_context) {
+                    declare_router_token(&mut tables, &mut self.state.clone(), key_expr, router)
+                }
+            }
+            (WhatAmI::Router, WhatAmI::Peer)
+            | (WhatAmI::Peer, WhatAmI::Router)
+            | (WhatAmI::Peer, WhatAmI::Peer) => {
+                if let Some(peer) = self.state.get_peer(&tables, routing_context) {
+                    declare_peer_token(&mut tables, &mut self.state.clone(), key_expr, peer)
+                }
+            }
+            _ => declare_client_token(&mut tables, &mut self.state.clone(), key_expr),
+        }
+    }
+
+    fn forget_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        let mut tables = zwrite!(self.tables);
+        match (tables.whatami, self.state.whatami) {
+            (WhatAmI::Router, WhatAmI::Router) => {
+                if let Some(router) = self.state.get_router(&tables, routing_context) {
+                    forget_router_token(&mut tables, &mut self.state.clone(), key_expr, &router)
+                }
+            }
+            (WhatAmI::Router, WhatAmI::Peer)
+            | (WhatAmI::Peer, WhatAmI::Router)
+            | (WhatAmI::Peer, WhatAmI::Peer) => {
+                if let Some(peer) = self.state.get_peer(&tables, routing_context) {
+                    forget_peer_token(&mut tables, &mut self.state.clone(), key_expr, &peer)
+                }
+            }
+            _ => forget_client_token(&mut tables, &mut self.state.clone(), key_expr),
+        }
+    }
+
     fn send_data(
         &self,
         key_expr: &KeyExpr,
@@ -332,6 +466,7 @@ impl Primitives for Face {
         congestion_control: CongestionControl,
         data_info: Option<DataInfo>,
         routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
     ) {
         full_reentrant_route_data(
             &self.tables,
@@ -342,6 +477,7 @@ impl Primitives for Face {
             data_info,
             payload,
             routing_context,
+            attachment,
         );
     }
 
@@ -352,7 +488,9 @@ impl Primitives for Face {
         qid: ZInt,
         target: QueryTarget,
         consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
         routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
     ) {
         route_query(
             &self.tables,
@@ -362,7 +500,10 @@ impl Primitives for Face {
             qid,
             target,
             consolidation,
+            body,
             routing_context,
+            None,
+            attachment,
         );
     }
 
@@ -371,9 +512,11 @@ impl Primitives for Face {
         qid: ZInt,
         replier_kind: ZInt,
         replier_id: PeerId,
+        is_error: bool,
         key_expr: KeyExpr,
         info: Option<DataInfo>,
         payload: ZBuf,
+        attachment: Option<Attachment>,
     ) {
         let mut tables = zwrite!(self.tables);
         route_send_reply_data(
@@ -382,9 +525,11 @@ impl Primitives for Face {
             qid,
             replier_kind,
             replier_id,
+            is_error,
             key_expr,
             info,
             payload,
+            attachment,
         );
     }
 
@@ -11,7 +11,10 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
+pub mod acl;
+pub mod downsampling;
 pub mod face;
+pub mod multicast;
 pub mod network;
 pub mod pubsub;
 pub mod queries;
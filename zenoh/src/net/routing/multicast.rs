@@ -0,0 +1,724 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! Routing over multicast transports.
+//!
+//! All the peers of a multicast group share a single routing face: what the router sends
+//! to this face reaches every peer of the group at once, and what is received from any peer
+//! of the group enters the routing tables through it. Since each peer numbers its own
+//! resources, key expressions are always exchanged as full strings over the group and the
+//! resource declarations received from each peer are resolved per peer.
+//!
+//! Each peer of the group sends a final reply to every query received over the group,
+//! whether it routed it or not: the queries sent to the group are final once all the peers
+//! of the group sent their final reply or left the group. The replies of the peers are
+//! received by the whole group, and the queries of this router are told apart from the ones
+//! of the other peers by a random prefix in their id.
+use super::face::Face;
+use async_std::sync::Arc;
+use async_std::task;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zenoh_core::{zlock, zread, Result as ZResult};
+use zenoh_protocol::io::ZBuf;
+use zenoh_protocol::proto::{Attachment, DataInfo, QueryBody, RoutingContext};
+use zenoh_protocol_core::{
+    Channel, CongestionControl, ConsolidationStrategy, KeyExpr, PeerId, QueryTarget, QueryableInfo,
+    Reliability, SubInfo, SubMode, ZInt,
+};
+use zenoh_transport::{
+    DeMux, Mux, Primitives, TransportMulticast, TransportMulticastEventHandler, TransportPeer,
+    TransportPeerEventHandler,
+};
+
+// The bits of the id of a query sent to a group holding the id of the query on the face
+// of the group, the other ones holding the prefix of the router that sent it.
+const QID_MASK: ZInt = 0xFFFF_FFFF;
+
+/// A query sent to a multicast group, waiting for the final replies of the peers of the group.
+struct PendingQuery {
+    peers: HashSet<PeerId>,
+    // After this instant, the query is left to the timeout of the routing tables.
+    expiration: Instant,
+}
+
+/// The state shared by the face of a multicast group and the primitives of its peers.
+pub(crate) struct McastGroup {
+    peers: Mutex<HashSet<PeerId>>,
+    // The number of peers of the group declaring each subscription, queryable and token.
+    subs: Mutex<HashMap<String, usize>>,
+    qabls: Mutex<HashMap<(String, ZInt), usize>>,
+    tokens: Mutex<HashMap<String, usize>>,
+    qid_prefix: ZInt,
+    queries: Mutex<HashMap<ZInt, PendingQuery>>,
+    queries_timeout: Duration,
+}
+
+impl McastGroup {
+    pub(crate) fn new(queries_timeout: Duration) -> Self {
+        McastGroup {
+            peers: Mutex::new(HashSet::new()),
+            subs: Mutex::new(HashMap::new()),
+            qabls: Mutex::new(HashMap::new()),
+            tokens: Mutex::new(HashMap::new()),
+            qid_prefix: rand::random::<ZInt>() & !QID_MASK,
+            queries: Mutex::new(HashMap::new()),
+            queries_timeout,
+        }
+    }
+
+    // The id of the query of this router a reply received from the group is for, if any.
+    fn local_qid(&self, qid: ZInt) -> Option<ZInt> {
+        if qid & !QID_MASK == self.qid_prefix {
+            Some(qid & QID_MASK)
+        } else {
+            None
+        }
+    }
+
+    // Remove `pid` from the peers expected to send a final reply to the query `qid`,
+    // returning whether it was the last one.
+    fn final_reply(&self, qid: ZInt, pid: &PeerId) -> bool {
+        let mut queries = zlock!(self.queries);
+        let last = match queries.get_mut(&qid) {
+            Some(query) => query.peers.remove(pid) && query.peers.is_empty(),
+            None => false,
+        };
+        if last {
+            queries.remove(&qid);
+        }
+        last
+    }
+
+    // Remove a peer leaving the group, returning the queries it was the last one
+    // expected to send a final reply to.
+    fn leave(&self, pid: &PeerId) -> Vec<ZInt> {
+        zlock!(self.peers).remove(pid);
+        let mut queries = zlock!(self.queries);
+        let mut finals = vec![];
+        queries.retain(|qid, query| {
+            if query.peers.remove(pid) && query.peers.is_empty() {
+                finals.push(*qid);
+                false
+            } else {
+                true
+            }
+        });
+        finals
+    }
+}
+
+fn resolve(mappings: &HashMap<ZInt, String>, key_expr: &KeyExpr) -> Option<KeyExpr<'static>> {
+    if key_expr.scope == 0 {
+        Some(key_expr.to_owned())
+    } else {
+        match mappings.get(&key_expr.scope) {
+            Some(prefix) => Some(KeyExpr::from(format!("{}{}", prefix, key_expr.suffix))),
+            None => {
+                log::error!("Unknown expr_id {} in {}", key_expr.scope, key_expr);
+                None
+            }
+        }
+    }
+}
+
+/// The primitives of the face shared by all the peers of a multicast transport.
+pub(crate) struct McastGroupPrimitives {
+    mux: Mux<TransportMulticast>,
+    group: Arc<McastGroup>,
+    mappings: Mutex<HashMap<ZInt, String>>,
+}
+
+impl McastGroupPrimitives {
+    pub(crate) fn new(transport: TransportMulticast, group: Arc<McastGroup>) -> Self {
+        McastGroupPrimitives {
+            mux: Mux::new(transport),
+            group,
+            mappings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn resolve(&self, key_expr: &KeyExpr) -> Option<KeyExpr<'static>> {
+        resolve(&*zlock!(self.mappings), key_expr)
+    }
+}
+
+impl Primitives for McastGroupPrimitives {
+    // Resources are resolved locally and never declared over the group:
+    // peers joining the group later on would not know them.
+    fn decl_resource(&self, expr_id: ZInt, key_expr: &KeyExpr) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            zlock!(self.mappings).insert(expr_id, key_expr.suffix.to_string());
+        }
+    }
+
+    fn forget_resource(&self, expr_id: ZInt) {
+        zlock!(self.mappings).remove(&expr_id);
+    }
+
+    fn decl_publisher(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux.decl_publisher(&key_expr, routing_context)
+        }
+    }
+
+    fn forget_publisher(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux.forget_publisher(&key_expr, routing_context)
+        }
+    }
+
+    fn decl_subscriber(
+        &self,
+        key_expr: &KeyExpr,
+        sub_info: &SubInfo,
+        routing_context: Option<RoutingContext>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux
+                .decl_subscriber(&key_expr, sub_info, routing_context)
+        }
+    }
+
+    fn forget_subscriber(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux.forget_subscriber(&key_expr, routing_context)
+        }
+    }
+
+    fn decl_queryable(
+        &self,
+        key_expr: &KeyExpr,
+        kind: ZInt,
+        qabl_info: &QueryableInfo,
+        routing_context: Option<RoutingContext>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux
+                .decl_queryable(&key_expr, kind, qabl_info, routing_context)
+        }
+    }
+
+    fn forget_queryable(
+        &self,
+        key_expr: &KeyExpr,
+        kind: ZInt,
+        routing_context: Option<RoutingContext>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux.forget_queryable(&key_expr, kind, routing_context)
+        }
+    }
+
+    fn decl_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux.decl_token(&key_expr, routing_context)
+        }
+    }
+
+    fn forget_token(&self, key_expr: &KeyExpr, routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux.forget_token(&key_expr, routing_context)
+        }
+    }
+
+    fn send_data(
+        &self,
+        key_expr: &KeyExpr,
+        payload: ZBuf,
+        channel: Channel,
+        congestion_control: CongestionControl,
+        data_info: Option<DataInfo>,
+        routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux.send_data(
+                &key_expr,
+                payload,
+                channel,
+                congestion_control,
+                data_info,
+                routing_context,
+                attachment,
+            )
+        }
+    }
+
+    fn send_query(
+        &self,
+        key_expr: &KeyExpr,
+        value_selector: &str,
+        qid: ZInt,
+        target: QueryTarget,
+        consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
+        routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            let now = Instant::now();
+            let peers = zlock!(self.group.peers).clone();
+            let mut queries = zlock!(self.group.queries);
+            queries.retain(|_, query| query.expiration > now);
+            queries.insert(
+                qid,
+                PendingQuery {
+                    peers,
+                    expiration: now + self.group.queries_timeout,
+                },
+            );
+            drop(queries);
+            self.mux.send_query(
+                &key_expr,
+                value_selector,
+                self.group.qid_prefix | (qid & QID_MASK),
+                target,
+                consolidation,
+                body,
+                routing_context,
+                attachment,
+            )
+        }
+    }
+
+    // Replies are sent with the id of the query given by the peer that sent it
+    fn send_reply_data(
+        &self,
+        qid: ZInt,
+        replier_kind: ZInt,
+        replier_id: PeerId,
+        is_error: bool,
+        key_expr: KeyExpr,
+        info: Option<DataInfo>,
+        payload: ZBuf,
+        attachment: Option<Attachment>,
+    ) {
+        self.mux.send_reply_data(
+            qid,
+            replier_kind,
+            replier_id,
+            is_error,
+            key_expr,
+            info,
+            payload,
+            attachment,
+        )
+    }
+
+    fn send_reply_final(&self, qid: ZInt) {
+        self.mux.send_reply_final(qid)
+    }
+
+    fn send_pull(
+        &self,
+        is_final: bool,
+        key_expr: &KeyExpr,
+        pull_id: ZInt,
+        max_samples: &Option<ZInt>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.mux
+                .send_pull(is_final, &key_expr, pull_id, max_samples)
+        }
+    }
+
+    fn send_close(&self) {}
+}
+
+/// The primitives receiving the messages of one peer of a multicast transport
+/// and forwarding them to the face of the group.
+struct McastPeerPrimitives {
+    face: Face,
+    group: Arc<McastGroup>,
+    pid: PeerId,
+    mappings: Mutex<HashMap<ZInt, String>>,
+    subs: Mutex<HashSet<String>>,
+    qabls: Mutex<HashSet<(String, ZInt)>>,
+    tokens: Mutex<HashSet<String>>,
+}
+
+impl McastPeerPrimitives {
+    fn resolve(&self, key_expr: &KeyExpr) -> Option<KeyExpr<'static>> {
+        resolve(&*zlock!(self.mappings), key_expr)
+    }
+
+    // The face of the group holds a single subscription per key expression,
+    // it is forgotten when no peer of the group is subscribed anymore.
+    fn undeclare_subscription(&self, key: &str) {
+        let mut group_subs = zlock!(self.group.subs);
+        if let Some(count) = group_subs.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                group_subs.remove(key);
+                drop(group_subs);
+                self.face.forget_subscriber(&key.into(), None);
+            }
+        }
+    }
+
+    // Same as subscriptions for queryables, per key expression and kind.
+    fn undeclare_queryable(&self, key: &str, kind: ZInt) {
+        let mut group_qabls = zlock!(self.group.qabls);
+        let qabl = (key.to_string(), kind);
+        if let Some(count) = group_qabls.get_mut(&qabl) {
+            *count -= 1;
+            if *count == 0 {
+                group_qabls.remove(&qabl);
+                drop(group_qabls);
+                self.face.forget_queryable(&key.into(), kind, None);
+            }
+        }
+    }
+
+    // Same as subscriptions for tokens.
+    fn undeclare_token(&self, key: &str) {
+        let mut group_tokens = zlock!(self.group.tokens);
+        if let Some(count) = group_tokens.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                group_tokens.remove(key);
+                drop(group_tokens);
+                self.face.forget_token(&key.into(), None);
+            }
+        }
+    }
+}
+
+impl Primitives for McastPeerPrimitives {
+    fn decl_resource(&self, expr_id: ZInt, key_expr: &KeyExpr) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            zlock!(self.mappings).insert(expr_id, key_expr.suffix.to_string());
+        }
+    }
+
+    fn forget_resource(&self, expr_id: ZInt) {
+        zlock!(self.mappings).remove(&expr_id);
+    }
+
+    fn decl_publisher(&self, key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.face.decl_publisher(&key_expr, None)
+        }
+    }
+
+    fn forget_publisher(&self, key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.face.forget_publisher(&key_expr, None)
+        }
+    }
+
+    fn decl_subscriber(
+        &self,
+        key_expr: &KeyExpr,
+        sub_info: &SubInfo,
+        _routing_context: Option<RoutingContext>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            let key = key_expr.suffix.to_string();
+            if zlock!(self.subs).insert(key.clone()) {
+                let mut group_subs = zlock!(self.group.subs);
+                let count = group_subs.entry(key).or_insert(0);
+                *count += 1;
+                if *count == 1 {
+                    drop(group_subs);
+                    self.face.decl_subscriber(&key_expr, sub_info, None);
+                }
+            }
+        }
+    }
+
+    fn forget_subscriber(&self, key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            if zlock!(self.subs).remove(key_expr.suffix.as_ref()) {
+                self.undeclare_subscription(&key_expr.suffix);
+            }
+        }
+    }
+
+    fn decl_queryable(
+        &self,
+        key_expr: &KeyExpr,
+        kind: ZInt,
+        qabl_info: &QueryableInfo,
+        _routing_context: Option<RoutingContext>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            let qabl = (key_expr.suffix.to_string(), kind);
+            if zlock!(self.qabls).insert(qabl.clone()) {
+                let mut group_qabls = zlock!(self.group.qabls);
+                let count = group_qabls.entry(qabl).or_insert(0);
+                *count += 1;
+                if *count == 1 {
+                    drop(group_qabls);
+                    self.face.decl_queryable(&key_expr, kind, qabl_info, None);
+                }
+            }
+        }
+    }
+
+    fn forget_queryable(
+        &self,
+        key_expr: &KeyExpr,
+        kind: ZInt,
+        _routing_context: Option<RoutingContext>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            let qabl = (key_expr.suffix.to_string(), kind);
+            if zlock!(self.qabls).remove(&qabl) {
+                self.undeclare_queryable(&qabl.0, kind);
+            }
+        }
+    }
+
+    fn decl_token(&self, key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            let key = key_expr.suffix.to_string();
+            if zlock!(self.tokens).insert(key.clone()) {
+                let mut group_tokens = zlock!(self.group.tokens);
+                let count = group_tokens.entry(key).or_insert(0);
+                *count += 1;
+                if *count == 1 {
+                    drop(group_tokens);
+                    self.face.decl_token(&key_expr, None);
+                }
+            }
+        }
+    }
+
+    fn forget_token(&self, key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            if zlock!(self.tokens).remove(key_expr.suffix.as_ref()) {
+                self.undeclare_token(&key_expr.suffix);
+            }
+        }
+    }
+
+    fn send_data(
+        &self,
+        key_expr: &KeyExpr,
+        payload: ZBuf,
+        channel: Channel,
+        congestion_control: CongestionControl,
+        data_info: Option<DataInfo>,
+        _routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.face.send_data(
+                &key_expr,
+                payload,
+                channel,
+                congestion_control,
+                data_info,
+                None,
+                attachment,
+            )
+        }
+    }
+
+    // The query is routed with the id given by the peer: the final reply of this router
+    // is sent to the whole group with this id.
+    fn send_query(
+        &self,
+        key_expr: &KeyExpr,
+        value_selector: &str,
+        qid: ZInt,
+        target: QueryTarget,
+        consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
+        _routing_context: Option<RoutingContext>,
+        attachment: Option<Attachment>,
+    ) {
+        match self.resolve(key_expr) {
+            Some(key_expr) => self.face.send_query(
+                &key_expr,
+                value_selector,
+                qid,
+                target,
+                consolidation,
+                body,
+                None,
+                attachment,
+            ),
+            None => self.face.state.primitives.send_reply_final(qid),
+        }
+    }
+
+    // The replies received for the queries of the other peers of the group are dropped.
+    fn send_reply_data(
+        &self,
+        qid: ZInt,
+        replier_kind: ZInt,
+        replier_id: PeerId,
+        is_error: bool,
+        key_expr: KeyExpr,
+        info: Option<DataInfo>,
+        payload: ZBuf,
+        attachment: Option<Attachment>,
+    ) {
+        if let Some(qid) = self.group.local_qid(qid) {
+            if let Some(key_expr) = self.resolve(&key_expr) {
+                self.face.send_reply_data(
+                    qid,
+                    replier_kind,
+                    replier_id,
+                    is_error,
+                    key_expr,
+                    info,
+                    payload,
+                    attachment,
+                )
+            }
+        }
+    }
+
+    fn send_reply_final(&self, qid: ZInt) {
+        if let Some(qid) = self.group.local_qid(qid) {
+            if self.group.final_reply(qid, &self.pid) {
+                self.face.send_reply_final(qid)
+            }
+        }
+    }
+
+    fn send_pull(
+        &self,
+        is_final: bool,
+        key_expr: &KeyExpr,
+        pull_id: ZInt,
+        max_samples: &Option<ZInt>,
+    ) {
+        if let Some(key_expr) = self.resolve(key_expr) {
+            self.face
+                .send_pull(is_final, &key_expr, pull_id, max_samples)
+        }
+    }
+
+    // The peer left the group: forget its subscriptions, queryables and tokens,
+    // and stop waiting for its final replies.
+    fn send_close(&self) {
+        let subs = std::mem::take(&mut *zlock!(self.subs));
+        for key in subs {
+            self.undeclare_subscription(&key);
+        }
+        let qabls = std::mem::take(&mut *zlock!(self.qabls));
+        for (key, kind) in qabls {
+            self.undeclare_queryable(&key, kind);
+        }
+        let tokens = std::mem::take(&mut *zlock!(self.tokens));
+        for key in tokens {
+            self.undeclare_token(&key);
+        }
+        for qid in self.group.leave(&self.pid) {
+            self.face.send_reply_final(qid);
+        }
+    }
+}
+
+pub struct McastTransportHandler {
+    pub(crate) face: Face,
+    group: Arc<McastGroup>,
+    redeclare_delay: Duration,
+}
+
+impl McastTransportHandler {
+    pub(crate) fn new(face: Face, group: Arc<McastGroup>, redeclare_delay: Duration) -> Self {
+        McastTransportHandler {
+            face,
+            group,
+            redeclare_delay,
+        }
+    }
+}
+
+impl TransportMulticastEventHandler for McastTransportHandler {
+    fn new_peer(&self, peer: TransportPeer) -> ZResult<Arc<dyn TransportPeerEventHandler>> {
+        log::debug!(
+            "New peer {} ({}) on multicast transport {}",
+            peer.pid,
+            peer.whatami,
+            self.face
+        );
+        zlock!(self.group.peers).insert(peer.pid);
+
+        // A new peer did not receive the subscriptions, queryables and tokens already declared
+        // to the group and only accepts our messages once it received our Join: declare them
+        // again after a join interval.
+        let face = self.face.clone();
+        let delay = self.redeclare_delay;
+        task::spawn(async move {
+            task::sleep(delay).await;
+            let (keys, qabls, tokens) = {
+                let _tables = zread!(face.tables);
+                (
+                    face.state
+                        .local_subs
+                        .iter()
+                        .map(|res| res.expr())
+                        .collect::<Vec<String>>(),
+                    face.state
+                        .local_qabls
+                        .iter()
+                        .map(|((res, kind), info)| (res.expr(), *kind, info.clone()))
+                        .collect::<Vec<(String, ZInt, QueryableInfo)>>(),
+                    face.state
+                        .local_tokens
+                        .iter()
+                        .map(|res| res.expr())
+                        .collect::<Vec<String>>(),
+                )
+            };
+            let sub_info = SubInfo {
+                reliability: Reliability::Reliable,
+                mode: SubMode::Push,
+                period: None,
+            };
+            for key in keys {
+                face.state
+                    .primitives
+                    .decl_subscriber(&key.into(), &sub_info, None);
+            }
+            for (key, kind, info) in qabls {
+                face.state
+                    .primitives
+                    .decl_queryable(&key.into(), kind, &info, None);
+            }
+            for key in tokens {
+                face.state.primitives.decl_token(&key.into(), None);
+            }
+        });
+
+        Ok(Arc::new(DeMux::new(McastPeerPrimitives {
+            face: self.face.clone(),
+            group: self.group.clone(),
+            pid: peer.pid,
+            mappings: Mutex::new(HashMap::new()),
+            subs: Mutex::new(HashSet::new()),
+            qabls: Mutex::new(HashSet::new()),
+            tokens: Mutex::new(HashSet::new()),
+        })))
+    }
+
+    fn closing(&self) {
+        self.face.send_close();
+    }
+
+    fn closed(&self) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -12,21 +12,25 @@
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
 use async_std::sync::Arc;
+use flume::Sender;
 use petgraph::graph::NodeIndex;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::sync::RwLock;
+use zenoh_config::AclAction;
 use zenoh_core::zread;
 use zenoh_sync::get_mut_unchecked;
 
 use zenoh_protocol::io::ZBuf;
-use zenoh_protocol::proto::{DataInfo, RoutingContext};
+use zenoh_protocol::proto::{Attachment, DataInfo, RoutingContext};
+use zenoh_protocol_core::key_expr;
 use zenoh_protocol_core::{
     Channel, CongestionControl, KeyExpr, PeerId, Priority, Reliability, SubInfo, SubMode, WhatAmI,
     ZInt,
 };
 
-use super::face::FaceState;
+use super::acl::{self, AdminConfigWrite};
+use super::face::{FaceKind, FaceState};
 use super::network::Network;
 use super::resource::{elect_router, PullCaches, Resource, Route, SessionContext};
 use super::router::Tables;
@@ -85,6 +89,30 @@ fn propagate_simple_subscription(
     }
 }
 
+// In peer mode, the subscriptions of the local sessions and of the other peers are registered
+// as peer subscriptions, which are only propagated over the peers network. The peers of
+// a multicast group only send to the group the publications matching the subscriptions
+// declared to it: the face of the group is declared all the peer subscriptions instead.
+fn propagate_mcast_subscription(
+    tables: &mut Tables,
+    res: &Arc<Resource>,
+    sub_info: &SubInfo,
+    src_face: &mut Arc<FaceState>,
+) {
+    for dst_face in &mut tables.faces.values_mut() {
+        if src_face.id != dst_face.id
+            && dst_face.kind == FaceKind::Multicast
+            && !dst_face.local_subs.contains(res)
+        {
+            get_mut_unchecked(dst_face).local_subs.insert(res.clone());
+            let key_expr = Resource::decl_key(res, dst_face);
+            dst_face
+                .primitives
+                .decl_subscriber(&key_expr, sub_info, None);
+        }
+    }
+}
+
 fn propagate_sourced_subscription(
     tables: &Tables,
     res: &Arc<Resource>,
@@ -166,6 +194,15 @@ pub fn declare_router_subscription(
 ) {
     match tables.get_mapping(face, &expr.scope).cloned() {
         Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Subscribe,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
             let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
             Resource::match_resource(tables, &mut res);
             register_router_subscription(tables, face, &mut res, sub_info, router);
@@ -197,6 +234,11 @@ fn register_peer_subscription(
         // Propagate subscription to peers
         propagate_sourced_subscription(tables, res, sub_info, Some(face), &peer, WhatAmI::Peer);
     }
+
+    if tables.whatami == WhatAmI::Peer {
+        // Propagate subscription to multicast groups
+        propagate_mcast_subscription(tables, res, sub_info, face);
+    }
 }
 
 pub fn declare_peer_subscription(
@@ -208,6 +250,15 @@ pub fn declare_peer_subscription(
 ) {
     match tables.get_mapping(face, &expr.scope).cloned() {
         Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Subscribe,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
             let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
             Resource::match_resource(tables, &mut res);
             register_peer_subscription(tables, face, &mut res, sub_info, peer);
@@ -257,6 +308,7 @@ fn register_client_subscription(
                         remote_expr_id: None,
                         subs: Some(sub_info.clone()),
                         qabl: HashMap::new(),
+                        token: false,
                         last_values: HashMap::new(),
                     }),
                 );
@@ -275,6 +327,15 @@ pub fn declare_client_subscription(
     log::debug!("Register client subscription");
     match tables.get_mapping(face, &expr.scope).cloned() {
         Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Subscribe,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
             let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
             log::debug!("Register client subscription {}", res.expr());
             Resource::match_resource(tables, &mut res);
@@ -302,48 +363,709 @@ pub fn declare_client_subscription(
                 }
             }
 
-            compute_matches_data_routes(tables, &mut res);
+            compute_matches_data_routes(tables, &mut res);
+        }
+        None => log::error!("Declare subscription for unknown scope {}!", expr.scope),
+    }
+}
+
+#[inline]
+fn remote_router_subs(tables: &Tables, res: &Arc<Resource>) -> bool {
+    res.context.is_some()
+        && res
+            .context()
+            .router_subs
+            .iter()
+            .any(|peer| peer != &tables.pid)
+}
+
+#[inline]
+fn remote_peer_subs(tables: &Tables, res: &Arc<Resource>) -> bool {
+    res.context.is_some()
+        && res
+            .context()
+            .peer_subs
+            .iter()
+            .any(|peer| peer != &tables.pid)
+}
+
+#[inline]
+fn client_subs(res: &Arc<Resource>) -> Vec<Arc<FaceState>> {
+    res.session_ctxs
+        .values()
+        .filter_map(|ctx| {
+            if ctx.subs.is_some() {
+                Some(ctx.face.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[inline]
+fn send_forget_sourced_subscription_to_net_childs(
+    tables: &Tables,
+    net: &Network,
+    childs: &[NodeIndex],
+    res: &Arc<Resource>,
+    src_face: Option<&Arc<FaceState>>,
+    routing_context: Option<RoutingContext>,
+) {
+    for child in childs {
+        if net.graph.contains_node(*child) {
+            match tables.get_face(&net.graph[*child].pid).cloned() {
+                Some(mut someface) => {
+                    if src_face.is_none() || someface.id != src_face.unwrap().id {
+                        let key_expr = Resource::decl_key(res, &mut someface);
+
+                        log::debug!("Send forget subscription {} on {}", res.expr(), someface);
+
+                        someface
+                            .primitives
+                            .forget_subscriber(&key_expr, routing_context);
+                    }
+                }
+                None => log::trace!("Unable to find face for pid {}", net.graph[*child].pid),
+            }
+        }
+    }
+}
+
+fn propagate_forget_simple_subscription(tables: &mut Tables, res: &Arc<Resource>) {
+    for face in tables.faces.values_mut() {
+        if face.local_subs.contains(res) {
+            let key_expr = Resource::get_best_key(res, "", face.id);
+            face.primitives.forget_subscriber(&key_expr, None);
+
+            get_mut_unchecked(face).local_subs.remove(res);
+        }
+    }
+}
+
+fn propagate_forget_sourced_subscription(
+    tables: &Tables,
+    res: &Arc<Resource>,
+    src_face: Option<&Arc<FaceState>>,
+    source: &PeerId,
+    net_type: WhatAmI,
+) {
+    let net = tables.get_net(net_type).unwrap();
+    match net.get_idx(source) {
+        Some(tree_sid) => {
+            if net.trees.len() > tree_sid.index() {
+                send_forget_sourced_subscription_to_net_childs(
+                    tables,
+                    net,
+                    &net.trees[tree_sid.index()].childs,
+                    res,
+                    src_face,
+                    Some(RoutingContext::new(tree_sid.index() as ZInt)),
+                );
+            } else {
+                log::trace!(
+                    "Propagating forget sub {}: tree for node {} sid:{} not yet ready",
+                    res.expr(),
+                    tree_sid.index(),
+                    source
+                );
+            }
+        }
+        None => log::error!(
+            "Error propagating forget sub {}: cannot get index of {}!",
+            res.expr(),
+            source
+        ),
+    }
+}
+
+fn unregister_router_subscription(tables: &mut Tables, res: &mut Arc<Resource>, router: &PeerId) {
+    log::debug!(
+        "Unregister router subscription {} (router: {})",
+        res.expr(),
+        router
+    );
+    get_mut_unchecked(res)
+        .context_mut()
+        .router_subs
+        .retain(|sub| sub != router);
+
+    if res.context().router_subs.is_empty() {
+        tables.router_subs.retain(|sub| !Arc::ptr_eq(sub, res));
+
+        undeclare_peer_subscription(tables, None, res, &tables.pid.clone());
+        propagate_forget_simple_subscription(tables, res);
+    }
+}
+
+fn undeclare_router_subscription(
+    tables: &mut Tables,
+    face: Option<&Arc<FaceState>>,
+    res: &mut Arc<Resource>,
+    router: &PeerId,
+) {
+    if res.context().router_subs.contains(router) {
+        unregister_router_subscription(tables, res, router);
+        propagate_forget_sourced_subscription(tables, res, face, router, WhatAmI::Router);
+    }
+}
+
+pub fn forget_router_subscription(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    expr: &KeyExpr,
+    router: &PeerId,
+) {
+    match tables.get_mapping(face, &expr.scope) {
+        Some(prefix) => match Resource::get_resource(prefix, expr.suffix.as_ref()) {
+            Some(mut res) => {
+                undeclare_router_subscription(tables, Some(face), &mut res, router);
+
+                compute_matches_data_routes(tables, &mut res);
+                Resource::clean(&mut res)
+            }
+            None => log::error!("Undeclare unknown router subscription!"),
+        },
+        None => log::error!("Undeclare router subscription with unknown scope!"),
+    }
+}
+
+fn unregister_peer_subscription(tables: &mut Tables, res: &mut Arc<Resource>, peer: &PeerId) {
+    log::debug!(
+        "Unregister peer subscription {} (peer: {})",
+        res.expr(),
+        peer
+    );
+    get_mut_unchecked(res)
+        .context_mut()
+        .peer_subs
+        .retain(|sub| sub != peer);
+
+    if res.context().peer_subs.is_empty() {
+        tables.peer_subs.retain(|sub| !Arc::ptr_eq(sub, res));
+
+        if tables.whatami == WhatAmI::Peer {
+            // Only multicast groups were declared the subscription on top of the peers
+            propagate_forget_simple_subscription(tables, res);
+        }
+    }
+}
+
+fn undeclare_peer_subscription(
+    tables: &mut Tables,
+    face: Option<&Arc<FaceState>>,
+    res: &mut Arc<Resource>,
+    peer: &PeerId,
+) {
+    if res.context().peer_subs.contains(peer) {
+        unregister_peer_subscription(tables, res, peer);
+        propagate_forget_sourced_subscription(tables, res, face, peer, WhatAmI::Peer);
+    }
+}
+
+pub fn forget_peer_subscription(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    expr: &KeyExpr,
+    peer: &PeerId,
+) {
+    match tables.get_mapping(face, &expr.scope) {
+        Some(prefix) => match Resource::get_resource(prefix, expr.suffix.as_ref()) {
+            Some(mut res) => {
+                undeclare_peer_subscription(tables, Some(face), &mut res, peer);
+
+                if tables.whatami == WhatAmI::Router {
+                    let client_subs = res.session_ctxs.values().any(|ctx| ctx.subs.is_some());
+                    let peer_subs = remote_peer_subs(tables, &res);
+                    if !client_subs && !peer_subs {
+                        undeclare_router_subscription(tables, None, &mut res, &tables.pid.clone());
+                    }
+                }
+
+                compute_matches_data_routes(tables, &mut res);
+                Resource::clean(&mut res)
+            }
+            None => log::error!("Undeclare unknown peer subscription!"),
+        },
+        None => log::error!("Undeclare peer subscription with unknown scope!"),
+    }
+}
+
+pub(crate) fn undeclare_client_subscription(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    res: &mut Arc<Resource>,
+) {
+    log::debug!("Unregister client subscription {} for {}", res.expr(), face);
+    if let Some(ctx) = get_mut_unchecked(res).session_ctxs.get_mut(&face.id) {
+        get_mut_unchecked(ctx).subs = None;
+    }
+    get_mut_unchecked(face).remote_subs.remove(res);
+
+    let mut client_subs = client_subs(res);
+    let router_subs = remote_router_subs(tables, res);
+    let peer_subs = remote_peer_subs(tables, res);
+    match tables.whatami {
+        WhatAmI::Router => {
+            if client_subs.is_empty() && !peer_subs {
+                undeclare_router_subscription(tables, None, res, &tables.pid.clone());
+            }
+        }
+        WhatAmI::Peer => {
+            if client_subs.is_empty() {
+                undeclare_peer_subscription(tables, None, res, &tables.pid.clone());
+            }
+        }
+        _ => {
+            if client_subs.is_empty() {
+                propagate_forget_simple_subscription(tables, res);
+            }
+        }
+    }
+    if client_subs.len() == 1 && !router_subs && !peer_subs {
+        let face = &mut client_subs[0];
+        if face.local_subs.contains(res) {
+            let key_expr = Resource::get_best_key(res, "", face.id);
+            face.primitives.forget_subscriber(&key_expr, None);
+
+            get_mut_unchecked(face).local_subs.remove(res);
+        }
+    }
+
+    compute_matches_data_routes(tables, res);
+    Resource::clean(res)
+}
+
+pub fn forget_client_subscription(tables: &mut Tables, face: &mut Arc<FaceState>, expr: &KeyExpr) {
+    match tables.get_mapping(face, &expr.scope) {
+        Some(prefix) => match Resource::get_resource(prefix, expr.suffix.as_ref()) {
+            Some(mut res) => {
+                undeclare_client_subscription(tables, face, &mut res);
+            }
+            None => log::error!("Undeclare unknown subscription!"),
+        },
+        None => log::error!("Undeclare subscription with unknown scope!"),
+    }
+}
+
+pub(crate) fn pubsub_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable, // @TODO
+        mode: SubMode::Push,
+        period: None,
+    };
+    if face.whatami == WhatAmI::Client && tables.whatami != WhatAmI::Client {
+        for sub in &tables.router_subs {
+            get_mut_unchecked(face).local_subs.insert(sub.clone());
+            let key_expr = Resource::decl_key(sub, face);
+            face.primitives.decl_subscriber(&key_expr, &sub_info, None);
+        }
+    }
+    if face.kind == FaceKind::Multicast && tables.whatami == WhatAmI::Peer {
+        for sub in &tables.peer_subs {
+            get_mut_unchecked(face).local_subs.insert(sub.clone());
+            let key_expr = Resource::decl_key(sub, face);
+            face.primitives.decl_subscriber(&key_expr, &sub_info, None);
+        }
+    }
+    if tables.whatami == WhatAmI::Client {
+        for face in tables
+            .faces
+            .values()
+            .cloned()
+            .collect::<Vec<Arc<FaceState>>>()
+        {
+            for sub in &face.remote_subs {
+                propagate_simple_subscription(tables, sub, &sub_info, &mut face.clone());
+            }
+        }
+    }
+}
+
+pub(crate) fn pubsub_remove_node(tables: &mut Tables, node: &PeerId, net_type: WhatAmI) {
+    match net_type {
+        WhatAmI::Router => {
+            for mut res in tables
+                .router_subs
+                .iter()
+                .filter(|res| res.context().router_subs.contains(node))
+                .cloned()
+                .collect::<Vec<Arc<Resource>>>()
+            {
+                unregister_router_subscription(tables, &mut res, node);
+
+                compute_matches_data_routes(tables, &mut res);
+                Resource::clean(&mut res)
+            }
+        }
+        WhatAmI::Peer => {
+            for mut res in tables
+                .peer_subs
+                .iter()
+                .filter(|res| res.context().peer_subs.contains(node))
+                .cloned()
+                .collect::<Vec<Arc<Resource>>>()
+            {
+                unregister_peer_subscription(tables, &mut res, node);
+
+                if tables.whatami == WhatAmI::Router {
+                    let client_subs = res.session_ctxs.values().any(|ctx| ctx.subs.is_some());
+                    let peer_subs = remote_peer_subs(tables, &res);
+                    if !client_subs && !peer_subs {
+                        undeclare_router_subscription(tables, None, &mut res, &tables.pid.clone());
+                    }
+                }
+
+                compute_matches_data_routes(tables, &mut res);
+                Resource::clean(&mut res)
+            }
+        }
+        _ => (),
+    }
+}
+
+pub(crate) fn pubsub_tree_change(
+    tables: &mut Tables,
+    new_childs: &[Vec<NodeIndex>],
+    net_type: WhatAmI,
+) {
+    // propagate subs to new childs
+    for (tree_sid, tree_childs) in new_childs.iter().enumerate() {
+        if !tree_childs.is_empty() {
+            let net = tables.get_net(net_type).unwrap();
+            let tree_idx = NodeIndex::new(tree_sid);
+            if net.graph.contains_node(tree_idx) {
+                let tree_id = net.graph[tree_idx].pid;
+
+                let subs_res = match net_type {
+                    WhatAmI::Router => &tables.router_subs,
+                    _ => &tables.peer_subs,
+                };
+
+                for res in subs_res {
+                    let subs = match net_type {
+                        WhatAmI::Router => &res.context().router_subs,
+                        _ => &res.context().peer_subs,
+                    };
+                    for sub in subs {
+                        if *sub == tree_id {
+                            let sub_info = SubInfo {
+                                reliability: Reliability::Reliable, // @TODO
+                                mode: SubMode::Push,
+                                period: None,
+                            };
+                            send_sourced_subscription_to_net_childs(
+                                tables,
+                                net,
+                                tree_childs,
+                                res,
+                                None,
+                                &sub_info,
+                                Some(RoutingContext::new(tree_sid as ZInt)),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // recompute routes
+    compute_data_routes_from(tables, &mut tables.root_res.clone());
+    update_matching_listeners(tables);
+}
+
+// Liveliness tokens are routed as subscriptions are, in their own tables since they neither
+// receive data nor count as matching subscribers. In peer mode, the tokens of the peers
+// network are also declared to the clients and local sessions, which track them.
+
+#[inline]
+fn send_sourced_token_to_net_childs(
+    tables: &Tables,
+    net: &Network,
+    childs: &[NodeIndex],
+    res: &Arc<Resource>,
+    src_face: Option<&Arc<FaceState>>,
+    routing_context: Option<RoutingContext>,
+) {
+    for child in childs {
+        if net.graph.contains_node(*child) {
+            match tables.get_face(&net.graph[*child].pid).cloned() {
+                Some(mut someface) => {
+                    if src_face.is_none() || someface.id != src_face.unwrap().id {
+                        let key_expr = Resource::decl_key(res, &mut someface);
+
+                        log::debug!("Send token {} on {}", res.expr(), someface);
+
+                        someface.primitives.decl_token(&key_expr, routing_context);
+                    }
+                }
+                None => log::trace!("Unable to find face for pid {}", net.graph[*child].pid),
+            }
+        }
+    }
+}
+
+fn propagate_simple_token(tables: &mut Tables, res: &Arc<Resource>, src_face: &mut Arc<FaceState>) {
+    for dst_face in &mut tables.faces.values_mut() {
+        if src_face.id != dst_face.id
+            && !dst_face.local_tokens.contains(res)
+            && match tables.whatami {
+                WhatAmI::Router => dst_face.whatami == WhatAmI::Client,
+                WhatAmI::Peer => dst_face.whatami == WhatAmI::Client,
+                _ => src_face.whatami == WhatAmI::Client || dst_face.whatami == WhatAmI::Client,
+            }
+        {
+            get_mut_unchecked(dst_face).local_tokens.insert(res.clone());
+            let key_expr = Resource::decl_key(res, dst_face);
+            dst_face.primitives.decl_token(&key_expr, None);
+        }
+    }
+}
+
+fn propagate_mcast_token(tables: &mut Tables, res: &Arc<Resource>, src_face: &mut Arc<FaceState>) {
+    for dst_face in &mut tables.faces.values_mut() {
+        if src_face.id != dst_face.id
+            && dst_face.kind == FaceKind::Multicast
+            && !dst_face.local_tokens.contains(res)
+        {
+            get_mut_unchecked(dst_face).local_tokens.insert(res.clone());
+            let key_expr = Resource::decl_key(res, dst_face);
+            dst_face.primitives.decl_token(&key_expr, None);
+        }
+    }
+}
+
+fn propagate_sourced_token(
+    tables: &Tables,
+    res: &Arc<Resource>,
+    src_face: Option<&Arc<FaceState>>,
+    source: &PeerId,
+    net_type: WhatAmI,
+) {
+    let net = tables.get_net(net_type).unwrap();
+    match net.get_idx(source) {
+        Some(tree_sid) => {
+            if net.trees.len() > tree_sid.index() {
+                send_sourced_token_to_net_childs(
+                    tables,
+                    net,
+                    &net.trees[tree_sid.index()].childs,
+                    res,
+                    src_face,
+                    Some(RoutingContext::new(tree_sid.index() as ZInt)),
+                );
+            } else {
+                log::trace!(
+                    "Propagating token {}: tree for node {} sid:{} not yet ready",
+                    res.expr(),
+                    tree_sid.index(),
+                    source
+                );
+            }
+        }
+        None => log::error!(
+            "Error propagating token {}: cannot get index of {}!",
+            res.expr(),
+            source
+        ),
+    }
+}
+
+fn register_router_token(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    res: &mut Arc<Resource>,
+    router: PeerId,
+) {
+    if !res.context().router_tokens.contains(&router) {
+        // Register router token
+        {
+            log::debug!("Register router token {} (router: {})", res.expr(), router);
+            get_mut_unchecked(res)
+                .context_mut()
+                .router_tokens
+                .insert(router);
+            tables.router_tokens.insert(res.clone());
+        }
+
+        // Propagate token to routers
+        propagate_sourced_token(tables, res, Some(face), &router, WhatAmI::Router);
+    }
+    // Propagate token to peers
+    if face.whatami != WhatAmI::Peer {
+        register_peer_token(tables, face, res, tables.pid)
+    }
+
+    // Propagate token to clients
+    propagate_simple_token(tables, res, face);
+}
+
+pub fn declare_router_token(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    expr: &KeyExpr,
+    router: PeerId,
+) {
+    match tables.get_mapping(face, &expr.scope).cloned() {
+        Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Token,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
+            let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
+            Resource::match_resource(tables, &mut res);
+            register_router_token(tables, face, &mut res, router);
+        }
+        None => log::error!("Declare router token for unknown scope {}!", expr.scope),
+    }
+}
+
+fn register_peer_token(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    res: &mut Arc<Resource>,
+    peer: PeerId,
+) {
+    if !res.context().peer_tokens.contains(&peer) {
+        // Register peer token
+        {
+            log::debug!("Register peer token {} (peer: {})", res.expr(), peer);
+            get_mut_unchecked(res)
+                .context_mut()
+                .peer_tokens
+                .insert(peer);
+            tables.peer_tokens.insert(res.clone());
+        }
+
+        // Propagate token to peers
+        propagate_sourced_token(tables, res, Some(face), &peer, WhatAmI::Peer);
+    }
+
+    if tables.whatami == WhatAmI::Peer {
+        // Propagate token to multicast groups and clients
+        propagate_mcast_token(tables, res, face);
+        propagate_simple_token(tables, res, face);
+    }
+}
+
+pub fn declare_peer_token(
+    tables: &mut Tables,
+    face: &mut Arc<FaceState>,
+    expr: &KeyExpr,
+    peer: PeerId,
+) {
+    match tables.get_mapping(face, &expr.scope).cloned() {
+        Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Token,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
+            let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
+            Resource::match_resource(tables, &mut res);
+            register_peer_token(tables, face, &mut res, peer);
+
+            if tables.whatami == WhatAmI::Router {
+                register_router_token(tables, face, &mut res, tables.pid);
+            }
+        }
+        None => log::error!("Declare peer token for unknown scope {}!", expr.scope),
+    }
+}
+
+fn register_client_token(face: &mut Arc<FaceState>, res: &mut Arc<Resource>) {
+    // Register token
+    {
+        let res = get_mut_unchecked(res);
+        log::debug!("Register token {} for {}", res.expr(), face);
+        match res.session_ctxs.get_mut(&face.id) {
+            Some(ctx) => get_mut_unchecked(ctx).token = true,
+            None => {
+                res.session_ctxs.insert(
+                    face.id,
+                    Arc::new(SessionContext {
+                        face: face.clone(),
+                        local_expr_id: None,
+                        remote_expr_id: None,
+                        subs: None,
+                        qabl: HashMap::new(),
+                        token: true,
+                        last_values: HashMap::new(),
+                    }),
+                );
+            }
+        }
+    }
+    get_mut_unchecked(face).remote_tokens.insert(res.clone());
+}
+
+pub fn declare_client_token(tables: &mut Tables, face: &mut Arc<FaceState>, expr: &KeyExpr) {
+    match tables.get_mapping(face, &expr.scope).cloned() {
+        Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Token,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
+            let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
+            Resource::match_resource(tables, &mut res);
+
+            register_client_token(face, &mut res);
+            match tables.whatami {
+                WhatAmI::Router => register_router_token(tables, face, &mut res, tables.pid),
+                WhatAmI::Peer => register_peer_token(tables, face, &mut res, tables.pid),
+                _ => propagate_simple_token(tables, &res, face),
+            }
         }
-        None => log::error!("Declare subscription for unknown scope {}!", expr.scope),
+        None => log::error!("Declare token for unknown scope {}!", expr.scope),
     }
 }
 
 #[inline]
-fn remote_router_subs(tables: &Tables, res: &Arc<Resource>) -> bool {
+fn remote_router_tokens(tables: &Tables, res: &Arc<Resource>) -> bool {
     res.context.is_some()
         && res
             .context()
-            .router_subs
+            .router_tokens
             .iter()
             .any(|peer| peer != &tables.pid)
 }
 
 #[inline]
-fn remote_peer_subs(tables: &Tables, res: &Arc<Resource>) -> bool {
+fn remote_peer_tokens(tables: &Tables, res: &Arc<Resource>) -> bool {
     res.context.is_some()
         && res
             .context()
-            .peer_subs
+            .peer_tokens
             .iter()
             .any(|peer| peer != &tables.pid)
 }
 
 #[inline]
-fn client_subs(res: &Arc<Resource>) -> Vec<Arc<FaceState>> {
+fn client_tokens(res: &Arc<Resource>) -> Vec<Arc<FaceState>> {
     res.session_ctxs
         .values()
-        .filter_map(|ctx| {
-            if ctx.subs.is_some() {
-                Some(ctx.face.clone())
-            } else {
-                None
-            }
-        })
+        .filter(|ctx| ctx.token)
+        .map(|ctx| ctx.face.clone())
         .collect()
 }
 
 #[inline]
-fn send_forget_sourced_subscription_to_net_childs(
+fn send_forget_sourced_token_to_net_childs(
     tables: &Tables,
     net: &Network,
     childs: &[NodeIndex],
@@ -358,11 +1080,9 @@ fn send_forget_sourced_subscription_to_net_childs(
                     if src_face.is_none() || someface.id != src_face.unwrap().id {
                         let key_expr = Resource::decl_key(res, &mut someface);
 
-                        log::debug!("Send forget subscription {} on {}", res.expr(), someface);
+                        log::debug!("Send forget token {} on {}", res.expr(), someface);
 
-                        someface
-                            .primitives
-                            .forget_subscriber(&key_expr, routing_context);
+                        someface.primitives.forget_token(&key_expr, routing_context);
                     }
                 }
                 None => log::trace!("Unable to find face for pid {}", net.graph[*child].pid),
@@ -371,18 +1091,18 @@ fn send_forget_sourced_subscription_to_net_childs(
     }
 }
 
-fn propagate_forget_simple_subscription(tables: &mut Tables, res: &Arc<Resource>) {
+fn propagate_forget_simple_token(tables: &mut Tables, res: &Arc<Resource>) {
     for face in tables.faces.values_mut() {
-        if face.local_subs.contains(res) {
+        if face.local_tokens.contains(res) {
             let key_expr = Resource::get_best_key(res, "", face.id);
-            face.primitives.forget_subscriber(&key_expr, None);
+            face.primitives.forget_token(&key_expr, None);
 
-            get_mut_unchecked(face).local_subs.remove(res);
+            get_mut_unchecked(face).local_tokens.remove(res);
         }
     }
 }
 
-fn propagate_forget_sourced_subscription(
+fn propagate_forget_sourced_token(
     tables: &Tables,
     res: &Arc<Resource>,
     src_face: Option<&Arc<FaceState>>,
@@ -393,7 +1113,7 @@ fn propagate_forget_sourced_subscription(
     match net.get_idx(source) {
         Some(tree_sid) => {
             if net.trees.len() > tree_sid.index() {
-                send_forget_sourced_subscription_to_net_childs(
+                send_forget_sourced_token_to_net_childs(
                     tables,
                     net,
                     &net.trees[tree_sid.index()].childs,
@@ -403,7 +1123,7 @@ fn propagate_forget_sourced_subscription(
                 );
             } else {
                 log::trace!(
-                    "Propagating forget sub {}: tree for node {} sid:{} not yet ready",
+                    "Propagating forget token {}: tree for node {} sid:{} not yet ready",
                     res.expr(),
                     tree_sid.index(),
                     source
@@ -411,45 +1131,47 @@ fn propagate_forget_sourced_subscription(
             }
         }
         None => log::error!(
-            "Error propagating forget sub {}: cannot get index of {}!",
+            "Error propagating forget token {}: cannot get index of {}!",
             res.expr(),
             source
         ),
     }
 }
 
-fn unregister_router_subscription(tables: &mut Tables, res: &mut Arc<Resource>, router: &PeerId) {
+fn unregister_router_token(tables: &mut Tables, res: &mut Arc<Resource>, router: &PeerId) {
     log::debug!(
-        "Unregister router subscription {} (router: {})",
+        "Unregister router token {} (router: {})",
         res.expr(),
         router
     );
     get_mut_unchecked(res)
         .context_mut()
-        .router_subs
-        .retain(|sub| sub != router);
+        .router_tokens
+        .retain(|token| token != router);
 
-    if res.context().router_subs.is_empty() {
-        tables.router_subs.retain(|sub| !Arc::ptr_eq(sub, res));
+    if res.context().router_tokens.is_empty() {
+        tables
+            .router_tokens
+            .retain(|token| !Arc::ptr_eq(token, res));
 
-        undeclare_peer_subscription(tables, None, res, &tables.pid.clone());
-        propagate_forget_simple_subscription(tables, res);
+        undeclare_peer_token(tables, None, res, &tables.pid.clone());
+        propagate_forget_simple_token(tables, res);
     }
 }
 
-fn undeclare_router_subscription(
+fn undeclare_router_token(
     tables: &mut Tables,
     face: Option<&Arc<FaceState>>,
     res: &mut Arc<Resource>,
     router: &PeerId,
 ) {
-    if res.context().router_subs.contains(router) {
-        unregister_router_subscription(tables, res, router);
-        propagate_forget_sourced_subscription(tables, res, face, router, WhatAmI::Router);
+    if res.context().router_tokens.contains(router) {
+        unregister_router_token(tables, res, router);
+        propagate_forget_sourced_token(tables, res, face, router, WhatAmI::Router);
     }
 }
 
-pub fn forget_router_subscription(
+pub fn forget_router_token(
     tables: &mut Tables,
     face: &mut Arc<FaceState>,
     expr: &KeyExpr,
@@ -458,46 +1180,44 @@ pub fn forget_router_subscription(
     match tables.get_mapping(face, &expr.scope) {
         Some(prefix) => match Resource::get_resource(prefix, expr.suffix.as_ref()) {
             Some(mut res) => {
-                undeclare_router_subscription(tables, Some(face), &mut res, router);
-
-                compute_matches_data_routes(tables, &mut res);
+                undeclare_router_token(tables, Some(face), &mut res, router);
                 Resource::clean(&mut res)
             }
-            None => log::error!("Undeclare unknown router subscription!"),
+            None => log::error!("Undeclare unknown router token!"),
         },
-        None => log::error!("Undeclare router subscription with unknown scope!"),
+        None => log::error!("Undeclare router token with unknown scope!"),
     }
 }
 
-fn unregister_peer_subscription(tables: &mut Tables, res: &mut Arc<Resource>, peer: &PeerId) {
-    log::debug!(
-        "Unregister peer subscription {} (peer: {})",
-        res.expr(),
-        peer
-    );
+fn unregister_peer_token(tables: &mut Tables, res: &mut Arc<Resource>, peer: &PeerId) {
+    log::debug!("Unregister peer token {} (peer: {})", res.expr(), peer);
     get_mut_unchecked(res)
         .context_mut()
-        .peer_subs
-        .retain(|sub| sub != peer);
+        .peer_tokens
+        .retain(|token| token != peer);
 
-    if res.context().peer_subs.is_empty() {
-        tables.peer_subs.retain(|sub| !Arc::ptr_eq(sub, res));
+    if res.context().peer_tokens.is_empty() {
+        tables.peer_tokens.retain(|token| !Arc::ptr_eq(token, res));
+
+        if tables.whatami == WhatAmI::Peer {
+            propagate_forget_simple_token(tables, res);
+        }
     }
 }
 
-fn undeclare_peer_subscription(
+fn undeclare_peer_token(
     tables: &mut Tables,
     face: Option<&Arc<FaceState>>,
     res: &mut Arc<Resource>,
     peer: &PeerId,
 ) {
-    if res.context().peer_subs.contains(peer) {
-        unregister_peer_subscription(tables, res, peer);
-        propagate_forget_sourced_subscription(tables, res, face, peer, WhatAmI::Peer);
+    if res.context().peer_tokens.contains(peer) {
+        unregister_peer_token(tables, res, peer);
+        propagate_forget_sourced_token(tables, res, face, peer, WhatAmI::Peer);
     }
 }
 
-pub fn forget_peer_subscription(
+pub fn forget_peer_token(
     tables: &mut Tables,
     face: &mut Arc<FaceState>,
     expr: &KeyExpr,
@@ -506,93 +1226,94 @@ pub fn forget_peer_subscription(
     match tables.get_mapping(face, &expr.scope) {
         Some(prefix) => match Resource::get_resource(prefix, expr.suffix.as_ref()) {
             Some(mut res) => {
-                undeclare_peer_subscription(tables, Some(face), &mut res, peer);
+                undeclare_peer_token(tables, Some(face), &mut res, peer);
 
-                if tables.whatami == WhatAmI::Router {
-                    let client_subs = res.session_ctxs.values().any(|ctx| ctx.subs.is_some());
-                    let peer_subs = remote_peer_subs(tables, &res);
-                    if !client_subs && !peer_subs {
-                        undeclare_router_subscription(tables, None, &mut res, &tables.pid.clone());
-                    }
+                if tables.whatami == WhatAmI::Router
+                    && client_tokens(&res).is_empty()
+                    && !remote_peer_tokens(tables, &res)
+                {
+                    undeclare_router_token(tables, None, &mut res, &tables.pid.clone());
                 }
 
-                compute_matches_data_routes(tables, &mut res);
                 Resource::clean(&mut res)
             }
-            None => log::error!("Undeclare unknown peer subscription!"),
+            None => log::error!("Undeclare unknown peer token!"),
         },
-        None => log::error!("Undeclare peer subscription with unknown scope!"),
+        None => log::error!("Undeclare peer token with unknown scope!"),
     }
 }
 
-pub(crate) fn undeclare_client_subscription(
+pub(crate) fn undeclare_client_token(
     tables: &mut Tables,
     face: &mut Arc<FaceState>,
     res: &mut Arc<Resource>,
 ) {
-    log::debug!("Unregister client subscription {} for {}", res.expr(), face);
+    log::debug!("Unregister client token {} for {}", res.expr(), face);
     if let Some(ctx) = get_mut_unchecked(res).session_ctxs.get_mut(&face.id) {
-        get_mut_unchecked(ctx).subs = None;
+        get_mut_unchecked(ctx).token = false;
     }
-    get_mut_unchecked(face).remote_subs.remove(res);
+    get_mut_unchecked(face).remote_tokens.remove(res);
 
-    let mut client_subs = client_subs(res);
-    let router_subs = remote_router_subs(tables, res);
-    let peer_subs = remote_peer_subs(tables, res);
+    let mut client_tokens = client_tokens(res);
+    let router_tokens = remote_router_tokens(tables, res);
+    let peer_tokens = remote_peer_tokens(tables, res);
     match tables.whatami {
         WhatAmI::Router => {
-            if client_subs.is_empty() && !peer_subs {
-                undeclare_router_subscription(tables, None, res, &tables.pid.clone());
+            if client_tokens.is_empty() && !peer_tokens {
+                undeclare_router_token(tables, None, res, &tables.pid.clone());
             }
         }
         WhatAmI::Peer => {
-            if client_subs.is_empty() {
-                undeclare_peer_subscription(tables, None, res, &tables.pid.clone());
+            if client_tokens.is_empty() {
+                undeclare_peer_token(tables, None, res, &tables.pid.clone());
             }
         }
         _ => {
-            if client_subs.is_empty() {
-                propagate_forget_simple_subscription(tables, res);
+            if client_tokens.is_empty() {
+                propagate_forget_simple_token(tables, res);
             }
         }
     }
-    if client_subs.len() == 1 && !router_subs && !peer_subs {
-        let face = &mut client_subs[0];
-        if face.local_subs.contains(res) {
+    if client_tokens.len() == 1 && !router_tokens && !peer_tokens {
+        let face = &mut client_tokens[0];
+        if face.local_tokens.contains(res) {
             let key_expr = Resource::get_best_key(res, "", face.id);
-            face.primitives.forget_subscriber(&key_expr, None);
+            face.primitives.forget_token(&key_expr, None);
 
-            get_mut_unchecked(face).local_subs.remove(res);
+            get_mut_unchecked(face).local_tokens.remove(res);
         }
     }
 
-    compute_matches_data_routes(tables, res);
     Resource::clean(res)
 }
 
-pub fn forget_client_subscription(tables: &mut Tables, face: &mut Arc<FaceState>, expr: &KeyExpr) {
+pub fn forget_client_token(tables: &mut Tables, face: &mut Arc<FaceState>, expr: &KeyExpr) {
     match tables.get_mapping(face, &expr.scope) {
         Some(prefix) => match Resource::get_resource(prefix, expr.suffix.as_ref()) {
             Some(mut res) => {
-                undeclare_client_subscription(tables, face, &mut res);
+                undeclare_client_token(tables, face, &mut res);
             }
-            None => log::error!("Undeclare unknown subscription!"),
+            None => log::error!("Undeclare unknown token!"),
         },
-        None => log::error!("Undeclare subscription with unknown scope!"),
+        None => log::error!("Undeclare token with unknown scope!"),
     }
 }
 
-pub(crate) fn pubsub_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
-    let sub_info = SubInfo {
-        reliability: Reliability::Reliable, // @TODO
-        mode: SubMode::Push,
-        period: None,
-    };
-    if face.whatami == WhatAmI::Client && tables.whatami != WhatAmI::Client {
-        for sub in &tables.router_subs {
-            get_mut_unchecked(face).local_subs.insert(sub.clone());
-            let key_expr = Resource::decl_key(sub, face);
-            face.primitives.decl_subscriber(&key_expr, &sub_info, None);
+pub(crate) fn tokens_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
+    if face.whatami == WhatAmI::Client && tables.whatami == WhatAmI::Router {
+        for token in &tables.router_tokens {
+            get_mut_unchecked(face).local_tokens.insert(token.clone());
+            let key_expr = Resource::decl_key(token, face);
+            face.primitives.decl_token(&key_expr, None);
+        }
+    }
+    if (face.whatami == WhatAmI::Client || face.kind == FaceKind::Multicast)
+        && tables.whatami == WhatAmI::Peer
+    {
+        for token in &tables.peer_tokens {
+            get_mut_unchecked(face).local_tokens.insert(token.clone());
+            let key_expr = Resource::decl_key(token, face);
+            face.primitives.decl_token(&key_expr, None);
         }
     }
     if tables.whatami == WhatAmI::Client {
@@ -602,48 +1323,44 @@ pub(crate) fn pubsub_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
             .cloned()
             .collect::<Vec<Arc<FaceState>>>()
         {
-            for sub in &face.remote_subs {
-                propagate_simple_subscription(tables, sub, &sub_info, &mut face.clone());
+            for token in &face.remote_tokens {
+                propagate_simple_token(tables, token, &mut face.clone());
             }
         }
     }
 }
 
-pub(crate) fn pubsub_remove_node(tables: &mut Tables, node: &PeerId, net_type: WhatAmI) {
+pub(crate) fn tokens_remove_node(tables: &mut Tables, node: &PeerId, net_type: WhatAmI) {
     match net_type {
         WhatAmI::Router => {
             for mut res in tables
-                .router_subs
+                .router_tokens
                 .iter()
-                .filter(|res| res.context().router_subs.contains(node))
+                .filter(|res| res.context().router_tokens.contains(node))
                 .cloned()
                 .collect::<Vec<Arc<Resource>>>()
             {
-                unregister_router_subscription(tables, &mut res, node);
-
-                compute_matches_data_routes(tables, &mut res);
+                unregister_router_token(tables, &mut res, node);
                 Resource::clean(&mut res)
             }
         }
         WhatAmI::Peer => {
             for mut res in tables
-                .peer_subs
+                .peer_tokens
                 .iter()
-                .filter(|res| res.context().peer_subs.contains(node))
+                .filter(|res| res.context().peer_tokens.contains(node))
                 .cloned()
                 .collect::<Vec<Arc<Resource>>>()
             {
-                unregister_peer_subscription(tables, &mut res, node);
+                unregister_peer_token(tables, &mut res, node);
 
-                if tables.whatami == WhatAmI::Router {
-                    let client_subs = res.session_ctxs.values().any(|ctx| ctx.subs.is_some());
-                    let peer_subs = remote_peer_subs(tables, &res);
-                    if !client_subs && !peer_subs {
-                        undeclare_router_subscription(tables, None, &mut res, &tables.pid.clone());
-                    }
+                if tables.whatami == WhatAmI::Router
+                    && client_tokens(&res).is_empty()
+                    && !remote_peer_tokens(tables, &res)
+                {
+                    undeclare_router_token(tables, None, &mut res, &tables.pid.clone());
                 }
 
-                compute_matches_data_routes(tables, &mut res);
                 Resource::clean(&mut res)
             }
         }
@@ -651,12 +1368,12 @@ pub(crate) fn pubsub_remove_node(tables: &mut Tables, node: &PeerId, net_type: W
     }
 }
 
-pub(crate) fn pubsub_tree_change(
+pub(crate) fn tokens_tree_change(
     tables: &mut Tables,
     new_childs: &[Vec<NodeIndex>],
     net_type: WhatAmI,
 ) {
-    // propagate subs to new childs
+    // propagate tokens to new childs
     for (tree_sid, tree_childs) in new_childs.iter().enumerate() {
         if !tree_childs.is_empty() {
             let net = tables.get_net(net_type).unwrap();
@@ -664,41 +1381,59 @@ pub(crate) fn pubsub_tree_change(
             if net.graph.contains_node(tree_idx) {
                 let tree_id = net.graph[tree_idx].pid;
 
-                let subs_res = match net_type {
-                    WhatAmI::Router => &tables.router_subs,
-                    _ => &tables.peer_subs,
-                };
-
-                for res in subs_res {
-                    let subs = match net_type {
-                        WhatAmI::Router => &res.context().router_subs,
-                        _ => &res.context().peer_subs,
+                for res in match net_type {
+                    WhatAmI::Router => &tables.router_tokens,
+                    _ => &tables.peer_tokens,
+                } {
+                    let tokens = match net_type {
+                        WhatAmI::Router => &res.context().router_tokens,
+                        _ => &res.context().peer_tokens,
                     };
-                    for sub in subs {
-                        if *sub == tree_id {
-                            let sub_info = SubInfo {
-                                reliability: Reliability::Reliable, // @TODO
-                                mode: SubMode::Push,
-                                period: None,
-                            };
-                            send_sourced_subscription_to_net_childs(
-                                tables,
-                                net,
-                                tree_childs,
-                                res,
-                                None,
-                                &sub_info,
-                                Some(RoutingContext::new(tree_sid as ZInt)),
-                            );
-                        }
+                    if tokens.contains(&tree_id) {
+                        send_sourced_token_to_net_childs(
+                            tables,
+                            net,
+                            tree_childs,
+                            res,
+                            None,
+                            Some(RoutingContext::new(tree_sid as ZInt)),
+                        );
                     }
                 }
             }
         }
     }
+}
 
-    // recompute routes
-    compute_data_routes_from(tables, &mut tables.root_res.clone());
+/// The liveliness tokens known to the routing tables intersecting the given key expression.
+pub(crate) fn alive_tokens(
+    tables: &Tables,
+    face: &Arc<FaceState>,
+    expr: &KeyExpr,
+) -> Option<Vec<String>> {
+    let expr = match tables.get_mapping(face, &expr.scope) {
+        Some(prefix) => [&prefix.expr(), expr.suffix.as_ref()].concat(),
+        None => {
+            log::error!("Get alive tokens with unknown scope {}!", expr.scope);
+            return None;
+        }
+    };
+    let mut tokens = tables
+        .router_tokens
+        .iter()
+        .chain(tables.peer_tokens.iter())
+        .chain(
+            tables
+                .faces
+                .values()
+                .flat_map(|face| face.remote_tokens.iter()),
+        )
+        .map(|res| res.expr())
+        .filter(|token| key_expr::matches(&expr, token))
+        .collect::<Vec<String>>();
+    tokens.sort();
+    tokens.dedup();
+    Some(tokens)
 }
 
 #[inline]
@@ -928,6 +1663,115 @@ pub(crate) fn compute_matches_data_routes(tables: &mut Tables, res: &mut Arc<Res
                 compute_data_routes(tables, &mut match_.upgrade().unwrap());
             }
         }
+        update_matching_listeners(tables);
+    }
+}
+
+/// A listener of the matching status of the publications of a face on a key expression.
+pub(crate) struct MatchingListenerState {
+    pub(super) face: usize,
+    expr: String,
+    local: bool,
+    matching: bool,
+    sender: Sender<bool>,
+}
+
+// Whether the data published by `face` on `expr` reaches at least one subscriber.
+// The subscribers of `face` itself only count when `local` is set.
+fn compute_matching_status(
+    tables: &Tables,
+    face: &Arc<FaceState>,
+    expr: &str,
+    local: bool,
+) -> bool {
+    let res = Resource::get_resource(&tables.root_res, expr);
+    let route = get_data_route(tables, face, &res, &tables.root_res, expr, None);
+    let matching_pulls = get_matching_pulls(tables, &res, &tables.root_res, expr);
+    route
+        .values()
+        .any(|(outface, _, _)| local || outface.id != face.id)
+        || matching_pulls
+            .iter()
+            .any(|ctx| local || ctx.face.id != face.id)
+}
+
+pub(crate) fn matching_status(
+    tables: &Tables,
+    face: &Arc<FaceState>,
+    expr: &KeyExpr,
+    local: bool,
+) -> Option<bool> {
+    match tables.get_mapping(face, &expr.scope) {
+        Some(prefix) => {
+            let expr = [&prefix.expr(), expr.suffix.as_ref()].concat();
+            Some(compute_matching_status(tables, face, &expr, local))
+        }
+        None => {
+            log::error!("Matching status with unknown scope {}!", expr.scope);
+            None
+        }
+    }
+}
+
+pub(crate) fn declare_matching_listener(
+    tables: &mut Tables,
+    face: &Arc<FaceState>,
+    expr: &KeyExpr,
+    local: bool,
+    sender: Sender<bool>,
+) -> Option<(usize, bool)> {
+    let expr = match tables.get_mapping(face, &expr.scope) {
+        Some(prefix) => [&prefix.expr(), expr.suffix.as_ref()].concat(),
+        None => {
+            log::error!(
+                "Declare matching listener with unknown scope {}!",
+                expr.scope
+            );
+            return None;
+        }
+    };
+    log::debug!("Register matching listener {} for {}", expr, face);
+    let matching = compute_matching_status(tables, face, &expr, local);
+    let id = tables.matching_listener_counter;
+    tables.matching_listener_counter += 1;
+    tables.matching_listeners.insert(
+        id,
+        MatchingListenerState {
+            face: face.id,
+            expr,
+            local,
+            matching,
+            sender,
+        },
+    );
+    Some((id, matching))
+}
+
+pub(crate) fn undeclare_matching_listener(tables: &mut Tables, id: usize) {
+    if let Some(listener) = tables.matching_listeners.remove(&id) {
+        log::debug!("Unregister matching listener {}", listener.expr);
+    }
+}
+
+// Notify the matching listeners whose status changed with the last routes computation
+fn update_matching_listeners(tables: &mut Tables) {
+    if tables.matching_listeners.is_empty() {
+        return;
+    }
+    let changes = tables
+        .matching_listeners
+        .iter()
+        .filter_map(|(id, listener)| {
+            let face = tables.faces.get(&listener.face)?;
+            let matching = compute_matching_status(tables, face, &listener.expr, listener.local);
+            (matching != listener.matching).then_some((*id, matching))
+        })
+        .collect::<Vec<(usize, bool)>>();
+    for (id, matching) in changes {
+        let listener = tables.matching_listeners.get_mut(&id).unwrap();
+        log::trace!("Matching status of {}: {}", listener.expr, matching);
+        listener.matching = matching;
+        let _ = listener.sender.send(matching);
     }
 }
 
@@ -1062,38 +1906,79 @@ fn get_matching_pulls(
         .unwrap_or_else(|| compute_matching_pulls(tables, prefix, suffix))
 }
 
+/// Send data to the given face, enforcing its downsampling rules if any.
+/// The full key expression is only computed when the face is downsampled.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+fn send_data_to_face<F: FnOnce() -> String>(
+    outface: &Arc<FaceState>,
+    expr: F,
+    key_expr: &KeyExpr,
+    payload: ZBuf,
+    channel: Channel,
+    congestion_control: CongestionControl,
+    data_info: Option<DataInfo>,
+    routing_context: Option<RoutingContext>,
+    attachment: Option<Attachment>,
+) {
+    match &outface.downsampler {
+        Some(downsampler) => downsampler.send_data(
+            &outface.primitives,
+            expr(),
+            key_expr,
+            payload,
+            channel,
+            congestion_control,
+            data_info,
+            routing_context,
+            attachment,
+        ),
+        None => outface.primitives.send_data(
+            key_expr,
+            payload,
+            channel,
+            congestion_control,
+            data_info,
+            routing_context,
+            attachment,
+        ),
+    }
+}
+
 macro_rules! send_to_first {
-    ($route:expr, $srcface:expr, $payload:expr, $channel:expr, $cong_ctrl:expr, $data_info:expr) => {
+    ($route:expr, $srcface:expr, $expr:expr, $payload:expr, $channel:expr, $cong_ctrl:expr, $data_info:expr, $attachment:expr) => {
         let (outface, key_expr, context) = $route.values().next().unwrap();
         if $srcface.id != outface.id {
-            outface
-                .primitives
-                .send_data(
-                    &key_expr,
-                    $payload,
-                    $channel, // @TODO: Need to check the active subscriptions to determine the right reliability value
-                    $cong_ctrl,
-                    $data_info,
-                    *context,
-                )
+            send_data_to_face(
+                outface,
+                || $expr,
+                &key_expr,
+                $payload,
+                $channel, // @TODO: Need to check the active subscriptions to determine the right reliability value
+                $cong_ctrl,
+                $data_info,
+                *context,
+                $attachment,
+            )
         }
     }
 }
 
 macro_rules! send_to_all {
-    ($route:expr, $srcface:expr, $payload:expr, $channel:expr, $cong_ctrl:expr, $data_info:expr) => {
+    ($route:expr, $srcface:expr, $expr:expr, $payload:expr, $channel:expr, $cong_ctrl:expr, $data_info:expr, $attachment:expr) => {
         for (outface, key_expr, context) in $route.values() {
             if $srcface.id != outface.id {
-                outface
-                    .primitives
-                    .send_data(
-                        &key_expr,
-                        $payload.clone(),
-                        $channel, // @TODO: Need to check the active subscriptions to determine the right reliability value
-                        $cong_ctrl,
-                        $data_info.clone(),
-                        *context,
-                    )
+                send_data_to_face(
+                    outface,
+                    || $expr,
+                    &key_expr,
+                    $payload.clone(),
+                    $channel, // @TODO: Need to check the active subscriptions to determine the right reliability value
+                    $cong_ctrl,
+                    $data_info.clone(),
+                    *context,
+                    $attachment.clone(),
+                )
             }
         }
     }
@@ -1127,6 +2012,7 @@ pub fn route_data(
     info: Option<DataInfo>,
     payload: ZBuf,
     routing_context: Option<RoutingContext>,
+    attachment: Option<Attachment>,
 ) {
     match tables.get_mapping(face, &expr.scope).cloned() {
         Some(prefix) => {
@@ -1136,6 +2022,19 @@ pub fn route_data(
                 expr.suffix.as_ref()
             );
 
+            let action = acl::data_action(&info);
+            if !acl::check(tables, face, action, &prefix, expr.suffix.as_ref()) {
+                return;
+            }
+            let config_change =
+                match acl::check_admin_config_write(tables, face, &prefix, expr.suffix.as_ref()) {
+                    AdminConfigWrite::Route => None,
+                    AdminConfigWrite::Apply(change) => {
+                        Some((change, payload.clone(), info.clone()))
+                    }
+                    AdminConfigWrite::Reject => return,
+                };
+
             let res = Resource::get_resource(&prefix, expr.suffix.as_ref());
             let route = get_data_route(
                 tables,
@@ -1151,7 +2050,16 @@ pub fn route_data(
                 let data_info = treat_timestamp!(&tables.hlc, info);
 
                 if route.len() == 1 && matching_pulls.len() == 0 {
-                    send_to_first!(route, face, payload, channel, congestion_control, data_info);
+                    send_to_first!(
+                        route,
+                        face,
+                        [&prefix.expr(), expr.suffix.as_ref()].concat(),
+                        payload,
+                        channel,
+                        congestion_control,
+                        data_info,
+                        attachment
+                    );
                 } else {
                     if !matching_pulls.is_empty() {
                         let lock = zlock!(tables.pull_caches_lock);
@@ -1164,9 +2072,22 @@ pub fn route_data(
                         );
                         drop(lock);
                     }
-                    send_to_all!(route, face, payload, channel, congestion_control, data_info);
+                    send_to_all!(
+                        route,
+                        face,
+                        [&prefix.expr(), expr.suffix.as_ref()].concat(),
+                        payload,
+                        channel,
+                        congestion_control,
+                        data_info,
+                        attachment
+                    );
                 }
             }
+
+            if let Some((change, payload, info)) = config_change {
+                change.apply(payload, info);
+            }
         }
         None => {
             log::error!("Route data with unknown scope {}!", expr.scope);
@@ -1185,6 +2106,7 @@ pub fn full_reentrant_route_data(
     info: Option<DataInfo>,
     payload: ZBuf,
     routing_context: Option<RoutingContext>,
+    attachment: Option<Attachment>,
 ) {
     let tables = zread!(tables_ref);
     match tables.get_mapping(face, &expr.scope).cloned() {
@@ -1195,6 +2117,19 @@ pub fn full_reentrant_route_data(
                 expr.suffix.as_ref()
             );
 
+            let action = acl::data_action(&info);
+            if !acl::check(&tables, face, action, &prefix, expr.suffix.as_ref()) {
+                return;
+            }
+            let config_change =
+                match acl::check_admin_config_write(&tables, face, &prefix, expr.suffix.as_ref()) {
+                    AdminConfigWrite::Route => None,
+                    AdminConfigWrite::Apply(change) => {
+                        Some((change, payload.clone(), info.clone()))
+                    }
+                    AdminConfigWrite::Reject => return,
+                };
+
             let res = Resource::get_resource(&prefix, expr.suffix.as_ref());
             let route = get_data_route(
                 &tables,
@@ -1211,7 +2146,16 @@ pub fn full_reentrant_route_data(
 
                 if route.len() == 1 && matching_pulls.len() == 0 {
                     drop(tables);
-                    send_to_first!(route, face, payload, channel, congestion_control, data_info);
+                    send_to_first!(
+                        route,
+                        face,
+                        [&prefix.expr(), expr.suffix.as_ref()].concat(),
+                        payload,
+                        channel,
+                        congestion_control,
+                        data_info,
+                        attachment
+                    );
                 } else {
                     if !matching_pulls.is_empty() {
                         let lock = zlock!(tables.pull_caches_lock);
@@ -1225,8 +2169,23 @@ pub fn full_reentrant_route_data(
                         drop(lock);
                     }
                     drop(tables);
-                    send_to_all!(route, face, payload, channel, congestion_control, data_info);
+                    send_to_all!(
+                        route,
+                        face,
+                        [&prefix.expr(), expr.suffix.as_ref()].concat(),
+                        payload,
+                        channel,
+                        congestion_control,
+                        data_info,
+                        attachment
+                    );
                 }
+            } else {
+                drop(tables);
+            }
+
+            if let Some((change, payload, info)) = config_change {
+                change.apply(payload, info);
             }
         }
         None => {
@@ -1264,6 +2223,7 @@ pub fn pull_data(
                                     CongestionControl::default(), // @TODO: Default value for the time being
                                     info.clone(),
                                     None,
+                                    None,
                                 );
                             }
                             get_mut_unchecked(ctx).last_values.clear();
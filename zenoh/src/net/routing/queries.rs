@@ -16,21 +16,22 @@ use async_trait::async_trait;
 use ordered_float::OrderedFloat;
 use petgraph::graph::NodeIndex;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{RwLock, Weak};
-// use std::time::Instant;
-// use zenoh_collections::{Timed, TimedEvent};
-use zenoh_collections::Timed;
+use std::time::{Duration, Instant};
+use zenoh_collections::{Timed, TimedEvent};
+use zenoh_config::AclAction;
 use zenoh_sync::get_mut_unchecked;
 
 use zenoh_protocol::io::ZBuf;
-use zenoh_protocol::proto::{DataInfo, RoutingContext};
+use zenoh_protocol::proto::{Attachment, DataInfo, QueryBody, RoutingContext};
 use zenoh_protocol_core::{
     key_expr, queryable, ConsolidationStrategy, KeyExpr, PeerId, QueryTarget, QueryableInfo,
     Target, WhatAmI, ZInt,
 };
 
-use super::face::FaceState;
+use super::acl;
+use super::face::{FaceKind, FaceState};
 use super::network::Network;
 use super::resource::{
     elect_router, QueryRoute, Resource, SessionContext, TargetQabl, TargetQablSet,
@@ -261,6 +262,35 @@ fn propagate_simple_queryable(
     }
 }
 
+// In peer mode, the queryables of the local sessions and of the other peers are registered
+// as peer queryables, which are only propagated over the peers network. The peers of
+// a multicast group only send to the group the queries matching the queryables declared
+// to it: the face of the group is declared all the peer queryables instead.
+fn propagate_mcast_queryable(
+    tables: &mut Tables,
+    res: &Arc<Resource>,
+    kind: ZInt,
+    src_face_id: Option<usize>,
+) {
+    let whatami = tables.whatami;
+    let pid = tables.pid;
+    for dst_face in &mut tables.faces.values_mut() {
+        if dst_face.kind == FaceKind::Multicast && src_face_id != Some(dst_face.id) {
+            let info = local_qabl_info(whatami, &pid, res, kind, dst_face);
+            let current_info = dst_face.local_qabls.get(&(res.clone(), kind));
+            if current_info.is_none() || *current_info.unwrap() != info {
+                get_mut_unchecked(dst_face)
+                    .local_qabls
+                    .insert((res.clone(), kind), info.clone());
+                let key_expr = Resource::decl_key(res, dst_face);
+                dst_face
+                    .primitives
+                    .decl_queryable(&key_expr, kind, &info, None);
+            }
+        }
+    }
+}
+
 fn propagate_sourced_queryable<Face: std::borrow::Borrow<Arc<FaceState>>>(
     tables: &Tables,
     res: &Arc<Resource>,
@@ -357,6 +387,15 @@ pub fn declare_router_queryable(
 ) {
     match tables.get_mapping(face, &expr.scope).cloned() {
         Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Reply,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
             let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
             Resource::match_resource(tables, &mut res);
             register_router_queryable(tables, Some(face), &mut res, kind, qabl_info, router);
@@ -375,6 +414,7 @@ fn register_peer_queryable<Face: std::borrow::Borrow<Arc<FaceState>>>(
     qabl_info: &QueryableInfo,
     peer: PeerId,
 ) {
+    let src_face_id = face.as_ref().map(|face| face.borrow().id);
     let current_info = res.context().peer_qabls.get(&(peer, kind));
     if current_info.is_none() || current_info.unwrap() != qabl_info {
         // Register peer queryable
@@ -395,6 +435,11 @@ fn register_peer_queryable<Face: std::borrow::Borrow<Arc<FaceState>>>(
         // Propagate queryable to peers
         propagate_sourced_queryable(tables, res, kind, qabl_info, face, &peer, WhatAmI::Peer);
     }
+
+    if tables.whatami == WhatAmI::Peer {
+        // Propagate queryable to multicast groups
+        propagate_mcast_queryable(tables, res, kind, src_face_id);
+    }
 }
 
 pub fn declare_peer_queryable(
@@ -407,6 +452,15 @@ pub fn declare_peer_queryable(
 ) {
     match tables.get_mapping(face, &expr.scope).cloned() {
         Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Reply,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
             let face = Some(face);
             let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
             Resource::match_resource(tables, &mut res);
@@ -446,6 +500,7 @@ fn register_client_queryable(
                 remote_expr_id: None,
                 subs: None,
                 qabl: HashMap::new(),
+                token: false,
                 last_values: HashMap::new(),
             })
         }))
@@ -466,6 +521,15 @@ pub fn declare_client_queryable(
 ) {
     match tables.get_mapping(face, &expr.scope).cloned() {
         Some(mut prefix) => {
+            if !acl::check(
+                tables,
+                face,
+                AclAction::Reply,
+                &prefix,
+                expr.suffix.as_ref(),
+            ) {
+                return;
+            }
             let mut res = Resource::make_resource(tables, &mut prefix, expr.suffix.as_ref());
             Resource::match_resource(tables, &mut res);
 
@@ -704,6 +768,15 @@ fn unregister_peer_queryable(
     if res.context().peer_qabls.is_empty() {
         tables.peer_qabls.retain(|qabl| !Arc::ptr_eq(qabl, res));
     }
+
+    if tables.whatami == WhatAmI::Peer {
+        // Only multicast groups were declared the queryable on top of the peers
+        if res.context().peer_qabls.keys().any(|(_, k)| *k == kind) {
+            propagate_mcast_queryable(tables, res, kind, None);
+        } else {
+            propagate_forget_simple_queryable(tables, res, kind);
+        }
+    }
 }
 
 fn undeclare_peer_queryable(
@@ -874,6 +947,21 @@ pub(crate) fn queries_new_face(tables: &mut Tables, face: &mut Arc<FaceState>) {
             }
         }
     }
+    if face.kind == FaceKind::Multicast && tables.whatami == WhatAmI::Peer {
+        for qabl in &tables.peer_qabls {
+            if let Some(ctx) = qabl.context.as_ref() {
+                let kinds: HashSet<ZInt> = ctx.peer_qabls.keys().map(|(_, kind)| *kind).collect();
+                for kind in kinds {
+                    let info = local_qabl_info(tables.whatami, &tables.pid, qabl, kind, face);
+                    get_mut_unchecked(face)
+                        .local_qabls
+                        .insert((qabl.clone(), kind), info.clone());
+                    let key_expr = Resource::decl_key(qabl, face);
+                    face.primitives.decl_queryable(&key_expr, kind, &info, None);
+                }
+            }
+        }
+    }
     if tables.whatami == WhatAmI::Client {
         for face in tables
             .faces
@@ -1358,7 +1446,10 @@ pub fn route_query(
     qid: ZInt,
     target: QueryTarget,
     consolidation: ConsolidationStrategy,
+    body: Option<QueryBody>,
     routing_context: Option<RoutingContext>,
+    timeout: Option<Duration>,
+    attachment: Option<Attachment>,
 ) {
     let tables = zwrite!(tables_ref);
     match tables.get_mapping(face, &expr.scope) {
@@ -1371,6 +1462,18 @@ pub fn route_query(
                 expr.suffix.as_ref(),
             );
 
+            if !acl::check(
+                &tables,
+                face,
+                AclAction::Query,
+                prefix,
+                expr.suffix.as_ref(),
+            ) {
+                drop(tables);
+                face.primitives.clone().send_reply_final(qid);
+                return;
+            }
+
             let route = match tables.whatami {
                 WhatAmI::Router => match face.whatami {
                     WhatAmI::Router => {
@@ -1470,9 +1573,8 @@ pub fn route_query(
                     src_qid: qid,
                 });
 
-                // let timer = tables.timer.clone();
-                // let timeout = tables.queries_default_timeout;
-                // drop(tables);
+                let timeout = timeout.unwrap_or(tables.queries_default_timeout);
+                let mut cleanups = Vec::with_capacity(route.len());
                 #[cfg(feature = "complete_n")]
                 for ((outface, key_expr, context), t) in route.values() {
                     let mut outface = outface.clone();
@@ -1480,14 +1582,14 @@ pub fn route_query(
                     outface_mut.next_qid += 1;
                     let qid = outface_mut.next_qid;
                     outface_mut.pending_queries.insert(qid, query.clone());
-                    // timer.add(TimedEvent::once(
-                    //     Instant::now() + timout,
-                    //     QueryCleanup {
-                    //         tables: tables_ref.clone(),
-                    //         face: Arc::downgrade(&outface),
-                    //         qid,
-                    //     },
-                    // ));
+                    cleanups.push(TimedEvent::once(
+                        Instant::now() + timeout,
+                        QueryCleanup {
+                            tables: tables_ref.clone(),
+                            face: Arc::downgrade(&outface),
+                            qid,
+                        },
+                    ));
 
                     log::trace!("Propagate query {}:{} to {}", query.src_face, qid, outface);
 
@@ -1500,7 +1602,9 @@ pub fn route_query(
                             target: t.clone(),
                         },
                         consolidation.clone(),
+                        body.clone(),
                         *context,
+                        attachment.clone(),
                     );
                 }
 
@@ -1511,14 +1615,14 @@ pub fn route_query(
                     outface_mut.next_qid += 1;
                     let qid = outface_mut.next_qid;
                     outface_mut.pending_queries.insert(qid, query.clone());
-                    // timer.add(TimedEvent::once(
-                    //     Instant::now() + timeout,
-                    //     QueryCleanup {
-                    //         tables: tables_ref.clone(),
-                    //         face: Arc::downgrade(&outface),
-                    //         qid,
-                    //     },
-                    // ));
+                    cleanups.push(TimedEvent::once(
+                        Instant::now() + timeout,
+                        QueryCleanup {
+                            tables: tables_ref.clone(),
+                            face: Arc::downgrade(&outface),
+                            qid,
+                        },
+                    ));
 
                     log::trace!("Propagate query {}:{} to {}", query.src_face, qid, outface);
 
@@ -1528,9 +1632,19 @@ pub fn route_query(
                         qid,
                         target.clone(),
                         consolidation.clone(),
+                        body.clone(),
                         *context,
+                        attachment.clone(),
                     );
                 }
+
+                // The timer task needs the tables lock to run the cleanups:
+                // release it before registering them to avoid a deadlock.
+                let timer = tables.timer.clone();
+                drop(tables);
+                for cleanup in cleanups {
+                    timer.add(cleanup);
+                }
             }
         }
         None => {
@@ -1545,24 +1659,42 @@ pub fn route_query(
 
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn route_send_reply_data(
-    _tables: &mut Tables,
+    tables: &mut Tables,
     face: &mut Arc<FaceState>,
     qid: ZInt,
     replier_kind: ZInt,
     replier_id: PeerId,
+    is_error: bool,
     key_expr: KeyExpr,
     info: Option<DataInfo>,
     payload: ZBuf,
+    attachment: Option<Attachment>,
 ) {
     match face.pending_queries.get(&qid) {
         Some(query) => {
+            if tables.acl.is_some() {
+                match tables.get_mapping(face, &key_expr.scope) {
+                    Some(prefix) => {
+                        let suffix = key_expr.suffix.as_ref();
+                        if !acl::check(tables, face, AclAction::Reply, prefix, suffix) {
+                            return;
+                        }
+                    }
+                    None => {
+                        log::error!("Route reply with unknown scope {}!", key_expr.scope);
+                        return;
+                    }
+                }
+            }
             query.src_face.primitives.clone().send_reply_data(
                 query.src_qid,
                 replier_kind,
                 replier_id,
+                is_error,
                 key_expr,
                 info,
                 payload,
+                attachment,
             );
         }
         None => log::warn!(
@@ -44,6 +44,7 @@ pub(super) struct SessionContext {
     pub(super) remote_expr_id: Option<ZInt>,
     pub(super) subs: Option<SubInfo>,
     pub(super) qabl: HashMap<ZInt, QueryableInfo>,
+    pub(super) token: bool,
     pub(super) last_values: HashMap<String, (Option<DataInfo>, ZBuf)>,
 }
 
@@ -52,6 +53,8 @@ pub(super) struct ResourceContext {
     pub(super) peer_subs: HashSet<PeerId>,
     pub(super) router_qabls: HashMap<(PeerId, ZInt), QueryableInfo>,
     pub(super) peer_qabls: HashMap<(PeerId, ZInt), QueryableInfo>,
+    pub(super) router_tokens: HashSet<PeerId>,
+    pub(super) peer_tokens: HashSet<PeerId>,
     pub(super) matches: Vec<Weak<Resource>>,
     pub(super) matching_pulls: Arc<PullCaches>,
     pub(super) routers_data_routes: Vec<Arc<Route>>,
@@ -69,6 +72,8 @@ impl ResourceContext {
             peer_subs: HashSet::new(),
             router_qabls: HashMap::new(),
             peer_qabls: HashMap::new(),
+            router_tokens: HashSet::new(),
+            peer_tokens: HashSet::new(),
             matches: Vec::new(),
             matching_pulls: Arc::new(Vec::new()),
             routers_data_routes: Vec::new(),
@@ -373,6 +378,7 @@ impl Resource {
                             remote_expr_id: None,
                             subs: None,
                             qabl: HashMap::new(),
+                            token: false,
                             last_values: HashMap::new(),
                         })
                     });
@@ -550,6 +556,7 @@ pub fn register_expr(
                             remote_expr_id: Some(expr_id),
                             subs: None,
                             qabl: HashMap::new(),
+                            token: false,
                             last_values: HashMap::new(),
                         })
                     })
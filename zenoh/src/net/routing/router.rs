@@ -11,7 +11,10 @@
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
 //
-use super::face::{Face, FaceState};
+use super::acl::{AccessControl, AdminConfigHandler, AdminConfigPolicy};
+use super::downsampling::Downsampling;
+use super::face::{Face, FaceKind, FaceState};
+use super::multicast::{McastGroup, McastGroupPrimitives, McastTransportHandler};
 use super::network::{shared_nodes, Network};
 pub use super::pubsub::*;
 pub use super::queries::*;
@@ -21,30 +24,50 @@ use async_std::sync::{Arc, Weak};
 use async_std::task::JoinHandle;
 use std::any::Any;
 use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Mutex, RwLock};
 use std::time::Duration;
 use uhlc::HLC;
+use zenoh_collections::Timer;
+use zenoh_core::zconfigurable;
+use zenoh_core::Result as ZResult;
 use zenoh_link::Link;
 use zenoh_protocol::proto::{ZenohBody, ZenohMessage};
 use zenoh_protocol_core::{PeerId, WhatAmI, ZInt};
-use zenoh_transport::{DeMux, Mux, Primitives, TransportPeerEventHandler, TransportUnicast};
-// use zenoh_collections::Timer;
-use zenoh_core::zconfigurable;
-use zenoh_core::Result as ZResult;
 use zenoh_sync::get_mut_unchecked;
+use zenoh_transport::unicast::establishment::authenticator::AuthId;
+use zenoh_transport::{
+    DeMux, Mux, Primitives, TransportMulticast, TransportPeerEventHandler, TransportUnicast,
+};
 
 zconfigurable! {
     static ref TREES_COMPUTATION_DELAY: u64 = 100;
 }
 
+pub struct FaceStats {
+    pub id: usize,
+    pub pid: PeerId,
+    pub whatami: WhatAmI,
+    /// The subscriptions declared by the face.
+    pub subscribers: usize,
+    /// The queryables declared by the face.
+    pub queryables: usize,
+    /// The queries routed to the face and still waiting for their final reply.
+    pub pending_queries: usize,
+}
+
 pub struct Tables {
     pub(crate) pid: PeerId,
     pub(crate) whatami: WhatAmI,
     face_counter: usize,
     #[allow(dead_code)]
     pub(crate) hlc: Option<Arc<HLC>>,
-    // pub(crate) timer: Timer,
-    // pub(crate) queries_default_timeout: Duration,
+    pub(crate) timer: Timer,
+    pub(crate) queries_default_timeout: Duration,
+    pub(crate) acl: Option<AccessControl>,
+    pub(crate) admin_config_policy: AdminConfigPolicy,
+    pub(crate) admin_config_handler: Option<Arc<dyn AdminConfigHandler>>,
+    pub(crate) downsampling: Option<Downsampling>,
     pub(crate) root_res: Arc<Resource>,
     pub(crate) faces: HashMap<usize, Arc<FaceState>>,
     pub(crate) pull_caches_lock: Mutex<()>,
@@ -52,11 +75,15 @@ pub struct Tables {
     pub(crate) peer_subs: HashSet<Arc<Resource>>,
     pub(crate) router_qabls: HashSet<Arc<Resource>>,
     pub(crate) peer_qabls: HashSet<Arc<Resource>>,
+    pub(crate) router_tokens: HashSet<Arc<Resource>>,
+    pub(crate) peer_tokens: HashSet<Arc<Resource>>,
     pub(crate) routers_net: Option<Network>,
     pub(crate) peers_net: Option<Network>,
     pub(crate) shared_nodes: Vec<PeerId>,
     pub(crate) routers_trees_task: Option<JoinHandle<()>>,
     pub(crate) peers_trees_task: Option<JoinHandle<()>>,
+    pub(crate) matching_listeners: HashMap<usize, MatchingListenerState>,
+    pub(crate) matching_listener_counter: usize,
 }
 
 impl Tables {
@@ -64,15 +91,19 @@ impl Tables {
         pid: PeerId,
         whatami: WhatAmI,
         hlc: Option<Arc<HLC>>,
-        _queries_default_timeout: Duration,
+        queries_default_timeout: Duration,
     ) -> Self {
         Tables {
             pid,
             whatami,
             face_counter: 0,
             hlc,
-            // timer: Timer::new(true),
-            // queries_default_timeout,
+            timer: Timer::new(true),
+            queries_default_timeout,
+            acl: None,
+            admin_config_policy: AdminConfigPolicy::default(),
+            admin_config_handler: None,
+            downsampling: None,
             root_res: Resource::root(),
             faces: HashMap::new(),
             pull_caches_lock: Mutex::new(()),
@@ -80,14 +111,48 @@ impl Tables {
             peer_subs: HashSet::new(),
             router_qabls: HashSet::new(),
             peer_qabls: HashSet::new(),
+            router_tokens: HashSet::new(),
+            peer_tokens: HashSet::new(),
             routers_net: None,
             peers_net: None,
             shared_nodes: vec![],
             routers_trees_task: None,
             peers_trees_task: None,
+            matching_listeners: HashMap::new(),
+            matching_listener_counter: 0,
         }
     }
 
+    /// Set the access control policy enforced on the declarations and messages received from remote faces.
+    pub fn set_access_control(&mut self, acl: Option<AccessControl>) {
+        self.acl = acl;
+    }
+
+    /// The access control policy enforced on the declarations and messages received from remote faces, if any.
+    pub fn access_control(&self) -> Option<&AccessControl> {
+        self.acl.as_ref()
+    }
+
+    /// Set the policy restricting the configuration changes sent to the adminspace of this router.
+    pub fn set_admin_config_policy(&mut self, policy: AdminConfigPolicy) {
+        self.admin_config_policy = policy;
+    }
+
+    /// Set the handler applying the configuration changes accepted by the adminspace policy.
+    pub fn set_admin_config_handler(&mut self, handler: Arc<dyn AdminConfigHandler>) {
+        self.admin_config_handler = Some(handler);
+    }
+
+    /// Set the downsampling policy enforced on the publications routed to remote faces.
+    pub fn set_downsampling(&mut self, downsampling: Option<Downsampling>) {
+        self.downsampling = downsampling;
+    }
+
+    /// The downsampling policy enforced on the publications routed to remote faces, if any.
+    pub fn downsampling(&self) -> Option<&Downsampling> {
+        self.downsampling.as_ref()
+    }
+
     #[doc(hidden)]
     pub fn _get_root(&self) -> &Arc<Resource> {
         &self.root_res
@@ -97,6 +162,29 @@ impl Tables {
         Resource::print_tree(&self.root_res)
     }
 
+    /// The number of resources in the routing tables.
+    pub fn resources_count(&self) -> usize {
+        fn count(res: &Arc<Resource>) -> usize {
+            1 + res.childs.values().map(count).sum::<usize>()
+        }
+        count(&self.root_res) - 1
+    }
+
+    /// The sizes of the routing state of each face.
+    pub fn faces_stats(&self) -> Vec<FaceStats> {
+        self.faces
+            .values()
+            .map(|face| FaceStats {
+                id: face.id,
+                pid: face.pid,
+                whatami: face.whatami,
+                subscribers: face.remote_subs.len(),
+                queryables: face.remote_qabls.len(),
+                pending_queries: face.pending_queries.len(),
+            })
+            .collect()
+    }
+
     #[inline]
     #[allow(clippy::trivially_copy_pass_by_ref)]
     pub(crate) fn get_mapping<'a>(
@@ -130,29 +218,55 @@ impl Tables {
         whatami: WhatAmI,
         primitives: Arc<dyn Primitives + Send + Sync>,
         link_id: usize,
+        auth_ids: Vec<AuthId>,
+        kind: FaceKind,
     ) -> Weak<FaceState> {
         let fid = self.face_counter;
         self.face_counter += 1;
         let mut newface = self
             .faces
             .entry(fid)
-            .or_insert_with(|| FaceState::new(fid, pid, whatami, primitives.clone(), link_id))
+            .or_insert_with(|| {
+                FaceState::new(
+                    fid,
+                    pid,
+                    whatami,
+                    primitives.clone(),
+                    link_id,
+                    auth_ids,
+                    kind,
+                )
+            })
             .clone();
         log::debug!("New {}", newface);
 
         pubsub_new_face(self, &mut newface);
         queries_new_face(self, &mut newface);
+        tokens_new_face(self, &mut newface);
 
         Arc::downgrade(&newface)
     }
 
+    /// Open a face for primitives of this process (e.g. a session or the adminspace).
+    /// Such local faces are not subject to the access control policy.
     pub fn open_face(
         &mut self,
         pid: PeerId,
         whatami: WhatAmI,
         primitives: Arc<dyn Primitives + Send + Sync>,
     ) -> Weak<FaceState> {
-        self.open_net_face(pid, whatami, primitives, 0)
+        self.open_net_face(pid, whatami, primitives, 0, vec![], FaceKind::Local)
+    }
+
+    #[doc(hidden)]
+    pub fn _open_remote_face(
+        &mut self,
+        pid: PeerId,
+        whatami: WhatAmI,
+        primitives: Arc<dyn Primitives + Send + Sync>,
+        auth_ids: Vec<AuthId>,
+    ) -> Weak<FaceState> {
+        self.open_net_face(pid, whatami, primitives, 0, auth_ids, FaceKind::Unicast)
     }
 
     pub fn close_face(&mut self, face: &Weak<FaceState>) {
@@ -160,6 +274,8 @@ impl Tables {
             Some(mut face) => {
                 log::debug!("Close {}", face);
                 finalize_pending_queries(self, &mut face);
+                self.matching_listeners
+                    .retain(|_, listener| listener.face != face.id);
 
                 let mut face_clone = face.clone();
                 let face = get_mut_unchecked(&mut face);
@@ -183,6 +299,11 @@ impl Tables {
                     undeclare_client_queryable(self, &mut face_clone, &mut res, kind);
                     Resource::clean(&mut res);
                 }
+                for mut res in face.remote_tokens.drain() {
+                    get_mut_unchecked(&mut res).session_ctxs.remove(&face.id);
+                    undeclare_client_token(self, &mut face_clone, &mut res);
+                    Resource::clean(&mut res);
+                }
                 self.faces.remove(&face.id);
             }
             None => log::error!("Face already closed!"),
@@ -231,6 +352,7 @@ impl Tables {
                 log::trace!("Compute routes");
                 pubsub_tree_change(&mut tables, &new_childs, net_type);
                 queries_tree_change(&mut tables, &new_childs, net_type);
+                tokens_tree_change(&mut tables, &new_childs, net_type);
 
                 log::trace!("Computations completed");
                 match net_type {
@@ -342,20 +464,35 @@ impl Router {
             );
         }
 
+        let mut face = tables
+            .open_net_face(
+                transport.get_pid().unwrap(),
+                whatami,
+                Arc::new(Mux::new(transport.clone())),
+                link_id,
+                transport.get_auth_ids().unwrap_or_default(),
+                FaceKind::Unicast,
+            )
+            .upgrade()
+            .unwrap();
+        if let Some(downsampling) = &tables.downsampling {
+            let addrs: Vec<IpAddr> = transport
+                .get_links()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|link| link.src.address().parse::<SocketAddr>().ok())
+                .map(|addr| addr.ip())
+                .collect();
+            get_mut_unchecked(&mut face).downsampler =
+                downsampling.face_downsampler(&face, &addrs, &tables.timer);
+        }
+
         let handler = Arc::new(LinkStateInterceptor::new(
             transport.clone(),
             self.tables.clone(),
             Face {
                 tables: self.tables.clone(),
-                state: tables
-                    .open_net_face(
-                        transport.get_pid().unwrap(),
-                        whatami,
-                        Arc::new(Mux::new(transport)),
-                        link_id,
-                    )
-                    .upgrade()
-                    .unwrap(),
+                state: face,
             },
         ));
 
@@ -372,6 +509,36 @@ impl Router {
         }
         Ok(handler)
     }
+
+    pub fn new_transport_multicast(
+        &self,
+        transport: TransportMulticast,
+        redeclare_delay: Duration,
+    ) -> ZResult<Arc<McastTransportHandler>> {
+        let mut tables = zwrite!(self.tables);
+        let group = Arc::new(McastGroup::new(tables.queries_default_timeout));
+        // The face of the group stands for several remote peers: it gets an id of its own,
+        // distinct from the id of this router and of the peers, and is never a local face.
+        let face = Face {
+            tables: self.tables.clone(),
+            state: tables
+                .open_net_face(
+                    PeerId::rand(),
+                    WhatAmI::Client,
+                    Arc::new(McastGroupPrimitives::new(transport, group.clone())),
+                    0,
+                    vec![],
+                    FaceKind::Multicast,
+                )
+                .upgrade()
+                .unwrap(),
+        };
+        Ok(Arc::new(McastTransportHandler::new(
+            face,
+            group,
+            redeclare_delay,
+        )))
+    }
 }
 
 pub struct LinkStateInterceptor {
@@ -414,6 +581,7 @@ impl TransportPeerEventHandler for LinkStateInterceptor {
                                     &removed_node.pid,
                                     WhatAmI::Router,
                                 );
+                                tokens_remove_node(&mut tables, &removed_node.pid, WhatAmI::Router);
                             }
 
                             tables.shared_nodes = shared_nodes(
@@ -434,6 +602,7 @@ impl TransportPeerEventHandler for LinkStateInterceptor {
                             {
                                 pubsub_remove_node(&mut tables, &removed_node.pid, WhatAmI::Peer);
                                 queries_remove_node(&mut tables, &removed_node.pid, WhatAmI::Peer);
+                                tokens_remove_node(&mut tables, &removed_node.pid, WhatAmI::Peer);
                             }
 
                             if tables.whatami == WhatAmI::Router {
@@ -472,6 +641,7 @@ impl TransportPeerEventHandler for LinkStateInterceptor {
                         {
                             pubsub_remove_node(&mut tables, &removed_node.pid, WhatAmI::Router);
                             queries_remove_node(&mut tables, &removed_node.pid, WhatAmI::Router);
+                            tokens_remove_node(&mut tables, &removed_node.pid, WhatAmI::Router);
                         }
 
                         tables.shared_nodes = shared_nodes(
@@ -489,6 +659,7 @@ impl TransportPeerEventHandler for LinkStateInterceptor {
                         {
                             pubsub_remove_node(&mut tables, &removed_node.pid, WhatAmI::Peer);
                             queries_remove_node(&mut tables, &removed_node.pid, WhatAmI::Peer);
+                            tokens_remove_node(&mut tables, &removed_node.pid, WhatAmI::Peer);
                         }
 
                         if tables.whatami == WhatAmI::Router {
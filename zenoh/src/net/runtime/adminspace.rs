@@ -10,6 +10,7 @@
 //
 // Contributors:
 //   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+use super::routing::acl::AdminConfigHandler;
 use super::routing::face::Face;
 use super::Runtime;
 use crate::plugins::PluginsManager;
@@ -23,7 +24,8 @@ use std::collections::HashMap;
 use std::sync::Mutex;
 use zenoh_buffers::{SplitBuffer, ZBuf};
 use zenoh_config::ValidatedMap;
-use zenoh_protocol::proto::{data_kind, DataInfo, RoutingContext};
+use zenoh_core::{zread, zwrite};
+use zenoh_protocol::proto::{data_kind, Attachment, DataInfo, QueryBody, RoutingContext};
 use zenoh_protocol_core::{
     key_expr, queryable::EVAL, Channel, CongestionControl, ConsolidationStrategy, Encoding,
     KeyExpr, PeerId, QueryTarget, QueryableInfo, SubInfo, ZInt, EMPTY_EXPR_ID,
@@ -69,6 +71,12 @@ impl AdminSpace {
                 router_data(context, key, args).boxed()
             })),
         );
+        handlers.insert(
+            [&root_key, "/metrics"].concat(),
+            Arc::new(Box::new(|context, key, args| {
+                metrics_data(context, key, args).boxed()
+            })),
+        );
         handlers.insert(
             [&root_key, "/linkstate/routers"].concat(),
             Arc::new(Box::new(|context, key, args| {
@@ -208,6 +216,8 @@ impl AdminSpace {
             None,
         );
 
+        zwrite!(runtime.router.tables).set_admin_config_handler(admin.clone());
+        // The subscription attracts the configuration changes to this router
         primitives.decl_subscriber(
             &[&root_key, "/config/**"].concat().into(),
             &SubInfo::default(),
@@ -228,6 +238,117 @@ impl AdminSpace {
     }
 }
 
+impl AdminConfigHandler for AdminSpace {
+    fn config_change(&self, who: &str, key_expr: &str, payload: ZBuf, data_info: Option<DataInfo>) {
+        if let Some(key) =
+            key_expr.strip_prefix(&format!("/@/router/{}/config/", &self.context.pid_str))
+        {
+            let old_value = self.audit_value(key);
+            if let Some(DataInfo {
+                kind: Some(data_kind::DELETE),
+                ..
+            }) = data_info
+            {
+                log::trace!(
+                    "Deleting conf value /@/router/{}/config/{}",
+                    &self.context.pid_str,
+                    key
+                );
+                match self.context.runtime.config.remove(key) {
+                    Ok(()) => log::info!(
+                        "Config change by {}: deleted {} (old value: {}, new value: {})",
+                        who,
+                        key,
+                        old_value,
+                        self.audit_value(key)
+                    ),
+                    Err(e) => log::error!("Error deleting conf value {}: {}", key_expr, e),
+                }
+            } else {
+                match std::str::from_utf8(&payload.contiguous()) {
+                    Ok(json) => {
+                        log::trace!(
+                            "Insert conf value /@/router/{}/config/{}:{}",
+                            &self.context.pid_str,
+                            key,
+                            json
+                        );
+                        match self
+                            .context
+                            .runtime
+                            .config
+                            .mutable()
+                            .insert_json5(key, json)
+                        {
+                            Ok(()) => log::info!(
+                                "Config change by {}: set {} (old value: {}, new value: {})",
+                                who,
+                                key,
+                                old_value,
+                                self.audit_value(key)
+                            ),
+                            Err(e) => error!(
+                                "Error inserting conf value /@/router/{}/config/{}:{} - {}",
+                                &self.context.pid_str, key, json, e
+                            ),
+                        }
+                    }
+                    Err(e) => error!(
+                        "Received non utf8 conf value on /@/router/{}/config/{} : {}",
+                        &self.context.pid_str, key, e
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl AdminSpace {
+    // The current value of the configuration `key` as logged in the audit entries,
+    // with private values and passwords hidden.
+    fn audit_value(&self, key: &str) -> String {
+        match self.context.runtime.config.get_json(key) {
+            Ok(json) => match serde_json::from_str::<serde_json::Value>(&json) {
+                Ok(_)
+                    if key.rsplit('/').next() == Some("password")
+                        || key.starts_with("transport/auth/usrpwd/users/") =>
+                {
+                    "\"***\"".to_string()
+                }
+                Ok(mut value) => {
+                    zenoh_config::sift_privates(&mut value);
+                    hide_passwords(&mut value);
+                    value.to_string()
+                }
+                Err(_) => json,
+            },
+            Err(_) => "null".to_string(),
+        }
+    }
+}
+
+fn hide_passwords(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Array(a) => a.iter_mut().for_each(hide_passwords),
+        serde_json::Value::Object(o) => {
+            for (k, v) in o.iter_mut() {
+                if k == "password" && !v.is_null() {
+                    *v = json!("***");
+                } else if k == "users" && v.is_object() {
+                    // the user-password authenticator's users, as "<user>": "<password>"
+                    v.as_object_mut()
+                        .unwrap()
+                        .values_mut()
+                        .for_each(|p| *p = json!("***"));
+                } else {
+                    hide_passwords(v);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
 impl Primitives for AdminSpace {
     fn decl_resource(&self, expr_id: ZInt, key_expr: &KeyExpr) {
         trace!("recv Resource {} {:?}", expr_id, key_expr);
@@ -283,6 +404,14 @@ impl Primitives for AdminSpace {
         trace!("recv Forget Queryable {:?}", _key_expr);
     }
 
+    fn decl_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        trace!("recv Token {:?}", _key_expr);
+    }
+
+    fn forget_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        trace!("recv Forget Token {:?}", _key_expr);
+    }
+
     fn send_data(
         &self,
         key_expr: &KeyExpr,
@@ -291,6 +420,7 @@ impl Primitives for AdminSpace {
         congestion_control: CongestionControl,
         data_info: Option<DataInfo>,
         _routing_context: Option<RoutingContext>,
+        _attachment: Option<Attachment>,
     ) {
         trace!(
             "recv Data {:?} {:?} {:?} {:?} {:?}",
@@ -301,52 +431,8 @@ impl Primitives for AdminSpace {
             data_info,
         );
 
-        if let Some(key) = key_expr
-            .as_str()
-            .strip_prefix(&format!("/@/router/{}/config/", &self.context.pid_str))
-        {
-            if let Some(DataInfo {
-                kind: Some(data_kind::DELETE),
-                ..
-            }) = data_info
-            {
-                log::trace!(
-                    "Deleting conf value /@/router/{}/config/{}",
-                    &self.context.pid_str,
-                    key
-                );
-                if let Err(e) = self.context.runtime.config.remove(key) {
-                    log::error!("Error deleting conf value {}: {}", key_expr, e)
-                }
-            } else {
-                match std::str::from_utf8(&payload.contiguous()) {
-                    Ok(json) => {
-                        log::trace!(
-                            "Insert conf value /@/router/{}/config/{}:{}",
-                            &self.context.pid_str,
-                            key,
-                            json
-                        );
-                        if let Err(e) = self
-                            .context
-                            .runtime
-                            .config
-                            .mutable()
-                            .insert_json5(key, json)
-                        {
-                            error!(
-                                "Error inserting conf value /@/router/{}/config/{}:{} - {}",
-                                &self.context.pid_str, key, json, e
-                            );
-                        }
-                    }
-                    Err(e) => error!(
-                        "Received non utf8 conf value on /@/router/{}/config/{} : {}",
-                        &self.context.pid_str, key, e
-                    ),
-                }
-            }
-        }
+        // The configuration changes are applied by `AdminConfigHandler::config_change`, called
+        // by the routing layer with the identity of their issuer once it authorized them.
     }
 
     fn send_query(
@@ -356,7 +442,9 @@ impl Primitives for AdminSpace {
         qid: ZInt,
         target: QueryTarget,
         _consolidation: ConsolidationStrategy,
+        _body: Option<QueryBody>,
         _routing_context: Option<RoutingContext>,
+        _attachment: Option<Attachment>,
     ) {
         trace!(
             "recv Query {:?} {:?} {:?} {:?}",
@@ -400,9 +488,11 @@ impl Primitives for AdminSpace {
                         qid,
                         EVAL,
                         pid,
+                        false,
                         key.into(),
                         Some(data_info),
                         payload,
+                        None,
                     );
                 },
             ));
@@ -420,9 +510,11 @@ impl Primitives for AdminSpace {
                             qid,
                             EVAL,
                             pid,
+                            false,
                             key.into(),
                             Some(data_info),
                             payload.into(),
+                            None,
                         );
                     }
                 });
@@ -439,15 +531,18 @@ impl Primitives for AdminSpace {
         qid: ZInt,
         replier_kind: ZInt,
         replier_id: PeerId,
+        is_error: bool,
         key_expr: KeyExpr,
         info: Option<DataInfo>,
         payload: ZBuf,
+        _attachment: Option<Attachment>,
     ) {
         trace!(
-            "recv ReplyData {:?} {:?} {:?} {:?} {:?} {:?}",
+            "recv ReplyData {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
             qid,
             replier_kind,
             replier_id,
+            is_error,
             key_expr,
             info,
             payload
@@ -517,6 +612,10 @@ pub async fn router_data(
                 |_| Vec::new(),
                 |links| links.iter().map(|link| link.dst.to_string()).collect()
             ),
+            "auth_ids": transport.get_auth_ids().map_or_else(
+                |_| Vec::new(),
+                |ids| ids.iter().map(|id| id.to_string()).collect()
+            ),
         });
         #[cfg(feature = "stats")]
         {
@@ -542,13 +641,36 @@ pub async fn router_data(
         .map(transport_to_json)
         .collect();
 
-    let json = json!({
+    let mut json = json!({
         "pid": context.pid_str,
         "version": context.version,
         "locators": locators,
         "sessions": transports,
         "plugins": plugins,
     });
+
+    // access control info
+    if let Some(acl) = zread!(context.runtime.router.tables).access_control() {
+        let denials: serde_json::Map<String, serde_json::Value> = acl
+            .denials()
+            .into_iter()
+            .map(|(action, count)| (action.to_string(), json!(count)))
+            .collect();
+        json.as_object_mut()
+            .unwrap()
+            .insert("access_control".to_string(), json!({ "denials": denials }));
+    }
+    // downsampling info
+    if let Some(downsampling) = zread!(context.runtime.router.tables).downsampling() {
+        let rules: Vec<serde_json::Value> = downsampling
+            .drops()
+            .into_iter()
+            .map(|(key_exprs, drops)| json!({ "key_exprs": key_exprs, "drops": drops }))
+            .collect();
+        json.as_object_mut()
+            .unwrap()
+            .insert("downsampling".to_string(), json!({ "rules": rules }));
+    }
     log::trace!("AdminSpace router_data: {:?}", json);
     (
         ZBuf::from(json.to_string().as_bytes().to_vec()),
@@ -556,6 +678,229 @@ pub async fn router_data(
     )
 }
 
+/// Accumulates metric samples and renders them in the OpenMetrics text format.
+#[derive(Default)]
+struct MetricsWriter {
+    families: Vec<MetricFamily>,
+}
+
+struct MetricFamily {
+    name: String,
+    kind: &'static str,
+    help: &'static str,
+    samples: Vec<String>,
+}
+
+impl MetricsWriter {
+    fn gauge(&mut self, name: &str, help: &'static str, labels: &[(&str, &str)], value: usize) {
+        self.add(name, "gauge", help, labels, value)
+    }
+
+    fn counter(&mut self, name: &str, help: &'static str, labels: &[(&str, &str)], value: usize) {
+        self.add(name, "counter", help, labels, value)
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        kind: &'static str,
+        help: &'static str,
+        labels: &[(&str, &str)],
+        value: usize,
+    ) {
+        let idx = match self.families.iter().position(|f| f.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.families.push(MetricFamily {
+                    name: name.to_string(),
+                    kind,
+                    help,
+                    samples: vec![],
+                });
+                self.families.len() - 1
+            }
+        };
+        let labels = labels
+            .iter()
+            .map(|(k, v)| {
+                let v = v
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                format!("{}=\"{}\"", k, v)
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let suffix = if kind == "counter" { "_total" } else { "" };
+        self.families[idx]
+            .samples
+            .push(format!("{}{}{{{}}} {}", name, suffix, labels, value));
+    }
+
+    fn finish(self) -> String {
+        let mut text = String::new();
+        for family in self.families {
+            text.push_str(&format!("# TYPE {} {}\n", family.name, family.kind));
+            text.push_str(&format!("# HELP {} {}\n", family.name, family.help));
+            for sample in family.samples {
+                text.push_str(&sample);
+                text.push('\n');
+            }
+        }
+        text.push_str("# EOF\n");
+        text
+    }
+}
+
+pub async fn metrics_data(
+    context: &AdminContext,
+    _key: &KeyExpr<'_>,
+    _selector: &str,
+) -> (ZBuf, Encoding) {
+    let transport_mgr = context.runtime.manager().clone();
+    let whatami = context.runtime.whatami.to_string();
+    let node = [
+        ("pid", context.pid_str.as_str()),
+        ("whatami", whatami.as_str()),
+    ];
+    let mut metrics = MetricsWriter::default();
+
+    // transports info
+    for transport in transport_mgr.get_transports() {
+        let peer = transport
+            .get_pid()
+            .map_or_else(|_| "unknown".to_string(), |p| p.to_string());
+        let peer_whatami = transport
+            .get_whatami()
+            .map_or_else(|_| "unknown".to_string(), |w| w.to_string());
+        let labels = [
+            node[0],
+            node[1],
+            ("peer", peer.as_str()),
+            ("peer_whatami", peer_whatami.as_str()),
+        ];
+        let links = transport.get_links().unwrap_or_default();
+        metrics.gauge(
+            "zenoh_transport_links",
+            "Number of links of the transport.",
+            &labels,
+            links.len(),
+        );
+        for link in links {
+            let (src, dst) = (link.src.to_string(), link.dst.to_string());
+            let mut link_labels = labels.to_vec();
+            link_labels.extend([("src", src.as_str()), ("dst", dst.as_str())]);
+            metrics.gauge(
+                "zenoh_transport_link",
+                "Links of the transport, always 1.",
+                &link_labels,
+                1,
+            );
+        }
+        #[cfg(feature = "stats")]
+        if let Ok(stats) = transport.get_stats() {
+            transport_stats_metrics(&mut metrics, &labels, json!(stats));
+        }
+    }
+    for transport in transport_mgr.get_transports_multicast() {
+        let group = transport
+            .get_link()
+            .map_or_else(|_| "unknown".to_string(), |l| l.dst.to_string());
+        let labels = [node[0], node[1], ("group", group.as_str())];
+        metrics.gauge(
+            "zenoh_transport_peers",
+            "Number of peers of the multicast transport.",
+            &labels,
+            transport.get_peers().map_or(0, |peers| peers.len()),
+        );
+        #[cfg(feature = "stats")]
+        if let Ok(stats) = transport.get_stats() {
+            transport_stats_metrics(&mut metrics, &labels, json!(stats));
+        }
+    }
+
+    // routing tables info
+    {
+        let tables = zread!(context.runtime.router.tables);
+        metrics.gauge(
+            "zenoh_routing_resources",
+            "Number of resources in the routing tables.",
+            &node,
+            tables.resources_count(),
+        );
+        if let Some(downsampling) = tables.downsampling() {
+            for (key_exprs, drops) in downsampling.drops() {
+                let key_exprs = key_exprs.join(",");
+                let mut labels = node.to_vec();
+                labels.push(("key_exprs", key_exprs.as_str()));
+                metrics.counter(
+                    "zenoh_downsampling_drops",
+                    "Number of publications dropped by the downsampling rule.",
+                    &labels,
+                    drops,
+                );
+            }
+        }
+        for face in tables.faces_stats() {
+            let (id, peer, peer_whatami) = (
+                face.id.to_string(),
+                face.pid.to_string(),
+                face.whatami.to_string(),
+            );
+            let labels = [
+                node[0],
+                node[1],
+                ("face", id.as_str()),
+                ("peer", peer.as_str()),
+                ("peer_whatami", peer_whatami.as_str()),
+            ];
+            metrics.gauge(
+                "zenoh_routing_subscribers",
+                "Number of subscribers declared by the face.",
+                &labels,
+                face.subscribers,
+            );
+            metrics.gauge(
+                "zenoh_routing_queryables",
+                "Number of queryables declared by the face.",
+                &labels,
+                face.queryables,
+            );
+            metrics.gauge(
+                "zenoh_routing_pending_queries",
+                "Number of queries routed to the face waiting for their final reply.",
+                &labels,
+                face.pending_queries,
+            );
+        }
+    }
+
+    (
+        ZBuf::from(metrics.finish().into_bytes()),
+        Encoding::from("application/openmetrics-text; version=1.0.0; charset=utf-8"),
+    )
+}
+
+#[cfg(feature = "stats")]
+fn transport_stats_metrics(
+    metrics: &mut MetricsWriter,
+    labels: &[(&str, &str)],
+    stats: serde_json::Value,
+) {
+    if let serde_json::Value::Object(stats) = stats {
+        for (name, value) in stats {
+            if let Some(value) = value.as_u64() {
+                metrics.counter(
+                    &format!("zenoh_transport_{}", name),
+                    "Transport statistics counter.",
+                    labels,
+                    value as usize,
+                );
+            }
+        }
+    }
+}
+
 pub async fn linkstate_routers_data(
     context: &AdminContext,
     _key: &KeyExpr<'_>,
@@ -659,3 +1004,27 @@ fn with_extended_string<R, F: FnMut(&mut String) -> R>(
     prefix.truncate(prefix_len);
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MetricsWriter;
+
+    #[test]
+    fn metrics_openmetrics_format() {
+        let mut metrics = MetricsWriter::default();
+        metrics.gauge("zenoh_a", "A gauge.", &[("pid", "1"), ("dst", "x\"y")], 2);
+        metrics.counter("zenoh_b", "A counter.", &[("pid", "1")], 3);
+        metrics.gauge("zenoh_a", "A gauge.", &[("pid", "2"), ("dst", "z")], 4);
+        assert_eq!(
+            metrics.finish(),
+            "# TYPE zenoh_a gauge\n\
+             # HELP zenoh_a A gauge.\n\
+             zenoh_a{pid=\"1\",dst=\"x\\\"y\"} 2\n\
+             zenoh_a{pid=\"2\",dst=\"z\"} 4\n\
+             # TYPE zenoh_b counter\n\
+             # HELP zenoh_b A counter.\n\
+             zenoh_b_total{pid=\"1\"} 3\n\
+             # EOF\n"
+        );
+    }
+}
@@ -15,6 +15,8 @@ mod adminspace;
 pub mod orchestrator;
 
 use super::routing;
+use super::routing::acl::{AccessControl, AdminConfigPolicy};
+use super::routing::downsampling::Downsampling;
 use super::routing::pubsub::full_reentrant_route_data;
 use super::routing::router::{LinkStateInterceptor, Router};
 use crate::config::{Config, Notifier};
@@ -29,8 +31,8 @@ use std::time::Duration;
 use stop_token::future::FutureExt;
 use stop_token::{StopSource, TimedOutError};
 use uhlc::{HLCBuilder, HLC};
-use zenoh_core::bail;
 use zenoh_core::Result as ZResult;
+use zenoh_core::{bail, zwrite};
 use zenoh_link::{EndPoint, Link};
 use zenoh_protocol;
 use zenoh_protocol::core::{PeerId, WhatAmI};
@@ -108,6 +110,9 @@ impl Runtime {
             hlc.clone(),
             Duration::from_millis(queries_default_timeout),
         ));
+        zwrite!(router.tables).set_access_control(AccessControl::from_config(&config)?);
+        zwrite!(router.tables).set_downsampling(Downsampling::from_config(&config)?);
+        zwrite!(router.tables).set_admin_config_policy(AdminConfigPolicy::from_config(&config)?);
 
         let handler = Arc::new(RuntimeTransportEventHandler {
             runtime: std::sync::RwLock::new(None),
@@ -153,6 +158,40 @@ impl Runtime {
                             log::error!("Error updating peers : {}", e);
                         }
                     }
+                    // The TLS configuration is also used by QUIC
+                    let key = event.trim_start_matches('/');
+                    if key.starts_with("transport/link/tls")
+                        || "transport/link/tls".starts_with(key)
+                    {
+                        let config = runtime2.config.lock().clone();
+                        if let Err(e) = runtime2.manager().reload_listeners(&config).await {
+                            log::error!("Error reloading listeners : {}", e);
+                        }
+                    }
+                    if key.starts_with("adminspace") || "adminspace".starts_with(key) {
+                        let policy = match AdminConfigPolicy::from_config(&runtime2.config.lock()) {
+                            Ok(policy) => policy,
+                            Err(e) => {
+                                log::error!("Error updating adminspace policy : {}", e);
+                                AdminConfigPolicy::read_only()
+                            }
+                        };
+                        zwrite!(runtime2.router.tables).set_admin_config_policy(policy);
+                    }
+                    if key.starts_with("access_control") || "access_control".starts_with(key) {
+                        // An invalid access control configuration keeps the current one enforced
+                        let acl = AccessControl::from_config(&runtime2.config.lock());
+                        match acl {
+                            Ok(acl) => zwrite!(runtime2.router.tables).set_access_control(acl),
+                            Err(e) => log::error!("Error updating access control : {}", e),
+                        }
+                    }
+                    if key.starts_with("transport/auth") || "transport/auth".starts_with(key) {
+                        let config = runtime2.config.lock().clone();
+                        if let Err(e) = runtime2.manager().reload_authenticators(&config).await {
+                            log::error!("Error reloading authenticators : {}", e);
+                        }
+                    }
                 }
             }
         });
@@ -218,10 +257,27 @@ impl TransportEventHandler for RuntimeTransportEventHandler {
 
     fn new_multicast(
         &self,
-        _transport: TransportMulticast,
+        transport: TransportMulticast,
     ) -> ZResult<Arc<dyn TransportMulticastEventHandler>> {
-        // @TODO
-        unimplemented!();
+        match zread!(self.runtime).as_ref() {
+            Some(runtime) => {
+                let join_interval = runtime
+                    .config
+                    .lock()
+                    .transport()
+                    .multicast()
+                    .join_interval()
+                    .unwrap_or_else(|| {
+                        zenoh_cfg_properties::config::ZN_JOIN_INTERVAL_DEFAULT
+                            .parse()
+                            .unwrap()
+                    });
+                Ok(runtime
+                    .router
+                    .new_transport_multicast(transport, Duration::from_millis(join_interval))?)
+            }
+            None => bail!("Runtime not yet ready!"),
+        }
     }
 }
 
@@ -246,6 +302,7 @@ impl TransportPeerEventHandler for RuntimeSession {
                     data.data_info,
                     data.payload,
                     msg.routing_context,
+                    msg.attachment,
                 );
                 return Ok(());
             } else {
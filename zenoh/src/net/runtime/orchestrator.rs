@@ -115,7 +115,17 @@ impl Runtime {
     }
 
     async fn start_peer(&self) -> ZResult<()> {
-        let (listeners, peers, scouting, peers_autoconnect, addr, ifaces, delay) = {
+        let (
+            listeners,
+            peers,
+            mcast_listeners,
+            mcast_peers,
+            scouting,
+            peers_autoconnect,
+            addr,
+            ifaces,
+            delay,
+        ) = {
             let guard = &self.config.lock();
             let listeners = if guard.listen().endpoints().is_empty() {
                 vec![PEER_DEFAULT_LISTENER.parse().unwrap()]
@@ -126,6 +136,8 @@ impl Runtime {
             (
                 listeners,
                 peers,
+                guard.transport().multicast().listen().clone(),
+                guard.transport().multicast().connect().clone(),
                 guard.scouting().multicast().enabled().unwrap_or(true),
                 guard
                     .scouting()
@@ -157,6 +169,13 @@ impl Runtime {
             self.spawn(async move { this.peer_connector(peer).await });
         }
 
+        self.open_mcast_transports(&mcast_listeners).await?;
+
+        for endpoint in mcast_peers {
+            let this = self.clone();
+            self.spawn(async move { this.mcast_connector(endpoint).await });
+        }
+
         if scouting {
             let ifaces = Runtime::get_interfaces(&ifaces);
             let mcast_socket = Runtime::bind_mcast_port(&addr, &ifaces).await?;
@@ -328,6 +347,23 @@ impl Runtime {
         Ok(())
     }
 
+    async fn open_mcast_transports(&self, endpoints: &[EndPoint]) -> ZResult<()> {
+        for endpoint in endpoints {
+            match self
+                .manager()
+                .open_transport_multicast(endpoint.clone())
+                .await
+            {
+                Ok(_) => log::debug!("Multicast transport opened on {}", endpoint),
+                Err(err) => {
+                    log::error!("Unable to open multicast transport {} : {}", endpoint, err);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_interfaces(names: &str) -> Vec<IpAddr> {
         if names == "auto" {
             let ifaces = zenoh_util::net::get_multicast_interfaces();
@@ -496,6 +532,32 @@ impl Runtime {
         }
     }
 
+    async fn mcast_connector(&self, endpoint: EndPoint) {
+        let mut delay = CONNECTION_RETRY_INITIAL_PERIOD;
+        loop {
+            log::trace!("Trying to open multicast transport {}", endpoint);
+            if self
+                .manager()
+                .open_transport_multicast(endpoint.clone())
+                .await
+                .is_ok()
+            {
+                log::debug!("Multicast transport opened on {}", endpoint);
+                break;
+            }
+            log::debug!(
+                "Unable to open multicast transport {}. Retry in {} ms.",
+                endpoint,
+                delay
+            );
+            async_std::task::sleep(Duration::from_millis(delay)).await;
+            delay *= CONNECTION_RETRY_PERIOD_INCREASE_FACTOR;
+            if delay > CONNECTION_RETRY_MAX_PERIOD {
+                delay = CONNECTION_RETRY_MAX_PERIOD;
+            }
+        }
+    }
+
     pub async fn scout<Fut, F>(
         sockets: &[UdpSocket],
         matcher: WhatAmIMatcher,
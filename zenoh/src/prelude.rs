@@ -34,10 +34,14 @@ use crate::time::{new_reception_timestamp, Timestamp};
 use async_std::sync::Arc;
 use regex::Regex;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::iter::FromIterator;
+use zenoh_buffers::reader::HasReader;
 pub use zenoh_buffers::SplitBuffer;
-use zenoh_core::bail;
+use zenoh_buffers::WBuf;
+use zenoh_core::{bail, zerror};
 pub use zenoh_protocol::io::{WBufCodec, ZBufCodec};
 use zenoh_protocol::proto::DataInfo;
 pub use zenoh_protocol::proto::{MessageReader, MessageWriter};
@@ -328,6 +332,131 @@ impl From<Option<DataInfo>> for SourceInfo {
     }
 }
 
+/// A user attachment: a map of small metadata (e.g. tracing ids or schema versions) sent
+/// alongside the payload of a publication, a query or a reply.
+///
+/// # Examples
+/// ```
+/// use zenoh::prelude::*;
+///
+/// let mut attachment = Attachment::new();
+/// attachment.insert("trace-id", "4bf92f3577b34da6");
+/// assert_eq!(attachment.get("trace-id"), Some(&b"4bf92f3577b34da6"[..]));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attachment(HashMap<String, Vec<u8>>);
+
+impl Attachment {
+    /// Creates an empty Attachment.
+    #[inline]
+    pub fn new() -> Self {
+        Attachment(HashMap::new())
+    }
+
+    /// Inserts a value, returning the previous value of the key if any.
+    pub fn insert<IntoKey, IntoValue>(&mut self, key: IntoKey, value: IntoValue) -> Option<Vec<u8>>
+    where
+        IntoKey: Into<String>,
+        IntoValue: Into<Vec<u8>>,
+    {
+        self.0.insert(key.into(), value.into())
+    }
+
+    /// Gets the value of a key.
+    #[inline]
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.0.get(key).map(Vec::as_slice)
+    }
+
+    /// Removes a key, returning its value if any.
+    #[inline]
+    pub fn remove(&mut self, key: &str) -> Option<Vec<u8>> {
+        self.0.remove(key)
+    }
+
+    /// An iterator over the keys and values of this Attachment.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    /// The number of keys of this Attachment.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if this Attachment has no key.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<IntoKey, IntoValue> FromIterator<(IntoKey, IntoValue)> for Attachment
+where
+    IntoKey: Into<String>,
+    IntoValue: Into<Vec<u8>>,
+{
+    fn from_iter<T: IntoIterator<Item = (IntoKey, IntoValue)>>(iter: T) -> Self {
+        Attachment(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+// The Attachment is encoded in the attachment decorator of the zenoh messages as
+// the number of keys followed by each key and value as arrays of bytes.
+impl From<&Attachment> for zenoh_protocol::proto::Attachment {
+    fn from(attachment: &Attachment) -> Self {
+        let mut wbuf = WBuf::new(64, false);
+        wbuf.write_zint(attachment.0.len() as ZInt);
+        for (key, value) in attachment.0.iter() {
+            wbuf.write_string(key);
+            wbuf.write_bytes_array(value);
+        }
+        zenoh_protocol::proto::Attachment::new(wbuf.into())
+    }
+}
+
+impl TryFrom<&zenoh_protocol::proto::Attachment> for Attachment {
+    type Error = zenoh_core::Error;
+
+    fn try_from(attachment: &zenoh_protocol::proto::Attachment) -> Result<Self, Self::Error> {
+        let mut reader = attachment.buffer.reader();
+        let len = reader
+            .read_zint_as_usize()
+            .ok_or_else(|| zerror!("Invalid attachment"))?;
+        let mut map = HashMap::with_capacity(len.min(64));
+        for _ in 0..len {
+            let key = reader
+                .read_string()
+                .ok_or_else(|| zerror!("Invalid attachment key"))?;
+            let value = reader
+                .read_bytes_array()
+                .ok_or_else(|| zerror!("Invalid attachment value for {}", key))?;
+            map.insert(key, value);
+        }
+        Ok(Attachment(map))
+    }
+}
+
+impl Attachment {
+    // Decode the attachment decorator of a received message, ignoring the ones that are
+    // not user attachments
+    pub(crate) fn decode(attachment: Option<zenoh_protocol::proto::Attachment>) -> Option<Self> {
+        let attachment = attachment?;
+        match Attachment::try_from(&attachment) {
+            Ok(attachment) => Some(attachment),
+            Err(e) => {
+                log::warn!("Ignoring invalid attachment: {}", e);
+                None
+            }
+        }
+    }
+}
+
 /// The kind of a [`Sample`].
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum SampleKind {
@@ -386,6 +515,8 @@ pub struct Sample {
     pub timestamp: Option<Timestamp>,
     // Infos on the source of this Sample.
     pub source_info: SourceInfo,
+    /// The user [`Attachment`] of this Sample.
+    pub attachment: Option<Attachment>,
 }
 
 impl Sample {
@@ -402,6 +533,7 @@ impl Sample {
             kind: SampleKind::default(),
             timestamp: None,
             source_info: SourceInfo::empty(),
+            attachment: None,
         }
     }
 
@@ -422,6 +554,7 @@ impl Sample {
                 kind: data_info.kind.unwrap_or(data_kind::DEFAULT).into(),
                 timestamp: data_info.timestamp,
                 source_info: data_info.into(),
+                attachment: None,
             }
         } else {
             Sample {
@@ -430,12 +563,13 @@ impl Sample {
                 kind: SampleKind::default(),
                 timestamp: None,
                 source_info: SourceInfo::empty(),
+                attachment: None,
             }
         }
     }
 
     #[inline]
-    pub(crate) fn split(self) -> (KeyExpr<'static>, ZBuf, DataInfo) {
+    pub(crate) fn split(self) -> (KeyExpr<'static>, ZBuf, DataInfo, Option<Attachment>) {
         let info = DataInfo {
             kind: None,
             encoding: Some(self.value.encoding),
@@ -447,7 +581,7 @@ impl Sample {
             first_router_id: self.source_info.first_router_id,
             first_router_sn: self.source_info.first_router_sn,
         };
-        (self.key_expr, self.value.payload, info)
+        (self.key_expr, self.value.payload, info, self.attachment)
     }
 
     /// Gets the timestamp of this Sample.
@@ -470,6 +604,13 @@ impl Sample {
         self
     }
 
+    /// Sets the user attachment of this Sample.
+    #[inline]
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachment = Some(attachment);
+        self
+    }
+
     #[inline]
     /// Ensure that an associated Timestamp is present in this Sample.
     /// If not, a new one is created with the current system time and 0x00 as id.
@@ -615,7 +756,9 @@ pub const PROP_STOPTIME: &str = "stoptime";
 ///    It allows to select only some fields within the structure. A new structure with only the selected fields
 ///    will be used in place of the original value.
 ///
-/// _**NOTE**_: _the filters and fragments are not yet supported in current zenoh version._
+/// _**NOTE**_: _the filters and fragments are supported on values encoded with `APP_JSON`, `TEXT_JSON`
+/// and `APP_PROPERTIES` (see [`ValueFilter`](crate::selector::ValueFilter)). Queryables may apply them,
+/// otherwise they are applied on the replies received by [`get`](crate::Session::get)._
 pub struct Selector<'a> {
     /// The part of this selector identifying which keys should be part of the selection.
     /// I.e. all characters before `?`.
@@ -820,6 +963,18 @@ impl<'a> ValueSelector<'a> {
     pub fn has_time_range(&self) -> bool {
         self.properties.contains_key(PROP_STARTTIME) || self.properties.contains_key(PROP_STOPTIME)
     }
+
+    /// Parses the `"starttime"` and `"stoptime"` properties of this `ValueSelector` into a
+    /// [`TimeRange`](crate::selector::TimeRange), if any.
+    pub fn time_range(&self) -> crate::Result<Option<crate::selector::TimeRange>> {
+        crate::selector::TimeRange::from_properties(&self.properties)
+    }
+
+    /// Parses the filter and fragment parts of this `ValueSelector` into a
+    /// [`ValueFilter`](crate::selector::ValueFilter) that can be applied on values.
+    pub fn value_filter(&self) -> crate::selector::ValueFilter {
+        crate::selector::ValueFilter::new(self)
+    }
 }
 
 impl fmt::Display for ValueSelector<'_> {
@@ -977,4 +1132,17 @@ pub trait EntityFactory {
     fn publish<'a, IntoKeyExpr>(&self, key_expr: IntoKeyExpr) -> PublisherBuilder<'a>
     where
         IntoKeyExpr: Into<KeyExpr<'a>>;
+
+    /// Get the liveliness primitives of this session.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap().into_arc();
+    /// let token = session.liveliness().declare_token("/node/1").await.unwrap();
+    /// # })
+    /// ```
+    fn liveliness(&self) -> crate::liveliness::Liveliness<'static>;
 }
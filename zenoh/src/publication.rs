@@ -19,6 +19,9 @@ use crate::prelude::*;
 use crate::subscriber::Reliability;
 use crate::Encoding;
 use crate::SessionRef;
+use async_std::sync::Arc;
+use std::fmt;
+use std::sync::RwLock;
 use zenoh_core::zread;
 use zenoh_core::zresult::ZResult;
 use zenoh_protocol::proto::{data_kind, DataInfo, Options};
@@ -57,6 +60,7 @@ derive_zfuture! {
         pub(crate) congestion_control: CongestionControl,
         pub(crate) priority: Priority,
         pub(crate) local_routing: Option<bool>,
+        pub(crate) attachment: Option<Attachment>,
     }
 }
 
@@ -103,7 +107,14 @@ impl<'a> Writer<'a> {
         self
     }
 
-    fn write(&self, value: Value) -> zenoh_core::Result<()> {
+    /// Attach a user [`Attachment`] to the written data.
+    #[inline]
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachment = Some(attachment);
+        self
+    }
+
+    fn write(&self, value: Value, attachment: Option<&Attachment>) -> zenoh_core::Result<()> {
         log::trace!("write({:?}, [...])", self.key_expr);
         let state = zread!(self.session.state);
         let primitives = state.primitives.as_ref().unwrap().clone();
@@ -132,12 +143,14 @@ impl<'a> Writer<'a> {
             self.congestion_control,
             data_info.clone(),
             None,
+            attachment.map(Into::into),
         );
         self.session.handle_data(
             true,
             &self.key_expr,
             data_info,
             value.payload,
+            attachment.cloned(),
             self.local_routing,
         );
         Ok(())
@@ -150,7 +163,7 @@ impl Runnable for Writer<'_> {
     #[inline]
     fn run(&mut self) -> Self::Output {
         let value = self.value.take().unwrap();
-        self.write(value)
+        self.write(value, self.attachment.as_ref())
     }
 }
 
@@ -189,7 +202,7 @@ use zenoh_core::zresult::Error;
 /// ```
 pub type Publisher<'a> = Writer<'a>;
 
-impl Publisher<'_> {
+impl<'a> Publisher<'a> {
     /// Send a value.
     ///
     /// # Examples
@@ -207,7 +220,93 @@ impl Publisher<'_> {
     where
         IntoValue: Into<Value>,
     {
-        self.write(value.into())
+        self.write(value.into(), self.attachment.as_ref())
+    }
+
+    /// Send a value with a user [`Attachment`], overriding the attachment the publisher
+    /// was built with, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap().into_arc();
+    /// let publisher = session.publish("/key/expression").await.unwrap();
+    /// let attachment: Attachment = vec![("trace-id", "42")].into_iter().collect();
+    /// publisher.send_with_attachment("value", attachment).unwrap();
+    /// # })
+    /// ```
+    #[inline]
+    pub fn send_with_attachment<IntoValue>(
+        &self,
+        value: IntoValue,
+        attachment: Attachment,
+    ) -> zenoh_core::Result<()>
+    where
+        IntoValue: Into<Value>,
+    {
+        self.write(value.into(), Some(&attachment))
+    }
+
+    /// Return the [`MatchingStatus`] of the publisher.
+    ///
+    /// The publisher matches when at least one subscriber receives the data it sends.
+    /// The subscribers of its own session only count when local routing is enabled.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap().into_arc();
+    /// let publisher = session.publish("/key/expression").await.unwrap();
+    /// let matching_subscribers: bool = publisher
+    ///     .matching_status()
+    ///     .unwrap()
+    ///     .matching_subscribers();
+    /// # })
+    /// ```
+    pub fn matching_status(&self) -> zenoh_core::Result<MatchingStatus> {
+        self.session
+            .matching_status(&self.key_expr, self.local_routing)
+    }
+
+    /// Create a [`MatchingListener`] calling the given callback each time the
+    /// [`MatchingStatus`] of the publisher changes.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap().into_arc();
+    /// let publisher = session.publish("/key/expression").await.unwrap();
+    /// let listener = publisher
+    ///     .matching_listener(|status| {
+    ///         if status.matching_subscribers() {
+    ///             println!(">> Publisher has matching subscribers.");
+    ///         } else {
+    ///             println!(">> Publisher has NO MORE matching subscribers.");
+    ///         }
+    ///     })
+    ///     .await
+    ///     .unwrap();
+    /// # })
+    /// ```
+    pub fn matching_listener<MatchingHandler>(
+        &self,
+        handler: MatchingHandler,
+    ) -> MatchingListenerBuilder<'a>
+    where
+        MatchingHandler: FnMut(MatchingStatus) + Send + Sync + 'static,
+    {
+        MatchingListenerBuilder {
+            session: self.session.clone(),
+            key_expr: self.key_expr.clone(),
+            local_routing: self.local_routing,
+            handler: Arc::new(RwLock::new(handler)),
+        }
     }
 }
 
@@ -224,7 +323,7 @@ where
 
     #[inline]
     fn start_send(self: Pin<&mut Self>, item: IntoValue) -> Result<(), Self::Error> {
-        self.write(item.into())
+        self.write(item.into(), self.attachment.as_ref())
     }
 
     #[inline]
@@ -320,3 +419,95 @@ impl<'a> Runnable for PublisherBuilder<'a> {
         Ok(publisher)
     }
 }
+
+/// The matching status of a [`Publisher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchingStatus {
+    pub(crate) matching: bool,
+}
+
+impl MatchingStatus {
+    /// Return true if at least one subscriber matches the [`Publisher`].
+    #[inline]
+    pub fn matching_subscribers(&self) -> bool {
+        self.matching
+    }
+}
+
+/// The callback of a [`MatchingListener`].
+pub type MatchingHandler = dyn FnMut(MatchingStatus) + Send + Sync + 'static;
+
+derive_zfuture! {
+    /// A builder for initializing a [`MatchingListener`].
+    ///
+    /// The result of this builder can be accessed synchronously via [`wait()`](ZFuture::wait())
+    /// or asynchronously via `.await`.
+    #[derive(Clone)]
+    pub struct MatchingListenerBuilder<'a> {
+        pub(crate) session: SessionRef<'a>,
+        pub(crate) key_expr: KeyExpr<'a>,
+        pub(crate) local_routing: Option<bool>,
+        pub(crate) handler: Arc<RwLock<MatchingHandler>>,
+    }
+}
+
+impl fmt::Debug for MatchingListenerBuilder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MatchingListenerBuilder")
+            .field("session", &self.session)
+            .field("key_expr", &self.key_expr)
+            .field("local_routing", &self.local_routing)
+            .finish()
+    }
+}
+
+impl<'a> Runnable for MatchingListenerBuilder<'a> {
+    type Output = ZResult<MatchingListener<'a>>;
+
+    fn run(&mut self) -> Self::Output {
+        log::trace!("matching_listener({:?})", self.key_expr);
+        self.session
+            .declare_matching_listener(&self.key_expr, self.local_routing, self.handler.clone())
+            .map(|id| MatchingListener {
+                session: self.session.clone(),
+                id,
+                alive: true,
+            })
+    }
+}
+
+/// A listener calling a callback each time the [`MatchingStatus`] of a [`Publisher`] changes.
+///
+/// Matching listeners are automatically undeclared when dropped.
+pub struct MatchingListener<'a> {
+    pub(crate) session: SessionRef<'a>,
+    pub(crate) id: usize,
+    pub(crate) alive: bool,
+}
+
+impl MatchingListener<'_> {
+    /// Undeclare a [`MatchingListener`].
+    ///
+    /// Matching listeners are automatically undeclared when dropped, but you may want to use this
+    /// function to handle errors or undeclare the listener asynchronously.
+    #[inline]
+    #[must_use = "ZFutures do nothing unless you `.wait()`, `.await` or poll them"]
+    pub fn undeclare(mut self) -> impl ZFuture<Output = ZResult<()>> {
+        self.alive = false;
+        self.session.undeclare_matching_listener(self.id)
+    }
+}
+
+impl Drop for MatchingListener<'_> {
+    fn drop(&mut self) {
+        if self.alive {
+            let _ = self.session.undeclare_matching_listener(self.id).wait();
+        }
+    }
+}
+
+impl fmt::Debug for MatchingListener<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MatchingListener{{ id:{} }}", self.id)
+    }
+}
@@ -14,17 +14,25 @@
 
 //! Query primitives.
 
-use crate::net::transport::Primitives;
 use crate::prelude::*;
+use crate::selector::ValueFilter;
+use crate::session::SessionState;
 use crate::sync::channel::Receiver;
 use crate::Session;
 use crate::API_REPLY_RECEPTION_CHANNEL_SIZE;
+use async_std::sync::Arc;
+use async_trait::async_trait;
 use flume::r#async::RecvFut;
 use flume::{bounded, Iter, RecvError, RecvTimeoutError, Sender, TryIter, TryRecvError};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::pin::Pin;
 use std::sync::atomic::Ordering;
+use std::sync::RwLock;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use zenoh_collections::{Timed, TimedEvent};
+use zenoh_protocol::proto::{DataInfo, QueryBody};
 use zenoh_sync::{derive_zfuture, zreceiver, Runnable};
 
 /// The [`Queryable`](crate::queryable::Queryable)s that should be target of a [`get`](Session::get).
@@ -116,8 +124,9 @@ impl Default for QueryConsolidation {
 /// Structs returned by a [`get`](Session::get).
 #[derive(Clone, Debug)]
 pub struct Reply {
-    /// The [`Sample`] for this Reply.
-    pub sample: Sample,
+    /// The [`Sample`] for this Reply, or the error [`Value`] sent by the queryable with
+    /// [`Query::reply_err()`](crate::queryable::Query::reply_err).
+    pub sample: Result<Sample, Value>,
     /// The kind of [`Queryable`](crate::queryable::Queryable) that answered this Reply.
     pub replier_kind: ZInt,
     /// The id of the zenoh instance that answered this Reply.
@@ -130,6 +139,31 @@ pub(crate) struct QueryState {
     pub(crate) reception_mode: ConsolidationMode,
     pub(crate) replies: Option<HashMap<String, Reply>>,
     pub(crate) rep_sender: Sender<Reply>,
+    pub(crate) value_filter: ValueFilter,
+}
+
+/// Closes a query that did not receive all its final replies in time.
+pub(crate) struct QueryTimeout {
+    pub(crate) state: Arc<RwLock<SessionState>>,
+    pub(crate) qid: ZInt,
+}
+
+#[async_trait]
+impl Timed for QueryTimeout {
+    async fn run(&mut self) {
+        let query = zwrite!(self.state).queries.remove(&self.qid);
+        if let Some(query) = query {
+            log::debug!("Timeout on query {}! Close it.", self.qid);
+            if query.reception_mode == ConsolidationMode::Full {
+                // Deliver the consolidated replies without stalling the timer.
+                async_std::task::spawn(async move {
+                    for (_, reply) in query.replies.unwrap().into_iter() {
+                        let _ = query.rep_sender.send_async(reply).await;
+                    }
+                });
+            }
+        }
+    }
 }
 
 zreceiver! {
@@ -193,6 +227,7 @@ derive_zfuture! {
     ///     .get("/key/expression?value>1")
     ///     .target(QueryTarget{ kind: queryable::ALL_KINDS, target: Target::All })
     ///     .consolidation(QueryConsolidation::none())
+    ///     .timeout(std::time::Duration::from_secs(2))
     ///     .await
     ///     .unwrap();
     /// # })
@@ -204,6 +239,9 @@ derive_zfuture! {
         pub(crate) target: Option<QueryTarget>,
         pub(crate) consolidation: Option<QueryConsolidation>,
         pub(crate) local_routing: Option<bool>,
+        pub(crate) value: Option<Value>,
+        pub(crate) attachment: Option<Attachment>,
+        pub(crate) timeout: Option<Duration>,
     }
 }
 
@@ -228,6 +266,54 @@ impl<'a, 'b> Getter<'a, 'b> {
         self.local_routing = Some(local_routing);
         self
     }
+
+    /// Send a [`Value`] with the query, e.g. the arguments of a request/response call.
+    ///
+    /// The queryables receiving the query can read it with
+    /// [`Query::value()`](crate::queryable::Query::value).
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap();
+    /// let replies = session
+    ///     .get("/key/expression")
+    ///     .with_value(Value::from("arguments").encoding(Encoding::TEXT_PLAIN))
+    ///     .await
+    ///     .unwrap();
+    /// # })
+    /// ```
+    #[inline]
+    pub fn with_value<IntoValue>(mut self, value: IntoValue) -> Self
+    where
+        IntoValue: Into<Value>,
+    {
+        self.value = Some(value.into());
+        self
+    }
+
+    /// Attach a user [`Attachment`] to the query.
+    ///
+    /// The queryables receiving the query can read it with
+    /// [`Query::attachment()`](crate::queryable::Query::attachment).
+    #[inline]
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachment = Some(attachment);
+        self
+    }
+
+    /// Change the timeout of the query.
+    ///
+    /// When the timeout expires, the query is closed even if some of the
+    /// matching queryables did not send their final reply. The default is
+    /// the `queries_default_timeout` of the configuration.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 }
 
 impl Runnable for Getter<'_, '_> {
@@ -253,6 +339,13 @@ impl Runnable for Getter<'_, '_> {
             QueryConsolidation::Manual(strategy) => strategy,
         };
         let local_routing = self.local_routing.unwrap_or(state.local_routing);
+        let (timer, timeout) = {
+            let tables = zread!(self.session.runtime.router.tables);
+            (
+                tables.timer.clone(),
+                self.timeout.unwrap_or(tables.queries_default_timeout),
+            )
+        };
         let qid = state.qid_counter.fetch_add(1, Ordering::SeqCst);
         let (rep_sender, rep_receiver) = bounded(*API_REPLY_RECEPTION_CHANNEL_SIZE);
         let nb_final = if local_routing { 2 } else { 1 };
@@ -268,19 +361,35 @@ impl Runnable for Getter<'_, '_> {
                     None
                 },
                 rep_sender,
+                value_filter: ValueSelector::try_from(self.selector.value_selector.as_ref())
+                    .map(|value_selector| value_selector.value_filter())
+                    .unwrap_or_default(),
             },
         );
 
         let primitives = state.primitives.as_ref().unwrap().clone();
 
         drop(state);
-        primitives.send_query(
+        let body = self.value.as_ref().map(|value| {
+            let mut data_info = DataInfo::new();
+            if value.encoding != Encoding::default() {
+                data_info.encoding = Some(value.encoding.clone());
+            }
+            QueryBody {
+                data_info,
+                payload: value.payload.clone(),
+            }
+        });
+        primitives.send_query_with_timeout(
             &self.selector.key_selector,
             self.selector.value_selector.as_ref(),
             qid,
             target.clone(),
             consolidation.clone(),
+            body,
             None,
+            timeout,
+            self.attachment.as_ref().map(Into::into),
         );
         if local_routing {
             self.session.handle_query(
@@ -290,8 +399,17 @@ impl Runnable for Getter<'_, '_> {
                 qid,
                 target,
                 consolidation,
+                self.value.clone(),
+                self.attachment.clone(),
             );
         }
+        timer.add(TimedEvent::once(
+            Instant::now() + timeout,
+            QueryTimeout {
+                state: self.session.state.clone(),
+                qid,
+            },
+        ));
 
         Ok(ReplyReceiver::new(rep_receiver))
     }
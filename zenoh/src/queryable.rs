@@ -41,6 +41,10 @@ pub struct Query {
     pub(crate) key_selector: KeyExpr<'static>,
     /// The value_selector of this Query.
     pub(crate) value_selector: String,
+    /// The value of this Query.
+    pub(crate) value: Option<Value>,
+    /// The user attachment of this Query.
+    pub(crate) attachment: Option<Attachment>,
     /// The sender to use to send replies to this query.
     /// When this sender is dropped, the reply is finalized.
     pub replies_sender: RepliesSender,
@@ -68,12 +72,49 @@ impl Query {
         &self.value_selector
     }
 
+    /// The [`Value`] sent with this Query, if the querier provided one with
+    /// [`Getter::with_value()`](crate::query::Getter::with_value).
+    #[inline(always)]
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    /// The user [`Attachment`] of this Query, if the querier provided one.
+    ///
+    /// Replies carry their own attachment, see [`Sample::with_attachment`].
+    #[inline(always)]
+    pub fn attachment(&self) -> Option<&Attachment> {
+        self.attachment.as_ref()
+    }
+
     /// Sends a reply to this Query.
     #[inline(always)]
     pub fn reply(&'_ self, msg: Sample) {
         self.replies_sender.send(msg);
     }
 
+    /// Sends an error reply to this Query, e.g. when the queryable failed to compute its replies.
+    ///
+    /// The querier receives it as a [`Reply`](crate::query::Reply) whose `sample` is `Err(value)`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # async_std::task::block_on(async {
+    /// use futures::prelude::*;
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap();
+    /// let mut queryable = session.queryable("/key/expression").await.unwrap();
+    /// while let Some(query) = queryable.next().await {
+    ///     query.reply_err(Value::from("storage unavailable"));
+    /// }
+    /// # })
+    /// ```
+    #[inline(always)]
+    pub fn reply_err(&'_ self, value: Value) {
+        self.replies_sender.send_err(value);
+    }
+
     /// Tries sending a reply to this Query.
     #[inline(always)]
     pub fn try_reply(&self, msg: Sample) -> core::result::Result<(), TrySendError<Sample>> {
@@ -110,6 +151,7 @@ impl fmt::Display for Query {
 pub(crate) struct QueryableState {
     pub(crate) id: Id,
     pub(crate) key_expr: KeyExpr<'static>,
+    pub(crate) key_expr_str: String,
     pub(crate) kind: ZInt,
     pub(crate) complete: bool,
     pub(crate) sender: Sender<Query>,
@@ -269,14 +311,22 @@ impl fmt::Debug for Queryable<'_> {
 #[derive(Clone)]
 pub struct RepliesSender {
     pub(crate) kind: ZInt,
-    pub(crate) sender: Sender<(ZInt, Sample)>,
+    pub(crate) sender: Sender<(ZInt, Result<Sample, Value>)>,
 }
 
 impl RepliesSender {
     #[inline(always)]
     /// Send a reply.
     pub fn send(&'_ self, msg: Sample) {
-        if let Err(e) = self.sender.send((self.kind, msg)) {
+        if let Err(e) = self.sender.send((self.kind, Ok(msg))) {
+            log::error!("Error sending reply: {}", e);
+        }
+    }
+
+    #[inline(always)]
+    /// Send an error reply.
+    pub fn send_err(&'_ self, value: Value) {
+        if let Err(e) = self.sender.send((self.kind, Err(value))) {
             log::error!("Error sending reply: {}", e);
         }
     }
@@ -284,10 +334,13 @@ impl RepliesSender {
     /// Attempt to send a reply. If the channel is full, an error is returned.
     #[inline(always)]
     pub fn try_send(&self, msg: Sample) -> core::result::Result<(), TrySendError<Sample>> {
-        match self.sender.try_send((self.kind, msg)) {
+        // The reply sent back in the error is the Sample we just sent
+        match self.sender.try_send((self.kind, Ok(msg))) {
             Ok(()) => Ok(()),
-            Err(TrySendError::Full(sample)) => Err(TrySendError::Full(sample.1)),
-            Err(TrySendError::Disconnected(sample)) => Err(TrySendError::Disconnected(sample.1)),
+            Err(TrySendError::Full((_, reply))) => Err(TrySendError::Full(reply.unwrap())),
+            Err(TrySendError::Disconnected((_, reply))) => {
+                Err(TrySendError::Disconnected(reply.unwrap()))
+            }
         }
     }
 
@@ -319,7 +372,7 @@ impl RepliesSender {
     /// Asynchronously send a reply. If the channel is full, the returned future
     /// will yield to the async runtime.
     pub async fn send_async(&self, msg: Sample) {
-        if let Err(e) = self.sender.send_async((self.kind, msg)).await {
+        if let Err(e) = self.sender.send_async((self.kind, Ok(msg))).await {
             log::error!("Error sending reply: {}", e);
         }
     }
@@ -384,10 +437,12 @@ impl<'a> Runnable for QueryableBuilder<'a, '_> {
         log::trace!("queryable({:?}, {:?})", self.key_expr, self.kind);
         let mut state = zwrite!(self.session.state);
         let id = state.decl_id_counter.fetch_add(1, Ordering::SeqCst);
+        let key_expr_str = state.localkey_to_expr(&self.key_expr)?;
         let (sender, receiver) = bounded(*API_QUERY_RECEPTION_CHANNEL_SIZE);
         let qable_state = Arc::new(QueryableState {
             id,
             key_expr: self.key_expr.to_owned(),
+            key_expr_str,
             kind: self.kind,
             complete: self.complete,
             sender,
@@ -395,6 +450,9 @@ impl<'a> Runnable for QueryableBuilder<'a, '_> {
         #[cfg(feature = "complete_n")]
         {
             state.queryables.insert(id, qable_state.clone());
+            state
+                .queryable_tree
+                .insert(&qable_state.key_expr_str, qable_state.clone());
 
             if self.complete {
                 let primitives = state.primitives.as_ref().unwrap().clone();
@@ -414,6 +472,9 @@ impl<'a> Runnable for QueryableBuilder<'a, '_> {
                 twin_qabl && Session::complete_twin_qabl(&state, &self.key_expr, self.kind);
 
             state.queryables.insert(id, qable_state.clone());
+            state
+                .queryable_tree
+                .insert(&qable_state.key_expr_str, qable_state.clone());
 
             if !twin_qabl || (!complete_twin_qabl && self.complete) {
                 let primitives = state.primitives.as_ref().unwrap().clone();
@@ -0,0 +1,529 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+
+//! Evaluation of the filter, fragment and time range parts of a [`ValueSelector`].
+//!
+//! Filters and fragments apply to values with a self-describing encoding:
+//! `APP_JSON`, `TEXT_JSON` and `APP_PROPERTIES`. In JSON values, fields
+//! can be nested using `.` as separator (e.g. `?pos.x>1[pos.x;name]`).
+//!
+//! # Examples
+//! ```
+//! use std::convert::TryFrom;
+//! use zenoh::prelude::*;
+//!
+//! let value_selector = ValueSelector::try_from("?x>1&y<2[x;z]").unwrap();
+//! let filter = value_selector.value_filter();
+//!
+//! let value = Value::from(serde_json::json!({"x": 2, "y": 1, "z": "zenoh"}));
+//! assert!(filter.matches(&value));
+//! assert_eq!(
+//!     filter.project(value).as_json().unwrap(),
+//!     serde_json::json!({"x": 2, "z": "zenoh"})
+//! );
+//! assert!(!filter.matches(&Value::from(serde_json::json!({"x": 0, "y": 1}))));
+//! ```
+//!
+//! The `starttime` and `stoptime` properties of a value selector define a
+//! [`TimeRange`]. Their values are either a number of seconds since the UNIX epoch,
+//! a RFC3339 date or `now()` optionally followed by a positive or negative duration:
+//! ```
+//! use std::convert::TryFrom;
+//! use std::time::{Duration, SystemTime};
+//! use zenoh::prelude::*;
+//!
+//! let value_selector = ValueSelector::try_from("?(starttime=now()-1h)").unwrap();
+//! let time_range = value_selector.time_range().unwrap().unwrap();
+//!
+//! assert!(time_range.contains(SystemTime::now() - Duration::from_secs(60)));
+//! assert!(!time_range.contains(SystemTime::now() - Duration::from_secs(7200)));
+//! ```
+use crate::prelude::{
+    KnownEncoding, Properties, Sample, Value, ValueSelector, PROP_STARTTIME, PROP_STOPTIME,
+};
+use crate::time::Timestamp;
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zenoh_core::{bail, zerror};
+
+/// The comparison operators allowed in a filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Operator {
+    fn eval(self, ordering: Ordering) -> bool {
+        match self {
+            Operator::Lt => ordering == Ordering::Less,
+            Operator::Le => ordering != Ordering::Greater,
+            Operator::Gt => ordering == Ordering::Greater,
+            Operator::Ge => ordering != Ordering::Less,
+            Operator::Eq => ordering == Ordering::Equal,
+            Operator::Ne => ordering != Ordering::Equal,
+        }
+    }
+}
+
+/// A single `field`-`operator`-`value` condition of a filter.
+#[derive(Debug, Clone)]
+enum Predicate {
+    Compare {
+        field: Vec<String>,
+        op: Operator,
+        literal: String,
+    },
+    // A condition that could not be parsed: it is never satisfied.
+    Invalid,
+}
+
+impl Predicate {
+    fn parse(s: &str) -> Predicate {
+        // Two-characters operators must be looked up before their one-character prefix.
+        const OPERATORS: [(&str, Operator); 6] = [
+            ("<=", Operator::Le),
+            (">=", Operator::Ge),
+            ("!=", Operator::Ne),
+            ("<", Operator::Lt),
+            (">", Operator::Gt),
+            ("=", Operator::Eq),
+        ];
+        let pos = s.find(['<', '>', '=', '!']);
+        if let Some(pos) = pos {
+            let (field, rest) = s.split_at(pos);
+            let field = field.trim();
+            if let Some((op_str, op)) = OPERATORS.iter().find(|(o, _)| rest.starts_with(o)) {
+                if !field.is_empty() {
+                    return Predicate::Compare {
+                        field: split_path(field),
+                        op: *op,
+                        literal: unquote(rest[op_str.len()..].trim()).to_string(),
+                    };
+                }
+            }
+        }
+        log::debug!("Invalid condition in value selector filter: {}", s);
+        Predicate::Invalid
+    }
+
+    fn field(&self) -> Option<&[String]> {
+        match self {
+            Predicate::Compare { field, .. } => Some(field),
+            Predicate::Invalid => None,
+        }
+    }
+
+    fn eval_json(&self, json: &serde_json::Value) -> Option<bool> {
+        match self {
+            Predicate::Compare { field, op, literal } => {
+                let ordering = match get_json_field(json, field)? {
+                    serde_json::Value::Number(n) => {
+                        n.as_f64()?.partial_cmp(&literal.parse::<f64>().ok()?)
+                    }
+                    serde_json::Value::Bool(b) => Some(b.cmp(&literal.parse::<bool>().ok()?)),
+                    serde_json::Value::String(s) => Some(s.as_str().cmp(literal.as_str())),
+                    serde_json::Value::Null => (literal == "null").then_some(Ordering::Equal),
+                    _ => None,
+                };
+                Some(ordering.is_some_and(|o| op.eval(o)))
+            }
+            Predicate::Invalid => Some(false),
+        }
+    }
+
+    fn eval_properties(&self, props: &Properties) -> Option<bool> {
+        match self {
+            Predicate::Compare { field, op, literal } => {
+                let value = props.get(&field.join("."))?;
+                let ordering = match (value.parse::<f64>(), literal.parse::<f64>()) {
+                    (Ok(v), Ok(l)) => v.partial_cmp(&l),
+                    _ => Some(value.as_str().cmp(literal.as_str())),
+                };
+                Some(ordering.is_some_and(|o| op.eval(o)))
+            }
+            Predicate::Invalid => Some(false),
+        }
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('.').map(|s| s.trim().to_string()).collect()
+}
+
+fn unquote(s: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if s.len() >= 2 && s.starts_with(quote) && s.ends_with(quote) {
+            return &s[1..s.len() - 1];
+        }
+    }
+    s
+}
+
+fn get_json_field<'a>(
+    json: &'a serde_json::Value,
+    path: &[String],
+) -> Option<&'a serde_json::Value> {
+    path.iter()
+        .try_fold(json, |json, name| json.as_object()?.get(name))
+}
+
+fn insert_json_field(target: &mut serde_json::Value, path: &[String], value: serde_json::Value) {
+    if let Some((last, parents)) = path.split_last() {
+        let mut current = target;
+        for name in parents {
+            current = current
+                .as_object_mut()
+                .unwrap()
+                .entry(name.clone())
+                .or_insert_with(|| serde_json::Value::Object(Default::default()));
+        }
+        current.as_object_mut().unwrap().insert(last.clone(), value);
+    }
+}
+
+/// The parsed filter and fragment of a [`ValueSelector`], ready to be applied on values.
+///
+/// A value satisfies the filter if it satisfies all its conditions. A condition on a
+/// field that does not exist in the value, or that could not be parsed, is never satisfied.
+/// Values with an encoding that is not structured only satisfy an empty filter and
+/// are returned unchanged by [`project`](ValueFilter::project).
+#[derive(Debug, Clone, Default)]
+pub struct ValueFilter {
+    predicates: Vec<Predicate>,
+    fragment: Option<Vec<Vec<String>>>,
+}
+
+impl ValueFilter {
+    /// Creates a `ValueFilter` from the filter and fragment parts of a [`ValueSelector`].
+    pub fn new(value_selector: &ValueSelector) -> Self {
+        ValueFilter {
+            predicates: value_selector
+                .filter
+                .split('&')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(Predicate::parse)
+                .collect(),
+            fragment: value_selector.fragment.map(|fragment| {
+                fragment
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(split_path)
+                    .collect()
+            }),
+        }
+    }
+
+    /// Returns true if this `ValueFilter` has neither a filter nor a fragment.
+    pub fn is_empty(&self) -> bool {
+        self.predicates.is_empty() && self.fragment.is_none()
+    }
+
+    /// Returns true if the given [`Value`] satisfies the filter.
+    pub fn matches(&self, value: &Value) -> bool {
+        self.matches_with(value, |_| false)
+    }
+
+    /// Returns the part of the given [`Value`] selected by the fragment.
+    pub fn project(&self, value: Value) -> Value {
+        let fragment = match &self.fragment {
+            Some(fragment) => fragment,
+            None => return value,
+        };
+        match value.encoding.prefix() {
+            KnownEncoding::AppJson | KnownEncoding::TextJson => match value.as_json() {
+                Some(json) if json.is_object() => {
+                    let mut result = serde_json::Value::Object(Default::default());
+                    for path in fragment {
+                        if let Some(field) = get_json_field(&json, path) {
+                            insert_json_field(&mut result, path, field.clone());
+                        }
+                    }
+                    Value::from(result).encoding(value.encoding)
+                }
+                _ => value,
+            },
+            KnownEncoding::AppProperties => match value.as_properties() {
+                Some(props) => {
+                    let selected: Vec<String> = fragment.iter().map(|p| p.join(".")).collect();
+                    let mut result = Properties::default();
+                    for (k, v) in props.0.into_iter() {
+                        if selected.contains(&k) {
+                            result.insert(k, v);
+                        }
+                    }
+                    Value::from(result)
+                }
+                None => value,
+            },
+            _ => value,
+        }
+    }
+
+    /// Applies the filter and the fragment on the value of the given [`Sample`].
+    ///
+    /// Returns `None` if the sample does not satisfy the filter.
+    pub fn apply(&self, mut sample: Sample) -> Option<Sample> {
+        if self.is_empty() {
+            return Some(sample);
+        }
+        if !self.matches(&sample.value) {
+            return None;
+        }
+        sample.value = self.project(sample.value);
+        Some(sample)
+    }
+
+    /// Applies the filter and the fragment on a reply that may already have been
+    /// filtered and projected by the replier.
+    ///
+    /// A condition on a field that is missing from the reply and excluded by the
+    /// fragment is considered as already evaluated by the replier.
+    pub(crate) fn apply_on_reply(&self, mut sample: Sample) -> Option<Sample> {
+        if self.is_empty() {
+            return Some(sample);
+        }
+        let projected_out = |field: &[String]| match &self.fragment {
+            Some(fragment) => !fragment.iter().any(|path| field.starts_with(path)),
+            None => false,
+        };
+        if !self.matches_with(&sample.value, projected_out) {
+            return None;
+        }
+        sample.value = self.project(sample.value);
+        Some(sample)
+    }
+
+    fn matches_with<F: Fn(&[String]) -> bool>(&self, value: &Value, skip_missing: F) -> bool {
+        if self.predicates.is_empty() {
+            return true;
+        }
+        let eval = |result: Option<bool>, predicate: &Predicate| match result {
+            Some(result) => result,
+            None => predicate.field().is_some_and(&skip_missing),
+        };
+        match value.encoding.prefix() {
+            KnownEncoding::AppJson | KnownEncoding::TextJson => match value.as_json() {
+                Some(json) => self.predicates.iter().all(|p| eval(p.eval_json(&json), p)),
+                None => false,
+            },
+            KnownEncoding::AppProperties => match value.as_properties() {
+                Some(props) => self
+                    .predicates
+                    .iter()
+                    .all(|p| eval(p.eval_properties(&props), p)),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// The time range selected by the `starttime` and `stoptime` properties of a [`ValueSelector`].
+///
+/// Relative times (i.e. `now()-1h`) are resolved when the time range is parsed.
+/// Both bounds are inclusive, and a missing bound leaves the range open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start: Option<SystemTime>,
+    pub stop: Option<SystemTime>,
+}
+
+impl TimeRange {
+    /// Parses the time range defined by the properties of a [`ValueSelector`], if any.
+    pub fn from_properties(properties: &Properties) -> crate::Result<Option<TimeRange>> {
+        let start = properties.get(PROP_STARTTIME);
+        let stop = properties.get(PROP_STOPTIME);
+        if start.is_none() && stop.is_none() {
+            return Ok(None);
+        }
+        let now = SystemTime::now();
+        Ok(Some(TimeRange {
+            start: start.map(|s| parse_time(s, now)).transpose()?,
+            stop: stop.map(|s| parse_time(s, now)).transpose()?,
+        }))
+    }
+
+    /// Returns true if the given time is in this `TimeRange`.
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.start.is_none_or(|start| start <= time) && self.stop.is_none_or(|stop| time <= stop)
+    }
+
+    /// Returns true if the time of the given [`Timestamp`] is in this `TimeRange`.
+    #[inline]
+    pub fn contains_timestamp(&self, timestamp: &Timestamp) -> bool {
+        self.contains(timestamp.get_time().to_system_time())
+    }
+}
+
+/// Parses a time expression: either a number of seconds since the UNIX epoch, a RFC3339 date,
+/// or `now()` optionally followed by `+` or `-` and a duration (e.g. `now()-1h30m`),
+/// resolved relatively to `now`.
+pub fn parse_time(s: &str, now: SystemTime) -> crate::Result<SystemTime> {
+    let s = s.trim();
+    if let Some(offset) = s.strip_prefix("now()") {
+        let offset = offset.trim();
+        if offset.is_empty() {
+            return Ok(now);
+        }
+        let (negative, duration) = match (offset.strip_prefix('-'), offset.strip_prefix('+')) {
+            (Some(duration), _) => (true, duration),
+            (_, Some(duration)) => (false, duration),
+            _ => bail!(
+                "Invalid time expression '{}': expecting '+' or '-' after now()",
+                s
+            ),
+        };
+        let duration = humantime::parse_duration(duration.trim())
+            .map_err(|e| zerror!("Invalid duration in time expression '{}': {}", s, e))?;
+        let time = if negative {
+            now.checked_sub(duration)
+        } else {
+            now.checked_add(duration)
+        };
+        time.ok_or_else(|| zerror!("Time expression '{}' is out of range", s).into())
+    } else if let Ok(secs) = s.parse::<f64>() {
+        let duration = Duration::try_from_secs_f64(secs)
+            .map_err(|e| zerror!("Invalid time expression '{}': {}", s, e))?;
+        UNIX_EPOCH
+            .checked_add(duration)
+            .ok_or_else(|| zerror!("Time expression '{}' is out of range", s).into())
+    } else {
+        humantime::parse_rfc3339_weak(s)
+            .map_err(|e| zerror!("Invalid time expression '{}': {}", s, e).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    fn filter(s: &str) -> ValueFilter {
+        ValueSelector::try_from(s).unwrap().value_filter()
+    }
+
+    #[test]
+    fn json_filter() {
+        let value =
+            Value::from(json!({"x": 2, "y": 1.5, "name": "zenoh", "ok": true, "pos": {"z": 3}}));
+        assert!(filter("").matches(&value));
+        assert!(filter("?x>1").matches(&value));
+        assert!(filter("?x>=2&y<2").matches(&value));
+        assert!(filter("?x=2&y!=2&ok=true").matches(&value));
+        assert!(filter("?name=zenoh&name='zenoh'").matches(&value));
+        assert!(filter("?pos.z<=3(starttime=0)").matches(&value));
+        assert!(!filter("?x<2").matches(&value));
+        assert!(!filter("?x>1&y>2").matches(&value));
+        assert!(!filter("?unknown=1").matches(&value));
+        assert!(!filter("?name>zz").matches(&value));
+        assert!(!filter("?x").matches(&value));
+        assert!(!filter("?x>1").matches(&Value::from("x=2")));
+    }
+
+    #[test]
+    fn properties_filter() {
+        let value = Value::from(Properties::from("x=2;name=zenoh"));
+        assert!(filter("?x>1&x<10").matches(&value));
+        assert!(filter("?name=zenoh").matches(&value));
+        assert!(!filter("?name!=zenoh").matches(&value));
+        assert!(!filter("?y=1").matches(&value));
+    }
+
+    #[test]
+    fn fragment() {
+        let value = Value::from(json!({"x": 2, "y": 1, "pos": {"z": 3, "w": 4}}))
+            .encoding(crate::prelude::Encoding::TEXT_JSON);
+        let projected = filter("?[x;pos.z;unknown]").project(value);
+        assert_eq!(*projected.encoding.prefix(), KnownEncoding::TextJson);
+        assert_eq!(
+            projected.as_json().unwrap(),
+            json!({"x": 2, "pos": {"z": 3}})
+        );
+
+        let value = Value::from(Properties::from("x=2;y=3"));
+        let projected = filter("?[y]").project(value);
+        assert_eq!(projected.as_properties().unwrap().get("y").unwrap(), "3");
+        assert!(projected.as_properties().unwrap().get("x").is_none());
+
+        let value = Value::from("raw");
+        assert_eq!(filter("?[x]").project(value.clone()).payload, value.payload);
+    }
+
+    #[test]
+    fn apply_on_reply() {
+        let f = filter("?x>1[y]");
+        let sample = Sample::new("/a", json!({"x": 2, "y": 1}));
+        let projected = f.apply(sample).unwrap();
+        assert_eq!(projected.value.as_json().unwrap(), json!({"y": 1}));
+        // An already projected reply is not filtered out again.
+        assert!(f.apply_on_reply(projected.clone()).is_some());
+        assert!(f.apply(projected).is_none());
+        assert!(f
+            .apply_on_reply(Sample::new("/a", json!({"x": 0, "y": 1})))
+            .is_none());
+    }
+
+    #[test]
+    fn time_range() {
+        let time_range = |s: &str| ValueSelector::try_from(s).unwrap().time_range();
+        assert_eq!(time_range("?x>1").unwrap(), None);
+
+        let range = time_range("?(starttime=0)").unwrap().unwrap();
+        assert_eq!(range.start, Some(UNIX_EPOCH));
+        assert_eq!(range.stop, None);
+        assert!(range.contains(SystemTime::now()));
+
+        let range = time_range("?(starttime=1.5;stoptime=2022-03-01T12:00:00Z)")
+            .unwrap()
+            .unwrap();
+        assert_eq!(range.start, Some(UNIX_EPOCH + Duration::from_millis(1500)));
+        assert_eq!(
+            range.stop,
+            Some(UNIX_EPOCH + Duration::from_secs(1_646_136_000))
+        );
+        assert!(!range.contains(SystemTime::now()));
+
+        let now = SystemTime::now();
+        let hour = Duration::from_secs(3600);
+        assert_eq!(parse_time("now()", now).unwrap(), now);
+        assert_eq!(parse_time("now()-1h", now).unwrap(), now - hour);
+        assert_eq!(
+            parse_time(" now() + 1h 30m ", now).unwrap(),
+            now + hour * 3 / 2
+        );
+        assert!(parse_time("now()1h", now).is_err());
+        assert!(parse_time("now()-1x", now).is_err());
+        assert!(parse_time("-1", now).is_err());
+        assert!(parse_time("1e30", now).is_err());
+        assert!(parse_time("NaN", now).is_err());
+        assert!(parse_time("inf", now).is_err());
+        assert!(parse_time("yesterday", now).is_err());
+        assert!(time_range("?(stoptime=later)").is_err());
+
+        let range = time_range("?(starttime=now()-1h;stoptime=now())")
+            .unwrap()
+            .unwrap();
+        assert!(range.contains(SystemTime::now() - hour / 2));
+        assert!(!range.contains(SystemTime::now() - hour * 2));
+        assert!(!range.contains(SystemTime::now() + hour));
+    }
+}
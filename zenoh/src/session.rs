@@ -15,10 +15,11 @@ use crate::config::Config;
 use crate::config::Notifier;
 use crate::data_kind;
 use crate::info::*;
+use crate::liveliness::*;
 use crate::net::routing::face::Face;
 use crate::net::runtime::Runtime;
 use crate::net::transport::Primitives;
-use crate::prelude::EntityFactory;
+use crate::prelude::{Attachment, EntityFactory};
 use crate::publication::*;
 use crate::query::*;
 use crate::queryable::*;
@@ -35,7 +36,7 @@ use async_std::task;
 use flume::{bounded, Sender};
 use futures_lite::StreamExt;
 use log::{error, trace, warn};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::ops::Deref;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -49,8 +50,9 @@ use zenoh_protocol::{
         KeyExpr, QueryTarget, QueryableInfo, SubInfo, ZInt,
     },
     io::ZBuf,
-    proto::{DataInfo, RoutingContext},
+    proto::{Attachment as ZAttachment, DataInfo, QueryBody, RoutingContext},
 };
+use zenoh_protocol_core::KeTree;
 use zenoh_protocol_core::PeerId;
 use zenoh_protocol_core::WhatAmI;
 use zenoh_protocol_core::EMPTY_EXPR_ID;
@@ -75,7 +77,14 @@ pub(crate) struct SessionState {
     pub(crate) subscribers: HashMap<Id, Arc<SubscriberState>>,
     pub(crate) local_subscribers: HashMap<Id, Arc<SubscriberState>>,
     pub(crate) queryables: HashMap<Id, Arc<QueryableState>>,
+    // Indexes of the subscribers, local subscribers and queryables by key expression
+    pub(crate) subscriber_tree: KeTree<Arc<SubscriberState>>,
+    pub(crate) local_subscriber_tree: KeTree<Arc<SubscriberState>>,
+    pub(crate) queryable_tree: KeTree<Arc<QueryableState>>,
     pub(crate) queries: HashMap<ZInt, QueryState>,
+    pub(crate) tokens: HashMap<Id, String>,
+    pub(crate) remote_tokens: HashSet<String>,
+    pub(crate) liveliness_subscribers: HashMap<Id, Arc<SubscriberState>>,
     pub(crate) local_routing: bool,
     pub(crate) join_subscriptions: Vec<String>,
     pub(crate) join_publications: Vec<String>,
@@ -98,7 +107,13 @@ impl SessionState {
             subscribers: HashMap::new(),
             local_subscribers: HashMap::new(),
             queryables: HashMap::new(),
+            subscriber_tree: KeTree::new(),
+            local_subscriber_tree: KeTree::new(),
+            queryable_tree: KeTree::new(),
             queries: HashMap::new(),
+            tokens: HashMap::new(),
+            remote_tokens: HashSet::new(),
+            liveliness_subscribers: HashMap::new(),
             local_routing,
             join_subscriptions,
             join_publications,
@@ -165,6 +180,19 @@ impl SessionState {
         }
     }
 
+    // Whether a local or remote liveliness token is alive on `key_expr`
+    fn token_alive(&self, key_expr: &str) -> bool {
+        self.remote_tokens.contains(key_expr) || self.tokens.values().any(|t| t == key_expr)
+    }
+
+    fn liveliness_subscribers_for(&self, key_expr: &str) -> Vec<Arc<SubscriberState>> {
+        self.liveliness_subscribers
+            .values()
+            .filter(|sub| key_expr::matches(&sub.key_expr_str, key_expr))
+            .cloned()
+            .collect()
+    }
+
     pub fn key_expr_to_expr(&self, key_expr: &KeyExpr, local: bool) -> ZResult<String> {
         if local {
             self.localkey_to_expr(key_expr)
@@ -540,7 +568,7 @@ impl Session {
                 None => {
                     let expr_id = state.expr_id_counter.fetch_add(1, Ordering::SeqCst) as ZInt;
                     let mut res = Resource::new(expr.clone());
-                    for sub in state.subscribers.values() {
+                    for sub in state.subscriber_tree.intersecting(&expr) {
                         if key_expr::matches(&expr, &sub.key_expr_str) {
                             res.subscribers.push(sub.clone());
                         }
@@ -742,6 +770,9 @@ impl Session {
         };
 
         state.subscribers.insert(sub_state.id, sub_state.clone());
+        state
+            .subscriber_tree
+            .insert(&sub_state.key_expr_str, sub_state.clone());
         for res in state.local_resources.values_mut() {
             if key_expr::matches(&sub_state.key_expr_str, &res.name) {
                 res.subscribers.push(sub_state.clone());
@@ -802,6 +833,9 @@ impl Session {
         state
             .local_subscribers
             .insert(sub_state.id, sub_state.clone());
+        state
+            .local_subscriber_tree
+            .insert(&sub_state.key_expr_str, sub_state.clone());
         for res in state.local_resources.values_mut() {
             if key_expr::matches(&sub_state.key_expr_str, &res.name) {
                 res.local_subscribers.push(sub_state.clone());
@@ -856,6 +890,9 @@ impl Session {
         let mut state = zwrite!(self.state);
         zready(if let Some(sub_state) = state.subscribers.remove(&sid) {
             trace!("unsubscribe({:?})", sub_state);
+            state
+                .subscriber_tree
+                .remove(&sub_state.key_expr_str, |sub| sub.id == sub_state.id);
             for res in state.local_resources.values_mut() {
                 res.subscribers.retain(|sub| sub.id != sub_state.id);
             }
@@ -900,6 +937,9 @@ impl Session {
             })
         } else if let Some(sub_state) = state.local_subscribers.remove(&sid) {
             trace!("unsubscribe({:?})", sub_state);
+            state
+                .local_subscriber_tree
+                .remove(&sub_state.key_expr_str, |sub| sub.id == sub_state.id);
             for res in state.local_resources.values_mut() {
                 res.local_subscribers.retain(|sub| sub.id != sub_state.id);
             }
@@ -907,11 +947,211 @@ impl Session {
                 res.local_subscribers.retain(|sub| sub.id != sub_state.id);
             }
             Ok(())
+        } else if let Some(sub_state) = state.liveliness_subscribers.remove(&sid) {
+            trace!("unsubscribe({:?})", sub_state);
+            Ok(())
         } else {
             Err(zerror!("Unable to find subscriber").into())
         })
     }
 
+    /// Get the liveliness primitives of this session.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap();
+    /// let token = session.liveliness().declare_token("/node/1").await.unwrap();
+    /// # })
+    /// ```
+    pub fn liveliness(&self) -> Liveliness<'_> {
+        Liveliness {
+            session: SessionRef::Borrow(self),
+        }
+    }
+
+    pub(crate) fn declare_token(&self, key_expr: &KeyExpr) -> ZResult<(Id, String)> {
+        let mut state = zwrite!(self.state);
+        let key_expr_str = state.localkey_to_expr(key_expr)?;
+        if !key_expr_str.starts_with('/') || key_expr_str.contains('*') {
+            bail!(
+                "Invalid liveliness token key expression: {} (wildcards are not allowed)",
+                key_expr_str
+            )
+        }
+        let id = state.decl_id_counter.fetch_add(1, Ordering::SeqCst);
+        let alive = state.token_alive(&key_expr_str);
+        // Note: there might be several tokens on the same key expression.
+        // Only the first one is declared to the routing infrastructure.
+        let twin_token = state.tokens.values().any(|t| *t == key_expr_str);
+        state.tokens.insert(id, key_expr_str.clone());
+        let subs = if alive {
+            vec![]
+        } else {
+            state.liveliness_subscribers_for(&key_expr_str)
+        };
+        let primitives = state.primitives.as_ref().unwrap().clone();
+        drop(state);
+
+        if !twin_token {
+            primitives.decl_token(&key_expr_str.as_str().into(), None);
+        }
+        Session::notify_liveliness(&subs, &key_expr_str, data_kind::PUT);
+        Ok((id, key_expr_str))
+    }
+
+    pub(crate) fn undeclare_token(&self, id: Id) -> impl ZFuture<Output = ZResult<()>> {
+        let mut state = zwrite!(self.state);
+        zready(match state.tokens.remove(&id) {
+            Some(key_expr_str) => {
+                trace!("undeclare_token({:?})", key_expr_str);
+                let twin_token = state.tokens.values().any(|t| *t == key_expr_str);
+                let subs = if state.token_alive(&key_expr_str) {
+                    vec![]
+                } else {
+                    state.liveliness_subscribers_for(&key_expr_str)
+                };
+                let primitives = state.primitives.as_ref().unwrap().clone();
+                drop(state);
+
+                if !twin_token {
+                    primitives.forget_token(&key_expr_str.as_str().into(), None);
+                }
+                Session::notify_liveliness(&subs, &key_expr_str, data_kind::DELETE);
+                Ok(())
+            }
+            None => Err(zerror!("Unable to find liveliness token").into()),
+        })
+    }
+
+    pub(crate) fn matching_status(
+        &self,
+        key_expr: &KeyExpr,
+        local_routing: Option<bool>,
+    ) -> ZResult<MatchingStatus> {
+        let state = zread!(self.state);
+        let local = local_routing.unwrap_or(state.local_routing);
+        let primitives = state.primitives.as_ref().unwrap().clone();
+        drop(state);
+        match primitives.matching_status(key_expr, local) {
+            Some(matching) => Ok(MatchingStatus { matching }),
+            None => bail!("Unable to compute the matching status of {}", key_expr),
+        }
+    }
+
+    pub(crate) fn declare_matching_listener(
+        &self,
+        key_expr: &KeyExpr,
+        local_routing: Option<bool>,
+        handler: Arc<RwLock<MatchingHandler>>,
+    ) -> ZResult<usize> {
+        let state = zread!(self.state);
+        let local = local_routing.unwrap_or(state.local_routing);
+        let primitives = state.primitives.as_ref().unwrap().clone();
+        drop(state);
+        let (sender, receiver) = flume::unbounded();
+        match primitives.declare_matching_listener(key_expr, local, sender) {
+            Some((id, _)) => {
+                // The sender is dropped by the routing tables when the listener is undeclared
+                // or the session closed, which ends this task.
+                task::spawn(async move {
+                    while let Ok(matching) = receiver.recv_async().await {
+                        zwrite!(handler)(MatchingStatus { matching });
+                    }
+                });
+                Ok(id)
+            }
+            None => bail!("Unable to declare a matching listener on {}", key_expr),
+        }
+    }
+
+    pub(crate) fn undeclare_matching_listener(
+        &self,
+        id: usize,
+    ) -> impl ZFuture<Output = ZResult<()>> {
+        trace!("undeclare_matching_listener({:?})", id);
+        let state = zread!(self.state);
+        let primitives = state.primitives.as_ref().unwrap().clone();
+        drop(state);
+        primitives.undeclare_matching_listener(id);
+        zready(Ok(()))
+    }
+
+    pub(crate) fn declare_liveliness_subscriber(
+        &self,
+        key_expr: &KeyExpr,
+        invoker: SubscriberInvoker,
+    ) -> ZResult<Arc<SubscriberState>> {
+        let mut state = zwrite!(self.state);
+        let id = state.decl_id_counter.fetch_add(1, Ordering::SeqCst);
+        let key_expr_str = state.localkey_to_expr(key_expr)?;
+        let sub_state = Arc::new(SubscriberState {
+            id,
+            key_expr: key_expr.to_owned(),
+            key_expr_str,
+            invoker,
+        });
+        state
+            .liveliness_subscribers
+            .insert(sub_state.id, sub_state.clone());
+        Ok(sub_state)
+    }
+
+    pub(crate) fn alive_tokens(&self, key_expr: &KeyExpr) -> ZResult<Vec<Sample>> {
+        let state = zread!(self.state);
+        let primitives = state.primitives.as_ref().unwrap().clone();
+        drop(state);
+        match primitives.alive_tokens(key_expr) {
+            Some(tokens) => Ok(tokens
+                .into_iter()
+                .map(|token| Sample::new(token, Value::empty()))
+                .collect()),
+            None => bail!("Unable to get the liveliness tokens alive on {}", key_expr),
+        }
+    }
+
+    fn notify_liveliness(subs: &[Arc<SubscriberState>], key_expr: &str, kind: ZInt) {
+        for sub in subs {
+            let mut info = DataInfo::new();
+            info.kind = Some(kind);
+            Session::invoke_subscriber(
+                &sub.invoker,
+                key_expr.to_string(),
+                ZBuf::default(),
+                Some(info),
+                None,
+            );
+        }
+    }
+
+    // Track the liveliness tokens declared by the routing infrastructure
+    fn handle_token(&self, key_expr: &KeyExpr, alive: bool) {
+        let mut state = zwrite!(self.state);
+        let token = match state.remotekey_to_expr(key_expr) {
+            Ok(token) => token,
+            Err(_) => return,
+        };
+        let token = token.as_str();
+        let was_alive = state.token_alive(token);
+        if alive {
+            state.remote_tokens.insert(token.to_string());
+        } else {
+            state.remote_tokens.remove(token);
+        }
+        if was_alive != state.token_alive(token) {
+            let subs = state.liveliness_subscribers_for(token);
+            drop(state);
+            let kind = if alive {
+                data_kind::PUT
+            } else {
+                data_kind::DELETE
+            };
+            Session::notify_liveliness(&subs, token, kind);
+        }
+    }
+
     pub(crate) fn twin_qabl(state: &SessionState, key: &KeyExpr, kind: ZInt) -> bool {
         state.queryables.values().any(|q| {
             q.kind == kind
@@ -986,6 +1226,11 @@ impl Session {
         let mut state = zwrite!(self.state);
         zready(if let Some(qable_state) = state.queryables.remove(&qid) {
             trace!("close_queryable({:?})", qable_state);
+            state
+                .queryable_tree
+                .remove(&qable_state.key_expr_str, |qable| {
+                    qable.id == qable_state.id
+                });
             if Session::twin_qabl(&state, &qable_state.key_expr, qable_state.kind) {
                 // There still exist Queryables on the same KeyExpr.
                 if qable_state.complete {
@@ -1069,6 +1314,7 @@ impl Session {
                 congestion_control: CongestionControl::default(),
                 priority: Priority::default(),
                 local_routing: None,
+                attachment: None,
             }),
         }
     }
@@ -1108,6 +1354,7 @@ impl Session {
             congestion_control: CongestionControl::default(),
             priority: Priority::default(),
             local_routing: None,
+            attachment: None,
         }
     }
 
@@ -1139,6 +1386,7 @@ impl Session {
             congestion_control: CongestionControl::default(),
             priority: Priority::default(),
             local_routing: None,
+            attachment: None,
         }
     }
 
@@ -1148,15 +1396,17 @@ impl Session {
         key_expr: String,
         payload: ZBuf,
         data_info: Option<DataInfo>,
+        attachment: Option<Attachment>,
     ) {
+        let mut sample = Sample::with_info(key_expr.into(), payload, data_info);
+        sample.attachment = attachment;
         match invoker {
             SubscriberInvoker::Handler(handler) => {
                 let handler = &mut *zwrite!(handler);
-                handler(Sample::with_info(key_expr.into(), payload, data_info));
+                handler(sample);
             }
             SubscriberInvoker::Sender(sender) => {
-                if let Err(e) = sender.send(Sample::with_info(key_expr.into(), payload, data_info))
-                {
+                if let Err(e) = sender.send(sample) {
                     error!("SubscriberInvoker error: {}", e);
                 }
             }
@@ -1169,6 +1419,7 @@ impl Session {
         key_expr: &KeyExpr,
         info: Option<DataInfo>,
         payload: ZBuf,
+        attachment: Option<Attachment>,
         local_routing: Option<bool>,
     ) {
         let state = zread!(self.state);
@@ -1178,7 +1429,13 @@ impl Session {
                 Some(res) => {
                     if !local && res.subscribers.len() == 1 {
                         let sub = res.subscribers.get(0).unwrap();
-                        Session::invoke_subscriber(&sub.invoker, res.name.clone(), payload, info);
+                        Session::invoke_subscriber(
+                            &sub.invoker,
+                            res.name.clone(),
+                            payload,
+                            info,
+                            attachment,
+                        );
                     } else {
                         if !local || local_routing {
                             for sub in &res.subscribers {
@@ -1187,6 +1444,7 @@ impl Session {
                                     res.name.clone(),
                                     payload.clone(),
                                     info.clone(),
+                                    attachment.clone(),
                                 );
                             }
                         }
@@ -1197,6 +1455,7 @@ impl Session {
                                     res.name.clone(),
                                     payload.clone(),
                                     info.clone(),
+                                    attachment.clone(),
                                 );
                             }
                         }
@@ -1210,25 +1469,27 @@ impl Session {
             match state.key_expr_to_expr(key_expr, local) {
                 Ok(key_expr) => {
                     if !local || local_routing {
-                        for sub in state.subscribers.values() {
+                        for sub in state.subscriber_tree.intersecting(&key_expr) {
                             if key_expr::matches(&sub.key_expr_str, &key_expr) {
                                 Session::invoke_subscriber(
                                     &sub.invoker,
                                     key_expr.clone(),
                                     payload.clone(),
                                     info.clone(),
+                                    attachment.clone(),
                                 );
                             }
                         }
                     }
                     if local {
-                        for sub in state.local_subscribers.values() {
+                        for sub in state.local_subscriber_tree.intersecting(&key_expr) {
                             if key_expr::matches(&sub.key_expr_str, &key_expr) {
                                 Session::invoke_subscriber(
                                     &sub.invoker,
                                     key_expr.clone(),
                                     payload.clone(),
                                     info.clone(),
+                                    attachment.clone(),
                                 );
                             }
                         }
@@ -1281,6 +1542,9 @@ impl Session {
             target: Some(QueryTarget::default()),
             consolidation: Some(QueryConsolidation::default()),
             local_routing: None,
+            value: None,
+            attachment: None,
+            timeout: None,
         }
     }
 
@@ -1292,31 +1556,23 @@ impl Session {
         qid: ZInt,
         target: QueryTarget,
         _consolidation: ConsolidationStrategy,
+        value: Option<Value>,
+        attachment: Option<Attachment>,
     ) {
         let (primitives, key_expr, kinds_and_senders) = {
             let state = zread!(self.state);
             match state.key_expr_to_expr(key_expr, local) {
                 Ok(key_expr) => {
                     let kinds_and_senders = state
-                        .queryables
-                        .values()
-                        .filter(
-                            |queryable| match state.localkey_to_expr(&queryable.key_expr) {
-                                Ok(qablname) => {
-                                    key_expr::matches(&qablname, &key_expr)
-                                        && ((queryable.kind == queryable::ALL_KINDS
-                                            || target.kind == queryable::ALL_KINDS)
-                                            || (queryable.kind & target.kind != 0))
-                                }
-                                Err(err) => {
-                                    error!(
-                                        "{}. Internal error (queryable key_expr to key_expr failed).",
-                                        err
-                                    );
-                                    false
-                                }
-                            },
-                        )
+                        .queryable_tree
+                        .intersecting(&key_expr)
+                        .into_iter()
+                        .filter(|queryable| {
+                            key_expr::matches(&queryable.key_expr_str, &key_expr)
+                                && ((queryable.kind == queryable::ALL_KINDS
+                                    || target.kind == queryable::ALL_KINDS)
+                                    || (queryable.kind & target.kind != 0))
+                        })
                         .map(|qable| (qable.kind, qable.sender.clone()))
                         .collect::<Vec<(ZInt, Sender<Query>)>>();
                     (
@@ -1333,6 +1589,7 @@ impl Session {
         };
 
         let value_selector = value_selector.to_string();
+        let query_key_expr = key_expr.clone();
         let (rep_sender, rep_receiver) = bounded(*API_REPLY_EMISSION_CHANNEL_SIZE);
 
         let pid = self.runtime.pid; // @TODO build/use prebuilt specific pid
@@ -1341,6 +1598,8 @@ impl Session {
             let _ = req_sender.send(Query {
                 key_selector: key_expr.clone().into(),
                 value_selector: value_selector.clone(),
+                value: value.clone(),
+                attachment: attachment.clone(),
                 replies_sender: RepliesSender {
                     kind,
                     sender: rep_sender.clone(),
@@ -1354,30 +1613,38 @@ impl Session {
         if local {
             let this = self.clone();
             task::spawn(async move {
-                while let Some((replier_kind, sample)) = rep_receiver.stream().next().await {
-                    let (key_expr, payload, data_info) = sample.split();
+                while let Some((replier_kind, reply)) = rep_receiver.stream().next().await {
+                    let is_error = reply.is_err();
+                    let (key_expr, payload, data_info, attachment) =
+                        Session::split_reply(&query_key_expr, reply);
                     this.send_reply_data(
                         qid,
                         replier_kind,
                         pid,
+                        is_error,
                         key_expr,
                         Some(data_info),
                         payload,
+                        attachment.as_ref().map(Into::into),
                     );
                 }
                 this.send_reply_final(qid);
             });
         } else {
             task::spawn(async move {
-                while let Some((replier_kind, sample)) = rep_receiver.stream().next().await {
-                    let (key_expr, payload, data_info) = sample.split();
+                while let Some((replier_kind, reply)) = rep_receiver.stream().next().await {
+                    let is_error = reply.is_err();
+                    let (key_expr, payload, data_info, attachment) =
+                        Session::split_reply(&query_key_expr, reply);
                     primitives.send_reply_data(
                         qid,
                         replier_kind,
                         pid,
+                        is_error,
                         key_expr,
                         Some(data_info),
                         payload,
+                        attachment.as_ref().map(Into::into),
                     );
                 }
                 primitives.send_reply_final(qid);
@@ -1385,6 +1652,26 @@ impl Session {
         }
     }
 
+    // An error reply is sent on the key expression of the query it answers
+    fn split_reply(
+        query_key_expr: &str,
+        reply: Result<Sample, Value>,
+    ) -> (KeyExpr<'static>, ZBuf, DataInfo, Option<Attachment>) {
+        match reply {
+            Ok(sample) => sample.split(),
+            Err(value) => {
+                let mut data_info = DataInfo::new();
+                data_info.encoding = Some(value.encoding);
+                (
+                    query_key_expr.to_string().into(),
+                    value.payload,
+                    data_info,
+                    None,
+                )
+            }
+        }
+    }
+
     pub fn key_expr_to_expr(&self, key_expr: &KeyExpr) -> ZResult<String> {
         let state = zread!(self.state);
         state.remotekey_to_expr(key_expr)
@@ -1493,9 +1780,27 @@ impl EntityFactory for Arc<Session> {
                 congestion_control: CongestionControl::default(),
                 priority: Priority::default(),
                 local_routing: None,
+                attachment: None,
             }),
         }
     }
+
+    /// Get the liveliness primitives of this session.
+    ///
+    /// # Examples
+    /// ```
+    /// # async_std::task::block_on(async {
+    /// use zenoh::prelude::*;
+    ///
+    /// let session = zenoh::open(config::peer()).await.unwrap().into_arc();
+    /// let token = session.liveliness().declare_token("/node/1").await.unwrap();
+    /// # })
+    /// ```
+    fn liveliness(&self) -> Liveliness<'static> {
+        Liveliness {
+            session: SessionRef::Shared(self.clone()),
+        }
+    }
 }
 
 impl Primitives for Session {
@@ -1505,7 +1810,7 @@ impl Primitives for Session {
         match state.remotekey_to_expr(key_expr) {
             Ok(key_expr) => {
                 let mut res = Resource::new(key_expr.clone());
-                for sub in state.subscribers.values() {
+                for sub in state.subscriber_tree.intersecting(&key_expr) {
                     if key_expr::matches(&key_expr, &sub.key_expr_str) {
                         res.subscribers.push(sub.clone());
                     }
@@ -1561,6 +1866,16 @@ impl Primitives for Session {
         trace!("recv Forget Queryable {:?}", _key_expr);
     }
 
+    fn decl_token(&self, key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        trace!("recv Decl Token {:?}", key_expr);
+        self.handle_token(key_expr, true);
+    }
+
+    fn forget_token(&self, key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {
+        trace!("recv Forget Token {:?}", key_expr);
+        self.handle_token(key_expr, false);
+    }
+
     fn send_data(
         &self,
         key_expr: &KeyExpr,
@@ -1569,6 +1884,7 @@ impl Primitives for Session {
         congestion_control: CongestionControl,
         info: Option<DataInfo>,
         _routing_context: Option<RoutingContext>,
+        attachment: Option<ZAttachment>,
     ) {
         trace!(
             "recv Data {:?} {:?} {:?} {:?} {:?}",
@@ -1578,7 +1894,8 @@ impl Primitives for Session {
             congestion_control,
             info,
         );
-        self.handle_data(false, key_expr, info, payload, None)
+        let attachment = Attachment::decode(attachment);
+        self.handle_data(false, key_expr, info, payload, attachment, None)
     }
 
     fn send_query(
@@ -1588,7 +1905,9 @@ impl Primitives for Session {
         qid: ZInt,
         target: QueryTarget,
         consolidation: ConsolidationStrategy,
+        body: Option<QueryBody>,
         _routing_context: Option<RoutingContext>,
+        attachment: Option<ZAttachment>,
     ) {
         trace!(
             "recv Query {:?} {:?} {:?} {:?}",
@@ -1597,7 +1916,24 @@ impl Primitives for Session {
             target,
             consolidation
         );
-        self.handle_query(false, key_expr, value_selector, qid, target, consolidation)
+        let value = body.map(|body| {
+            let value: Value = body.payload.into();
+            match body.data_info.encoding {
+                Some(encoding) => value.encoding(encoding),
+                None => value,
+            }
+        });
+        let attachment = Attachment::decode(attachment);
+        self.handle_query(
+            false,
+            key_expr,
+            value_selector,
+            qid,
+            target,
+            consolidation,
+            value,
+            attachment,
+        )
     }
 
     fn send_reply_data(
@@ -1605,15 +1941,18 @@ impl Primitives for Session {
         qid: ZInt,
         replier_kind: ZInt,
         replier_id: PeerId,
+        is_error: bool,
         key_expr: KeyExpr,
         data_info: Option<DataInfo>,
         payload: ZBuf,
+        attachment: Option<ZAttachment>,
     ) {
         trace!(
-            "recv ReplyData {:?} {:?} {:?} {:?} {:?} {:?}",
+            "recv ReplyData {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
             qid,
             replier_kind,
             replier_id,
+            is_error,
             key_expr,
             data_info,
             payload
@@ -1627,61 +1966,74 @@ impl Primitives for Session {
             }
         };
         match state.queries.get_mut(&qid) {
+            Some(query) if is_error => {
+                // Errors are not consolidated: each of them is delivered as soon as received
+                let mut value: Value = payload.into();
+                if let Some(encoding) = data_info.and_then(|info| info.encoding) {
+                    value.encoding = encoding;
+                }
+                let _ = query.rep_sender.send(Reply {
+                    sample: Err(value),
+                    replier_kind,
+                    replier_id,
+                });
+            }
             Some(query) => {
+                // Apply the filter and fragment of the selector in case the replier didn't
+                let mut sample = Sample::with_info(key_expr.into(), payload, data_info);
+                sample.attachment = Attachment::decode(attachment);
+                let sample = match query.value_filter.apply_on_reply(sample) {
+                    Some(sample) => sample,
+                    None => {
+                        trace!("Reply {} filtered out by the value selector", qid);
+                        return;
+                    }
+                };
+                let key = sample.key_expr.to_string();
+                let timestamp = sample.timestamp;
                 let new_reply = Reply {
-                    sample: Sample::with_info(key_expr.into(), payload, data_info),
+                    sample: Ok(sample),
                     replier_kind,
                     replier_id,
                 };
+                // The consolidated replies are all Ok, errors are never stored
+                let newer = |reply: &Reply| match &reply.sample {
+                    Ok(sample) => timestamp > sample.timestamp,
+                    Err(_) => true,
+                };
                 match query.reception_mode {
                     ConsolidationMode::None => {
                         let _ = query.rep_sender.send(new_reply);
                     }
-                    ConsolidationMode::Lazy => {
-                        match query
-                            .replies
-                            .as_ref()
-                            .unwrap()
-                            .get(new_reply.sample.key_expr.as_str())
-                        {
-                            Some(reply) => {
-                                if new_reply.sample.timestamp > reply.sample.timestamp {
-                                    query.replies.as_mut().unwrap().insert(
-                                        new_reply.sample.key_expr.to_string(),
-                                        new_reply.clone(),
-                                    );
-                                    let _ = query.rep_sender.send(new_reply);
-                                }
-                            }
-                            None => {
-                                query.replies.as_mut().unwrap().insert(
-                                    new_reply.sample.key_expr.to_string(),
-                                    new_reply.clone(),
-                                );
+                    ConsolidationMode::Lazy => match query.replies.as_ref().unwrap().get(&key) {
+                        Some(reply) => {
+                            if newer(reply) {
+                                query
+                                    .replies
+                                    .as_mut()
+                                    .unwrap()
+                                    .insert(key, new_reply.clone());
                                 let _ = query.rep_sender.send(new_reply);
                             }
                         }
-                    }
+                        None => {
+                            query
+                                .replies
+                                .as_mut()
+                                .unwrap()
+                                .insert(key, new_reply.clone());
+                            let _ = query.rep_sender.send(new_reply);
+                        }
+                    },
                     ConsolidationMode::Full => {
-                        match query
-                            .replies
-                            .as_ref()
-                            .unwrap()
-                            .get(new_reply.sample.key_expr.as_str())
-                        {
+                        match query.replies.as_ref().unwrap().get(&key) {
                             Some(reply) => {
-                                if new_reply.sample.timestamp > reply.sample.timestamp {
-                                    query.replies.as_mut().unwrap().insert(
-                                        new_reply.sample.key_expr.to_string(),
-                                        new_reply.clone(),
-                                    );
+                                if newer(reply) {
+                                    query.replies.as_mut().unwrap().insert(key, new_reply);
                                 }
                             }
                             None => {
-                                query.replies.as_mut().unwrap().insert(
-                                    new_reply.sample.key_expr.to_string(),
-                                    new_reply.clone(),
-                                );
+                                query.replies.as_mut().unwrap().insert(key, new_reply);
                             }
                         };
                     }
@@ -0,0 +1,141 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::future::timeout;
+use async_std::prelude::*;
+use async_std::task;
+use std::time::Duration;
+use zenoh::config::{Config, EndPoint, WhatAmI};
+use zenoh::prelude::*;
+use zenoh::subscriber::Subscriber;
+use zenoh::Session;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const SLEEP: Duration = Duration::from_secs(1);
+
+fn router_config(endpoint: &str) -> Config {
+    let mut config = zenoh::config::default();
+    config.set_mode(Some(WhatAmI::Router)).unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+        .insert_json5("listen/endpoints", &format!(r#"["{}"]"#, endpoint))
+        .unwrap();
+    config
+}
+
+fn client_config(endpoint: &str) -> Config {
+    let mut config = zenoh::config::client(vec![endpoint.parse::<EndPoint>().unwrap()]);
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+}
+
+fn attachment(trace_id: &str) -> Attachment {
+    vec![("trace-id", trace_id), ("schema", "v2")]
+        .into_iter()
+        .collect()
+}
+
+async fn next_sample(subscriber: &mut Subscriber<'_>) -> Sample {
+    timeout(TIMEOUT, subscriber.next()).await.unwrap().unwrap()
+}
+
+async fn check_attachments(publisher: &Session, subscriber: &Session) {
+    let key_expr = "/test/attachment";
+    let mut sub = subscriber.subscribe(key_expr).await.unwrap();
+    let mut queryable = subscriber.queryable(key_expr).await.unwrap();
+    task::sleep(SLEEP).await;
+
+    // Put
+    publisher
+        .put(key_expr, "put")
+        .attachment(attachment("1"))
+        .await
+        .unwrap();
+    let sample = next_sample(&mut sub).await;
+    assert_eq!(sample.attachment, Some(attachment("1")));
+
+    // Publisher
+    let publication = publisher.publish(key_expr).await.unwrap();
+    publication.send("send").unwrap();
+    assert_eq!(next_sample(&mut sub).await.attachment, None);
+    publication
+        .send_with_attachment("send", attachment("2"))
+        .unwrap();
+    let sample = next_sample(&mut sub).await;
+    let received = sample.attachment.unwrap();
+    assert_eq!(received.get("trace-id"), Some("2".as_bytes()));
+    assert_eq!(received.get("schema"), Some("v2".as_bytes()));
+    assert_eq!(received.len(), 2);
+
+    // Query and reply
+    let replies = publisher
+        .get(key_expr)
+        .attachment(attachment("3"))
+        .await
+        .unwrap();
+    let query = timeout(TIMEOUT, queryable.next()).await.unwrap().unwrap();
+    assert_eq!(query.attachment(), Some(&attachment("3")));
+    query.reply(Sample::new(key_expr, "reply").with_attachment(attachment("4")));
+    drop(query);
+    let replies: Vec<_> = timeout(TIMEOUT, replies.collect()).await.unwrap();
+    assert_eq!(replies.len(), 1);
+    assert_eq!(
+        replies[0].sample.as_ref().unwrap().attachment,
+        Some(attachment("4"))
+    );
+
+    drop(publication);
+    sub.close().await.unwrap();
+    queryable.close().await.unwrap();
+}
+
+#[test]
+fn attachment_client() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "tcp/127.0.0.1:17463";
+        let router = zenoh::open(router_config(endpoint)).await.unwrap();
+        task::sleep(SLEEP).await;
+        let publisher = zenoh::open(client_config(endpoint)).await.unwrap();
+        let subscriber = zenoh::open(client_config(endpoint)).await.unwrap();
+        task::sleep(SLEEP).await;
+
+        check_attachments(&publisher, &subscriber).await;
+
+        publisher.close().await.unwrap();
+        subscriber.close().await.unwrap();
+        router.close().await.unwrap();
+    });
+}
+
+#[test]
+fn attachment_local() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let mut config = zenoh::config::peer();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        let session = zenoh::open(config).await.unwrap();
+
+        check_attachments(&session, &session).await;
+
+        session.close().await.unwrap();
+    });
+}
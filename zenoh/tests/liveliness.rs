@@ -0,0 +1,180 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::future::timeout;
+use async_std::prelude::*;
+use async_std::task;
+use std::time::Duration;
+use zenoh::config::{Config, EndPoint, WhatAmI};
+use zenoh::prelude::*;
+use zenoh::subscriber::Subscriber;
+use zenoh::Session;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const SLEEP: Duration = Duration::from_secs(1);
+
+fn listen_config(mode: WhatAmI, endpoint: &str) -> Config {
+    let mut config = zenoh::config::default();
+    config.set_mode(Some(mode)).unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+        .insert_json5("listen/endpoints", &format!(r#"["{}"]"#, endpoint))
+        .unwrap();
+    config
+}
+
+fn connect_config(mode: WhatAmI, endpoint: &str) -> Config {
+    let mut config = zenoh::config::client(vec![endpoint.parse::<EndPoint>().unwrap()]);
+    config.set_mode(Some(mode)).unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+}
+
+async fn next_sample(subscriber: &mut Subscriber<'_>) -> Sample {
+    timeout(TIMEOUT, subscriber.next()).await.unwrap().unwrap()
+}
+
+async fn alive_tokens(session: &Session, key_expr: &str) -> Vec<String> {
+    let replies = session.liveliness().get(key_expr).await.unwrap();
+    replies
+        .map(|reply| reply.sample.unwrap().key_expr.to_string())
+        .collect()
+        .await
+}
+
+// Check the tokens of `owner` as seen by `observer`, and by `router` if any.
+async fn check_liveliness(owner: Session, observer: &Session, router: Option<&Session>) {
+    let mut subscriber = observer
+        .liveliness()
+        .subscribe("/test/liveliness/**")
+        .await
+        .unwrap();
+
+    // A token is propagated through the routing infrastructure
+    let token = owner
+        .liveliness()
+        .declare_token("/test/liveliness/1")
+        .await
+        .unwrap();
+    let sample = next_sample(&mut subscriber).await;
+    assert_eq!(sample.key_expr.as_str(), "/test/liveliness/1");
+    assert_eq!(sample.kind, SampleKind::Put);
+    assert_eq!(
+        alive_tokens(observer, "/test/liveliness/*").await,
+        vec!["/test/liveliness/1".to_string()]
+    );
+    if let Some(router) = router {
+        assert_eq!(
+            alive_tokens(router, "/test/**").await,
+            vec!["/test/liveliness/1".to_string()]
+        );
+    }
+    assert!(alive_tokens(observer, "/other/**").await.is_empty());
+
+    // Wildcards are not allowed in tokens
+    assert!(owner
+        .liveliness()
+        .declare_token("/test/liveliness/*")
+        .await
+        .is_err());
+
+    // Subscriptions are not tokens, and tokens are not subscribers
+    let plain = owner.subscribe("/test/liveliness/plain").await.unwrap();
+    let publisher = observer.publish("/test/liveliness/1").await.unwrap();
+    task::sleep(SLEEP).await;
+    assert!(!publisher.matching_status().unwrap().matching_subscribers());
+    assert_eq!(
+        alive_tokens(observer, "/test/liveliness/*").await,
+        vec!["/test/liveliness/1".to_string()]
+    );
+    plain.close().await.unwrap();
+    drop(publisher);
+
+    // Undeclaring the token retracts it
+    token.undeclare().await.unwrap();
+    let sample = next_sample(&mut subscriber).await;
+    assert_eq!(sample.key_expr.as_str(), "/test/liveliness/1");
+    assert_eq!(sample.kind, SampleKind::Delete);
+    assert!(alive_tokens(observer, "/test/liveliness/*")
+        .await
+        .is_empty());
+
+    // Closing the session owning the token retracts it
+    let token = owner
+        .liveliness()
+        .declare_token("/test/liveliness/2")
+        .await
+        .unwrap();
+    let sample = next_sample(&mut subscriber).await;
+    assert_eq!(sample.key_expr.as_str(), "/test/liveliness/2");
+    assert_eq!(sample.kind, SampleKind::Put);
+    // Keep the token declared until the session is closed
+    std::mem::forget(token);
+    owner.close().await.unwrap();
+    let sample = next_sample(&mut subscriber).await;
+    assert_eq!(sample.key_expr.as_str(), "/test/liveliness/2");
+    assert_eq!(sample.kind, SampleKind::Delete);
+    assert!(alive_tokens(observer, "/test/**").await.is_empty());
+
+    subscriber.close().await.unwrap();
+}
+
+#[test]
+fn liveliness_token() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "tcp/127.0.0.1:17460";
+        let router = zenoh::open(listen_config(WhatAmI::Router, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+        let owner = zenoh::open(connect_config(WhatAmI::Client, endpoint))
+            .await
+            .unwrap();
+        let observer = zenoh::open(connect_config(WhatAmI::Client, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+
+        check_liveliness(owner, &observer, Some(&router)).await;
+
+        observer.close().await.unwrap();
+        router.close().await.unwrap();
+    });
+}
+
+#[test]
+fn liveliness_token_peer() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "tcp/127.0.0.1:17466";
+        let owner = zenoh::open(listen_config(WhatAmI::Peer, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+        let observer = zenoh::open(connect_config(WhatAmI::Peer, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+
+        check_liveliness(owner, &observer, None).await;
+
+        observer.close().await.unwrap();
+    });
+}
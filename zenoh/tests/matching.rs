@@ -0,0 +1,168 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::future::timeout;
+use async_std::task;
+use flume::Receiver;
+use std::time::Duration;
+use zenoh::config::{Config, EndPoint, WhatAmI};
+use zenoh::prelude::*;
+use zenoh::publication::MatchingStatus;
+use zenoh::Session;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const SLEEP: Duration = Duration::from_secs(1);
+
+fn listen_config(mode: WhatAmI, endpoint: &str) -> Config {
+    let mut config = zenoh::config::default();
+    config.set_mode(Some(mode)).unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+        .insert_json5("listen/endpoints", &format!(r#"["{}"]"#, endpoint))
+        .unwrap();
+    config
+}
+
+fn connect_config(mode: WhatAmI, endpoint: &str) -> Config {
+    let mut config = zenoh::config::client(vec![endpoint.parse::<EndPoint>().unwrap()]);
+    config.set_mode(Some(mode)).unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+}
+
+async fn next_status(statuses: &Receiver<MatchingStatus>) -> bool {
+    timeout(TIMEOUT, statuses.recv_async())
+        .await
+        .unwrap()
+        .unwrap()
+        .matching_subscribers()
+}
+
+async fn check_matching(publisher_session: &Session, subscriber_session: Session) {
+    let key_expr = "/test/matching/status";
+    let publisher = publisher_session.publish(key_expr).await.unwrap();
+    let (sender, statuses) = flume::unbounded();
+    let _listener = publisher
+        .matching_listener(move |status| sender.send(status).unwrap())
+        .await
+        .unwrap();
+    assert!(!publisher.matching_status().unwrap().matching_subscribers());
+
+    // A remote subscriber matches the publisher
+    let subscriber = subscriber_session
+        .subscribe("/test/matching/*")
+        .await
+        .unwrap();
+    assert!(next_status(&statuses).await);
+    assert!(publisher.matching_status().unwrap().matching_subscribers());
+
+    // Undeclaring the subscriber unmatches the publisher
+    subscriber.close().await.unwrap();
+    assert!(!next_status(&statuses).await);
+    assert!(!publisher.matching_status().unwrap().matching_subscribers());
+
+    // A non-matching subscriber does not change the status
+    let other = subscriber_session.subscribe("/test/other").await.unwrap();
+    task::sleep(SLEEP).await;
+    assert!(statuses.is_empty());
+    other.close().await.unwrap();
+
+    // Closing the session of the subscriber unmatches the publisher
+    let subscriber = subscriber_session.subscribe(key_expr).await.unwrap();
+    assert!(next_status(&statuses).await);
+    std::mem::forget(subscriber);
+    subscriber_session.close().await.unwrap();
+    assert!(!next_status(&statuses).await);
+}
+
+#[test]
+fn matching_status_client() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "tcp/127.0.0.1:17461";
+        let router = zenoh::open(listen_config(WhatAmI::Router, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+        let publisher_session = zenoh::open(connect_config(WhatAmI::Client, endpoint))
+            .await
+            .unwrap();
+        let subscriber_session = zenoh::open(connect_config(WhatAmI::Client, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+
+        check_matching(&publisher_session, subscriber_session).await;
+
+        publisher_session.close().await.unwrap();
+        router.close().await.unwrap();
+    });
+}
+
+#[test]
+fn matching_status_peer() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "tcp/127.0.0.1:17462";
+        let publisher_session = zenoh::open(listen_config(WhatAmI::Peer, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+        let subscriber_session = zenoh::open(connect_config(WhatAmI::Peer, endpoint))
+            .await
+            .unwrap();
+        task::sleep(SLEEP).await;
+
+        check_matching(&publisher_session, subscriber_session).await;
+
+        publisher_session.close().await.unwrap();
+    });
+}
+
+#[test]
+fn matching_status_local() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let mut config = zenoh::config::peer();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        let session = zenoh::open(config).await.unwrap();
+        let key_expr = "/test/matching/local";
+        let publisher = session.publish(key_expr).await.unwrap();
+        let remote_publisher = session
+            .publish(key_expr)
+            .local_routing(false)
+            .await
+            .unwrap();
+
+        // The subscribers of the session only match with local routing
+        let subscriber = session.subscribe(key_expr).await.unwrap();
+        assert!(publisher.matching_status().unwrap().matching_subscribers());
+        assert!(!remote_publisher
+            .matching_status()
+            .unwrap()
+            .matching_subscribers());
+
+        subscriber.close().await.unwrap();
+        assert!(!publisher.matching_status().unwrap().matching_subscribers());
+        session.close().await.unwrap();
+    });
+}
@@ -0,0 +1,176 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::future::timeout;
+use async_std::prelude::*;
+use async_std::task;
+use std::time::Duration;
+use zenoh::config::Config;
+use zenoh::prelude::*;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const SLEEP: Duration = Duration::from_millis(100);
+const MSG_COUNT: usize = 10;
+
+fn mcast_peer_config(endpoint: &str) -> Config {
+    let mut config = zenoh::config::peer();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+        .insert_json5("listen/endpoints", r#"["tcp/127.0.0.1:0"]"#)
+        .unwrap();
+    config
+        .insert_json5("transport/multicast/join_interval", "200")
+        .unwrap();
+    config
+        .insert_json5(
+            "transport/multicast/listen",
+            &format!(r#"["{}"]"#, endpoint),
+        )
+        .unwrap();
+    config
+}
+
+#[test]
+fn multicast_pubsub() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "udp/224.0.0.224:17448";
+        let publisher = zenoh::open(mcast_peer_config(endpoint)).await.unwrap();
+        let subscriber_session = zenoh::open(mcast_peer_config(endpoint)).await.unwrap();
+
+        let key_expr = "/test/multicast/pubsub";
+        let mut subscriber = subscriber_session.subscribe(key_expr).await.unwrap();
+
+        // Publish until the subscription declared over the multicast group reaches the publisher
+        timeout(TIMEOUT, async {
+            loop {
+                publisher.put(key_expr, "ping").await.unwrap();
+                if timeout(SLEEP, subscriber.receiver().recv_async())
+                    .await
+                    .is_ok()
+                {
+                    break;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        for i in 0..MSG_COUNT {
+            publisher.put(key_expr, i as i64).await.unwrap();
+        }
+        let mut received = 0;
+        timeout(TIMEOUT, async {
+            while received < MSG_COUNT {
+                let sample = subscriber.receiver().recv_async().await.unwrap();
+                if sample.value.as_integer().is_some() {
+                    received += 1;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        subscriber.close().await.unwrap();
+        subscriber_session.close().await.unwrap();
+        publisher.close().await.unwrap();
+    });
+}
+
+#[test]
+fn multicast_query() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "udp/224.0.0.224:17451";
+        let querier = zenoh::open(mcast_peer_config(endpoint)).await.unwrap();
+        let replier = zenoh::open(mcast_peer_config(endpoint)).await.unwrap();
+
+        let key_expr = "/test/multicast/query";
+        let mut queryable = replier.queryable(key_expr).await.unwrap();
+
+        // Query until the queryable declared over the multicast group reaches the querier:
+        // the replies are complete once the replier sent its final reply.
+        timeout(TIMEOUT, async {
+            loop {
+                let replies = querier.get(key_expr).await.unwrap();
+                if let Ok(Some(query)) = timeout(SLEEP, queryable.next()).await {
+                    query.reply(Sample::new(key_expr, "pong"));
+                    drop(query);
+                }
+                let replies: Vec<_> = replies.collect().await;
+                if let Some(reply) = replies.first() {
+                    assert_eq!(replies.len(), 1);
+                    assert_eq!(reply.sample.as_ref().unwrap().key_expr.as_str(), key_expr);
+                    break;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        queryable.close().await.unwrap();
+        replier.close().await.unwrap();
+        querier.close().await.unwrap();
+    });
+}
+
+// The subscriptions of the peers connected over unicast are declared to the multicast group
+// as well, so that the publications of the group are routed to them.
+#[test]
+fn multicast_pubsub_unicast_peer() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "udp/224.0.0.224:17449";
+        let publisher = zenoh::open(mcast_peer_config(endpoint)).await.unwrap();
+        let mut config = mcast_peer_config(endpoint);
+        config
+            .insert_json5("listen/endpoints", r#"["tcp/127.0.0.1:17450"]"#)
+            .unwrap();
+        let relay = zenoh::open(config).await.unwrap();
+        let mut config = zenoh::config::peer();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        config
+            .insert_json5("connect/endpoints", r#"["tcp/127.0.0.1:17450"]"#)
+            .unwrap();
+        let subscriber_session = zenoh::open(config).await.unwrap();
+
+        let key_expr = "/test/multicast/unicast_peer";
+        let mut subscriber = subscriber_session.subscribe(key_expr).await.unwrap();
+
+        timeout(TIMEOUT, async {
+            loop {
+                publisher.put(key_expr, "ping").await.unwrap();
+                if timeout(SLEEP, subscriber.receiver().recv_async())
+                    .await
+                    .is_ok()
+                {
+                    break;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        subscriber.close().await.unwrap();
+        subscriber_session.close().await.unwrap();
+        relay.close().await.unwrap();
+        publisher.close().await.unwrap();
+    });
+}
@@ -0,0 +1,124 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::future::timeout;
+use async_std::prelude::*;
+use async_std::task;
+use std::time::Duration;
+use zenoh::config::{Config, EndPoint, WhatAmI};
+use zenoh::prelude::*;
+use zenoh::Session;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const SLEEP: Duration = Duration::from_secs(1);
+
+fn router_config(endpoint: &str) -> Config {
+    let mut config = zenoh::config::default();
+    config.set_mode(Some(WhatAmI::Router)).unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+        .insert_json5("listen/endpoints", &format!(r#"["{}"]"#, endpoint))
+        .unwrap();
+    config
+}
+
+fn client_config(endpoint: &str) -> Config {
+    let mut config = zenoh::config::client(vec![endpoint.parse::<EndPoint>().unwrap()]);
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+}
+
+// Query a queryable replying with the length of the value of the query
+async fn check_query_value(querier: &Session, replier: &Session) {
+    let key_expr = "/test/query/value";
+    let mut queryable = replier.queryable(key_expr).await.unwrap();
+    task::sleep(SLEEP).await;
+
+    let values = vec![
+        None,
+        Some(Value::from("hello").encoding(Encoding::TEXT_PLAIN)),
+        Some(Value::from(vec![0u8; 1024])),
+    ];
+    for value in values {
+        let getter = querier.get(key_expr);
+        let getter = match value.clone() {
+            Some(value) => getter.with_value(value),
+            None => getter,
+        };
+        let replies = getter.await.unwrap();
+
+        let query = timeout(TIMEOUT, queryable.next()).await.unwrap().unwrap();
+        let received = query.value().cloned();
+        assert_eq!(
+            received.as_ref().map(|v| v.payload.contiguous().to_vec()),
+            value.as_ref().map(|v| v.payload.contiguous().to_vec())
+        );
+        assert_eq!(
+            received.as_ref().map(|v| v.encoding.clone()),
+            value.as_ref().map(|v| v.encoding.clone())
+        );
+        let len = received.map(|v| v.payload.len()).unwrap_or(0);
+        query.reply(Sample::new(key_expr, len as i64));
+        drop(query);
+
+        let replies: Vec<_> = timeout(TIMEOUT, replies.collect()).await.unwrap();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(
+            replies[0].sample.as_ref().unwrap().value.as_integer(),
+            Some(value.map(|v| v.payload.len()).unwrap_or(0) as i64)
+        );
+    }
+
+    queryable.close().await.unwrap();
+}
+
+#[test]
+fn query_value_client() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "tcp/127.0.0.1:17464";
+        let router = zenoh::open(router_config(endpoint)).await.unwrap();
+        task::sleep(SLEEP).await;
+        let querier = zenoh::open(client_config(endpoint)).await.unwrap();
+        let replier = zenoh::open(client_config(endpoint)).await.unwrap();
+        task::sleep(SLEEP).await;
+
+        check_query_value(&querier, &replier).await;
+
+        querier.close().await.unwrap();
+        replier.close().await.unwrap();
+        router.close().await.unwrap();
+    });
+}
+
+#[test]
+fn query_value_local() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let mut config = zenoh::config::peer();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        let session = zenoh::open(config).await.unwrap();
+
+        check_query_value(&session, &session).await;
+
+        session.close().await.unwrap();
+    });
+}
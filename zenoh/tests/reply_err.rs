@@ -0,0 +1,112 @@
+//
+// Copyright (c) 2022 ZettaScale Technology
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ZettaScale Zenoh Team, <zenoh@zettascale.tech>
+//
+use async_std::future::timeout;
+use async_std::prelude::*;
+use async_std::task;
+use std::time::Duration;
+use zenoh::config::{Config, EndPoint, WhatAmI};
+use zenoh::prelude::*;
+use zenoh::Session;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const SLEEP: Duration = Duration::from_secs(1);
+
+fn router_config(endpoint: &str) -> Config {
+    let mut config = zenoh::config::default();
+    config.set_mode(Some(WhatAmI::Router)).unwrap();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+        .insert_json5("listen/endpoints", &format!(r#"["{}"]"#, endpoint))
+        .unwrap();
+    config
+}
+
+fn client_config(endpoint: &str) -> Config {
+    let mut config = zenoh::config::client(vec![endpoint.parse::<EndPoint>().unwrap()]);
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    config
+}
+
+// Query a queryable replying with an error and then with a sample
+async fn check_reply_err(querier: &Session, replier: &Session) {
+    let key_expr = "/test/reply/err";
+    let mut queryable = replier.queryable(key_expr).await.unwrap();
+    task::sleep(SLEEP).await;
+
+    let replies = querier.get(key_expr).await.unwrap();
+    let query = timeout(TIMEOUT, queryable.next()).await.unwrap().unwrap();
+    query.reply_err(Value::from("failure").encoding(Encoding::TEXT_PLAIN));
+    query.reply(Sample::new(key_expr, "success"));
+    drop(query);
+
+    let replier_id = replier.id().await;
+    let replies: Vec<_> = timeout(TIMEOUT, replies.collect()).await.unwrap();
+    assert_eq!(replies.len(), 2);
+    for reply in replies {
+        assert_eq!(reply.replier_id.to_string(), replier_id);
+        match reply.sample {
+            Ok(sample) => {
+                assert_eq!(sample.key_expr.as_str(), key_expr);
+                assert_eq!(&*sample.value.payload.contiguous(), b"success");
+            }
+            Err(value) => {
+                assert_eq!(value.encoding, Encoding::TEXT_PLAIN);
+                assert_eq!(&*value.payload.contiguous(), b"failure");
+            }
+        }
+    }
+
+    queryable.close().await.unwrap();
+}
+
+#[test]
+fn reply_err_client() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let endpoint = "tcp/127.0.0.1:17465";
+        let router = zenoh::open(router_config(endpoint)).await.unwrap();
+        task::sleep(SLEEP).await;
+        let querier = zenoh::open(client_config(endpoint)).await.unwrap();
+        let replier = zenoh::open(client_config(endpoint)).await.unwrap();
+        task::sleep(SLEEP).await;
+
+        check_reply_err(&querier, &replier).await;
+
+        querier.close().await.unwrap();
+        replier.close().await.unwrap();
+        router.close().await.unwrap();
+    });
+}
+
+#[test]
+fn reply_err_local() {
+    task::block_on(async {
+        let _ = env_logger::try_init();
+
+        let mut config = zenoh::config::peer();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        let session = zenoh::open(config).await.unwrap();
+
+        check_reply_err(&session, &session).await;
+
+        session.close().await.unwrap();
+    });
+}
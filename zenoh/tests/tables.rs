@@ -13,18 +13,23 @@
 //
 use async_std::sync::Arc;
 use std::convert::TryInto;
+use std::sync::Weak;
 use std::time::Duration;
 use uhlc::HLC;
+use zenoh::net::routing::acl::{AccessControl, AdminConfigHandler, AdminConfigPolicy};
+use zenoh::net::routing::face::FaceState;
 use zenoh::net::routing::router::*;
+use zenoh_config::Config;
 use zenoh_config::ZN_QUERIES_DEFAULT_TIMEOUT_DEFAULT;
-use zenoh_core::zlock;
+use zenoh_core::{zlock, zwrite};
 use zenoh_protocol::io::ZBuf;
-use zenoh_protocol::proto::{DataInfo, RoutingContext};
+use zenoh_protocol::proto::{data_kind, Attachment, DataInfo, QueryBody, RoutingContext};
 use zenoh_protocol_core::key_expr::intersect;
 use zenoh_protocol_core::{
-    Channel, CongestionControl, ConsolidationStrategy, KeyExpr, PeerId, QueryTarget, QueryableInfo,
-    Reliability, SubInfo, SubMode, WhatAmI, ZInt, EMPTY_EXPR_ID,
+    queryable, Channel, CongestionControl, ConsolidationStrategy, KeyExpr, PeerId, QueryTarget,
+    QueryableInfo, Reliability, SubInfo, SubMode, WhatAmI, ZInt, EMPTY_EXPR_ID,
 };
+use zenoh_transport::unicast::establishment::authenticator::AuthId;
 use zenoh_transport::{DummyPrimitives, Primitives};
 
 #[test]
@@ -362,6 +367,7 @@ fn clean_test() {
 pub struct ClientPrimitives {
     data: std::sync::Mutex<Option<KeyExpr<'static>>>,
     mapping: std::sync::Mutex<std::collections::HashMap<ZInt, String>>,
+    reply_finals: std::sync::Mutex<Vec<ZInt>>,
 }
 
 impl ClientPrimitives {
@@ -369,6 +375,7 @@ impl ClientPrimitives {
         ClientPrimitives {
             data: std::sync::Mutex::new(None),
             mapping: std::sync::Mutex::new(std::collections::HashMap::new()),
+            reply_finals: std::sync::Mutex::new(vec![]),
         }
     }
 
@@ -408,6 +415,10 @@ impl ClientPrimitives {
     fn get_last_key(&self) -> Option<KeyExpr> {
         self.data.lock().unwrap().as_ref().cloned()
     }
+
+    fn get_reply_finals(&self) -> Vec<ZInt> {
+        self.reply_finals.lock().unwrap().clone()
+    }
 }
 
 impl Primitives for ClientPrimitives {
@@ -448,6 +459,9 @@ impl Primitives for ClientPrimitives {
     ) {
     }
 
+    fn decl_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+    fn forget_token(&self, _key_expr: &KeyExpr, _routing_context: Option<RoutingContext>) {}
+
     fn send_data(
         &self,
         key_expr: &KeyExpr,
@@ -456,6 +470,7 @@ impl Primitives for ClientPrimitives {
         _congestion_control: CongestionControl,
         _info: Option<DataInfo>,
         _routing_context: Option<RoutingContext>,
+        _attachment: Option<Attachment>,
     ) {
         *zlock!(self.data) = Some(key_expr.to_owned());
     }
@@ -467,7 +482,9 @@ impl Primitives for ClientPrimitives {
         _qid: ZInt,
         _target: QueryTarget,
         _consolidation: ConsolidationStrategy,
+        _body: Option<QueryBody>,
         _routing_context: Option<RoutingContext>,
+        _attachment: Option<Attachment>,
     ) {
     }
 
@@ -476,12 +493,16 @@ impl Primitives for ClientPrimitives {
         _qid: ZInt,
         _replier_kind: ZInt,
         _replier_id: PeerId,
+        _is_error: bool,
         _key_expr: KeyExpr,
         _info: Option<DataInfo>,
         _payload: ZBuf,
+        _attachment: Option<Attachment>,
     ) {
     }
-    fn send_reply_final(&self, _qid: ZInt) {}
+    fn send_reply_final(&self, qid: ZInt) {
+        zlock!(self.reply_finals).push(qid);
+    }
 
     fn send_pull(
         &self,
@@ -595,6 +616,7 @@ fn client_test() {
         None,
         ZBuf::default(),
         None,
+        None,
     );
 
     // functionnal check
@@ -621,6 +643,7 @@ fn client_test() {
         None,
         ZBuf::default(),
         None,
+        None,
     );
 
     // functionnal check
@@ -647,6 +670,7 @@ fn client_test() {
         None,
         ZBuf::default(),
         None,
+        None,
     );
 
     // functionnal check
@@ -673,6 +697,7 @@ fn client_test() {
         None,
         ZBuf::default(),
         None,
+        None,
     );
 
     // functionnal check
@@ -699,6 +724,7 @@ fn client_test() {
         None,
         ZBuf::default(),
         None,
+        None,
     );
 
     // functionnal check
@@ -713,3 +739,357 @@ fn client_test() {
     // mapping strategy check
     // assert_eq!(primitives2.get_last_key().unwrap(), KeyExpr::IdWithSuffix(31, "/z2_pub1".to_string()));
 }
+
+#[test]
+fn query_timeout_test() {
+    let router = Router::new(
+        PeerId::new(0, [0; 16]),
+        WhatAmI::Client,
+        Some(Arc::new(HLC::default())),
+        Duration::from_millis(100),
+    );
+
+    let primitives0 = Arc::new(ClientPrimitives::new());
+    let primitives1 = Arc::new(ClientPrimitives::new());
+    let (face0, face1) = {
+        let mut tables = zwrite!(router.tables);
+        let face0 = tables.open_face(
+            PeerId::new(0, [0; 16]),
+            WhatAmI::Client,
+            primitives0.clone(),
+        );
+        let face1 = tables.open_face(
+            PeerId::new(0, [0; 16]),
+            WhatAmI::Client,
+            primitives1.clone(),
+        );
+        declare_client_queryable(
+            &mut tables,
+            &mut face1.upgrade().unwrap(),
+            &"/test/timeout".into(),
+            queryable::EVAL,
+            &QueryableInfo::default(),
+        );
+        (face0, face1)
+    };
+
+    // The queryable never sends its final reply: the router must send it on timeout.
+    route_query(
+        &router.tables,
+        &face0.upgrade().unwrap(),
+        &"/test/timeout".into(),
+        "",
+        1,
+        QueryTarget::default(),
+        ConsolidationStrategy::default(),
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(primitives0.get_reply_finals().is_empty());
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(primitives0.get_reply_finals(), vec![1]);
+
+    // A per-query timeout overrides the default one.
+    route_query(
+        &router.tables,
+        &face0.upgrade().unwrap(),
+        &"/test/timeout".into(),
+        "",
+        2,
+        QueryTarget::default(),
+        ConsolidationStrategy::default(),
+        None,
+        None,
+        Some(Duration::from_secs(2)),
+        None,
+    );
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(primitives0.get_reply_finals(), vec![1]);
+
+    std::thread::sleep(Duration::from_secs(2));
+    assert_eq!(primitives0.get_reply_finals(), vec![1, 2]);
+
+    // Expired queries are not finalized again when the queryable face closes.
+    let mut tables = zwrite!(router.tables);
+    tables.close_face(&face1);
+    assert_eq!(primitives0.get_reply_finals(), vec![1, 2]);
+}
+
+#[test]
+fn acl_test() {
+    let local_pid: PeerId = "a0".parse().unwrap();
+    let allowed_pid: PeerId = "a1".parse().unwrap();
+    let denied_pid: PeerId = "a2".parse().unwrap();
+    let config = Config::from_deserializer(&mut serde_json::Deserializer::from_str(&format!(
+        r#"{{"access_control": {{
+            "enabled": true,
+            "default_permission": "deny",
+            "rules": [
+                {{"peer_ids": ["{}"], "key_exprs": ["/test/**"], "actions": ["put", "subscribe"], "permission": "allow"}},
+                {{"key_exprs": ["/test/private/**"], "actions": ["put", "subscribe"], "permission": "deny"}}
+            ]
+        }}}}"#,
+        allowed_pid
+    )))
+    .unwrap();
+    let acl = AccessControl::from_config(&config).unwrap().unwrap();
+
+    let mut tables = Tables::new(
+        local_pid,
+        WhatAmI::Client,
+        Some(Arc::new(HLC::default())),
+        Duration::from_millis(ZN_QUERIES_DEFAULT_TIMEOUT_DEFAULT.parse().unwrap()),
+    );
+    tables.set_access_control(Some(acl));
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable,
+        mode: SubMode::Push,
+        period: None,
+    };
+
+    let primitives0 = Arc::new(ClientPrimitives::new());
+    let face0 = tables.open_face(local_pid, WhatAmI::Client, primitives0.clone());
+    let primitives1 = Arc::new(ClientPrimitives::new());
+    let face1 = tables._open_remote_face(allowed_pid, WhatAmI::Client, primitives1.clone(), vec![]);
+    let primitives2 = Arc::new(ClientPrimitives::new());
+    let face2 = tables._open_remote_face(denied_pid, WhatAmI::Client, primitives2.clone(), vec![]);
+    // a remote peer claiming the local peer id
+    let primitives3 = Arc::new(ClientPrimitives::new());
+    let face3 = tables._open_remote_face(local_pid, WhatAmI::Client, primitives3, vec![]);
+
+    // subscriptions from the denied peer and intersecting the private keys are rejected
+    declare_client_subscription(
+        &mut tables,
+        &mut face1.upgrade().unwrap(),
+        &"/test/public/**".into(),
+        &sub_info,
+    );
+    declare_client_subscription(
+        &mut tables,
+        &mut face1.upgrade().unwrap(),
+        &"/test/**".into(),
+        &sub_info,
+    );
+    declare_client_subscription(
+        &mut tables,
+        &mut face2.upgrade().unwrap(),
+        &"/test/public/**".into(),
+        &sub_info,
+    );
+
+    // local faces are not subject to access control
+    route_data(
+        &tables,
+        &face0.upgrade().unwrap(),
+        &"/test/public/a".into(),
+        Channel::default(),
+        CongestionControl::default(),
+        None,
+        ZBuf::default(),
+        None,
+        None,
+    );
+    assert_eq!(primitives1.get_last_name().unwrap(), "/test/public/a");
+    assert!(primitives2.get_last_name().is_none());
+
+    // publications from the denied peer are dropped
+    primitives1.clear_data();
+    route_data(
+        &tables,
+        &face2.upgrade().unwrap(),
+        &"/test/public/b".into(),
+        Channel::default(),
+        CongestionControl::default(),
+        None,
+        ZBuf::default(),
+        None,
+        None,
+    );
+    assert!(primitives1.get_last_name().is_none());
+
+    // remote faces are subject to access control whatever their peer id
+    route_data(
+        &tables,
+        &face3.upgrade().unwrap(),
+        &"/test/public/d".into(),
+        Channel::default(),
+        CongestionControl::default(),
+        None,
+        ZBuf::default(),
+        None,
+        None,
+    );
+    assert!(primitives1.get_last_name().is_none());
+
+    // publications from the allowed peer are routed, except on the private keys
+    declare_client_subscription(
+        &mut tables,
+        &mut face0.upgrade().unwrap(),
+        &"/test/**".into(),
+        &sub_info,
+    );
+    route_data(
+        &tables,
+        &face1.upgrade().unwrap(),
+        &"/test/c".into(),
+        Channel::default(),
+        CongestionControl::default(),
+        None,
+        ZBuf::default(),
+        None,
+        None,
+    );
+    assert_eq!(primitives0.get_last_name().unwrap(), "/test/c");
+    primitives0.clear_data();
+    route_data(
+        &tables,
+        &face1.upgrade().unwrap(),
+        &"/test/private/c".into(),
+        Channel::default(),
+        CongestionControl::default(),
+        None,
+        ZBuf::default(),
+        None,
+        None,
+    );
+    assert!(primitives0.get_last_name().is_none());
+
+    // deletions are not allowed
+    let mut info = DataInfo::new();
+    info.kind = Some(data_kind::DELETE);
+    route_data(
+        &tables,
+        &face1.upgrade().unwrap(),
+        &"/test/c".into(),
+        Channel::default(),
+        CongestionControl::default(),
+        Some(info),
+        ZBuf::default(),
+        None,
+        None,
+    );
+    assert!(primitives0.get_last_name().is_none());
+
+    // queryables are declared only where replies are allowed
+    declare_client_queryable(
+        &mut tables,
+        &mut face2.upgrade().unwrap(),
+        &"/test/public/**".into(),
+        queryable::EVAL,
+        &QueryableInfo::default(),
+    );
+
+    let denials = tables.access_control().unwrap().denials();
+    assert!(denials.contains(&("put", 3)));
+    assert!(denials.contains(&("delete", 1)));
+    assert!(denials.contains(&("subscribe", 2)));
+    assert!(denials.contains(&("reply", 1)));
+}
+
+#[derive(Default)]
+struct ConfigChanges(std::sync::Mutex<Vec<(String, String)>>);
+
+impl AdminConfigHandler for ConfigChanges {
+    fn config_change(&self, who: &str, key_expr: &str, _payload: ZBuf, _info: Option<DataInfo>) {
+        zlock!(self.0).push((who.to_string(), key_expr.to_string()));
+    }
+}
+
+#[test]
+fn admin_config_writers_test() {
+    let local_pid: PeerId = "a0".parse().unwrap();
+    let remote_pid: PeerId = "a1".parse().unwrap();
+    let config = Config::from_deserializer(&mut serde_json::Deserializer::from_str(
+        r#"{"adminspace": {"writers": {"users": ["admin"]}}}"#,
+    ))
+    .unwrap();
+    let mut tables = Tables::new(
+        local_pid,
+        WhatAmI::Client,
+        None,
+        Duration::from_millis(ZN_QUERIES_DEFAULT_TIMEOUT_DEFAULT.parse().unwrap()),
+    );
+    tables.set_admin_config_policy(AdminConfigPolicy::from_config(&config).unwrap());
+    let changes = Arc::new(ConfigChanges::default());
+    tables.set_admin_config_handler(changes.clone());
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable,
+        mode: SubMode::Push,
+        period: None,
+    };
+
+    let primitives0 = Arc::new(ClientPrimitives::new());
+    let face0 = tables.open_face(local_pid, WhatAmI::Client, primitives0.clone());
+    declare_client_subscription(
+        &mut tables,
+        &mut face0.upgrade().unwrap(),
+        &"/@/router/A0/config/**".into(),
+        &sub_info,
+    );
+    declare_client_subscription(
+        &mut tables,
+        &mut face0.upgrade().unwrap(),
+        &"/demo/**".into(),
+        &sub_info,
+    );
+    let admin = tables._open_remote_face(
+        remote_pid,
+        WhatAmI::Client,
+        Arc::new(DummyPrimitives::new()),
+        vec![AuthId::Username("admin".to_string())],
+    );
+    let other = tables._open_remote_face(
+        remote_pid,
+        WhatAmI::Client,
+        Arc::new(DummyPrimitives::new()),
+        vec![AuthId::Username("other".to_string())],
+    );
+    // a remote face claiming the id of this router is not trusted as a local one
+    let spoofer = tables._open_remote_face(
+        local_pid,
+        WhatAmI::Client,
+        Arc::new(DummyPrimitives::new()),
+        vec![],
+    );
+    let local = tables.open_face(local_pid, WhatAmI::Client, Arc::new(DummyPrimitives::new()));
+
+    let put = |tables: &Tables, face: &Weak<FaceState>, key_expr: &str| {
+        primitives0.clear_data();
+        route_data(
+            tables,
+            &face.upgrade().unwrap(),
+            &key_expr.into(),
+            Channel::default(),
+            CongestionControl::default(),
+            None,
+            ZBuf::default(),
+            None,
+            None,
+        );
+        primitives0.get_last_name()
+    };
+    let config_key = "/@/router/A0/config/plugins/foo";
+    assert_eq!(put(&tables, &admin, config_key).unwrap(), config_key);
+    assert!(put(&tables, &other, config_key).is_none());
+    assert!(put(&tables, &spoofer, config_key).is_none());
+    assert_eq!(put(&tables, &local, config_key).unwrap(), config_key);
+    // other publications are not restricted
+    assert_eq!(put(&tables, &other, "/demo/foo").unwrap(), "/demo/foo");
+    // only the accepted changes are applied, given the identity of their issuer
+    {
+        let changes = zlock!(changes.0);
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].0.contains("admin"));
+        assert!(changes[1].0.starts_with("local"));
+        assert!(changes.iter().all(|(_, key_expr)| key_expr == config_key));
+    }
+
+    // a read-only adminspace rejects every change, including the local ones
+    tables.set_admin_config_policy(AdminConfigPolicy::read_only());
+    assert!(put(&tables, &admin, config_key).is_none());
+    assert!(put(&tables, &local, config_key).is_none());
+    assert_eq!(put(&tables, &local, "/demo/foo").unwrap(), "/demo/foo");
+    assert_eq!(zlock!(changes.0).len(), 2);
+}